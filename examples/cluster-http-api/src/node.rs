@@ -123,10 +123,14 @@ impl Node {
         client: &Client,
         hash: &str,
     ) -> anyhow::Result<Option<Vec<ApiCertificate>>> {
-        client
-            .get_block_certificates(hash)
-            .await
-            .map_err(|e| e.into())
+        let certificates = client.get_block_certificates(hash).await?;
+        Ok(certificates.map(|certificates| {
+            certificates
+                .certificates
+                .into_iter()
+                .map(|signer| signer.certificate)
+                .collect()
+        }))
     }
 
     pub(crate) async fn get_block_and_certificates_by_height(
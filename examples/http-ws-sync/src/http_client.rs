@@ -36,7 +36,13 @@ impl SignedMessageClient {
         hash: &str,
     ) -> Option<Vec<ephemera_api::ApiCertificate>> {
         match self.client.get_block_certificates(hash).await {
-            Ok(certificates) => certificates,
+            Ok(certificates) => certificates.map(|certificates| {
+                certificates
+                    .certificates
+                    .into_iter()
+                    .map(|signer| signer.certificate)
+                    .collect()
+            }),
             Err(err) => {
                 println!("Error sending message: {err:?}",);
                 None
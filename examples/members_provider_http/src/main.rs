@@ -1,7 +1,9 @@
+use std::path::PathBuf;
+
 use clap::{Args, Parser};
 use tokio::sync::mpsc::channel;
 
-use ephemera::membership::PeerSetting;
+use ephemera::membership::{read_authority_keypair, PeerSetting};
 
 use crate::http::run_peers_http_server;
 use crate::provider::{
@@ -29,6 +31,9 @@ struct ProviderArgs {
 struct RunProviderArgs {
     #[command(flatten)]
     provider: ProviderArgs,
+    /// File with the membership authority keypair. If given, peers are served as a signed membership.
+    #[clap(long)]
+    sign_with_file: Option<PathBuf>,
 }
 
 const EPHEMERA_IP: &str = "127.0.0.1";
@@ -48,7 +53,11 @@ async fn main() -> anyhow::Result<()> {
     let provider = get_provider(&args.provider).await;
 
     let (tx, rcv) = channel(10);
-    let runner = ProviderRunner::new(provider, rcv);
+    let authority = match &args.sign_with_file {
+        Some(path) => Some(read_authority_keypair(path)?),
+        None => None,
+    };
+    let runner = ProviderRunner::new(provider, rcv, authority);
     let runner_handle = tokio::spawn(runner.run());
 
     let provider_handle = tokio::spawn(run_peers_http_server(tx.clone()));
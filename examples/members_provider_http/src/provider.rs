@@ -1,15 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::oneshot::Sender;
 
 use ephemera::configuration::Configuration;
-use ephemera::crypto::PublicKey;
+use ephemera::crypto::{Keypair, PublicKey};
 use ephemera::ephemera_api::Client;
-use ephemera::membership::JsonPeerInfo;
+use ephemera::membership::{JsonPeerInfo, SignedMembership};
 use ephemera::peer::PeerId;
 
 use crate::{PeerSettings, EPHEMERA_IP, HTTP_API_PORT_BASE};
@@ -29,22 +29,42 @@ pub(crate) struct ProviderRunner {
     /// Chooses peers from all peers
     provider: Box<dyn Provider>,
     /// Channel to receive request to send peers to http server
-    http_peers_ch: Receiver<Sender<Vec<JsonPeerInfo>>>,
+    http_peers_ch: Receiver<Sender<String>>,
+    /// Signs peers if set
+    authority: Option<Keypair>,
+    /// Epoch of the last signed membership
+    epoch: u64,
 }
 
 impl ProviderRunner {
     pub(crate) fn new(
         provider: Box<dyn Provider>,
-        rcv: Receiver<Sender<Vec<JsonPeerInfo>>>,
+        rcv: Receiver<Sender<String>>,
+        authority: Option<Keypair>,
     ) -> Self {
         let peers_status = PeersStatus::new();
         Self {
             peers_status,
             provider,
             http_peers_ch: rcv,
+            authority,
+            epoch: 0,
         }
     }
 
+    ///Peers json, or signed membership json if authority is set
+    fn peers_response(&mut self, peers: Vec<JsonPeerInfo>) -> anyhow::Result<String> {
+        let Some(authority) = &self.authority else {
+            return Ok(serde_json::to_string(&peers)?);
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        //Epoch keeps growing across restarts of the provider
+        self.epoch = now.max(self.epoch + 1);
+        let mut membership = SignedMembership::new(self.epoch, now, now + 60 * 60 * 1000, peers);
+        membership.sign(authority)?;
+        Ok(serde_json::to_string(&membership)?)
+    }
+
     ///Query peers from Provider and run check about membership
     pub(crate) async fn run(mut self) -> anyhow::Result<()> {
         println!("Starting provider runner, all peers:");
@@ -58,7 +78,7 @@ impl ProviderRunner {
                 peers.iter().map(|p| p.to_string()).collect::<Vec<String>>()
             );
 
-            let json = self.peers_status.json_info(&peers);
+            let json = self.peers_response(self.peers_status.json_info(&peers))?;
 
             let mut peers_query_count = 0;
             loop {
@@ -153,17 +173,24 @@ impl PeersStatus {
             .unwrap()
             .join("peers.toml");
 
+        //Peers config created with `--sign-with` is a signed membership
+        if let Ok(membership) = SignedMembership::try_load(&path) {
+            let mut peers = membership.peers;
+            peers.sort_by(|a, b| a.name.cmp(&b.name));
+            println!("Read {:?} peers from signed membership", peers.len());
+            return peers;
+        }
+
         let config = std::fs::read_to_string(path).unwrap();
 
         let mut settings = toml::from_str::<PeerSettings>(&config).unwrap();
         settings.peers.sort_by(|a, b| a.name.cmp(&b.name));
 
         settings.peers.into_iter().for_each(|setting| {
-            peers.push(JsonPeerInfo {
-                name: setting.name,
-                address: setting.address,
-                public_key: setting.public_key,
-            });
+            peers.push(
+                JsonPeerInfo::new(setting.name, setting.address, setting.public_key)
+                    .with_weight(setting.weight),
+            );
         });
         println!("Read {:?} peers from config", peers.len());
         peers
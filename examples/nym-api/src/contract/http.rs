@@ -86,11 +86,11 @@ pub(crate) async fn get_nym_apis(contract: web::Data<Arc<Mutex<SmartContract>>>)
             continue;
         }
 
-        peers.push(JsonPeerInfo {
-            name: peer_id.to_string(),
-            address: peer.address,
-            public_key: peer.public_key.to_string(),
-        });
+        peers.push(JsonPeerInfo::new(
+            peer_id.to_string(),
+            peer.address,
+            peer.public_key.to_string(),
+        ));
     }
 
     info!("Found {} peers", peers.len());
@@ -195,8 +195,8 @@ where
                 info!("No 'winner' found for epoch id from DHT: {epoch_id:?}");
                 Ok(None)
             }
-            Some((_, block)) => {
-                let block = serde_json::from_slice(block.as_slice())?;
+            Some(response) => {
+                let block = serde_json::from_slice(response.value().as_slice())?;
                 info!("'Winner' found for epoch id from DHT: {epoch_id:?} - {block:?}");
                 Ok(Some(block))
             }
@@ -0,0 +1 @@
+ALTER TABLE block_broadcast_group ADD COLUMN weights BLOB;
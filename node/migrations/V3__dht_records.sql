@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS dht_records (
+    id              INTEGER      NOT NULL PRIMARY KEY AUTOINCREMENT,
+    record_key      BLOB         NOT NULL UNIQUE,
+    record          BLOB         NOT NULL,
+    publisher       TEXT
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS broadcast_group_snapshots (
+    id                  INTEGER      NOT NULL PRIMARY KEY,
+    timestamp           INTEGER      NOT NULL,
+    activation_height   INTEGER      NOT NULL,
+    members             BLOB         NOT NULL,
+    weights             BLOB         NOT NULL
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS blocks_by_height (
+    id          INTEGER      NOT NULL PRIMARY KEY AUTOINCREMENT,
+    block_hash  TEXT         NOT NULL UNIQUE,
+    height      TEXT         NOT NULL,
+    block       BLOB         NOT NULL
+);
+
+INSERT INTO blocks_by_height (id, block_hash, height, block) SELECT id, block_hash, height, block FROM blocks;
+
+DROP TABLE blocks;
+
+ALTER TABLE blocks_by_height RENAME TO blocks;
+
+CREATE INDEX IF NOT EXISTS blocks_height ON blocks (height);
@@ -0,0 +1,287 @@
+//! Admin routes. They require the admin token from the HTTP configuration as a bearer token,
+//! and are disabled when the token is not configured.
+//!
+//! All requests to admin routes are logged to the audit log target, refused ones included.
+
+use actix_web::{http::header, post, put, web, HttpRequest, HttpResponse};
+use log::{error, info, warn};
+
+use crate::api::{
+    http::channel_error_response,
+    types::{ApiBlockCreationInterval, ApiBlockManagerState},
+    ApiError, CommandExecutor, AUDIT_LOG_TARGET,
+};
+
+/// Token which authorizes admin requests.
+pub(crate) struct AdminToken(pub(crate) Option<String>);
+
+/// Returns the response to send instead if the request is not authorized.
+fn authorize(req: &HttpRequest, token: &AdminToken) -> Option<HttpResponse> {
+    let remote = req
+        .peer_addr()
+        .map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+    let Some(expected) = &token.0 else {
+        warn!(
+            target: AUDIT_LOG_TARGET,
+            "Refused admin request {} {} from {remote}: admin API is disabled",
+            req.method(),
+            req.path()
+        );
+        return Some(HttpResponse::Forbidden().json("Admin API is disabled"));
+    };
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if provided.is_some_and(|provided| constant_time_eq(provided.as_bytes(), expected.as_bytes())) {
+        info!(
+            target: AUDIT_LOG_TARGET,
+            "Admin request {} {} from {remote}",
+            req.method(),
+            req.path()
+        );
+        None
+    } else {
+        warn!(
+            target: AUDIT_LOG_TARGET,
+            "Refused admin request {} {} from {remote}: invalid token",
+            req.method(),
+            req.path()
+        );
+        Some(HttpResponse::Unauthorized().json("Invalid admin token"))
+    }
+}
+
+//Doesn't leak through timing how much of the token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn block_manager_response(result: Result<ApiBlockManagerState, ApiError>) -> HttpResponse {
+    match result {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(ApiError::InvalidArgument(reason)) => HttpResponse::BadRequest().json(reason),
+        Err(ApiError::BlockProductionUnavailable(reason)) => HttpResponse::Conflict().json(reason),
+        Err(err @ ApiError::UnknownChannel(_)) => channel_error_response(&err),
+        Err(err) => {
+            error!("Error changing block production: {err}");
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Pause block production until it's resumed", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 500, description = "Server failed to process request")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/admin/block_manager/pause")]
+pub(crate) async fn pause_block_production(
+    req: HttpRequest,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.pause_block_production().await)
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Resume block production paused by admin", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 500, description = "Server failed to process request")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/admin/block_manager/resume")]
+pub(crate) async fn resume_block_production(
+    req: HttpRequest,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.resume_block_production().await)
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Produce the next block without waiting for the interval", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 409, description = "Node can't produce a block now"),
+(status = 500, description = "Server failed to process request")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/admin/block_manager/produce")]
+pub(crate) async fn produce_block(
+    req: HttpRequest,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.produce_block().await)
+}
+
+#[utoipa::path(
+request_body = ApiBlockCreationInterval,
+responses(
+(status = 200, description = "Change the interval of block creation until the node restarts", body = ApiBlockManagerState),
+(status = 400, description = "Invalid interval"),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 500, description = "Server failed to process request")),
+security(("admin_token" = [])),
+)]
+#[put("/ephemera/admin/block_manager/interval")]
+pub(crate) async fn set_block_creation_interval(
+    req: HttpRequest,
+    request: web::Json<ApiBlockCreationInterval>,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.set_block_creation_interval(request.interval_sec).await)
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Pause block production of a channel until it's resumed", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 404, description = "Channel not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/{channel}/admin/block_manager/pause")]
+pub(crate) async fn pause_channel_block_production(
+    req: HttpRequest,
+    channel: web::Path<String>,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.pause_channel_block_production(&channel).await)
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Resume block production of a channel paused by admin", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 404, description = "Channel not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/{channel}/admin/block_manager/resume")]
+pub(crate) async fn resume_channel_block_production(
+    req: HttpRequest,
+    channel: web::Path<String>,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.resume_channel_block_production(&channel).await)
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Produce the next block of a channel without waiting for the interval", body = ApiBlockManagerState),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 404, description = "Channel not found"),
+(status = 409, description = "Node can't produce a block now"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name")),
+security(("admin_token" = [])),
+)]
+#[post("/ephemera/{channel}/admin/block_manager/produce")]
+pub(crate) async fn produce_channel_block(
+    req: HttpRequest,
+    channel: web::Path<String>,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(api.produce_channel_block(&channel).await)
+}
+
+#[utoipa::path(
+request_body = ApiBlockCreationInterval,
+responses(
+(status = 200, description = "Change the interval of block creation of a channel until the node restarts", body = ApiBlockManagerState),
+(status = 400, description = "Invalid interval"),
+(status = 401, description = "Invalid admin token"),
+(status = 403, description = "Admin API is disabled"),
+(status = 404, description = "Channel not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name")),
+security(("admin_token" = [])),
+)]
+#[put("/ephemera/{channel}/admin/block_manager/interval")]
+pub(crate) async fn set_channel_block_creation_interval(
+    req: HttpRequest,
+    channel: web::Path<String>,
+    request: web::Json<ApiBlockCreationInterval>,
+    token: web::Data<AdminToken>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    if let Some(response) = authorize(&req, &token) {
+        return response;
+    }
+    block_manager_response(
+        api.set_channel_block_creation_interval(&channel, request.interval_sec)
+            .await,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_authorize_admin_token() {
+        let token = AdminToken(Some("secret".to_string()));
+
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_http_request();
+        assert!(authorize(&req, &token).is_none());
+
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer secreT"))
+            .to_http_request();
+        let response = authorize(&req, &token).unwrap();
+        assert_eq!(response.status(), 401);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(authorize(&req, &token).unwrap().status(), 401);
+
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_http_request();
+        let disabled = authorize(&req, &AdminToken(None)).unwrap();
+        assert_eq!(disabled.status(), 403);
+    }
+}
@@ -2,11 +2,16 @@ use std::time::Duration;
 
 use thiserror::Error;
 
-use crate::api::types::{ApiBlockBroadcastInfo, ApiBroadcastInfo, ApiHealth};
+use crate::api::types::{
+    ApiBlockBroadcastInfo, ApiBlockCreationInterval, ApiBlockManagerState, ApiBroadcastInfo,
+    ApiBroadcastProgress, ApiDeniedConnections, ApiGroupSnapshot, ApiHealth, ApiMemberHealth,
+    ApiPeerBan, ApiPeerInfo,
+};
 use crate::ephemera_api::{
-    ApiBlock, ApiCertificate, ApiDhtQueryRequest, ApiDhtQueryResponse, ApiDhtStoreRequest,
+    ApiBlock, ApiBlockCertificates, ApiDhtQueryRequest, ApiDhtQueryResponse, ApiDhtStoreRequest,
     ApiEphemeraConfig, ApiEphemeraMessage, ApiVerifyMessageInBlock,
 };
+use crate::peer::PeerId;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -22,9 +27,11 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Client to interact with the node over HTTP api.
+#[allow(clippy::struct_field_names)]
 pub struct Client {
     pub(crate) client: reqwest::Client,
     pub(crate) url: String,
+    pub(crate) admin_token: Option<String>,
 }
 
 impl Client {
@@ -35,7 +42,11 @@ impl Client {
     #[must_use]
     pub fn new(url: String) -> Self {
         let client = reqwest::Client::new();
-        Self { client, url }
+        Self {
+            client,
+            url,
+            admin_token: None,
+        }
     }
 
     /// Create a new client.
@@ -52,7 +63,21 @@ impl Client {
             .timeout(Duration::from_secs(timeout_sec))
             .build()
             .unwrap();
-        Self { client, url }
+        Self {
+            client,
+            url,
+            admin_token: None,
+        }
+    }
+
+    /// Sets the token sent with requests to the admin api.
+    ///
+    /// # Arguments
+    /// * `token` - The admin token configured on the node.
+    #[must_use]
+    pub fn with_admin_token(mut self, token: String) -> Self {
+        self.admin_token = Some(token);
+        self
     }
 
     /// Get the health of the node.
@@ -106,7 +131,7 @@ impl Client {
     ///
     /// # Example
     /// ```no_run
-    /// use ephemera::ephemera_api::{ApiCertificate, Client};
+    /// use ephemera::ephemera_api::Client;
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -120,11 +145,11 @@ impl Client {
     /// * `hash` - The hash of the block.
     ///
     /// # Returns
-    /// * Option<Vec<[`ApiCertificate`]>> - The block certificates.
+    /// * Option<[`ApiBlockCertificates`]> - The block certificates with the weights of the signers.
     ///
     /// # Errors
     /// If the request fails.
-    pub async fn get_block_certificates(&self, hash: &str) -> Result<Option<Vec<ApiCertificate>>> {
+    pub async fn get_block_certificates(&self, hash: &str) -> Result<Option<ApiBlockCertificates>> {
         let url = format!("ephemera/broadcast/block/certificates/{hash}",);
         self.query_optional(&url).await
     }
@@ -179,6 +204,82 @@ impl Client {
         self.query("ephemera/broadcast/blocks/last").await
     }
 
+    /// Get the block of a channel by hash.
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `hash` - The hash of the block.
+    ///
+    /// # Returns
+    /// * Option<[`ApiBlock`]> - The block, `None` also if the channel doesn't exist.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn get_channel_block_by_hash(
+        &self,
+        channel: &str,
+        hash: &str,
+    ) -> Result<Option<ApiBlock>> {
+        let url = format!("ephemera/{channel}/broadcast/block/{hash}");
+        self.query_optional(&url).await
+    }
+
+    /// Get the block certificates of a channel by hash.
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `hash` - The hash of the block.
+    ///
+    /// # Returns
+    /// * Option<[`ApiBlockCertificates`]> - The block certificates with the weights of the signers,
+    ///   `None` also if the channel doesn't exist.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn get_channel_block_certificates(
+        &self,
+        channel: &str,
+        hash: &str,
+    ) -> Result<Option<ApiBlockCertificates>> {
+        let url = format!("ephemera/{channel}/broadcast/block/certificates/{hash}");
+        self.query_optional(&url).await
+    }
+
+    /// Get the block of a channel by height.
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `height` - The height of the block.
+    ///
+    /// # Returns
+    /// * Option<[`ApiBlock`]> - The block, `None` also if the channel doesn't exist.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn get_channel_block_by_height(
+        &self,
+        channel: &str,
+        height: u64,
+    ) -> Result<Option<ApiBlock>> {
+        let url = format!("ephemera/{channel}/broadcast/block/height/{height}");
+        self.query_optional(&url).await
+    }
+
+    /// Get the last block of a channel.
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    ///
+    /// # Returns
+    /// * [`ApiBlock`] - The last block.
+    ///
+    /// # Errors
+    /// If the request fails or the channel doesn't exist.
+    pub async fn get_channel_last_block(&self, channel: &str) -> Result<ApiBlock> {
+        let url = format!("ephemera/{channel}/broadcast/blocks/last");
+        self.query(&url).await
+    }
+
     /// Get the node configuration.
     ///
     /// # Example
@@ -224,7 +325,29 @@ impl Client {
     /// # Errors
     /// If the request fails.
     pub async fn submit_message(&self, message: ApiEphemeraMessage) -> Result<()> {
-        let url = format!("{}/{}", self.url, "ephemera/broadcast/submit_message");
+        self.submit("ephemera/broadcast/submit_message", message)
+            .await
+    }
+
+    /// Submit a message to a channel of the node.
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `message` - The message to submit.
+    ///
+    /// # Errors
+    /// If the request fails or the channel doesn't exist.
+    pub async fn submit_channel_message(
+        &self,
+        channel: &str,
+        message: ApiEphemeraMessage,
+    ) -> Result<()> {
+        let path = format!("ephemera/{channel}/broadcast/submit_message");
+        self.submit(&path, message).await
+    }
+
+    async fn submit(&self, path: &str, message: ApiEphemeraMessage) -> Result<()> {
+        let url = format!("{}/{path}", self.url);
         let response = self.client.post(&url).json(&message).send().await?;
         if response.status().is_success() {
             Ok(())
@@ -244,15 +367,19 @@ impl Client {
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///    use ephemera::ephemera_api::Client;
+    ///    use std::time::Duration;
+    ///    use ephemera::ephemera_api::ApiDhtStoreRequest;
     ///    let client = Client::new("http://localhost:7000/".to_string());
-    ///    let request = unimplemented!("See how to create a ApiDhtStoreRequest");
+    ///    let request = ApiDhtStoreRequest::new(&[1, 2, 3], &[4, 5, 6])
+    ///        .with_ttl(Duration::from_secs(3600))
+    ///        .namespaced();
     ///    client.store_in_dht(request).await?;
     ///  Ok(())
     /// }
     /// ```
     ///
     /// # Arguments
-    /// * `request` - Key Value pair to store.
+    /// * `request` - Key Value pair to store, with optional TTL and namespace.
     ///
     /// # Errors
     /// If the request fails.
@@ -309,10 +436,10 @@ impl Client {
     /// ```
     ///
     /// # Arguments
-    /// * `request` - Key to query.
+    /// * `request` - Key to query and optional read quorum.
     ///
     /// # Returns
-    /// * Option<[`ApiDhtQueryResponse`]> - The value stored in the DHT for the given key.
+    /// * Option<[`ApiDhtQueryResponse`]> - The value stored in the DHT for the given key, its publisher and expiry.
     ///
     /// # Errors
     /// If the request fails.
@@ -320,7 +447,10 @@ impl Client {
         &self,
         request: ApiDhtQueryRequest,
     ) -> Result<Option<ApiDhtQueryResponse>> {
-        let url = format!("ephemera/dht/query/{}", request.key_encoded());
+        let mut url = format!("ephemera/dht/query/{}", request.key_encoded());
+        if let Some(quorum) = request.quorum() {
+            url = format!("{url}?quorum={quorum}");
+        }
         self.query_optional(&url).await
     }
 
@@ -342,7 +472,7 @@ impl Client {
     /// * `key` - Key to query.
     ///
     /// # Returns
-    /// * Option<[`ApiDhtQueryResponse`]> - The value stored in the DHT for the given key.
+    /// * Option<[`ApiDhtQueryResponse`]> - The value stored in the DHT for the given key, its publisher and expiry.
     ///
     /// # Errors
     /// If the request fails.
@@ -374,6 +504,80 @@ impl Client {
         self.query("ephemera/broadcast/group/info").await
     }
 
+    /// Get live progress of the most recently active broadcasts
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let progress = client.broadcast_progress().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * [`ApiBroadcastProgress`] - The broadcast progress.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn broadcast_progress(&self) -> Result<ApiBroadcastProgress> {
+        self.query("ephemera/broadcast/progress").await
+    }
+
+    /// Get all broadcast group snapshots of the node, oldest first
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let history = client.group_history().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * Vec<[`ApiGroupSnapshot`]> - The broadcast group snapshots.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn group_history(&self) -> Result<Vec<ApiGroupSnapshot>> {
+        self.query("ephemera/broadcast/group/history").await
+    }
+
+    /// Get broadcast group snapshot by id
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let snapshot = client.group_snapshot(1).await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `id` - Id of the snapshot to query.
+    ///
+    /// # Returns
+    /// * Some([`ApiGroupSnapshot`]) - The broadcast group snapshot.
+    /// * None - If the snapshot is not found.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn group_snapshot(&self, id: u64) -> Result<Option<ApiGroupSnapshot>> {
+        let url = format!("ephemera/broadcast/group/{id}");
+        self.query_optional(&url).await
+    }
+
     /// Get block broadcast info
     ///
     /// # Example
@@ -454,6 +658,414 @@ impl Client {
         }
     }
 
+    /// Get peers which are temporarily banned by the network
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let bans = client.banned_peers().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * Vec<[`ApiPeerBan`]> - The banned peers.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn banned_peers(&self) -> Result<Vec<ApiPeerBan>> {
+        self.query("ephemera/network/bans").await
+    }
+
+    /// Get the number of connections denied because the peer is not a member
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let denied = client.denied_connections().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * [`ApiDeniedConnections`] - Inbound and outbound denied connections.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn denied_connections(&self) -> Result<ApiDeniedConnections> {
+        self.query("ephemera/network/denied_connections").await
+    }
+
+    /// Get connected peers with their latency, health and versions
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let peers = client.network_peers().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * Vec<[`ApiPeerInfo`]> - The connected peers.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn network_peers(&self) -> Result<Vec<ApiPeerInfo>> {
+        self.query("ephemera/network/peers").await
+    }
+
+    /// Get members of the current membership with their health and exclusion reasons
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let members = client.members_health().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * Vec<[`ApiMemberHealth`]> - The members.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn members_health(&self) -> Result<Vec<ApiMemberHealth>> {
+        self.query("ephemera/network/members").await
+    }
+
+    /// Lift the ban of a peer
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    /// use ephemera::peer::PeerId;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let unbanned = client.unban_peer(&PeerId::random()).await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `peer_id` - The banned peer.
+    ///
+    /// # Returns
+    /// * bool - True if the peer was banned, false otherwise.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn unban_peer(&self, peer_id: &PeerId) -> Result<bool> {
+        let url = format!("{}/ephemera/network/bans/{peer_id}", self.url);
+        let response = self.client.delete(&url).send().await?;
+        if response.status().is_success() {
+            Ok(true)
+        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(false)
+        } else {
+            Err(Error::UnexpectedResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        }
+    }
+
+    /// Lift all bans
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let cleared = client.clear_bans().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * usize - The number of lifted bans.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn clear_bans(&self) -> Result<usize> {
+        let url = format!("{}/ephemera/network/bans", self.url);
+        let response = self.client.delete(&url).send().await?;
+        if response.status().is_success() {
+            let body = response.json::<usize>().await?;
+            Ok(body)
+        } else {
+            Err(Error::UnexpectedResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        }
+    }
+
+    /// Get DHT records stored by the node
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let records = client.dht_records().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * Vec<[`ApiDhtQueryResponse`]> - The stored records, with their publishers and expiry.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn dht_records(&self) -> Result<Vec<ApiDhtQueryResponse>> {
+        self.query("ephemera/dht/records").await
+    }
+
+    /// Remove a DHT record from the node. Other peers keep their copies of the record.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string());
+    ///   let removed = client.remove_dht_record(&[1, 2, 3]).await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `key` - The key of the record.
+    ///
+    /// # Returns
+    /// * bool - True if the node stored the record, false otherwise.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn remove_dht_record(&self, key: &[u8]) -> Result<bool> {
+        let key = ApiDhtQueryRequest::new(key).key_encoded();
+        let url = format!("{}/ephemera/dht/records/{key}", self.url);
+        let response = self.client.delete(&url).send().await?;
+        if response.status().is_success() {
+            Ok(true)
+        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(false)
+        } else {
+            Err(Error::UnexpectedResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        }
+    }
+
+    /// Pause block production until it's resumed.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ephemera::ephemera_api::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///   let client = Client::new("http://localhost:7000/".to_string())
+    ///     .with_admin_token("secret".to_string());
+    ///   let state = client.pause_block_production().await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager after the change.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn pause_block_production(&self) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/admin/block_manager/pause", self.url);
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Resume block production paused by [`Client::pause_block_production`].
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager after the change.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn resume_block_production(&self) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/admin/block_manager/resume", self.url);
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Produce the next block without waiting for the block creation interval.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager after the request.
+    ///
+    /// # Errors
+    /// If the request fails or the node can't produce a block now.
+    pub async fn produce_block(&self) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/admin/block_manager/produce", self.url);
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Change the block creation interval until the node restarts.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Arguments
+    /// * `interval_sec` - The new interval in seconds, from one second to one day.
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager after the change.
+    ///
+    /// # Errors
+    /// If the request fails.
+    pub async fn set_block_creation_interval(
+        &self,
+        interval_sec: u64,
+    ) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/admin/block_manager/interval", self.url);
+        let request = self
+            .client
+            .put(&url)
+            .json(&ApiBlockCreationInterval { interval_sec });
+        self.admin(request).await
+    }
+
+    /// Pause block production of a channel until it's resumed.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager of the channel after the change.
+    ///
+    /// # Errors
+    /// If the request fails or the channel is unknown.
+    pub async fn pause_channel_block_production(
+        &self,
+        channel: &str,
+    ) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/{channel}/admin/block_manager/pause", self.url);
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Resume block production of a channel paused by [`Client::pause_channel_block_production`].
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager of the channel after the change.
+    ///
+    /// # Errors
+    /// If the request fails or the channel is unknown.
+    pub async fn resume_channel_block_production(
+        &self,
+        channel: &str,
+    ) -> Result<ApiBlockManagerState> {
+        let url = format!("{}/ephemera/{channel}/admin/block_manager/resume", self.url);
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Produce the next block of a channel without waiting for the block creation interval.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager of the channel after the request.
+    ///
+    /// # Errors
+    /// If the request fails, the channel is unknown or the node can't produce a block now.
+    pub async fn produce_channel_block(&self, channel: &str) -> Result<ApiBlockManagerState> {
+        let url = format!(
+            "{}/ephemera/{channel}/admin/block_manager/produce",
+            self.url
+        );
+        self.admin(self.client.post(&url)).await
+    }
+
+    /// Change the block creation interval of a channel until the node restarts.
+    ///
+    /// Requires the admin token, see [`Client::with_admin_token`].
+    ///
+    /// # Arguments
+    /// * `channel` - The name of the channel.
+    /// * `interval_sec` - The new interval in seconds, from one second to one day.
+    ///
+    /// # Returns
+    /// * `ApiBlockManagerState` - The state of the block manager of the channel after the change.
+    ///
+    /// # Errors
+    /// If the request fails or the channel is unknown.
+    pub async fn set_channel_block_creation_interval(
+        &self,
+        channel: &str,
+        interval_sec: u64,
+    ) -> Result<ApiBlockManagerState> {
+        let url = format!(
+            "{}/ephemera/{channel}/admin/block_manager/interval",
+            self.url
+        );
+        let request = self
+            .client
+            .put(&url)
+            .json(&ApiBlockCreationInterval { interval_sec });
+        self.admin(request).await
+    }
+
+    async fn admin(&self, request: reqwest::RequestBuilder) -> Result<ApiBlockManagerState> {
+        let request = match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request.send().await?;
+        if response.status().is_success() {
+            Ok(response.json::<ApiBlockManagerState>().await?)
+        } else {
+            Err(Error::UnexpectedResponse {
+                status: response.status(),
+                body: response.text().await?,
+            })
+        }
+    }
+
     async fn query_optional<T: for<'de> serde::Deserialize<'de>>(
         &self,
         path: &str,
@@ -1,11 +1,12 @@
-use actix_web::{dev::Server, http::KeepAlive, web::Data, App, HttpServer};
-use log::info;
+use actix_web::{dev::Server, http::KeepAlive, web::Data, App, HttpResponse, HttpServer};
+use log::{error, info};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::api::CommandExecutor;
+use crate::api::{http::admin::AdminToken, ApiError, CommandExecutor};
 use crate::core::builder::NodeInfo;
 
+pub(crate) mod admin;
 pub(crate) mod client;
 pub(crate) mod query;
 pub(crate) mod submit;
@@ -14,9 +15,14 @@ pub(crate) mod submit;
 pub(crate) fn init(node_info: &NodeInfo, api: CommandExecutor) -> anyhow::Result<Server> {
     print_startup_messages(node_info);
 
+    let admin_token = Data::new(AdminToken(
+        node_info.initial_config.http.admin_token.clone(),
+    ));
+
     let server = HttpServer::new(move || {
         App::new()
             .app_data(Data::new(api.clone()))
+            .app_data(admin_token.clone())
             .service(query::health)
             .service(query::block_by_hash)
             .service(query::block_certificates)
@@ -26,9 +32,34 @@ pub(crate) fn init(node_info: &NodeInfo, api: CommandExecutor) -> anyhow::Result
             .service(query::node_config)
             .service(query::query_dht)
             .service(query::broadcast_info)
+            .service(query::broadcast_progress)
+            .service(query::group_history)
+            .service(query::group_snapshot)
+            .service(query::banned_peers)
+            .service(query::denied_connections)
+            .service(query::network_peers)
+            .service(query::members_health)
+            .service(query::dht_records)
             .service(submit::submit_message)
             .service(submit::store_in_dht)
             .service(submit::verify_message_in_block)
+            .service(submit::unban_peer)
+            .service(submit::clear_bans)
+            .service(submit::remove_dht_record)
+            .service(admin::pause_block_production)
+            .service(admin::resume_block_production)
+            .service(admin::produce_block)
+            .service(admin::set_block_creation_interval)
+            //Channel routes are registered last, so that they don't shadow the routes above
+            .service(query::channel_block_by_hash)
+            .service(query::channel_block_certificates)
+            .service(query::channel_block_by_height)
+            .service(query::channel_last_block)
+            .service(submit::submit_channel_message)
+            .service(admin::pause_channel_block_production)
+            .service(admin::resume_channel_block_production)
+            .service(admin::produce_channel_block)
+            .service(admin::set_channel_block_creation_interval)
             .service(swagger_ui())
     })
     .keep_alive(KeepAlive::Os)
@@ -42,6 +73,9 @@ pub(crate) fn init(node_info: &NodeInfo, api: CommandExecutor) -> anyhow::Result
 /// Note that all routes you want Swagger docs for must be in the `paths` annotation.
 fn swagger_ui() -> SwaggerUi {
     use crate::api::types;
+    use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+    use utoipa::Modify;
+
     #[derive(OpenApi)]
     #[openapi(
         paths(
@@ -54,30 +88,107 @@ fn swagger_ui() -> SwaggerUi {
             query::node_config,
             query::query_dht,
             query::broadcast_info,
+            query::broadcast_progress,
+            query::group_history,
+            query::group_snapshot,
+            query::banned_peers,
+            query::denied_connections,
+            query::network_peers,
+            query::members_health,
+            query::dht_records,
             submit::submit_message,
             submit::store_in_dht,
-            submit::verify_message_in_block
+            submit::verify_message_in_block,
+            submit::unban_peer,
+            submit::clear_bans,
+            submit::remove_dht_record,
+            admin::pause_block_production,
+            admin::resume_block_production,
+            admin::produce_block,
+            admin::set_block_creation_interval,
+            query::channel_block_by_hash,
+            query::channel_block_certificates,
+            query::channel_block_by_height,
+            query::channel_last_block,
+            submit::submit_channel_message,
+            admin::pause_channel_block_production,
+            admin::resume_channel_block_production,
+            admin::produce_channel_block,
+            admin::set_channel_block_creation_interval
         ),
         components(schemas(
             types::ApiBlock,
             types::ApiEphemeraMessage,
             types::ApiCertificate,
+            types::ApiBlockCertificate,
+            types::ApiBlockCertificates,
             types::ApiSignature,
             types::ApiPublicKey,
             types::ApiHealth,
             types::HealthStatus,
             types::ApiEphemeraConfig,
+            types::ApiQuorumPolicy,
             types::ApiDhtStoreRequest,
             types::ApiDhtQueryRequest,
             types::ApiDhtQueryResponse,
             types::ApiBroadcastInfo,
+            types::ApiBroadcastProgress,
+            types::ApiGroupSnapshot,
+            types::ApiBlockBroadcastProgress,
+            types::ApiBlockManagerState,
+            types::ApiBlockManagerStatus,
+            types::ApiPendingBlock,
             types::ApiVerifyMessageInBlock,
-        ))
+            types::ApiPeerBan,
+            types::ApiDeniedConnections,
+            types::ApiPeerInfo,
+            types::ApiMemberHealth,
+            types::ApiExclusionReason,
+            types::ApiBlockCreationInterval,
+        )),
+        modifiers(&AdminSecurity)
     )]
     struct ApiDoc;
+
+    struct AdminSecurity;
+
+    impl Modify for AdminSecurity {
+        fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+            if let Some(components) = openapi.components.as_mut() {
+                components.add_security_scheme(
+                    "admin_token",
+                    SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+                );
+            }
+        }
+    }
+
     SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-doc/openapi.json", ApiDoc::openapi())
 }
 
+/// Maps DHT errors to HTTP responses.
+fn dht_error_response(err: &ApiError) -> HttpResponse {
+    match err {
+        ApiError::DhtTimeout => HttpResponse::GatewayTimeout().json("DHT request timed out"),
+        ApiError::DhtQuorumFailed(reason) => HttpResponse::ServiceUnavailable().json(reason),
+        ApiError::InvalidDhtRecord(reason) => HttpResponse::BadRequest().json(reason),
+        _ => {
+            error!("DHT request failed: {err}");
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+/// Maps errors of channel requests to HTTP responses.
+fn channel_error_response(err: &ApiError) -> HttpResponse {
+    if let ApiError::UnknownChannel(channel) = err {
+        HttpResponse::NotFound().json(format!("Unknown channel: {channel}"))
+    } else {
+        error!("Channel request failed: {err}");
+        HttpResponse::InternalServerError().json("Server failed to process request")
+    }
+}
+
 /// Prints messages saying which ports HTTP is running on, and some helpful pointers
 /// `OpenAPI` and `Swagger UI` endpoints.
 fn print_startup_messages(info: &NodeInfo) {
@@ -1,11 +1,23 @@
 use actix_web::{get, web, HttpResponse, Responder};
 use log::error;
+use serde::Deserialize;
 
 use crate::{
-    api::{types::ApiHealth, types::HealthStatus::Healthy, CommandExecutor},
-    ephemera_api::{ApiDhtQueryRequest, ApiDhtQueryResponse},
+    api::{
+        http::{channel_error_response, dht_error_response},
+        types::ApiHealth,
+        types::HealthStatus::Healthy,
+        CommandExecutor,
+    },
+    config::DhtQuorum,
+    ephemera_api::ApiDhtQueryRequest,
 };
 
+#[derive(Deserialize)]
+pub(crate) struct DhtQueryParams {
+    quorum: Option<DhtQuorum>,
+}
+
 #[utoipa::path(
 responses(
 (status = 200, description = "Endpoint to check if the server is running")),
@@ -32,6 +44,125 @@ pub(crate) async fn broadcast_info(api: web::Data<CommandExecutor>) -> impl Resp
     }
 }
 
+#[utoipa::path(
+responses(
+(status = 200, description = "Get live progress of the most recently active broadcasts"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/broadcast/progress")]
+pub(crate) async fn broadcast_progress(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.get_broadcast_progress().await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(err) => {
+            error!("Failed to get broadcast progress: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get all broadcast group snapshots, oldest first"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/broadcast/group/history")]
+pub(crate) async fn group_history(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.get_group_history().await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => {
+            error!("Failed to get broadcast group history: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get broadcast group snapshot by id"),
+(status = 404, description = "Snapshot not found"),
+(status = 500, description = "Server failed to process request")),
+params(("id", description = "Snapshot id")),
+)]
+//Needs to be registered after group info and history routes, otherwise it overlaps with them
+#[get("/ephemera/broadcast/group/{id}")]
+pub(crate) async fn group_snapshot(
+    id: web::Path<u64>,
+    api: web::Data<CommandExecutor>,
+) -> impl Responder {
+    match api.get_group_snapshot(id.into_inner()).await {
+        Ok(Some(snapshot)) => HttpResponse::Ok().json(snapshot),
+        Ok(_) => HttpResponse::NotFound().json("Snapshot not found"),
+        Err(err) => {
+            error!("Failed to get broadcast group snapshot: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get peers which are temporarily banned by the network"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/network/bans")]
+pub(crate) async fn banned_peers(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.banned_peers().await {
+        Ok(bans) => HttpResponse::Ok().json(bans),
+        Err(err) => {
+            error!("Failed to get banned peers: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get the number of connections denied because the peer is not a member"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/network/denied_connections")]
+pub(crate) async fn denied_connections(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.denied_connections().await {
+        Ok(denied) => HttpResponse::Ok().json(denied),
+        Err(err) => {
+            error!("Failed to get denied connections: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get connected peers with their latency, health and versions"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/network/peers")]
+pub(crate) async fn network_peers(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.network_peers().await {
+        Ok(peers) => HttpResponse::Ok().json(peers),
+        Err(err) => {
+            error!("Failed to get network peers: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get members of the current membership with their health and exclusion reasons"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/network/members")]
+pub(crate) async fn members_health(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.members_health().await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(err) => {
+            error!("Failed to get members health: {err}",);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
 #[utoipa::path(
 responses(
 (status = 200, description = "GET block by hash"),
@@ -56,7 +187,7 @@ pub(crate) async fn block_by_hash(
 
 #[utoipa::path(
 responses(
-(status = 200, description = "Get block signatures"),
+(status = 200, description = "Get block certificates with the weights of the signers", body = ApiBlockCertificates),
 (status = 404, description = "Certificates not found"),
 (status = 500, description = "Server failed to process request")),
 params(("hash", description = "Block hash")),
@@ -116,6 +247,84 @@ pub(crate) async fn last_block(api: web::Data<CommandExecutor>) -> impl Responde
     }
 }
 
+#[utoipa::path(
+responses(
+(status = 200, description = "GET block of a channel by hash"),
+(status = 404, description = "Channel or block not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name"), ("hash", description = "Block hash")),
+)]
+#[get("/ephemera/{channel}/broadcast/block/{hash}")]
+pub(crate) async fn channel_block_by_hash(
+    path: web::Path<(String, String)>,
+    api: web::Data<CommandExecutor>,
+) -> impl Responder {
+    let (channel, hash) = path.into_inner();
+    match api.get_channel_block_by_id(&channel, hash).await {
+        Ok(Some(block)) => HttpResponse::Ok().json(block),
+        Ok(_) => HttpResponse::NotFound().json("Block not found"),
+        Err(err) => channel_error_response(&err),
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get block certificates of a channel with the weights of the signers", body = ApiBlockCertificates),
+(status = 404, description = "Channel or certificates not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name"), ("hash", description = "Block hash")),
+)]
+#[get("/ephemera/{channel}/broadcast/block/certificates/{hash}")]
+pub(crate) async fn channel_block_certificates(
+    path: web::Path<(String, String)>,
+    api: web::Data<CommandExecutor>,
+) -> impl Responder {
+    let (channel, hash) = path.into_inner();
+    match api.get_channel_block_certificates(&channel, hash).await {
+        Ok(Some(signatures)) => HttpResponse::Ok().json(signatures),
+        Ok(_) => HttpResponse::NotFound().json("Certificates not found"),
+        Err(err) => channel_error_response(&err),
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get block of a channel by height"),
+(status = 404, description = "Channel or block not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name"), ("height", description = "Block height")),
+)]
+#[get("/ephemera/{channel}/broadcast/block/height/{height}")]
+pub(crate) async fn channel_block_by_height(
+    path: web::Path<(String, u64)>,
+    api: web::Data<CommandExecutor>,
+) -> impl Responder {
+    let (channel, height) = path.into_inner();
+    match api.get_channel_block_by_height(&channel, height).await {
+        Ok(Some(block)) => HttpResponse::Ok().json(block),
+        Ok(_) => HttpResponse::NotFound().json("Block not found"),
+        Err(err) => channel_error_response(&err),
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get last block of a channel"),
+(status = 404, description = "Channel not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name")),
+)]
+#[get("/ephemera/{channel}/broadcast/blocks/last")]
+pub(crate) async fn channel_last_block(
+    channel: web::Path<String>,
+    api: web::Data<CommandExecutor>,
+) -> impl Responder {
+    match api.get_channel_last_block(&channel.into_inner()).await {
+        Ok(block) => HttpResponse::Ok().json(block),
+        Err(err) => channel_error_response(&err),
+    }
+}
+
 #[utoipa::path(
 responses(
 (status = 200, description = "Get block broadcast group"),
@@ -158,24 +367,45 @@ pub(crate) async fn node_config(api: web::Data<CommandExecutor>) -> impl Respond
 #[utoipa::path(
 responses(
 (status = 200, description = "Query dht"),
-(status = 500, description = "Server failed to process request")),
-params(("query", description = "Dht query")),
+(status = 404, description = "Key not found"),
+(status = 500, description = "Server failed to process request"),
+(status = 503, description = "Not enough peers returned the record"),
+(status = 504, description = "Query timed out")),
+params(
+("key", description = "Dht key in hex format"),
+("quorum" = Option<String>, Query, description = "Read quorum, `one`, `majority`, `all` or a number")
+),
 )]
 #[get("/ephemera/dht/query/{key}")]
 pub(crate) async fn query_dht(
     api: web::Data<CommandExecutor>,
     key: web::Path<String>,
+    params: web::Query<DhtQueryParams>,
 ) -> impl Responder {
     let key = ApiDhtQueryRequest::parse_key(key.into_inner().as_str());
+    let mut request = ApiDhtQueryRequest::new(&key);
+    if let Some(quorum) = params.quorum {
+        request = request.with_quorum(quorum);
+    }
 
-    match api.query_dht(key).await {
-        Ok(Some((key, value))) => {
-            let response = ApiDhtQueryResponse::new(key, value);
-            HttpResponse::Ok().json(response)
-        }
+    match api.query_record_in_dht(request).await {
+        Ok(Some(response)) => HttpResponse::Ok().json(response),
         Ok(_) => HttpResponse::NotFound().json("Not found"),
+        Err(err) => dht_error_response(&err),
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Get DHT records stored by this node"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[get("/ephemera/dht/records")]
+pub(crate) async fn dht_records(api: web::Data<CommandExecutor>) -> impl Responder {
+    match api.dht_records().await {
+        Ok(records) => HttpResponse::Ok().json(records),
         Err(err) => {
-            error!("Failed to query dht {err}",);
+            error!("Failed to get dht records: {err}",);
             HttpResponse::InternalServerError().json("Server failed to process request")
         }
     }
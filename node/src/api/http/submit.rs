@@ -1,11 +1,16 @@
-use actix_web::{post, web, HttpResponse};
+use std::str::FromStr;
+
+use actix_web::{delete, post, web, HttpResponse};
+use array_bytes::hex2bytes;
 use log::{debug, error};
 
 use crate::api::types::ApiVerifyMessageInBlock;
 use crate::api::{
+    http::{channel_error_response, dht_error_response},
     types::{ApiDhtStoreRequest, ApiEphemeraMessage},
     ApiError, CommandExecutor,
 };
+use crate::peer::PeerId;
 
 #[utoipa::path(
 request_body = ApiEphemeraMessage,
@@ -33,11 +38,41 @@ pub(crate) async fn submit_message(
     }
 }
 
+#[utoipa::path(
+request_body = ApiEphemeraMessage,
+responses(
+(status = 200, description = "Send a message to a channel of an Ephemera node which will be broadcast to the network"),
+(status = 404, description = "Channel not found"),
+(status = 500, description = "Server failed to process request")),
+params(("channel", description = "Channel name"), ("message", description = "Message to send"))
+)]
+#[post("/ephemera/{channel}/broadcast/submit_message")]
+pub(crate) async fn submit_channel_message(
+    channel: web::Path<String>,
+    message: web::Json<ApiEphemeraMessage>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    match api
+        .send_channel_message(&channel.into_inner(), message.into_inner())
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json("Message submitted"),
+        Err(ApiError::DuplicateMessage) => {
+            debug!("Message already submitted");
+            HttpResponse::BadRequest().json("Message already submitted")
+        }
+        Err(err) => channel_error_response(&err),
+    }
+}
+
 #[utoipa::path(
 request_body = ApiDhtStoreRequest,
 responses(
 (status = 200, description = "Request to store a value in the DHT"),
-(status = 500, description = "Server failed to process request")),
+(status = 400, description = "Invalid record"),
+(status = 500, description = "Server failed to process request"),
+(status = 503, description = "Not enough peers stored the record"),
+(status = 504, description = "Store timed out")),
 params(
 ("request", description = "Dht store request")
 )
@@ -47,17 +82,9 @@ pub(crate) async fn store_in_dht(
     request: web::Json<ApiDhtStoreRequest>,
     api: web::Data<CommandExecutor>,
 ) -> HttpResponse {
-    let request = request.into_inner();
-
-    let key = request.key();
-    let value = request.value();
-
-    match api.store_in_dht(key, value).await {
-        Ok(_) => HttpResponse::Ok().json("Store request submitted"),
-        Err(err) => {
-            error!("Error storing in dht: {}", err);
-            HttpResponse::InternalServerError().json("Server failed to process request")
-        }
+    match api.store_record_in_dht(request.into_inner()).await {
+        Ok(()) => HttpResponse::Ok().json("Record stored"),
+        Err(err) => dht_error_response(&err),
     }
 }
 
@@ -86,3 +113,75 @@ pub(crate) async fn verify_message_in_block(
         }
     }
 }
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Lift the ban of a peer"),
+(status = 400, description = "Invalid peer id"),
+(status = 404, description = "Peer is not banned"),
+(status = 500, description = "Server failed to process request")),
+params(("peer_id", description = "Banned peer id")),
+)]
+#[delete("/ephemera/network/bans/{peer_id}")]
+pub(crate) async fn unban_peer(
+    peer_id: web::Path<String>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    let peer_id = match PeerId::from_str(&peer_id.into_inner()) {
+        Ok(peer_id) => peer_id,
+        Err(err) => {
+            debug!("Invalid peer id: {err}");
+            return HttpResponse::BadRequest().json("Invalid peer id");
+        }
+    };
+    match api.unban_peer(peer_id).await {
+        Ok(true) => HttpResponse::Ok().json("Peer unbanned"),
+        Ok(false) => HttpResponse::NotFound().json("Peer is not banned"),
+        Err(err) => {
+            error!("Error unbanning peer: {}", err);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Lift all bans. Returns the number of lifted bans"),
+(status = 500, description = "Server failed to process request")),
+)]
+#[delete("/ephemera/network/bans")]
+pub(crate) async fn clear_bans(api: web::Data<CommandExecutor>) -> HttpResponse {
+    match api.clear_bans().await {
+        Ok(cleared) => HttpResponse::Ok().json(cleared),
+        Err(err) => {
+            error!("Error clearing bans: {}", err);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Remove a DHT record from this node. Other peers keep their copies"),
+(status = 400, description = "Invalid key"),
+(status = 404, description = "Record is not stored by this node"),
+(status = 500, description = "Server failed to process request")),
+params(("key", description = "Dht key in hex format")),
+)]
+#[delete("/ephemera/dht/records/{key}")]
+pub(crate) async fn remove_dht_record(
+    key: web::Path<String>,
+    api: web::Data<CommandExecutor>,
+) -> HttpResponse {
+    let Ok(key) = hex2bytes(key.into_inner().as_str()) else {
+        return HttpResponse::BadRequest().json("Invalid key");
+    };
+    match api.remove_dht_record(key).await {
+        Ok(true) => HttpResponse::Ok().json("Record removed"),
+        Ok(false) => HttpResponse::NotFound().json("Record is not stored"),
+        Err(err) => {
+            error!("Error removing dht record: {}", err);
+            HttpResponse::InternalServerError().json("Server failed to process request")
+        }
+    }
+}
@@ -13,9 +13,14 @@ use tokio::sync::{
 };
 
 use crate::api::types::{
-    ApiBlock, ApiBlockBroadcastInfo, ApiBroadcastInfo, ApiCertificate, ApiEphemeraConfig,
-    ApiEphemeraMessage, ApiError, ApiVerifyMessageInBlock,
+    ApiBlock, ApiBlockBroadcastInfo, ApiBlockCertificates, ApiBlockManagerState, ApiBroadcastInfo,
+    ApiBroadcastProgress, ApiDeniedConnections, ApiDhtQueryRequest, ApiDhtQueryResponse,
+    ApiDhtStoreRequest, ApiEphemeraConfig, ApiEphemeraMessage, ApiError, ApiGroupSnapshot,
+    ApiMemberHealth, ApiPeerBan, ApiPeerInfo, ApiVerifyMessageInBlock,
 };
+use crate::config::DhtQuorum;
+use crate::core::channel::DEFAULT_CHANNEL;
+use crate::peer::PeerId;
 
 pub(crate) mod application;
 pub(crate) mod http;
@@ -27,47 +32,72 @@ pub(crate) type DhtKey = Vec<u8>;
 /// Kademlia DHT value
 pub(crate) type DhtValue = Vec<u8>;
 
-/// Kademlia DHT key/value pair
-pub(crate) type DhtKV = (DhtKey, DhtValue);
-
 pub(crate) type Result<T> = std::result::Result<T, ApiError>;
 
+/// Log target of admin actions, so that they can be kept apart as an audit log.
+pub(crate) const AUDIT_LOG_TARGET: &str = "ephemera::audit";
+
 #[derive(Debug)]
 pub(crate) enum ToEphemeraApiCmd {
-    SubmitEphemeraMessage(Box<ApiEphemeraMessage>, oneshot::Sender<Result<()>>),
-    QueryBlockByHeight(u64, oneshot::Sender<Result<Option<ApiBlock>>>),
-    QueryBlockByHash(String, oneshot::Sender<Result<Option<ApiBlock>>>),
-    QueryLastBlock(oneshot::Sender<Result<ApiBlock>>),
-    QueryBlockCertificates(String, oneshot::Sender<Result<Option<Vec<ApiCertificate>>>>),
-    QueryDht(DhtKey, oneshot::Sender<Result<Option<DhtKV>>>),
-    StoreInDht(DhtKey, DhtValue, oneshot::Sender<Result<()>>),
+    SubmitEphemeraMessage(String, Box<ApiEphemeraMessage>, oneshot::Sender<Result<()>>),
+    QueryBlockByHeight(String, u64, oneshot::Sender<Result<Option<ApiBlock>>>),
+    QueryBlockByHash(String, String, oneshot::Sender<Result<Option<ApiBlock>>>),
+    QueryLastBlock(String, oneshot::Sender<Result<ApiBlock>>),
+    QueryBlockCertificates(
+        String,
+        String,
+        oneshot::Sender<Result<Option<ApiBlockCertificates>>>,
+    ),
+    QueryDht(
+        DhtKey,
+        Option<DhtQuorum>,
+        oneshot::Sender<Result<Option<ApiDhtQueryResponse>>>,
+    ),
+    StoreInDht(ApiDhtStoreRequest, oneshot::Sender<Result<()>>),
     QueryEphemeraConfig(oneshot::Sender<Result<ApiEphemeraConfig>>),
     QueryBroadcastGroup(oneshot::Sender<Result<ApiBroadcastInfo>>),
+    QueryBroadcastProgress(oneshot::Sender<Result<ApiBroadcastProgress>>),
+    QueryGroupHistory(oneshot::Sender<Result<Vec<ApiGroupSnapshot>>>),
+    QueryGroupSnapshot(u64, oneshot::Sender<Result<Option<ApiGroupSnapshot>>>),
     QueryBlockBroadcastInfo(
         String,
         oneshot::Sender<Result<Option<ApiBlockBroadcastInfo>>>,
     ),
     VerifyMessageInBlock(String, String, usize, oneshot::Sender<Result<bool>>),
+    QueryBannedPeers(oneshot::Sender<Result<Vec<ApiPeerBan>>>),
+    UnbanPeer(PeerId, oneshot::Sender<Result<bool>>),
+    ClearBans(oneshot::Sender<Result<usize>>),
+    QueryDeniedConnections(oneshot::Sender<Result<ApiDeniedConnections>>),
+    QueryNetworkPeers(oneshot::Sender<Result<Vec<ApiPeerInfo>>>),
+    QueryMembersHealth(oneshot::Sender<Result<Vec<ApiMemberHealth>>>),
+    QueryDhtRecords(oneshot::Sender<Result<Vec<ApiDhtQueryResponse>>>),
+    RemoveDhtRecord(DhtKey, oneshot::Sender<Result<bool>>),
+    PauseBlockProduction(String, oneshot::Sender<Result<ApiBlockManagerState>>),
+    ResumeBlockProduction(String, oneshot::Sender<Result<ApiBlockManagerState>>),
+    ProduceBlock(String, oneshot::Sender<Result<ApiBlockManagerState>>),
+    SetBlockCreationInterval(String, u64, oneshot::Sender<Result<ApiBlockManagerState>>),
 }
 
 impl Display for ToEphemeraApiCmd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ToEphemeraApiCmd::SubmitEphemeraMessage(message, _) => {
-                write!(f, "SubmitEphemeraMessage({message})",)
+            ToEphemeraApiCmd::SubmitEphemeraMessage(channel, message, _) => {
+                write!(f, "SubmitEphemeraMessage({channel}, {message})",)
+            }
+            ToEphemeraApiCmd::QueryBlockByHeight(channel, height, _) => {
+                write!(f, "QueryBlockByHeight({channel}, {height})",)
             }
-            ToEphemeraApiCmd::QueryBlockByHeight(height, _) => {
-                write!(f, "QueryBlockByHeight({height})",)
+            ToEphemeraApiCmd::QueryBlockByHash(channel, hash, _) => {
+                write!(f, "QueryBlockByHash({channel}, {hash})",)
             }
-            ToEphemeraApiCmd::QueryBlockByHash(hash, _) => write!(f, "QueryBlockByHash({hash})",),
-            ToEphemeraApiCmd::QueryLastBlock(_) => write!(f, "QueryLastBlock"),
-            ToEphemeraApiCmd::QueryBlockCertificates(id, _) => {
-                write!(f, "QueryBlockSignatures{id}")
+            ToEphemeraApiCmd::QueryLastBlock(channel, _) => write!(f, "QueryLastBlock({channel})"),
+            ToEphemeraApiCmd::QueryBlockCertificates(channel, id, _) => {
+                write!(f, "QueryBlockSignatures({channel}, {id})")
             }
-            ToEphemeraApiCmd::QueryDht(_, _) => {
+            ToEphemeraApiCmd::QueryDht(_, _, _) => {
                 write!(f, "QueryDht")
             }
-            ToEphemeraApiCmd::StoreInDht(_, _, _) => {
+            ToEphemeraApiCmd::StoreInDht(_, _) => {
                 write!(f, "StoreInDht")
             }
             ToEphemeraApiCmd::QueryEphemeraConfig(_) => {
@@ -76,6 +106,11 @@ impl Display for ToEphemeraApiCmd {
             ToEphemeraApiCmd::QueryBroadcastGroup(_) => {
                 write!(f, "BroadcastGroup")
             }
+            ToEphemeraApiCmd::QueryBroadcastProgress(_) => {
+                write!(f, "BroadcastProgress")
+            }
+            ToEphemeraApiCmd::QueryGroupHistory(_) => write!(f, "QueryGroupHistory"),
+            ToEphemeraApiCmd::QueryGroupSnapshot(id, _) => write!(f, "QueryGroupSnapshot({id})"),
             ToEphemeraApiCmd::QueryBlockBroadcastInfo(hash, ..) => {
                 write!(f, "BlockBroadcastInfo({hash})")
             }
@@ -85,6 +120,24 @@ impl Display for ToEphemeraApiCmd {
                     "VerifyMessageInBlock({block_id}, {message_id}, {height})",
                 )
             }
+            ToEphemeraApiCmd::QueryBannedPeers(_) => write!(f, "QueryBannedPeers"),
+            ToEphemeraApiCmd::UnbanPeer(peer_id, _) => write!(f, "UnbanPeer({peer_id})"),
+            ToEphemeraApiCmd::ClearBans(_) => write!(f, "ClearBans"),
+            ToEphemeraApiCmd::QueryDeniedConnections(_) => write!(f, "QueryDeniedConnections"),
+            ToEphemeraApiCmd::QueryNetworkPeers(_) => write!(f, "QueryNetworkPeers"),
+            ToEphemeraApiCmd::QueryMembersHealth(_) => write!(f, "QueryMembersHealth"),
+            ToEphemeraApiCmd::QueryDhtRecords(_) => write!(f, "QueryDhtRecords"),
+            ToEphemeraApiCmd::RemoveDhtRecord(_, _) => write!(f, "RemoveDhtRecord"),
+            ToEphemeraApiCmd::PauseBlockProduction(channel, _) => {
+                write!(f, "PauseBlockProduction({channel})")
+            }
+            ToEphemeraApiCmd::ResumeBlockProduction(channel, _) => {
+                write!(f, "ResumeBlockProduction({channel})")
+            }
+            ToEphemeraApiCmd::ProduceBlock(channel, _) => write!(f, "ProduceBlock({channel})"),
+            ToEphemeraApiCmd::SetBlockCreationInterval(channel, interval_sec, _) => {
+                write!(f, "SetBlockCreationInterval({channel}, {interval_sec})")
+            }
         }
     }
 }
@@ -123,12 +176,36 @@ impl CommandExecutor {
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn get_block_by_id(&self, block_id: String) -> Result<Option<ApiBlock>> {
-        trace!("get_block_by_id({:?})", block_id);
-        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryBlockByHash(block_id, tx))
+        self.get_channel_block_by_id(DEFAULT_CHANNEL, block_id)
             .await
     }
 
-    /// Returns block with given height if it exists
+    /// Returns block of the channel with given id if it exists
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    /// * `block_id` - Block id
+    ///
+    /// # Returns
+    /// * `ApiBlock` - Block
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn get_channel_block_by_id(
+        &self,
+        channel: &str,
+        block_id: String,
+    ) -> Result<Option<ApiBlock>> {
+        trace!("get_channel_block_by_id({channel}, {block_id:?})");
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::QueryBlockByHash(channel.to_string(), block_id, tx)
+        })
+        .await
+    }
+
+    /// Returns block with given height if it exists. An observer of a cluster without a proposer
+    /// schedule has a block of every producer at each height, then it returns the first it stored.
     ///
     /// # Arguments
     /// * `height` - Block height
@@ -139,11 +216,34 @@ impl CommandExecutor {
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn get_block_by_height(&self, height: u64) -> Result<Option<ApiBlock>> {
-        trace!("get_block_by_height({:?})", height);
-        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryBlockByHeight(height, tx))
+        self.get_channel_block_by_height(DEFAULT_CHANNEL, height)
             .await
     }
 
+    /// Returns block of the channel with given height if it exists
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    /// * `height` - Block height
+    ///
+    /// # Returns
+    /// * `ApiBlock` - Block
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn get_channel_block_by_height(
+        &self,
+        channel: &str,
+        height: u64,
+    ) -> Result<Option<ApiBlock>> {
+        trace!("get_channel_block_by_height({channel}, {height:?})");
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::QueryBlockByHeight(channel.to_string(), height, tx)
+        })
+        .await
+    }
+
     /// Returns last block. Which has maximum height and is stored in database
     ///
     /// # Returns
@@ -152,8 +252,23 @@ impl CommandExecutor {
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn get_last_block(&self) -> Result<ApiBlock> {
-        trace!("get_last_block()");
-        self.send_and_wait_response(ToEphemeraApiCmd::QueryLastBlock)
+        self.get_channel_last_block(DEFAULT_CHANNEL).await
+    }
+
+    /// Returns last block of the channel
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    ///
+    /// # Returns
+    /// * `ApiBlock` - Last block
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn get_channel_last_block(&self, channel: &str) -> Result<ApiBlock> {
+        trace!("get_channel_last_block({channel})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryLastBlock(channel.to_string(), tx))
             .await
     }
 
@@ -163,38 +278,84 @@ impl CommandExecutor {
     /// * `block_hash` - Block id
     ///
     /// # Returns
-    /// * `Vec<ApiCertificate>` - Certificates
+    /// * `ApiBlockCertificates` - Certificates with the weights of the signers
     ///
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn get_block_certificates(
         &self,
         block_hash: String,
-    ) -> Result<Option<Vec<ApiCertificate>>> {
-        trace!("get_block_certificates({block_hash:?})",);
-        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryBlockCertificates(block_hash, tx))
+    ) -> Result<Option<ApiBlockCertificates>> {
+        self.get_channel_block_certificates(DEFAULT_CHANNEL, block_hash)
             .await
     }
 
+    /// Returns signatures for given block id of the channel
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    /// * `block_hash` - Block id
+    ///
+    /// # Returns
+    /// * `ApiBlockCertificates` - Certificates with the weights of the signers
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn get_channel_block_certificates(
+        &self,
+        channel: &str,
+        block_hash: String,
+    ) -> Result<Option<ApiBlockCertificates>> {
+        trace!("get_channel_block_certificates({channel}, {block_hash:?})",);
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::QueryBlockCertificates(channel.to_string(), block_hash, tx)
+        })
+        .await
+    }
+
     /// Queries DHT for given key
     ///
     /// # Arguments
     /// * `key` - DHT key
     ///
     /// # Errors
+    /// * `ApiError::DhtTimeout` - If the query timed out
+    /// * `ApiError::DhtQuorumFailed` - If not enough peers returned the record
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Returns
+    /// * `Some(ApiDhtQueryResponse)` - If a valid record is found, with its publisher and expiry
+    /// * `None` - If key is not found
+    pub async fn query_dht(&self, key: DhtKey) -> Result<Option<ApiDhtQueryResponse>> {
+        self.query_record_in_dht(ApiDhtQueryRequest::new(&key))
+            .await
+    }
+
+    /// Queries DHT for given key, with optional read quorum
+    ///
+    /// # Arguments
+    /// * `request` - Key and optional read quorum
+    ///
+    /// # Errors
+    /// * `ApiError::DhtTimeout` - If the query timed out
+    /// * `ApiError::DhtQuorumFailed` - If not enough peers returned the record
     /// * `ApiError::InternalError` - If there is an internal error
     ///
     /// # Returns
-    /// * `Some((key, value))` - If key is found
+    /// * `Some(ApiDhtQueryResponse)` - If a valid record is found, with its publisher and expiry
     /// * `None` - If key is not found
-    pub async fn query_dht(&self, key: DhtKey) -> Result<Option<(DhtKey, DhtValue)>> {
-        trace!("get_dht({key:?})");
-        //TODO: this needs timeout(somewhere around dht query functionality)
-        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryDht(key, tx))
+    pub async fn query_record_in_dht(
+        &self,
+        request: ApiDhtQueryRequest,
+    ) -> Result<Option<ApiDhtQueryResponse>> {
+        trace!("query_record_in_dht({request:?})");
+        let quorum = request.quorum();
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryDht(request.key(), quorum, tx))
             .await
     }
 
-    /// Stores given key-value pair in DHT
+    /// Stores given key-value pair in DHT, signed by the node and with the configured TTL
     ///
     /// # Arguments
     /// * `key` - DHT key
@@ -203,8 +364,54 @@ impl CommandExecutor {
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn store_in_dht(&self, key: DhtKey, value: DhtValue) -> Result<()> {
-        trace!("store_in_dht({key:?}, {value:?})");
-        self.send_and_wait_response(|tx| ToEphemeraApiCmd::StoreInDht(key, value, tx))
+        self.store_record_in_dht(ApiDhtStoreRequest::new(&key, &value))
+            .await
+    }
+
+    /// Stores a record in DHT, signed by the node. Returns when the record is stored
+    /// by the write quorum of peers.
+    ///
+    /// # Arguments
+    /// * `request` - Key, value, optional TTL, namespace and write quorum
+    ///
+    /// # Errors
+    /// * `ApiError::InvalidDhtRecord` - If the record can't be signed or is invalid
+    /// * `ApiError::DhtTimeout` - If the store timed out
+    /// * `ApiError::DhtQuorumFailed` - If not enough peers stored the record
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn store_record_in_dht(&self, request: ApiDhtStoreRequest) -> Result<()> {
+        trace!("store_record_in_dht({request:?})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::StoreInDht(request, tx))
+            .await
+    }
+
+    /// Returns DHT records stored by this node, its own and the records of other peers.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `Vec<ApiDhtQueryResponse>` - Stored records
+    pub async fn dht_records(&self) -> Result<Vec<ApiDhtQueryResponse>> {
+        trace!("dht_records()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryDhtRecords)
+            .await
+    }
+
+    /// Removes a DHT record from this node. Other peers keep their copies of the record.
+    ///
+    /// # Arguments
+    /// * `key` - DHT key
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `true` - If the node stored the record
+    /// * `false` - If the node didn't store the record
+    pub async fn remove_dht_record(&self, key: DhtKey) -> Result<bool> {
+        trace!("remove_dht_record({key:?})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::RemoveDhtRecord(key, tx))
             .await
     }
 
@@ -234,6 +441,49 @@ impl CommandExecutor {
             .await
     }
 
+    /// Returns live progress of the most recently active broadcasts together with
+    /// the state of the local block production.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBroadcastProgress` - Broadcast progress
+    pub async fn get_broadcast_progress(&self) -> Result<ApiBroadcastProgress> {
+        trace!("get_broadcast_progress()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryBroadcastProgress)
+            .await
+    }
+
+    /// Returns all broadcast group snapshots of the node, oldest first.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `Vec<ApiGroupSnapshot>` - Broadcast group snapshots
+    pub async fn get_group_history(&self) -> Result<Vec<ApiGroupSnapshot>> {
+        trace!("get_group_history()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryGroupHistory)
+            .await
+    }
+
+    /// Returns broadcast group snapshot by its id.
+    ///
+    /// # Arguments
+    /// * `id` - Snapshot id
+    ///
+    /// # Return
+    /// * `ApiGroupSnapshot` - Broadcast group snapshot
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn get_group_snapshot(&self, id: u64) -> Result<Option<ApiGroupSnapshot>> {
+        trace!("get_group_snapshot({id})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::QueryGroupSnapshot(id, tx))
+            .await
+    }
+
     /// Returns block broadcast info.
     ///
     /// # Arguments
@@ -261,9 +511,26 @@ impl CommandExecutor {
     /// # Errors
     /// * `ApiError::InternalError` - If there is an internal error
     pub async fn send_ephemera_message(&self, message: ApiEphemeraMessage) -> Result<()> {
-        trace!("send_ephemera_message({message})",);
+        self.send_channel_message(DEFAULT_CHANNEL, message).await
+    }
+
+    /// Send a message to a channel, to be included in its mempool and broadcast to all peers
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    /// * `message` - Message to be sent
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    pub async fn send_channel_message(
+        &self,
+        channel: &str,
+        message: ApiEphemeraMessage,
+    ) -> Result<()> {
+        trace!("send_channel_message({channel}, {message})",);
         self.send_and_wait_response(|tx| {
-            ToEphemeraApiCmd::SubmitEphemeraMessage(message.into(), tx)
+            ToEphemeraApiCmd::SubmitEphemeraMessage(channel.to_string(), message.into(), tx)
         })
         .await
     }
@@ -288,6 +555,244 @@ impl CommandExecutor {
         .await
     }
 
+    /// Returns peers which are currently banned by the network.
+    ///
+    /// Peers get banned temporarily when they exceed rate limits or send invalid messages or blocks.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `Vec<ApiPeerBan>` - Banned peers
+    pub async fn banned_peers(&self) -> Result<Vec<ApiPeerBan>> {
+        trace!("banned_peers()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryBannedPeers)
+            .await
+    }
+
+    /// Lifts the ban of a peer.
+    ///
+    /// # Arguments
+    /// * `peer_id` - Banned peer
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `true` - If the peer was banned
+    /// * `false` - If the peer wasn't banned
+    pub async fn unban_peer(&self, peer_id: PeerId) -> Result<bool> {
+        trace!("unban_peer({peer_id})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::UnbanPeer(peer_id, tx))
+            .await
+    }
+
+    /// Lifts all bans.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `usize` - Number of lifted bans
+    pub async fn clear_bans(&self) -> Result<usize> {
+        trace!("clear_bans()");
+        self.send_and_wait_response(ToEphemeraApiCmd::ClearBans)
+            .await
+    }
+
+    /// Returns the number of connections denied because the peer is not a member.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiDeniedConnections` - Inbound and outbound denied connections
+    pub async fn denied_connections(&self) -> Result<ApiDeniedConnections> {
+        trace!("denied_connections()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryDeniedConnections)
+            .await
+    }
+
+    /// Returns peers which are connected to the node.
+    ///
+    /// Peers are pinged regularly. A peer whose last ping failed is unhealthy and doesn't count
+    /// as online when the node decides if the membership is acceptable.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `Vec<ApiPeerInfo>` - Connected peers with their latency and versions
+    pub async fn network_peers(&self) -> Result<Vec<ApiPeerInfo>> {
+        trace!("network_peers()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryNetworkPeers)
+            .await
+    }
+
+    /// Returns the members of the current membership with their health.
+    ///
+    /// When the health filter is enabled, members which fail consecutive health checks are excluded
+    /// from the effective membership and the broadcast group until they recover.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `Vec<ApiMemberHealth>` - Members with their health and exclusion reasons
+    pub async fn members_health(&self) -> Result<Vec<ApiMemberHealth>> {
+        trace!("members_health()");
+        self.send_and_wait_response(ToEphemeraApiCmd::QueryMembersHealth)
+            .await
+    }
+
+    /// Pauses block production until it's resumed, also when the node joins a new broadcast group.
+    /// The node keeps taking part in reliable broadcast of other nodes' blocks.
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production after the change
+    pub async fn pause_block_production(&self) -> Result<ApiBlockManagerState> {
+        self.pause_channel_block_production(DEFAULT_CHANNEL).await
+    }
+
+    /// Pauses block production of the channel, see [`CommandExecutor::pause_block_production`].
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production of the channel after the change
+    pub async fn pause_channel_block_production(
+        &self,
+        channel: &str,
+    ) -> Result<ApiBlockManagerState> {
+        trace!("pause_channel_block_production({channel})");
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::PauseBlockProduction(channel.to_string(), tx)
+        })
+        .await
+    }
+
+    /// Resumes block production paused by [`CommandExecutor::pause_block_production`].
+    ///
+    /// # Errors
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production after the change
+    pub async fn resume_block_production(&self) -> Result<ApiBlockManagerState> {
+        self.resume_channel_block_production(DEFAULT_CHANNEL).await
+    }
+
+    /// Resumes block production of the channel paused by
+    /// [`CommandExecutor::pause_channel_block_production`].
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production of the channel after the change
+    pub async fn resume_channel_block_production(
+        &self,
+        channel: &str,
+    ) -> Result<ApiBlockManagerState> {
+        trace!("resume_channel_block_production({channel})");
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::ResumeBlockProduction(channel.to_string(), tx)
+        })
+        .await
+    }
+
+    /// Produces the next block immediately instead of waiting for the interval.
+    ///
+    /// With a proposer schedule the node has to be the proposer of the next block.
+    ///
+    /// # Errors
+    /// * `ApiError::BlockProductionUnavailable` - If the node is not a producer, production is paused
+    ///   or it's not the turn of the node
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production
+    pub async fn produce_block(&self) -> Result<ApiBlockManagerState> {
+        self.produce_channel_block(DEFAULT_CHANNEL).await
+    }
+
+    /// Produces the next block of the channel immediately, see [`CommandExecutor::produce_block`].
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::BlockProductionUnavailable` - If the node is not a producer, production is paused
+    ///   or it's not the turn of the node
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production of the channel
+    pub async fn produce_channel_block(&self, channel: &str) -> Result<ApiBlockManagerState> {
+        trace!("produce_channel_block({channel})");
+        self.send_and_wait_response(|tx| ToEphemeraApiCmd::ProduceBlock(channel.to_string(), tx))
+            .await
+    }
+
+    /// Changes the interval of block creation until the node restarts. The next block is produced
+    /// after the new interval.
+    ///
+    /// # Arguments
+    /// * `interval_sec` - Interval in seconds
+    ///
+    /// # Errors
+    /// * `ApiError::InvalidArgument` - If the interval is zero or longer than a day
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production after the change
+    pub async fn set_block_creation_interval(
+        &self,
+        interval_sec: u64,
+    ) -> Result<ApiBlockManagerState> {
+        self.set_channel_block_creation_interval(DEFAULT_CHANNEL, interval_sec)
+            .await
+    }
+
+    /// Changes the interval of block creation of the channel, see
+    /// [`CommandExecutor::set_block_creation_interval`].
+    ///
+    /// # Arguments
+    /// * `channel` - Channel name
+    /// * `interval_sec` - Interval in seconds
+    ///
+    /// # Errors
+    /// * `ApiError::UnknownChannel` - If the channel is not configured
+    /// * `ApiError::InvalidArgument` - If the interval is zero or longer than a day
+    /// * `ApiError::InternalError` - If there is an internal error
+    ///
+    /// # Return
+    /// * `ApiBlockManagerState` - State of block production of the channel after the change
+    pub async fn set_channel_block_creation_interval(
+        &self,
+        channel: &str,
+        interval_sec: u64,
+    ) -> Result<ApiBlockManagerState> {
+        trace!("set_channel_block_creation_interval({channel}, {interval_sec})");
+        self.send_and_wait_response(|tx| {
+            ToEphemeraApiCmd::SetBlockCreationInterval(channel.to_string(), interval_sec, tx)
+        })
+        .await
+    }
+
     async fn send_and_wait_response<F, R>(&self, f: F) -> Result<R>
     where
         F: FnOnce(oneshot::Sender<Result<R>>) -> ToEphemeraApiCmd,
@@ -4,6 +4,7 @@
 //! - `RawApiEphemeraMessage`
 //! - `ApiBlock`
 //! - `ApiCertificate`
+//! - `ApiBlockCertificates`
 //! - `Health`
 //! - `ApiError`
 //! - `ApiEphemeraConfig`
@@ -12,10 +13,16 @@
 //! - `ApiDhtStoreRequest`
 //! - `ApiBroadcastInfo`
 //! - `ApiBlockBroadcastInfo`
+//! - `ApiBroadcastProgress`
 //! - `ApiVerifyMessageInBlock`
+//! - `ApiPeerBan`
+//! - `ApiDeniedConnections`
+//! - `ApiPeerInfo`
+//! - `ApiBlockCreationInterval`
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::time::Duration;
 
 use array_bytes::{bytes2hex, hex2bytes};
 use log::error;
@@ -23,13 +30,26 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use utoipa::ToSchema;
 
+use crate::config::{DhtQuorum, QuorumPolicy};
 use crate::peer::{PeerId, ToPeerId};
 use crate::utilities::codec::{Codec, DecodingError, EncodingError, EphemeraCodec};
 use crate::{
     block::types::{block::Block, block::BlockHeader, message::EphemeraMessage},
+    broadcast::ProtocolContext,
     codec::{Decode, Encode},
     crypto::{Keypair, PublicKey},
     ephemera_api,
+    network::libp2p::{
+        ban_list::Ban,
+        behaviours::membership::health::{ExclusionReason, MemberHealth},
+        dht::{
+            record::{namespaced_key, SignedRecord},
+            DhtError,
+        },
+        peers::PeerStats,
+        versions::NegotiatedVersions,
+    },
+    storage::StoredGroupSnapshot,
     utilities::{
         crypto::{Certificate, Signature},
         time::EphemeraTime,
@@ -46,10 +66,33 @@ pub enum ApiError {
     InvalidHash(String),
     #[error("ApplicationError: {0}")]
     Application(#[from] ephemera_api::ApplicationError),
+    #[error("DHT request timed out")]
+    DhtTimeout,
+    #[error("DHT quorum failed: {0}")]
+    DhtQuorumFailed(String),
+    #[error("Invalid DHT record: {0}")]
+    InvalidDhtRecord(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("Block can't be produced now: {0}")]
+    BlockProductionUnavailable(String),
+    #[error("Unknown channel: {0}")]
+    UnknownChannel(String),
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+impl ApiError {
+    pub(crate) fn from_dht_error(err: DhtError) -> Self {
+        match err {
+            DhtError::Timeout => ApiError::DhtTimeout,
+            DhtError::QuorumFailed { .. } => ApiError::DhtQuorumFailed(err.to_string()),
+            DhtError::InvalidRecord(reason) => ApiError::InvalidDhtRecord(reason),
+            DhtError::Failed(reason) => ApiError::Internal(reason),
+        }
+    }
+}
+
 /// # Ephemera message.
 ///
 /// A message submitted to an Ephemera node will be gossiped to other nodes.
@@ -157,6 +200,12 @@ pub struct ApiBlockHeader {
     pub height: u64,
     /// The hash of the current block.
     pub hash: String,
+    /// The channel of the block. Not set for the default channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// The proposer schedule round of the block. Not set without a schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
@@ -177,6 +226,24 @@ pub struct ApiCertificate {
     pub public_key: ApiPublicKey,
 }
 
+/// Certificate of a block signer together with its voting weight.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBlockCertificate {
+    pub certificate: ApiCertificate,
+    /// The voting weight of the signer in the broadcast group of the block, zero for non-members.
+    pub weight: u64,
+}
+
+/// Certificates of a block and the voting weight they represent.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBlockCertificates {
+    pub certificates: Vec<ApiBlockCertificate>,
+    /// The sum of the voting weights of the signers.
+    pub signed_weight: u64,
+    /// The sum of the voting weights of the broadcast group of the block.
+    pub total_weight: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
 pub struct ApiSignature(pub(crate) Signature);
 
@@ -209,12 +276,54 @@ pub struct ApiEphemeraConfig {
     pub block_producer: bool,
     /// The interval of block creation in seconds. It's a configuration option.
     pub block_creation_interval_sec: u64,
+    /// The quorum policy of reliable broadcast. It's a configuration option.
+    pub quorum_policy: ApiQuorumPolicy,
+    /// True if the node only follows blocks delivered by members. It's a configuration option.
+    pub observer: bool,
+    /// Names of the message channels of the node, the default one included.
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+/// Quorum policy of reliable broadcast. Thresholds are expressed in member weights.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ApiQuorumPolicy {
+    /// Byzantine fault tolerance, f < n/3.
+    Bft,
+    /// Crash fault tolerance, f < n/2.
+    CrashFault,
+    /// Explicit echo, vote and deliver thresholds.
+    Explicit { echo: u64, vote: u64, deliver: u64 },
+}
+
+impl From<QuorumPolicy> for ApiQuorumPolicy {
+    fn from(policy: QuorumPolicy) -> Self {
+        match policy {
+            QuorumPolicy::Bft => ApiQuorumPolicy::Bft,
+            QuorumPolicy::CrashFault => ApiQuorumPolicy::CrashFault,
+            QuorumPolicy::Explicit {
+                echo,
+                vote,
+                deliver,
+            } => ApiQuorumPolicy::Explicit {
+                echo,
+                vote,
+                deliver,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
 pub struct ApiDhtQueryRequest {
     /// The key to query for in hex format.
     key: String,
+    /// How many peers need to return the record, `one`, `majority`, `all` or a number.
+    /// Node's configured read quorum is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    quorum: Option<DhtQuorum>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
@@ -223,6 +332,10 @@ pub struct ApiDhtQueryResponse {
     key: String,
     /// The value that was stored under the queried key in hex format.
     value: String,
+    /// The public key of the peer who published and signed the record.
+    publisher: ApiPublicKey,
+    /// When the record expires, unix timestamp in milliseconds.
+    expires_at: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
@@ -242,6 +355,18 @@ pub struct ApiDhtStoreRequest {
     key: String,
     /// The value to store in hex format.
     value: String,
+    /// How long the record is stored, in seconds. Node's configured TTL is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl_sec: Option<u64>,
+    /// Stores the record in the namespace of the node's public key, `/ns/<public key>/<key>`.
+    /// Only the node can publish records in its namespace.
+    #[serde(default)]
+    namespaced: bool,
+    /// How many peers need to store the record, `one`, `majority`, `all` or a number.
+    /// Node's configured write quorum is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    quorum: Option<DhtQuorum>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
@@ -250,12 +375,37 @@ pub struct ApiBroadcastInfo {
     pub local_peer_id: PeerId,
     /// The list of the current members of the network.
     pub current_members: HashSet<PeerId>,
+    /// The voting weights of the current members.
+    pub weights: HashMap<PeerId, u64>,
+    /// The sum of the voting weights of the current members.
+    pub total_weight: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
 pub struct ApiBlockBroadcastInfo {
     pub local_peer_id: PeerId,
     pub broadcast_group: Vec<PeerId>,
+    /// The voting weights of the members which took part in the block broadcast.
+    pub weights: HashMap<PeerId, u64>,
+    /// The sum of the voting weights of the broadcast group.
+    pub total_weight: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiGroupSnapshot {
+    /// The id of the snapshot. Incremented every time the broadcast group changes.
+    pub id: u64,
+    /// The time in milliseconds when the snapshot became the current group.
+    pub timestamp: u64,
+    /// The height of the last local block committed before the snapshot became the current group.
+    /// Later local blocks were broadcast in this group until the next snapshot.
+    pub activation_height: u64,
+    /// The members of the group, sorted.
+    pub members: Vec<PeerId>,
+    /// The voting weights of the members.
+    pub weights: HashMap<PeerId, u64>,
+    /// The sum of the voting weights of the members.
+    pub total_weight: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
@@ -286,20 +436,321 @@ impl Display for ApiVerifyMessageInBlock {
     }
 }
 
+//Weights are validated by the broadcast quorum, but groups which failed it are reported too
+fn total_weight(weights: &HashMap<PeerId, u64>) -> u64 {
+    weights
+        .values()
+        .fold(0, |total, weight| total.saturating_add(*weight))
+}
+
+impl ApiBlockCertificates {
+    pub(crate) fn new(certificates: Vec<Certificate>, weights: &HashMap<PeerId, u64>) -> Self {
+        let certificates = certificates
+            .into_iter()
+            .map(|certificate| ApiBlockCertificate {
+                weight: weights
+                    .get(&certificate.public_key.peer_id())
+                    .copied()
+                    .unwrap_or_default(),
+                certificate: certificate.into(),
+            })
+            .collect::<Vec<_>>();
+        Self {
+            signed_weight: certificates
+                .iter()
+                .fold(0, |total, signer| total.saturating_add(signer.weight)),
+            total_weight: total_weight(weights),
+            certificates,
+        }
+    }
+}
+
 impl ApiBlockBroadcastInfo {
-    pub(crate) fn new(local_peer_id: PeerId, broadcast_group: Vec<PeerId>) -> Self {
+    pub(crate) fn new(local_peer_id: PeerId, weights: HashMap<PeerId, u64>) -> Self {
         Self {
             local_peer_id,
-            broadcast_group,
+            broadcast_group: weights.keys().copied().collect(),
+            total_weight: total_weight(&weights),
+            weights,
+        }
+    }
+}
+
+impl From<StoredGroupSnapshot> for ApiGroupSnapshot {
+    fn from(snapshot: StoredGroupSnapshot) -> Self {
+        let mut members = snapshot.members.keys().copied().collect::<Vec<_>>();
+        members.sort_by_key(ToString::to_string);
+        Self {
+            id: snapshot.id,
+            timestamp: snapshot.timestamp,
+            activation_height: snapshot.activation_height,
+            members,
+            total_weight: total_weight(&snapshot.members),
+            weights: snapshot.members,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub enum ApiBlockManagerStatus {
+    /// Node produces blocks at configured interval.
+    Running,
+    /// Block production is paused, for example because the node is not part of the broadcast group.
+    Paused,
+    /// Block production is paused by an admin until it's resumed.
+    PausedByAdmin,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiPendingBlock {
+    /// The hash of the block.
+    pub hash: String,
+    /// The height of the block.
+    pub height: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBlockManagerState {
+    /// True if the node is configured to produce blocks.
+    pub producer: bool,
+    /// Whether block production is running or paused.
+    pub status: ApiBlockManagerStatus,
+    /// The current interval of block creation in seconds.
+    pub creation_interval_sec: u64,
+    /// The last block produced by the local node which is not committed yet.
+    pub pending_block: Option<ApiPendingBlock>,
+    /// The number of backoff attempts made while the pending block is not committed.
+    pub backoff_attempt: Option<u32>,
+}
+
+/// New interval of block creation. It applies until the node restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBlockCreationInterval {
+    /// Interval in seconds, from one second to one day.
+    pub interval_sec: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBlockBroadcastProgress {
+    /// The hash of the block.
+    pub hash: String,
+    /// The id of the broadcast group snapshot the block is broadcast in.
+    pub group_id: u64,
+    /// Peers who have echoed the block.
+    pub echoed: Vec<PeerId>,
+    /// Peers who have voted for the block.
+    pub voted: Vec<PeerId>,
+    /// The sum of the weights of the peers who have echoed the block.
+    pub echo_weight: u64,
+    /// The sum of the weights of the peers who have voted for the block.
+    pub vote_weight: u64,
+    /// The weight of echoes needed to vote.
+    pub echo_threshold: u64,
+    /// The weight of votes needed to vote.
+    pub vote_threshold: u64,
+    /// The weight of votes needed to deliver the block.
+    pub deliver_threshold: u64,
+    /// The total weight of the broadcast group.
+    pub total_weight: u64,
+    /// Milliseconds since the block was seen first time.
+    pub elapsed_ms: u64,
+    /// True if the local node has echoed the block.
+    pub local_echoed: bool,
+    /// True if the local node has voted for the block.
+    pub local_voted: bool,
+    /// True if the block has been delivered.
+    pub delivered: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiBroadcastProgress {
+    /// The `PeerId` of the local node.
+    pub local_peer_id: PeerId,
+    /// The state of the local block production.
+    pub block_manager: ApiBlockManagerState,
+    /// The most recently active broadcasts, including delivered ones.
+    pub blocks: Vec<ApiBlockBroadcastProgress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ApiPeerBan {
+    /// The banned peer.
+    pub peer_id: PeerId,
+    /// Why the peer was banned.
+    pub reason: String,
+    /// When the ban started, unix timestamp in milliseconds.
+    pub banned_at: u64,
+    /// When the ban expires, unix timestamp in milliseconds.
+    pub expires_at: u64,
+}
+
+/// Number of connections denied because the peer is not a member, since the node started.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct ApiDeniedConnections {
+    /// Denied connections initiated by other peers.
+    pub inbound: u64,
+    /// Denied connections initiated by this node.
+    pub outbound: u64,
+}
+
+/// A connected peer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct ApiPeerInfo {
+    /// The connected peer.
+    pub peer_id: PeerId,
+    /// Remote address of the connection.
+    pub address: String,
+    /// When the peer connected, unix timestamp in milliseconds.
+    pub connected_at: u64,
+    /// Round-trip time of the last successful ping in milliseconds.
+    pub rtt_ms: Option<u64>,
+    /// Consecutive failed pings since the last successful one.
+    pub ping_failures: u32,
+    /// False if the last ping failed. Unhealthy peers don't count as online for membership.
+    pub healthy: bool,
+    /// Agent version the peer reported, e.g. `ephemera/0.1.0`.
+    pub agent_version: Option<String>,
+    /// Protocol version the peer reported.
+    pub protocol_version: Option<String>,
+    /// Protocols the peer supports.
+    pub protocols: Vec<String>,
+    /// Broadcast protocol negotiated with the peer, the highest version both nodes support.
+    pub broadcast_protocol: Option<String>,
+    /// Membership protocol negotiated with the peer, the highest version both nodes support.
+    pub membership_protocol: Option<String>,
+    /// False if the peer has no common broadcast or membership protocol version with this node.
+    /// Unknown until the peer is identified.
+    pub compatible: Option<bool>,
+}
+
+/// Health of a member of the current membership, as seen by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct ApiMemberHealth {
+    /// The member.
+    pub peer_id: PeerId,
+    /// False if the health filter excluded the member from the effective membership.
+    pub included: bool,
+    /// Why the member is excluded.
+    pub exclusion_reason: Option<ApiExclusionReason>,
+    /// Why the last health check failed. None if it passed or the health filter is disabled.
+    pub last_failure: Option<ApiExclusionReason>,
+    /// Consecutive failed health checks.
+    pub consecutive_failures: u32,
+    /// Consecutive successful health checks.
+    pub consecutive_successes: u32,
+    /// Unix timestamp in milliseconds of the last heartbeat from the member.
+    pub last_heartbeat: Option<u64>,
+}
+
+/// Why a member failed a health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub enum ApiExclusionReason {
+    /// There is no connection with the member.
+    Disconnected,
+    /// The last ping to the member failed.
+    PingFailed,
+    /// The member hasn't sent a heartbeat in time.
+    HeartbeatMissed,
+}
+
+impl From<ExclusionReason> for ApiExclusionReason {
+    fn from(reason: ExclusionReason) -> Self {
+        match reason {
+            ExclusionReason::Disconnected => ApiExclusionReason::Disconnected,
+            ExclusionReason::PingFailed => ApiExclusionReason::PingFailed,
+            ExclusionReason::HeartbeatMissed => ApiExclusionReason::HeartbeatMissed,
+        }
+    }
+}
+
+impl ApiMemberHealth {
+    pub(crate) fn new(peer_id: PeerId, health: &MemberHealth) -> Self {
+        Self {
+            peer_id,
+            included: health.excluded.is_none(),
+            exclusion_reason: health.excluded.map(Into::into),
+            last_failure: health.last_failure.map(Into::into),
+            consecutive_failures: health.consecutive_failures,
+            consecutive_successes: health.consecutive_successes,
+            last_heartbeat: health.last_heartbeat,
+        }
+    }
+}
+
+impl ApiBlockBroadcastProgress {
+    pub(crate) fn new(ctx: &ProtocolContext) -> Self {
+        let elapsed_ms = u64::try_from(ctx.started_at.elapsed().as_millis()).unwrap_or(u64::MAX);
+        Self {
+            hash: ctx.hash.to_string(),
+            group_id: ctx.group_id,
+            echoed: ctx.echo.iter().copied().collect(),
+            voted: ctx.vote.iter().copied().collect(),
+            echo_weight: ctx.quorum.weight_of(&ctx.echo),
+            vote_weight: ctx.quorum.weight_of(&ctx.vote),
+            echo_threshold: ctx.quorum.echo_threshold(),
+            vote_threshold: ctx.quorum.vote_threshold(),
+            deliver_threshold: ctx.quorum.deliver_threshold(),
+            total_weight: ctx.quorum.total_weight,
+            elapsed_ms,
+            local_echoed: ctx.echoed(),
+            local_voted: ctx.voted(),
+            delivered: ctx.delivered,
+        }
+    }
+}
+
+impl From<SignedRecord> for ApiDhtQueryResponse {
+    fn from(record: SignedRecord) -> Self {
+        let publisher = record.publisher().clone();
+        Self::new(record.key, record.value, publisher, record.expires_at)
+    }
+}
+
+impl ApiPeerBan {
+    pub(crate) fn new(peer_id: PeerId, ban: &Ban) -> Self {
+        Self {
+            peer_id,
+            reason: ban.reason.to_string(),
+            banned_at: ban.banned_at,
+            expires_at: ban.expires_at,
+        }
+    }
+}
+
+impl ApiPeerInfo {
+    pub(crate) fn new(peer_id: PeerId, stats: PeerStats) -> Self {
+        Self {
+            peer_id,
+            healthy: stats.is_healthy(),
+            broadcast_protocol: stats
+                .versions
+                .and_then(|versions| versions.broadcast)
+                .map(|version| version.broadcast_protocol().to_string()),
+            membership_protocol: stats
+                .versions
+                .and_then(|versions| versions.membership)
+                .map(|version| version.protocol().to_string()),
+            compatible: stats.versions.map(NegotiatedVersions::is_compatible),
+            address: stats.address,
+            connected_at: stats.connected_at,
+            rtt_ms: stats
+                .rtt
+                .map(|rtt| u64::try_from(rtt.as_millis()).unwrap_or(u64::MAX)),
+            ping_failures: stats.ping_failures,
+            agent_version: stats.agent_version,
+            protocol_version: stats.protocol_version,
+            protocols: stats.protocols,
         }
     }
 }
 
 impl ApiBroadcastInfo {
-    pub(crate) fn new(current_members: HashSet<PeerId>, local_peer_id: PeerId) -> Self {
+    pub(crate) fn new(weights: HashMap<PeerId, u64>, local_peer_id: PeerId) -> Self {
         Self {
             local_peer_id,
-            current_members,
+            current_members: weights.keys().copied().collect(),
+            total_weight: total_weight(&weights),
+            weights,
         }
     }
 }
@@ -309,8 +760,8 @@ impl Display for ApiBroadcastInfo {
         let current_members = self.current_members.iter().map(ToString::to_string);
         write!(
             f,
-            "{{ local_peer_id: {}, current_members: {current_members:?} }}",
-            self.local_peer_id,
+            "{{ local_peer_id: {}, current_members: {current_members:?}, total_weight: {} }}",
+            self.local_peer_id, self.total_weight,
         )
     }
 }
@@ -520,6 +971,8 @@ impl From<Block> for ApiBlock {
                 creator: block.header.creator,
                 height: block.header.height,
                 hash: block.header.hash.to_string(),
+                channel: block.header.channel,
+                round: block.header.round,
             },
             messages: block.messages.into_iter().map(Into::into).collect(),
         }
@@ -544,6 +997,8 @@ impl TryFrom<ApiBlock> for Block {
                     error!("Failed to parse block hash: {}", e);
                     ApiError::Internal("Failed to parse block hash".to_string())
                 })?,
+                channel: api_block.header.channel,
+                round: api_block.header.round,
             },
             messages,
         })
@@ -555,7 +1010,34 @@ impl ApiDhtStoreRequest {
     pub fn new(key: &[u8], value: &[u8]) -> Self {
         let key = bytes2hex("0x", key);
         let value = bytes2hex("0x", value);
-        Self { key, value }
+        Self {
+            key,
+            value,
+            ttl_sec: None,
+            namespaced: false,
+            quorum: None,
+        }
+    }
+
+    /// Sets the write quorum of the store.
+    #[must_use]
+    pub fn with_quorum(mut self, quorum: DhtQuorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Sets the TTL of the record.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_sec = Some(ttl.as_secs());
+        self
+    }
+
+    /// Stores the record in the namespace of the node's public key.
+    #[must_use]
+    pub fn namespaced(mut self) -> Self {
+        self.namespaced = true;
+        self
     }
 
     #[allow(clippy::missing_panics_doc)]
@@ -571,13 +1053,46 @@ impl ApiDhtStoreRequest {
         //We can unwrap here because the value is always valid.
         hex2bytes(&self.value).unwrap()
     }
+
+    #[must_use]
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl_sec.map(Duration::from_secs)
+    }
+
+    #[must_use]
+    pub fn is_namespaced(&self) -> bool {
+        self.namespaced
+    }
+
+    #[must_use]
+    pub fn quorum(&self) -> Option<DhtQuorum> {
+        self.quorum
+    }
 }
 
 impl ApiDhtQueryRequest {
     #[must_use]
     pub fn new(key: &[u8]) -> Self {
         let key = bytes2hex("0x", key);
-        Self { key }
+        Self { key, quorum: None }
+    }
+
+    /// Sets the read quorum of the query.
+    #[must_use]
+    pub fn with_quorum(mut self, quorum: DhtQuorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    #[must_use]
+    pub fn quorum(&self) -> Option<DhtQuorum> {
+        self.quorum
+    }
+
+    /// Query for a key in the namespace of the public key.
+    #[must_use]
+    pub fn namespaced(owner: &PublicKey, key: &[u8]) -> Self {
+        Self::new(&namespaced_key(owner, key))
     }
 
     #[must_use]
@@ -598,10 +1113,26 @@ impl ApiDhtQueryRequest {
 }
 
 impl ApiDhtQueryResponse {
-    pub(crate) fn new(key: Vec<u8>, value: Vec<u8>) -> Self {
+    pub(crate) fn new(key: Vec<u8>, value: Vec<u8>, publisher: PublicKey, expires_at: u64) -> Self {
         let key = bytes2hex("0x", key);
         let value = bytes2hex("0x", value);
-        Self { key, value }
+        Self {
+            key,
+            value,
+            publisher: ApiPublicKey(publisher),
+            expires_at,
+        }
+    }
+
+    #[must_use]
+    pub fn publisher(&self) -> PublicKey {
+        self.publisher.0.clone()
+    }
+
+    /// Unix timestamp in milliseconds.
+    #[must_use]
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
     }
 
     #[allow(clippy::missing_panics_doc)]
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::{sync::Arc, time::Duration};
 
 use log::{debug, info};
@@ -10,6 +10,7 @@ use crate::{
         manager::{BlockChainState, BlockManager},
         message_pool::MessagePool,
         producer::BlockProducer,
+        schedule::ProposerRotation,
         types::block::Block,
     },
     broadcast::signing::BlockSigner,
@@ -34,6 +35,12 @@ impl BlockManagerBuilder {
         }
     }
 
+    /// Blocks of the channel get its name in the header.
+    pub(crate) fn with_channel(mut self, channel: Option<String>) -> Self {
+        self.block_producer.channel = channel;
+        self
+    }
+
     pub(crate) fn build<D: EphemeraDatabase + ?Sized>(
         self,
         storage: &mut D,
@@ -47,7 +54,7 @@ impl BlockManagerBuilder {
             info!("No last block found in database. Creating genesis block.");
 
             let genesis_block = Block::new_genesis_block(self.block_producer.peer_id);
-            storage.store_block(&genesis_block, HashSet::new(), HashSet::new())?;
+            storage.store_block(&genesis_block, HashSet::new(), HashMap::new())?;
             most_recent_block = Some(genesis_block);
         }
 
@@ -60,6 +67,8 @@ impl BlockManagerBuilder {
         let block_creation_interval =
             tokio::time::interval(Duration::from_secs(self.config.creation_interval_sec));
 
+        let proposer_rotation = ProposerRotation::new(&self.config.proposer_schedule);
+
         Ok(BlockManager {
             config: self.config,
             block_producer: self.block_producer,
@@ -69,6 +78,9 @@ impl BlockManagerBuilder {
             state: State::Paused,
             backoff: None,
             block_creation_interval,
+            proposer_rotation,
+            admin_paused: false,
+            produce_now: false,
         })
     }
 }
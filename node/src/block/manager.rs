@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::task::Poll;
 use std::time::Duration;
@@ -25,6 +25,7 @@ use crate::{
     block::{
         message_pool::MessagePool,
         producer::BlockProducer,
+        schedule::ProposerRotation,
         types::{block::Block, message::EphemeraMessage},
     },
     broadcast::signing::BlockSigner,
@@ -38,6 +39,8 @@ pub(crate) type Result<T> = std::result::Result<T, BlockManagerError>;
 pub(crate) enum BlockManagerError {
     #[error("Message is already in pool: {0}")]
     DuplicateMessage(String),
+    #[error("Block can't be produced now: {0}")]
+    ProductionUnavailable(String),
     //Just a placeholder for now
     #[error("BlockManagerError: {0}")]
     BlockManager(#[from] anyhow::Error),
@@ -166,6 +169,12 @@ pub(crate) struct BlockManager {
     pub(crate) block_chain_state: BlockChainState,
     /// Current state of the block manager
     pub(crate) state: State,
+    /// Designates the proposer of each height, if producers take turns.
+    pub(crate) proposer_rotation: Option<ProposerRotation>,
+    /// Block production is paused by an admin, group updates don't resume it.
+    pub(crate) admin_paused: bool,
+    /// Next block is produced without waiting for the interval.
+    pub(crate) produce_now: bool,
 }
 
 impl BlockManager {
@@ -201,6 +210,15 @@ impl BlockManager {
             return Err(anyhow!("Block hash is invalid: {} != {hash}", block.header.hash).into());
         }
 
+        if block.header.channel != self.block_producer.channel {
+            return Err(anyhow!(
+                "Block of channel {:?} sent to channel {:?}",
+                block.header.channel,
+                self.block_producer.channel
+            )
+            .into());
+        }
+
         //Block signer should be also its sender
         let signer_peer_id = certificate.public_key.peer_id();
         if *sender != signer_peer_id {
@@ -289,6 +307,94 @@ impl BlockManager {
         Ok(())
     }
 
+    /// With a proposer schedule all members commit every delivered block, whoever proposed it.
+    ///
+    /// Pending local block at the same or lower height is dropped, its messages stay in the pool.
+    pub(crate) fn on_block_delivered(&mut self, block: &Block) -> Result<()> {
+        info!("Block delivered: {}", block);
+
+        let messages = block
+            .messages
+            .iter()
+            .filter(|msg| {
+                msg.hash_with_default_hasher()
+                    .is_ok_and(|hash| self.message_pool.contains(&hash))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.message_pool
+            .remove_messages(&messages)
+            .map_err(|e| anyhow!("Failed to remove messages from mempool: {}", e))?;
+
+        let state = &mut self.block_chain_state;
+        if state
+            .last_produced_block
+            .as_ref()
+            .is_some_and(|pending| pending.get_height() <= block.get_height())
+        {
+            state.last_produced_block = None;
+        }
+        if block.get_height() > state.last_committed_block.get_height() {
+            state.last_committed_block = block.clone();
+        }
+        if let Some(rotation) = &mut self.proposer_rotation {
+            rotation.height_advanced(state.last_committed_block.get_height());
+        }
+        Ok(())
+    }
+
+    /// Returns true if all members commit every delivered block.
+    pub(crate) fn rotates_proposers(&self) -> bool {
+        self.proposer_rotation.is_some()
+    }
+
+    /// Returns true if the local committed height keeps advancing: with a proposer schedule the node
+    /// commits everyone's blocks, otherwise only its own, which it doesn't produce while paused.
+    pub(crate) fn advances_height(&self) -> bool {
+        self.rotates_proposers()
+            || (self.config.producer && self.is_running() && !self.admin_paused)
+    }
+
+    /// Returns true if the creator of the block is scheduled to propose at its height and round.
+    pub(crate) fn is_scheduled_proposer(&self, block: &Block) -> bool {
+        self.proposer_rotation
+            .as_ref()
+            .is_none_or(|rotation| rotation.is_proposer(&block.header))
+    }
+
+    /// Returns true if the creator of the block is scheduled to propose at its height and round in
+    /// the group `members`.
+    pub(crate) fn is_scheduled_proposer_among(
+        &self,
+        block: &Block,
+        members: &HashMap<PeerId, u64>,
+    ) -> bool {
+        self.proposer_rotation
+            .as_ref()
+            .is_none_or(|rotation| rotation.is_proposer_among(&block.header, members))
+    }
+
+    /// Returns true if the local node echoes the block, see [`ProposerRotation::accepts`].
+    pub(crate) fn accepts_echo(&self, block: &Block) -> bool {
+        self.proposer_rotation.as_ref().is_none_or(|rotation| {
+            rotation.accepts(&block.header, self.block_chain_state.next_block_height())
+        })
+    }
+
+    /// The local node echoes no other block at the height of the block.
+    pub(crate) fn lock(&mut self, block: &Block) {
+        if let Some(rotation) = &mut self.proposer_rotation {
+            rotation.lock(block.get_height(), block.get_hash());
+        }
+    }
+
+    /// Proposers take turns among the members of the current broadcast group.
+    pub(crate) fn group_updated(&mut self, members: &HashMap<PeerId, u64>) {
+        if let Some(rotation) = &mut self.proposer_rotation {
+            rotation.set_members(members);
+        }
+    }
+
     pub(crate) fn get_block_by_hash(&mut self, block_id: &Hash) -> Option<Block> {
         self.block_chain_state.last_blocks.get(block_id).cloned()
     }
@@ -297,12 +403,81 @@ impl BlockManager {
         self.block_signer.get_block_certificates(hash)
     }
 
+    pub(crate) fn is_running(&self) -> bool {
+        matches!(self.state, State::Running)
+    }
+
+    /// Height of the last block the local node has committed.
+    pub(crate) fn last_committed_height(&self) -> u64 {
+        self.block_chain_state.last_committed_block.get_height()
+    }
+
+    /// Last produced block which is not committed yet.
+    pub(crate) fn pending_block(&self) -> Option<&Block> {
+        self.block_chain_state.last_produced_block.as_ref()
+    }
+
+    /// Number of backoff attempts made to produce a block while the previous one is pending.
+    pub(crate) fn backoff_attempt(&self) -> Option<u32> {
+        self.backoff.as_ref().map(|backoff| backoff.nr_of_attempts)
+    }
+
     pub(crate) fn stop(&mut self) {
         debug!("Stopping block creation");
         self.state = State::Paused;
         self.backoff = None;
     }
 
+    /// Pauses block production until [`BlockManager::resume_by_admin`]. Returns false if it was already paused.
+    pub(crate) fn pause_by_admin(&mut self) -> bool {
+        !std::mem::replace(&mut self.admin_paused, true)
+    }
+
+    /// Returns false if block production wasn't paused by an admin.
+    ///
+    /// Ticks missed while paused are dropped, otherwise they would produce a burst of identical blocks.
+    pub(crate) fn resume_by_admin(&mut self) -> bool {
+        let was_paused = std::mem::replace(&mut self.admin_paused, false);
+        if was_paused {
+            self.block_creation_interval.reset();
+        }
+        was_paused
+    }
+
+    pub(crate) fn is_admin_paused(&self) -> bool {
+        self.admin_paused
+    }
+
+    /// Produces the next block without waiting for the interval. The proposer schedule still applies.
+    pub(crate) fn request_block(&mut self) -> Result<()> {
+        let unavailable =
+            |reason: &str| Err(BlockManagerError::ProductionUnavailable(reason.into()));
+        if !self.config.producer {
+            return unavailable("node is not a block producer");
+        }
+        if self.admin_paused {
+            return unavailable("block production is paused by admin");
+        }
+        if !self.is_running() {
+            return unavailable("block production is paused");
+        }
+        if let Some(rotation) = &self.proposer_rotation {
+            let height = self.block_chain_state.next_block_height();
+            if rotation.proposer(height, rotation.round()) != Some(self.block_producer.peer_id) {
+                return unavailable("node is not the scheduled proposer of the next block");
+            }
+        }
+        self.produce_now = true;
+        Ok(())
+    }
+
+    /// Next block is produced after the new interval.
+    pub(crate) fn set_creation_interval(&mut self, creation_interval_sec: u64) {
+        self.config.creation_interval_sec = creation_interval_sec;
+        let period = Duration::from_secs(creation_interval_sec);
+        self.block_creation_interval = time::interval_at(Instant::now() + period, period);
+    }
+
     pub(crate) fn start(&mut self) {
         if !self.config.producer {
             return;
@@ -317,6 +492,22 @@ impl BlockManager {
     }
 }
 
+impl BlockManager {
+    /// Returns the current round if the local node is its proposer of the next height and doesn't
+    /// take part in another block at that height yet.
+    fn take_turn(&self) -> Option<u64> {
+        let local_peer_id = self.block_producer.peer_id;
+        let height = self.block_chain_state.next_block_height();
+        let rotation = self.proposer_rotation.as_ref()?;
+        let round = rotation.round();
+        if rotation.proposer(height, round) != Some(local_peer_id) || rotation.is_locked(height) {
+            return None;
+        }
+        debug!("Proposing block at height {height} in round {round}");
+        Some(round)
+    }
+}
+
 //Produces blocks at a predefined interval.
 //If blocks will be actually broadcast depends on the application.
 impl Stream for BlockManager {
@@ -334,12 +525,32 @@ impl Stream for BlockManager {
             return Pending;
         }
 
+        //Or by an admin.
+        if self.admin_paused {
+            self.produce_now = false;
+            return Pending;
+        }
+
         let is_previous_pending = self.block_chain_state.is_last_produced_block_is_pending();
         if !is_previous_pending {
             self.backoff = None;
         }
 
-        if self.block_creation_interval.poll_tick(cx).is_pending() {
+        let forced = std::mem::take(&mut self.produce_now);
+        let mut round = None;
+        if self.proposer_rotation.is_some() {
+            if !forced && self.block_creation_interval.poll_tick(cx).is_pending() {
+                return Pending;
+            }
+            round = self.take_turn();
+            if round.is_none() {
+                //Nothing else may wake the block manager before the next tick
+                while self.block_creation_interval.poll_tick(cx).is_ready() {}
+                return Pending;
+            }
+        } else if forced {
+            debug!("Producing block on request");
+        } else if self.block_creation_interval.poll_tick(cx).is_pending() {
             if let Some(mut backoff) = self.backoff.take() {
                 if backoff.is_expired() {
                     return Pending;
@@ -377,12 +588,15 @@ impl Stream for BlockManager {
         let new_height = self.block_chain_state.next_block_height();
         let created_block = self
             .block_producer
-            .create_block(new_height, pending_messages);
+            .create_block(new_height, round, pending_messages);
 
         if let Ok(block) = created_block {
             info!("Created block: {}", block);
 
             let hash = block.get_hash();
+            if let Some(rotation) = &mut self.proposer_rotation {
+                rotation.lock(new_height, hash);
+            }
             self.block_chain_state.last_produced_block = Some(block.clone());
             self.block_chain_state.last_blocks.put(hash, block.clone());
 
@@ -391,7 +605,7 @@ impl Stream for BlockManager {
                 .sign_block(&block, &hash)
                 .expect("Failed to sign block");
 
-            if self.backoff.is_none() {
+            if self.backoff.is_none() && self.proposer_rotation.is_none() {
                 let backoff = BackOffInterval::new(100, 2, Duration::from_secs(10));
                 self.backoff = Some(backoff);
             }
@@ -564,6 +778,31 @@ mod test {
         assert_eq!(block1.header.height, block2.header.height);
     }
 
+    #[tokio::test]
+    async fn test_admin_pause_and_forced_block() {
+        let (mut manager, _) = block_manager_with_defaults();
+        let wait = Duration::from_millis(50);
+
+        assert!(manager.pause_by_admin());
+        assert!(!manager.pause_by_admin());
+        assert_matches!(
+            manager.request_block(),
+            Err(BlockManagerError::ProductionUnavailable(_))
+        );
+        assert!(tokio::time::timeout(wait, manager.next()).await.is_err());
+
+        assert!(manager.resume_by_admin());
+        manager.set_creation_interval(3600);
+        assert!(tokio::time::timeout(wait, manager.next()).await.is_err());
+
+        manager.request_block().unwrap();
+        let (block, _) = tokio::time::timeout(wait, manager.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.header.height, 1);
+    }
+
     #[tokio::test]
     async fn test_on_committed_with_correct_pending_block() {
         let (mut manager, _) = block_manager_with_defaults();
@@ -657,6 +896,7 @@ mod test {
         let peer_id = keypair.public_key().peer_id();
         let genesis_block = Block::new_genesis_block(peer_id);
         let block_chain_state = BlockChainState::new(genesis_block);
+        let proposer_rotation = ProposerRotation::new(&config.proposer_schedule);
         (
             BlockManager {
                 config,
@@ -667,6 +907,9 @@ mod test {
                 block_signer: BlockSigner::new(keypair),
                 block_chain_state,
                 state: State::Running,
+                proposer_rotation,
+                admin_paused: false,
+                produce_now: false,
             },
             peer_id,
         )
@@ -676,7 +919,7 @@ mod test {
         let keypair: Arc<Keypair> = Keypair::generate(None).into();
         let peer_id = keypair.public_key().peer_id();
         let mut producer = BlockProducer::new(peer_id);
-        producer.create_block(1, vec![]).unwrap()
+        producer.create_block(1, None, vec![]).unwrap()
     }
 
     fn message(label: &str) -> EphemeraMessage {
@@ -18,9 +18,15 @@
 //!
 //! But it seems a reasonable assumption that in general duplicate messages are unwanted. Therefore, Ephemera solves this
 //! by dropping previous blocks which get Finalised/Committed after a new block has been created.
+//!
+//! # Proposer schedule
+//!
+//! Optionally producers take turns, see [`crate::config::ProposerSchedule`]. Then only the designated member
+//! proposes the next block and every member commits all delivered blocks.
 
 pub(crate) mod builder;
 pub(crate) mod manager;
 pub(crate) mod message_pool;
 pub(crate) mod producer;
+pub(crate) mod schedule;
 pub(crate) mod types;
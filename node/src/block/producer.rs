@@ -7,28 +7,41 @@ use log::trace;
 
 pub(crate) struct BlockProducer {
     pub(crate) peer_id: PeerId,
+    /// Channel of the blocks, `None` for the default channel.
+    pub(crate) channel: Option<String>,
 }
 
 impl BlockProducer {
     pub(super) fn new(peer_id: PeerId) -> Self {
-        Self { peer_id }
+        Self {
+            peer_id,
+            channel: None,
+        }
     }
 
     pub(super) fn create_block(
         &mut self,
         height: u64,
+        round: Option<u64>,
         pending_messages: Vec<EphemeraMessage>,
     ) -> anyhow::Result<Block> {
         trace!("Pending messages for new block: {:?}", pending_messages);
-        let block = self.new_block(height, pending_messages)?;
+        let block = self.new_block(height, round, pending_messages)?;
         Ok(block)
     }
 
-    fn new_block(&self, height: u64, mut messages: Vec<EphemeraMessage>) -> anyhow::Result<Block> {
+    fn new_block(
+        &self,
+        height: u64,
+        round: Option<u64>,
+        mut messages: Vec<EphemeraMessage>,
+    ) -> anyhow::Result<Block> {
         //Ordering is fundamental for block hash. Simple sort is fine for now.
         messages.sort();
 
-        let raw_header = RawBlockHeader::new(self.peer_id, height);
+        let raw_header = RawBlockHeader::new(self.peer_id, height)
+            .with_channel(self.channel.clone())
+            .with_round(round);
         let raw_block = RawBlock::new(raw_header, messages);
 
         //Better idea is probably combine header hash with Merkle tree root hash
@@ -67,7 +80,7 @@ mod test {
 
         let messages = vec![signed_message1.clone(), signed_message2.clone()];
 
-        let block = block_producer.create_block(1, messages).unwrap();
+        let block = block_producer.create_block(1, None, messages).unwrap();
 
         assert_eq!(block.header.height, 1);
         assert_eq!(block.header.creator, peer_id);
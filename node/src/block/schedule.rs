@@ -0,0 +1,306 @@
+//! Deterministic proposer schedule.
+//!
+//! Members of the broadcast group, sorted by peer id, take turns by height. With the weighted schedule
+//! each member gets as many heights in a row as its weight.
+//!
+//! Each node counts rounds locally from the moment it committed the previous block. When no block gets
+//! delivered during a round, the next member in the sorted order becomes the proposer of the height.
+//!
+//! Blocks carry the round they were proposed in, and only the proposer of that height and round is
+//! accepted. Once a node moves to the next round it doesn't echo blocks of earlier rounds anymore.
+//! A node also echoes a single block per height. Votes need a quorum of echoes, so two blocks at the
+//! same height can't both be delivered. Votes are counted for any scheduled block, so a node which
+//! echoed the original block still delivers the failover block voted by the others. If the members
+//! split between the original and the failover block so that neither gets a quorum, the height stalls.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::{
+    block::types::block::BlockHeader, config::ProposerSchedule, peer::PeerId, utilities::hash::Hash,
+};
+
+pub(crate) struct ProposerRotation {
+    weighted: bool,
+    /// Duration of a round.
+    timeout: Duration,
+    /// Broadcast group sorted by peer id, with weights.
+    members: Vec<(PeerId, u64)>,
+    /// When the local node started waiting for the next height.
+    height_started: Instant,
+    /// Block the local node takes part in at each height above the committed one.
+    locks: BTreeMap<u64, Hash>,
+}
+
+impl ProposerRotation {
+    /// Returns `None` if all producers propose at every height.
+    pub(crate) fn new(schedule: &ProposerSchedule) -> Option<Self> {
+        let (weighted, timeout_sec) = match schedule {
+            ProposerSchedule::All => return None,
+            ProposerSchedule::RoundRobin { timeout_sec } => (false, *timeout_sec),
+            ProposerSchedule::Weighted { timeout_sec } => (true, *timeout_sec),
+        };
+        Some(Self {
+            weighted,
+            timeout: Duration::from_secs(timeout_sec.max(1)),
+            members: vec![],
+            height_started: Instant::now(),
+            locks: BTreeMap::new(),
+        })
+    }
+
+    pub(crate) fn set_members(&mut self, members: &HashMap<PeerId, u64>) {
+        let mut members = members
+            .iter()
+            .map(|(peer_id, weight)| (*peer_id, *weight))
+            .collect::<Vec<_>>();
+        members.sort_by_key(|(peer_id, _)| peer_id.to_string());
+        self.members = members;
+    }
+
+    /// Starts the first round of the next height.
+    pub(crate) fn height_advanced(&mut self, committed_height: u64) {
+        self.height_started = Instant::now();
+        self.locks = self.locks.split_off(&(committed_height + 1));
+    }
+
+    /// Number of rounds passed without a delivered block.
+    pub(crate) fn round(&self) -> u64 {
+        let elapsed = self.height_started.elapsed().as_millis();
+        u64::try_from(elapsed / self.timeout.as_millis()).unwrap_or(u64::MAX)
+    }
+
+    /// Returns the member who proposes the block at `height` in `round`.
+    pub(crate) fn proposer(&self, height: u64, round: u64) -> Option<PeerId> {
+        let count = self.members.len() as u64;
+        if count == 0 {
+            return None;
+        }
+        let total_weight = self
+            .members
+            .iter()
+            .fold(0u64, |total, (_, weight)| total.saturating_add(*weight));
+        let first = if self.weighted && total_weight > 0 {
+            let mut slot = height % total_weight;
+            let mut first = 0;
+            for (i, (_, weight)) in self.members.iter().enumerate() {
+                if slot < *weight {
+                    first = i as u64;
+                    break;
+                }
+                slot -= weight;
+            }
+            first
+        } else {
+            height % count
+        };
+        let index = usize::try_from((first + round % count) % count)
+            .expect("Index is below the number of members");
+        Some(self.members[index].0)
+    }
+
+    /// Returns true if the creator of the block is the proposer of its height and round.
+    pub(crate) fn is_proposer(&self, header: &BlockHeader) -> bool {
+        header
+            .round
+            .is_some_and(|round| self.proposer(header.height, round) == Some(header.creator))
+    }
+
+    /// Like [`ProposerRotation::is_proposer`], but among `members` instead of the current group.
+    pub(crate) fn is_proposer_among(
+        &self,
+        header: &BlockHeader,
+        members: &HashMap<PeerId, u64>,
+    ) -> bool {
+        let mut rotation = Self {
+            members: vec![],
+            locks: BTreeMap::new(),
+            ..*self
+        };
+        rotation.set_members(members);
+        rotation.is_proposer(header)
+    }
+
+    /// Returns true if the local node should echo the block.
+    ///
+    /// The node echoes a single block per height, the one it took part in first. Otherwise blocks at
+    /// the next height are accepted in the current round, or in the next one when the creator's rounds
+    /// run a bit ahead. Blocks of earlier rounds are too late. A block one height above means that the
+    /// local node hasn't committed the previous block yet. It's accepted in any round up to the next one,
+    /// as the creator started the height later. Blocks at other heights are not accepted.
+    pub(crate) fn accepts(&self, header: &BlockHeader, next_height: u64) -> bool {
+        let Some(round) = header.round else {
+            return false;
+        };
+        if !self.is_proposer(header) {
+            return false;
+        }
+        if let Some(hash) = self.locks.get(&header.height) {
+            return *hash == header.hash;
+        }
+        let local_round = self.round();
+        match header.height.checked_sub(next_height) {
+            Some(0) => (local_round..=local_round.saturating_add(1)).contains(&round),
+            Some(1) => round <= local_round.saturating_add(1),
+            _ => false,
+        }
+    }
+
+    /// The local node takes part in the block, and in no other block at its height.
+    pub(crate) fn lock(&mut self, height: u64, hash: Hash) {
+        self.locks.entry(height).or_insert(hash);
+    }
+
+    /// Returns true if the local node already takes part in a block at `height`.
+    pub(crate) fn is_locked(&self, height: u64) -> bool {
+        self.locks.contains_key(&height)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crypto::{EphemeraKeypair, Keypair};
+    use crate::peer::ToPeerId;
+
+    use super::*;
+
+    fn rotation(schedule: &ProposerSchedule, weights: &[u64]) -> (ProposerRotation, Vec<PeerId>) {
+        let mut rotation = ProposerRotation::new(schedule).unwrap();
+        let members = weights
+            .iter()
+            .map(|weight| (Keypair::generate(None).peer_id(), *weight))
+            .collect::<HashMap<_, _>>();
+        rotation.set_members(&members);
+        let sorted = rotation
+            .members
+            .iter()
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+        (rotation, sorted)
+    }
+
+    #[test]
+    fn test_round_robin_takes_turns_by_height() {
+        let schedule = ProposerSchedule::RoundRobin { timeout_sec: 10 };
+        let (rotation, members) = rotation(&schedule, &[1, 1, 1]);
+
+        let proposers = (3..9)
+            .map(|height| rotation.proposer(height, 0).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(proposers, [members.clone(), members].concat());
+    }
+
+    #[test]
+    fn test_weighted_proposes_in_proportion_to_weight() {
+        let schedule = ProposerSchedule::Weighted { timeout_sec: 10 };
+        let (rotation, members) = rotation(&schedule, &[1, 2, 3]);
+        let weights = rotation.members.clone();
+
+        let proposers = (0..6)
+            .map(|height| rotation.proposer(height, 0).unwrap())
+            .collect::<Vec<_>>();
+
+        for (peer_id, weight) in weights {
+            let turns = proposers.iter().filter(|p| **p == peer_id).count() as u64;
+            assert_eq!(turns, weight);
+        }
+        assert_eq!(proposers[0], members[0]);
+    }
+
+    #[test]
+    fn test_failover_moves_to_next_member() {
+        let schedule = ProposerSchedule::Weighted { timeout_sec: 10 };
+        let (rotation, members) = rotation(&schedule, &[3, 1]);
+
+        assert_eq!(rotation.proposer(0, 0), Some(members[0]));
+        assert_eq!(rotation.proposer(0, 1), Some(members[1]));
+        assert_eq!(rotation.proposer(0, 2), Some(members[0]));
+    }
+
+    fn header(creator: PeerId, height: u64, round: u64) -> BlockHeader {
+        let mut hash = [0; 32];
+        hash[..8].copy_from_slice(&height.to_be_bytes());
+        hash[8..16].copy_from_slice(&round.to_be_bytes());
+        BlockHeader {
+            timestamp: 0,
+            creator,
+            height,
+            hash: Hash::new(hash),
+            channel: None,
+            round: Some(round),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_accepts_only_scheduled_proposer() {
+        let schedule = ProposerSchedule::RoundRobin { timeout_sec: 10 };
+        let (mut rotation, members) = rotation(&schedule, &[1, 1, 1]);
+        rotation.height_advanced(3);
+
+        assert!(rotation.accepts(&header(members[1], 4, 0), 4));
+        //Next round
+        assert!(rotation.accepts(&header(members[2], 4, 1), 4));
+        //Single proposer per round
+        assert!(!rotation.accepts(&header(members[2], 4, 0), 4));
+        assert!(!rotation.accepts(&header(members[0], 4, 1), 4));
+        let mut without_round = header(members[1], 4, 0);
+        without_round.round = None;
+        assert!(!rotation.accepts(&without_round, 4));
+        //Stale
+        assert!(!rotation.accepts(&header(members[0], 3, 0), 4));
+        //Local node is behind
+        assert!(rotation.accepts(&header(members[2], 5, 0), 4));
+        assert!(rotation.accepts(&header(members[0], 5, 1), 4));
+        assert!(!rotation.accepts(&header(members[1], 5, 2), 4));
+        assert!(!rotation.accepts(&header(members[0], 6, 0), 4));
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(rotation.round(), 1);
+        //Round is locked, a late block of the previous round is not accepted anymore
+        assert!(!rotation.accepts(&header(members[1], 4, 0), 4));
+        assert!(rotation.accepts(&header(members[2], 4, 1), 4));
+        assert!(rotation.accepts(&header(members[0], 4, 2), 4));
+    }
+
+    #[test]
+    fn test_proposer_among_other_group() {
+        let schedule = ProposerSchedule::RoundRobin { timeout_sec: 10 };
+        let (_, others) = rotation(&schedule, &[1, 1, 1]);
+        let others = others.iter().map(|peer_id| (*peer_id, 1)).collect();
+        let (rotation, members) = rotation(&schedule, &[1, 1, 1]);
+        let current = members.iter().map(|peer_id| (*peer_id, 1)).collect();
+
+        let block = header(members[1], 4, 0);
+        assert!(rotation.is_proposer_among(&block, &current));
+        assert!(!rotation.is_proposer_among(&block, &others));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_takes_part_in_one_block_per_height() {
+        let schedule = ProposerSchedule::RoundRobin { timeout_sec: 10 };
+        let (mut rotation, members) = rotation(&schedule, &[1, 1, 1]);
+        rotation.height_advanced(3);
+
+        let original = header(members[1], 4, 0);
+        assert!(rotation.accepts(&original, 4));
+        rotation.lock(original.height, original.hash);
+        let ahead = header(members[2], 5, 0);
+        rotation.lock(ahead.height, ahead.hash);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let failover = header(members[2], 4, 1);
+        assert!(!rotation.accepts(&failover, 4));
+        assert!(rotation.is_proposer(&failover));
+        //Still echoes the block it took part in
+        assert!(rotation.accepts(&original, 4));
+        assert!(rotation.is_locked(4));
+
+        rotation.height_advanced(4);
+        assert!(!rotation.is_locked(4));
+        assert!(rotation.is_locked(5));
+        assert!(rotation.accepts(&ahead, 5));
+    }
+}
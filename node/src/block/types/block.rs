@@ -23,6 +23,12 @@ pub(crate) struct BlockHeader {
     pub(crate) creator: PeerId,
     pub(crate) height: u64,
     pub(crate) hash: Hash,
+    /// Channel of the block, not set for the default channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) channel: Option<String>,
+    /// Round of the proposer schedule in which the block was proposed, not set without a schedule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) round: Option<u64>,
 }
 
 impl BlockHeader {
@@ -32,6 +38,8 @@ impl BlockHeader {
             creator: raw_header.creator,
             height: raw_header.height,
             hash,
+            channel: raw_header.channel.clone(),
+            round: raw_header.round,
         }
     }
 }
@@ -76,6 +84,13 @@ pub(crate) struct RawBlockHeader {
     pub(crate) timestamp: u64,
     pub(crate) creator: PeerId,
     pub(crate) height: u64,
+    /// Part of the hash, so that a block can't be moved to another channel.
+    /// Not serialized for the default channel, so that its hashes stay the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) channel: Option<String>,
+    /// Part of the hash, so that the same block can't be claimed for another round.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) round: Option<u64>,
 }
 
 impl RawBlockHeader {
@@ -84,9 +99,21 @@ impl RawBlockHeader {
             timestamp: EphemeraTime::now(),
             creator,
             height,
+            channel: None,
+            round: None,
         }
     }
 
+    pub(crate) fn with_channel(mut self, channel: Option<String>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub(crate) fn with_round(mut self, round: Option<u64>) -> Self {
+        self.round = round;
+        self
+    }
+
     pub(crate) fn hash_with_default_hasher(&self) -> anyhow::Result<Hash> {
         let mut hasher = Hasher::default();
         self.hash(&mut hasher)?;
@@ -109,6 +136,8 @@ impl From<BlockHeader> for RawBlockHeader {
             timestamp: block_header.timestamp,
             creator: block_header.creator,
             height: block_header.height,
+            channel: block_header.channel,
+            round: block_header.round,
         }
     }
 }
@@ -143,6 +172,8 @@ impl Block {
                 creator,
                 height: 0,
                 hash: Hash::new([0; 32]),
+                channel: None,
+                round: None,
             },
             messages: Vec::new(),
         };
@@ -292,6 +323,19 @@ mod test {
         assert_eq!(block_hash, block.get_hash());
     }
 
+    #[test]
+    fn test_block_hash_depends_on_channel() {
+        let header = RawBlockHeader::new(PeerId::random(), 1);
+        let default_hash = RawBlock::new(header.clone(), vec![])
+            .hash_with_default_hasher()
+            .unwrap();
+        let channel_hash = RawBlock::new(header.with_channel(Some("rewards".into())), vec![])
+            .hash_with_default_hasher()
+            .unwrap();
+
+        assert_ne!(default_hash, channel_hash);
+    }
+
     #[test]
     fn test_block_hash_with_messages() {
         let messages = create_ephemera_messages(10);
@@ -1,14 +1,16 @@
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 
 use log::{debug, trace};
 use lru::LruCache;
 
 use crate::broadcast::bracha::quorum::BrachaMessageType;
+use crate::config::QuorumPolicy;
 use crate::peer::PeerId;
 use crate::{
     block::types::block::Block,
     broadcast::{
-        bracha::quorum::Quorum,
+        bracha::quorum::{Quorum, QuorumError},
         MessageType::{Echo, Vote},
         ProtocolContext, RawRbMsg,
     },
@@ -23,22 +25,38 @@ pub(crate) enum BroadcastResponse {
     Drop(Hash),
 }
 
+/// Runs Bracha reliable broadcast for blocks.
+///
+/// Every block is broadcast within a single broadcast group snapshot - the one which was current
+/// when the block was seen first time. Group changes don't affect in-flight broadcasts:
+/// - only echoes and votes from the members of the block's snapshot are counted
+/// - thresholds stay the ones computed from the block's snapshot
+///
+/// If the local node is not part of the block's snapshot, it doesn't take part in its broadcast.
+/// When the local node is removed from the group, in-flight blocks from previous snapshots
+/// which it was a member of are still completed.
 pub(crate) struct Broadcaster {
     /// Local peer id
     local_peer_id: PeerId,
     /// We keep a context for each block we are processing.
     contexts: LruCache<Hash, ProtocolContext>,
-    /// Current cluster size
-    cluster_size: usize,
+    /// Id of the current broadcast group snapshot
+    group_id: u64,
+    /// Quorum of the current broadcast group
+    quorum: Quorum,
+    /// Policy how quorum thresholds are computed from the broadcast group
+    quorum_policy: QuorumPolicy,
 }
 
 impl Broadcaster {
-    pub fn new(peer_id: PeerId) -> Broadcaster {
+    pub fn new(peer_id: PeerId, quorum_policy: QuorumPolicy) -> Broadcaster {
         Broadcaster {
             //At any given time we are processing in parallel about n messages, where n is the number of peers in the group.
             //This is just large enough buffer.
             contexts: LruCache::new(NonZeroUsize::new(1000).unwrap()),
-            cluster_size: 0,
+            group_id: 0,
+            quorum: Quorum::default(),
+            quorum_policy,
             local_peer_id: peer_id,
         }
     }
@@ -55,7 +73,7 @@ impl Broadcaster {
         let hash = block.hash_with_default_hasher()?;
 
         let ctx = self.contexts.get_or_insert(hash, || {
-            ProtocolContext::new(hash, self.local_peer_id, Quorum::new(self.cluster_size))
+            ProtocolContext::new(hash, self.local_peer_id, self.group_id, self.quorum.clone())
         });
 
         if ctx.delivered {
@@ -63,6 +81,23 @@ impl Broadcaster {
             return Ok(BroadcastResponse::Drop(hash));
         }
 
+        if !ctx.quorum.is_member(&self.local_peer_id) {
+            trace!(
+                "Local peer is not a member of group {} of block {hash:?}, ignoring",
+                ctx.group_id
+            );
+            return Ok(BroadcastResponse::Drop(hash));
+        }
+
+        if !ctx.quorum.is_member(&rb_msg.original_sender) {
+            trace!(
+                "Sender {} is not a member of group {} of block {hash:?}, ignoring",
+                rb_msg.original_sender,
+                ctx.group_id
+            );
+            return Ok(BroadcastResponse::Drop(hash));
+        }
+
         match rb_msg.message_type {
             Echo(_) => {
                 trace!("Processing ECHO {:?}", rb_msg.id);
@@ -144,8 +179,32 @@ impl Broadcaster {
         BroadcastResponse::Drop(hash)
     }
 
-    pub(crate) fn group_updated(&mut self, size: usize) {
-        self.cluster_size = size;
+    /// Returns the contexts of the most recently active broadcasts, including delivered ones.
+    pub(crate) fn contexts(&self) -> impl Iterator<Item = &ProtocolContext> {
+        self.contexts.iter().map(|(_, ctx)| ctx)
+    }
+
+    /// Recomputes quorum for the new broadcast group snapshot. Only blocks seen after this
+    /// are broadcast in the new group.
+    ///
+    /// If the group doesn't satisfy the quorum policy, the quorum is reset to empty group
+    /// and no new broadcast can make progress until the group changes again.
+    pub(crate) fn group_updated(
+        &mut self,
+        group_id: u64,
+        weights: HashMap<PeerId, u64>,
+    ) -> Result<&Quorum, QuorumError> {
+        self.group_id = group_id;
+        match Quorum::new(&self.quorum_policy, weights) {
+            Ok(quorum) => {
+                self.quorum = quorum;
+                Ok(&self.quorum)
+            }
+            Err(err) => {
+                self.quorum = Quorum::default();
+                Err(err)
+            }
+        }
     }
 }
 
@@ -159,13 +218,13 @@ mod tests {
 
     //3.make sure that duplicate messages doesn't have impact
 
-    //4. "Ideally" make sure that when group changes, the ongoing broadcast can deal with it
-
+    use std::collections::HashMap;
     use std::iter;
 
     use assert_matches::assert_matches;
 
     use crate::broadcast::bracha::broadcast::BroadcastResponse;
+    use crate::config::QuorumPolicy;
     use crate::peer::PeerId;
     use crate::utilities::hash::Hash;
     use crate::{
@@ -179,8 +238,10 @@ mod tests {
         let local_peer_id = peers[0];
         let block_creator_peer_id = peers[1];
 
-        let mut broadcaster = Broadcaster::new(local_peer_id);
-        broadcaster.group_updated(peers.len());
+        let mut broadcaster = Broadcaster::new(local_peer_id, QuorumPolicy::Bft);
+        broadcaster
+            .group_updated(1, peers.iter().map(|peer_id| (*peer_id, 1)).collect())
+            .unwrap();
 
         let (block_hash, block) = create_block(block_creator_peer_id);
 
@@ -222,6 +283,169 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_group_grows_during_broadcast() {
+        let peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(4).collect();
+        let new_peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(6).collect();
+        let local_peer_id = peers[0];
+        let block_creator_peer_id = peers[1];
+
+        let mut broadcaster = Broadcaster::new(local_peer_id, QuorumPolicy::Bft);
+        broadcaster.group_updated(1, equal_weights(&peers)).unwrap();
+
+        let (block_hash, block) = create_block(block_creator_peer_id);
+        receive_echo_first_message(&mut broadcaster, &block, block_creator_peer_id);
+
+        let all_peers = [peers.clone(), new_peers.clone()].concat();
+        broadcaster
+            .group_updated(2, equal_weights(&all_peers))
+            .unwrap();
+
+        //Echoes from new members are not counted for the in-flight block
+        receive_nr_of_echo_messages_below_vote_threshold(&mut broadcaster, &block, &new_peers);
+
+        let ctx = broadcaster.contexts.get(&block_hash).unwrap();
+        assert_eq!(ctx.group_id, 1);
+        assert_eq!(ctx.echo.len(), 2);
+        assert!(!ctx.voted());
+
+        //Thresholds of the initial group still apply(n = 4, f = 1)
+        receive_echo_threshold_message(&mut broadcaster, &block, peers[2]);
+        receive_nr_of_vote_messages_below_deliver_threshold(
+            &mut broadcaster,
+            &block,
+            &[new_peers[0], new_peers[1], peers[1]],
+        );
+
+        let ctx = broadcaster.contexts.get(&block_hash).unwrap();
+        assert_eq!(ctx.vote.len(), 2);
+
+        receive_threshold_vote_message_for_deliver(&mut broadcaster, &block, peers[2]);
+
+        //New blocks are broadcast in the new group
+        let (new_block_hash, new_block) = create_block(new_peers[0]);
+        receive_echo_first_message(&mut broadcaster, &new_block, new_peers[0]);
+
+        let ctx = broadcaster.contexts.get(&new_block_hash).unwrap();
+        assert_eq!(ctx.group_id, 2);
+        assert_eq!(ctx.quorum.total_weight, 10);
+    }
+
+    #[test]
+    fn test_group_shrinks_during_broadcast() {
+        let peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(10).collect();
+        let local_peer_id = peers[0];
+        let block_creator_peer_id = peers[1];
+
+        let mut broadcaster = Broadcaster::new(local_peer_id, QuorumPolicy::Bft);
+        broadcaster.group_updated(1, equal_weights(&peers)).unwrap();
+
+        let (block_hash, block) = create_block(block_creator_peer_id);
+        receive_echo_first_message(&mut broadcaster, &block, block_creator_peer_id);
+
+        broadcaster
+            .group_updated(2, equal_weights(&peers[..4]))
+            .unwrap();
+
+        //Removed members are still counted for the in-flight block
+        receive_nr_of_echo_messages_below_vote_threshold(&mut broadcaster, &block, &peers[4..8]);
+
+        let ctx = broadcaster.contexts.get(&block_hash).unwrap();
+        assert_eq!(ctx.group_id, 1);
+        assert_eq!(ctx.echo.len(), 6);
+        assert!(!ctx.voted());
+
+        //Thresholds of the initial group still apply(n = 10, f = 3)
+        receive_echo_threshold_message(&mut broadcaster, &block, peers[8]);
+        receive_nr_of_vote_messages_below_deliver_threshold(&mut broadcaster, &block, &peers[3..8]);
+        receive_threshold_vote_message_for_deliver(&mut broadcaster, &block, peers[8]);
+
+        //New blocks are broadcast in the new group
+        let (new_block_hash, new_block) = create_block(peers[2]);
+        receive_echo_first_message(&mut broadcaster, &new_block, peers[2]);
+
+        let ctx = broadcaster.contexts.get(&new_block_hash).unwrap();
+        assert_eq!(ctx.group_id, 2);
+        assert_eq!(ctx.quorum.total_weight, 4);
+
+        //Removed members are not counted for the new block
+        receive_nr_of_echo_messages_below_vote_threshold(&mut broadcaster, &new_block, &peers[4..]);
+        let ctx = broadcaster.contexts.get(&new_block_hash).unwrap();
+        assert_eq!(ctx.echo.len(), 2);
+    }
+
+    #[test]
+    fn test_group_replaced_during_broadcast() {
+        let peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(4).collect();
+        let local_peer_id = peers[0];
+        let block_creator_peer_id = peers[1];
+        let mut new_peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(3).collect();
+        new_peers.push(local_peer_id);
+
+        let mut broadcaster = Broadcaster::new(local_peer_id, QuorumPolicy::Bft);
+        broadcaster.group_updated(1, equal_weights(&peers)).unwrap();
+
+        let (block_hash, block) = create_block(block_creator_peer_id);
+        receive_echo_first_message(&mut broadcaster, &block, block_creator_peer_id);
+
+        broadcaster
+            .group_updated(2, equal_weights(&new_peers))
+            .unwrap();
+
+        //Only the initial members are counted for the in-flight block
+        receive_nr_of_echo_messages_below_vote_threshold(&mut broadcaster, &block, &new_peers[..3]);
+        receive_echo_threshold_message(&mut broadcaster, &block, peers[2]);
+        receive_nr_of_vote_messages_below_deliver_threshold(
+            &mut broadcaster,
+            &block,
+            &[new_peers[0], peers[1]],
+        );
+        receive_threshold_vote_message_for_deliver(&mut broadcaster, &block, peers[2]);
+
+        let ctx = broadcaster.contexts.get(&block_hash).unwrap();
+        assert!(ctx.delivered);
+
+        //Only the new members are counted for the new block
+        let (new_block_hash, new_block) = create_block(new_peers[0]);
+        receive_echo_first_message(&mut broadcaster, &new_block, new_peers[0]);
+        receive_nr_of_echo_messages_below_vote_threshold(&mut broadcaster, &new_block, &peers[1..]);
+
+        let ctx = broadcaster.contexts.get(&new_block_hash).unwrap();
+        assert_eq!(ctx.group_id, 2);
+        assert_eq!(ctx.echo.len(), 2);
+    }
+
+    #[test]
+    fn test_local_peer_removed_during_broadcast() {
+        let peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(4).collect();
+        let local_peer_id = peers[0];
+        let block_creator_peer_id = peers[1];
+
+        let mut broadcaster = Broadcaster::new(local_peer_id, QuorumPolicy::Bft);
+        broadcaster.group_updated(1, equal_weights(&peers)).unwrap();
+
+        let (_, block) = create_block(block_creator_peer_id);
+        receive_echo_first_message(&mut broadcaster, &block, block_creator_peer_id);
+
+        broadcaster
+            .group_updated(2, equal_weights(&peers[1..]))
+            .unwrap();
+
+        //In-flight block is completed in its initial group
+        receive_echo_threshold_message(&mut broadcaster, &block, peers[2]);
+        receive_nr_of_vote_messages_below_deliver_threshold(&mut broadcaster, &block, &peers[1..2]);
+        receive_threshold_vote_message_for_deliver(&mut broadcaster, &block, peers[2]);
+
+        //Local node doesn't take part in new blocks broadcast
+        let (new_block_hash, new_block) = create_block(peers[2]);
+        let rb_msg = RawRbMsg::new(new_block, peers[2]);
+        let response = handle_double(&mut broadcaster, &rb_msg);
+        assert_matches!(response, BroadcastResponse::Drop(_));
+
+        let ctx = broadcaster.contexts.get(&new_block_hash).unwrap();
+        assert!(!ctx.echoed());
+    }
+
     fn receive_threshold_vote_message_for_deliver(
         broadcaster: &mut Broadcaster,
         block: &Block,
@@ -303,6 +527,10 @@ mod tests {
         );
     }
 
+    fn equal_weights(peers: &[PeerId]) -> HashMap<PeerId, u64> {
+        peers.iter().map(|peer_id| (*peer_id, 1)).collect()
+    }
+
     fn create_block(block_creator_peer_id: PeerId) -> (Hash, Block) {
         let header = RawBlockHeader::new(block_creator_peer_id, 0);
         let raw_block = RawBlock::new(header, vec![]);
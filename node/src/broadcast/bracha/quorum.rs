@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+
 use log::trace;
+use thiserror::Error;
 
 use crate::broadcast::{MessageType, ProtocolContext};
+use crate::config::QuorumPolicy;
+use crate::peer::PeerId;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum BrachaMessageType {
@@ -34,21 +39,100 @@ impl From<MessageType> for BrachaMessageType {
     }
 }
 
-const MAX_FAULTY_RATIO: f64 = 1.0 / 3.0;
+#[derive(Error, Debug, PartialEq, Eq)]
+pub(crate) enum QuorumError {
+    #[error("Quorum threshold {name}({threshold}) must be greater than zero")]
+    ZeroThreshold { name: &'static str, threshold: u64 },
+    #[error("Quorum threshold {name}({threshold}) exceeds total group weight {total_weight}")]
+    ThresholdExceedsTotalWeight {
+        name: &'static str,
+        threshold: u64,
+        total_weight: u64,
+    },
+    #[error("Quorum threshold {name}({threshold}) is not a majority of total group weight {total_weight}")]
+    ThresholdNotMajority {
+        name: &'static str,
+        threshold: u64,
+        total_weight: u64,
+    },
+    #[error("Quorum vote threshold({vote}) exceeds deliver threshold({deliver})")]
+    VoteExceedsDeliver { vote: u64, deliver: u64 },
+    #[error("Weight of group member {0} must be greater than zero")]
+    ZeroWeight(PeerId),
+    #[error("Total group weight overflows")]
+    TotalWeightOverflow,
+}
 
-#[derive(Debug, Clone, Copy)]
+/// Quorum thresholds of Bracha protocol.
+///
+/// Thresholds are computed over the weights of the broadcast group members rather than their count.
+/// When all members have equal weight, it is the same as counting peers.
+/// Peers who are not part of the group have no weight.
+#[derive(Debug, Clone, Default)]
 pub(crate) struct Quorum {
-    pub(crate) cluster_size: usize,
-    pub(crate) max_faulty_nodes: usize,
+    /// Weights of the broadcast group members.
+    pub(crate) weights: HashMap<PeerId, u64>,
+    /// Sum of the weights of all members.
+    pub(crate) total_weight: u64,
+    /// Weight of echoes needed to send a vote.
+    echo_threshold: u64,
+    /// Weight of votes needed to send our vote.
+    vote_threshold: u64,
+    /// Weight of votes needed to deliver the value.
+    deliver_threshold: u64,
 }
 
 impl Quorum {
-    pub fn new(cluster_size: usize) -> Self {
-        let max_faulty_nodes = Quorum::max_faulty_nodes(cluster_size);
-        Self {
-            cluster_size,
-            max_faulty_nodes,
+    /// Computes quorum thresholds for the given group according to the policy.
+    ///
+    /// Empty group is always accepted, it just never reaches any threshold.
+    pub(crate) fn new(
+        policy: &QuorumPolicy,
+        weights: HashMap<PeerId, u64>,
+    ) -> Result<Self, QuorumError> {
+        let total_weight = Quorum::total_weight(&weights)?;
+        let (echo_threshold, vote_threshold, deliver_threshold) =
+            Quorum::thresholds(policy, total_weight);
+
+        let quorum = Self {
+            weights,
+            total_weight,
+            echo_threshold,
+            vote_threshold,
+            deliver_threshold,
+        };
+        if total_weight > 0 {
+            quorum.validate()?;
         }
+        Ok(quorum)
+    }
+
+    /// Returns true if the peer is a member of the group this quorum is computed from.
+    pub(crate) fn is_member(&self, peer_id: &PeerId) -> bool {
+        self.weights.contains_key(peer_id)
+    }
+
+    /// Returns the sum of the weights of the given peers. Peers outside of the group are ignored.
+    pub(crate) fn weight_of<'a, I: IntoIterator<Item = &'a PeerId>>(&self, peers: I) -> u64 {
+        peers
+            .into_iter()
+            .filter_map(|peer_id| self.weights.get(peer_id))
+            .sum()
+    }
+
+    /// Weight of echoes needed to send a vote(n - f).
+    pub(crate) fn echo_threshold(&self) -> u64 {
+        self.echo_threshold
+    }
+
+    /// Weight of votes needed to send our vote(f + 1).
+    pub(crate) fn vote_threshold(&self) -> u64 {
+        self.vote_threshold
+    }
+
+    /// Weight of votes needed to deliver the value(n - f).
+    pub(crate) fn deliver_threshold(&self) -> u64 {
+        self.deliver_threshold
     }
 
     pub(crate) fn check_threshold(
@@ -56,83 +140,85 @@ impl Quorum {
         ctx: &ProtocolContext,
         phase: BrachaMessageType,
     ) -> BrachaAction {
-        if self.cluster_size == 0 {
-            trace!("Cluster size is 0, ignoring message");
+        if self.total_weight == 0 {
+            trace!("Cluster weight is 0, ignoring message");
             return BrachaAction::Ignore;
         }
 
         match phase {
             BrachaMessageType::Echo => {
-                if ctx.echo.len() >= self.cluster_size - self.max_faulty_nodes {
+                let echo_weight = self.weight_of(&ctx.echo);
+                if echo_weight >= self.echo_threshold() {
                     trace!(
                         "Echo threshold reached: Echoed:{} / Threshold:{} for Block:{}",
-                        ctx.echo.len(),
-                        self.cluster_size - self.max_faulty_nodes,
+                        echo_weight,
+                        self.echo_threshold(),
                         ctx.hash
                     );
                     BrachaAction::Vote
                 } else {
                     trace!(
                         "Echo threshold not reached: Echoed:{} / Threshold:{} for Block:{}",
-                        ctx.echo.len(),
-                        self.cluster_size - self.max_faulty_nodes,
+                        echo_weight,
+                        self.echo_threshold(),
                         ctx.hash
                     );
                     BrachaAction::Ignore
                 }
             }
             BrachaMessageType::Vote => {
+                let vote_weight = self.weight_of(&ctx.vote);
                 if ctx.voted() {
                     trace!("Voting already done for Block:{}", ctx.hash);
                 } else {
                     // f + 1 votes are enough to send our vote
-                    if ctx.vote.len() >= self.max_faulty_nodes {
+                    if vote_weight >= self.vote_threshold() {
                         trace!(
                             "Vote send threshold reached: Voted:{} / Threshold:{} for Block:{}",
-                            ctx.vote.len(),
-                            self.max_faulty_nodes + 1,
+                            vote_weight,
+                            self.vote_threshold(),
                             ctx.hash
                         );
                         return BrachaAction::Vote;
                     }
                     trace!(
                         "Vote send threshold not reached: Voted:{} / Threshold:{} for Block:{}",
-                        ctx.vote.len(),
-                        self.max_faulty_nodes + 1,
+                        vote_weight,
+                        self.vote_threshold(),
                         ctx.hash
                     );
                 }
 
                 if ctx.voted() {
                     // n-f votes are enough to deliver the value
-                    if ctx.vote.len() >= self.cluster_size - self.max_faulty_nodes {
+                    if vote_weight >= self.deliver_threshold() {
                         trace!(
                             "Deliver threshold reached: Voted:{} / Threshold:{} for Block:{}",
-                            ctx.vote.len(),
-                            self.cluster_size - self.max_faulty_nodes,
+                            vote_weight,
+                            self.deliver_threshold(),
                             ctx.hash
                         );
                         return BrachaAction::Deliver;
                     }
                     trace!(
                         "Deliver threshold not reached: Voted:{} / Threshold:{} for Block:{}",
-                        ctx.vote.len(),
-                        self.cluster_size - self.max_faulty_nodes,
+                        vote_weight,
+                        self.deliver_threshold(),
                         ctx.hash
                     );
                 } else {
                     trace!(
                         "Deliver threshold not reached: Voted:{} / Threshold:{} for Block:{}",
-                        ctx.vote.len(),
-                        self.cluster_size - self.max_faulty_nodes,
+                        vote_weight,
+                        self.deliver_threshold(),
                         ctx.hash
                     );
                 }
 
                 trace!(
                     "Vote threshold not reached: Voted:{} / Threshold:{} for Block:{}",
-                    ctx.vote.len(),
-                    self.max_faulty_nodes + 1,
+                    vote_weight,
+                    self.vote_threshold(),
                     ctx.hash
                 );
                 BrachaAction::Ignore
@@ -140,54 +226,256 @@ impl Quorum {
         }
     }
 
-    pub(crate) fn cluster_size_info(cluster_size: usize) -> String {
-        let max_faulty_nodes = Quorum::max_faulty_nodes(cluster_size);
-        format!("Cluster size: {cluster_size} / Max faulty nodes: {max_faulty_nodes}",)
+    pub(crate) fn cluster_size_info(&self) -> String {
+        format!(
+            "Cluster size: {} / Total weight: {} / Thresholds(echo: {}, vote: {}, deliver: {})",
+            self.weights.len(),
+            self.total_weight,
+            self.echo_threshold,
+            self.vote_threshold,
+            self.deliver_threshold
+        )
+    }
+
+    fn total_weight(weights: &HashMap<PeerId, u64>) -> Result<u64, QuorumError> {
+        weights.iter().try_fold(0u64, |total, (peer_id, weight)| {
+            if *weight == 0 {
+                return Err(QuorumError::ZeroWeight(*peer_id));
+            }
+            total
+                .checked_add(*weight)
+                .ok_or(QuorumError::TotalWeightOverflow)
+        })
+    }
+
+    /// Returns echo, vote and deliver thresholds for the policy.
+    fn thresholds(policy: &QuorumPolicy, total_weight: u64) -> (u64, u64, u64) {
+        // Maximum weight of faulty members the policy tolerates
+        let max_faulty_weight = match policy {
+            // f < n/3
+            QuorumPolicy::Bft => total_weight.saturating_sub(1) / 3,
+            // f < n/2
+            QuorumPolicy::CrashFault => total_weight.saturating_sub(1) / 2,
+            QuorumPolicy::Explicit {
+                echo,
+                vote,
+                deliver,
+            } => return (*echo, *vote, *deliver),
+        };
+        (
+            total_weight - max_faulty_weight,
+            max_faulty_weight + 1,
+            total_weight - max_faulty_weight,
+        )
     }
 
-    #[allow(
-        clippy::cast_precision_loss,
-        clippy::cast_sign_loss,
-        clippy::cast_possible_truncation
-    )]
-    fn max_faulty_nodes(cluster_size: usize) -> usize {
-        (cluster_size as f64 * MAX_FAULTY_RATIO).floor() as usize
+    fn validate(&self) -> Result<(), QuorumError> {
+        let thresholds = [
+            ("echo", self.echo_threshold),
+            ("vote", self.vote_threshold),
+            ("deliver", self.deliver_threshold),
+        ];
+        for (name, threshold) in thresholds {
+            if threshold == 0 {
+                return Err(QuorumError::ZeroThreshold { name, threshold });
+            }
+            if threshold > self.total_weight {
+                return Err(QuorumError::ThresholdExceedsTotalWeight {
+                    name,
+                    threshold,
+                    total_weight: self.total_weight,
+                });
+            }
+        }
+
+        //Two quorums of echoes or votes must always intersect
+        for (name, threshold) in [
+            ("echo", self.echo_threshold),
+            ("deliver", self.deliver_threshold),
+        ] {
+            if threshold * 2 <= self.total_weight {
+                return Err(QuorumError::ThresholdNotMajority {
+                    name,
+                    threshold,
+                    total_weight: self.total_weight,
+                });
+            }
+        }
+
+        if self.vote_threshold > self.deliver_threshold {
+            return Err(QuorumError::VoteExceedsDeliver {
+                vote: self.vote_threshold,
+                deliver: self.deliver_threshold,
+            });
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
+    use std::iter;
+    use std::time::Instant;
 
     use crate::broadcast::{
-        bracha::quorum::{BrachaAction, BrachaMessageType, Quorum},
+        bracha::quorum::{BrachaAction, BrachaMessageType, Quorum, QuorumError},
         ProtocolContext,
     };
+    use crate::config::QuorumPolicy;
     use crate::peer::PeerId;
 
     #[test]
-    fn test_max_faulty_nodes() {
-        let quorum = Quorum::new(10);
-        assert_eq!(quorum.max_faulty_nodes, 3);
+    fn test_bft_thresholds() {
+        let (quorum, _) = quorum_with_equal_weights(10);
+        assert_eq!(quorum.total_weight, 10);
+        assert_eq!(quorum.echo_threshold(), 7);
+        assert_eq!(quorum.vote_threshold(), 4);
+        assert_eq!(quorum.deliver_threshold(), 7);
+    }
+
+    #[test]
+    fn test_bft_thresholds_by_weight() {
+        let weights = [5, 1, 1, 1, 1]
+            .into_iter()
+            .map(|weight| (PeerId::random(), weight))
+            .collect::<HashMap<_, _>>();
+        let quorum = Quorum::new(&QuorumPolicy::Bft, weights).unwrap();
+        //f < n/3 => f = 2
+        assert_eq!(quorum.total_weight, 9);
+        assert_eq!(quorum.echo_threshold(), 7);
+        assert_eq!(quorum.vote_threshold(), 3);
+        assert_eq!(quorum.deliver_threshold(), 7);
+    }
+
+    #[test]
+    fn test_crash_fault_thresholds() {
+        let quorum = Quorum::new(&QuorumPolicy::CrashFault, equal_weights(10)).unwrap();
+        //f < n/2 => f = 4
+        assert_eq!(quorum.echo_threshold(), 6);
+        assert_eq!(quorum.vote_threshold(), 5);
+        assert_eq!(quorum.deliver_threshold(), 6);
+
+        let quorum = Quorum::new(&QuorumPolicy::CrashFault, equal_weights(3)).unwrap();
+        assert_eq!(quorum.echo_threshold(), 2);
+        assert_eq!(quorum.vote_threshold(), 2);
+        assert_eq!(quorum.deliver_threshold(), 2);
+    }
+
+    #[test]
+    fn test_explicit_thresholds() {
+        let policy = QuorumPolicy::Explicit {
+            echo: 8,
+            vote: 3,
+            deliver: 10,
+        };
+        let quorum = Quorum::new(&policy, equal_weights(10)).unwrap();
+        assert_eq!(quorum.echo_threshold(), 8);
+        assert_eq!(quorum.vote_threshold(), 3);
+        assert_eq!(quorum.deliver_threshold(), 10);
+    }
+
+    #[test]
+    fn test_explicit_thresholds_validated_against_group() {
+        let policy = QuorumPolicy::Explicit {
+            echo: 8,
+            vote: 3,
+            deliver: 10,
+        };
+        assert_eq!(
+            Quorum::new(&policy, equal_weights(9)).unwrap_err(),
+            QuorumError::ThresholdExceedsTotalWeight {
+                name: "deliver",
+                threshold: 10,
+                total_weight: 9,
+            }
+        );
+
+        let policy = QuorumPolicy::Explicit {
+            echo: 0,
+            vote: 3,
+            deliver: 10,
+        };
+        assert_eq!(
+            Quorum::new(&policy, equal_weights(10)).unwrap_err(),
+            QuorumError::ZeroThreshold {
+                name: "echo",
+                threshold: 0,
+            }
+        );
+
+        let policy = QuorumPolicy::Explicit {
+            echo: 8,
+            vote: 3,
+            deliver: 5,
+        };
+        assert_eq!(
+            Quorum::new(&policy, equal_weights(10)).unwrap_err(),
+            QuorumError::ThresholdNotMajority {
+                name: "deliver",
+                threshold: 5,
+                total_weight: 10,
+            }
+        );
+
+        let policy = QuorumPolicy::Explicit {
+            echo: 8,
+            vote: 9,
+            deliver: 8,
+        };
+        assert_eq!(
+            Quorum::new(&policy, equal_weights(10)).unwrap_err(),
+            QuorumError::VoteExceedsDeliver {
+                vote: 9,
+                deliver: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_weights_are_rejected() {
+        let zero = PeerId::random();
+        let weights = HashMap::from([(PeerId::random(), 1), (zero, 0)]);
+        let result = Quorum::new(&QuorumPolicy::Bft, weights);
+        assert_eq!(result.unwrap_err(), QuorumError::ZeroWeight(zero));
+
+        let weights = HashMap::from([(PeerId::random(), u64::MAX), (PeerId::random(), 1)]);
+        let result = Quorum::new(&QuorumPolicy::Bft, weights);
+        assert_eq!(result.unwrap_err(), QuorumError::TotalWeightOverflow);
+    }
+
+    #[test]
+    fn test_empty_group_is_accepted() {
+        let policy = QuorumPolicy::Explicit {
+            echo: 8,
+            vote: 3,
+            deliver: 10,
+        };
+        let quorum = Quorum::new(&policy, HashMap::new()).unwrap();
+        let ctx = ctx_with_echoes(&quorum, &[PeerId::random()]);
+        assert_eq!(
+            quorum.check_threshold(&ctx, BrachaMessageType::Echo),
+            BrachaAction::Ignore
+        );
     }
 
     #[test]
     fn test_vote_threshold_from_n_minus_f_peers() {
-        let quorum = Quorum::new(10);
+        let (quorum, peers) = quorum_with_equal_weights(10);
 
-        let ctx = ctx_with_nr_echoes(0);
+        let ctx = ctx_with_echoes(&quorum, &peers[..0]);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Echo),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_echoes(3);
+        let ctx = ctx_with_echoes(&quorum, &peers[..3]);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Echo),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_echoes(8);
+        let ctx = ctx_with_echoes(&quorum, &peers[..8]);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Echo),
             BrachaAction::Vote
@@ -196,21 +484,21 @@ mod test {
 
     #[test]
     fn test_vote_threshold_from_f_plus_one_peers() {
-        let quorum = Quorum::new(10);
+        let (quorum, peers) = quorum_with_equal_weights(10);
 
-        let ctx = ctx_with_nr_votes(0, None);
+        let ctx = ctx_with_votes(&quorum, &peers[..0], None);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_votes(2, None);
+        let ctx = ctx_with_votes(&quorum, &peers[..2], None);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_votes(5, None);
+        let ctx = ctx_with_votes(&quorum, &peers[..5], None);
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Vote
@@ -219,55 +507,109 @@ mod test {
 
     #[test]
     fn test_deliver_threshold_from_n_minus_f_peers() {
-        let quorum = Quorum::new(10);
+        let (quorum, peers) = quorum_with_equal_weights(10);
 
-        let local_peer_id = PeerId::random();
-        let ctx = ctx_with_nr_votes(0, local_peer_id.into());
+        let local_peer_id = peers[0];
+        let ctx = ctx_with_votes(&quorum, &peers[1..1], local_peer_id.into());
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_votes(3, local_peer_id.into());
+        let ctx = ctx_with_votes(&quorum, &peers[1..4], local_peer_id.into());
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Ignore
         );
 
-        let ctx = ctx_with_nr_votes(7, local_peer_id.into());
+        let ctx = ctx_with_votes(&quorum, &peers[1..8], local_peer_id.into());
         assert_eq!(
             quorum.check_threshold(&ctx, BrachaMessageType::Vote),
             BrachaAction::Deliver
         );
     }
 
-    fn ctx_with_nr_echoes(n: usize) -> ProtocolContext {
+    #[test]
+    fn test_non_members_have_no_weight() {
+        let (quorum, _) = quorum_with_equal_weights(10);
+
+        let outsiders: Vec<PeerId> = iter::repeat_with(PeerId::random).take(10).collect();
+        let ctx = ctx_with_echoes(&quorum, &outsiders);
+        assert_eq!(
+            quorum.check_threshold(&ctx, BrachaMessageType::Echo),
+            BrachaAction::Ignore
+        );
+    }
+
+    #[test]
+    fn test_echo_threshold_by_weight() {
+        let heavy = PeerId::random();
+        let light: Vec<PeerId> = iter::repeat_with(PeerId::random).take(4).collect();
+        let mut weights = light
+            .iter()
+            .map(|peer_id| (*peer_id, 1))
+            .collect::<HashMap<_, _>>();
+        weights.insert(heavy, 5);
+        let quorum = Quorum::new(&QuorumPolicy::Bft, weights).unwrap();
+
+        //All light peers together don't have enough weight
+        let ctx = ctx_with_echoes(&quorum, &light);
+        assert_eq!(
+            quorum.check_threshold(&ctx, BrachaMessageType::Echo),
+            BrachaAction::Ignore
+        );
+
+        //Heavy peer with two light peers have
+        let ctx = ctx_with_echoes(&quorum, &[heavy, light[0], light[1]]);
+        assert_eq!(
+            quorum.check_threshold(&ctx, BrachaMessageType::Echo),
+            BrachaAction::Vote
+        );
+    }
+
+    fn quorum_with_equal_weights(n: usize) -> (Quorum, Vec<PeerId>) {
+        let peers: Vec<PeerId> = iter::repeat_with(PeerId::random).take(n).collect();
+        let weights = peers.iter().map(|peer_id| (*peer_id, 1)).collect();
+        (Quorum::new(&QuorumPolicy::Bft, weights).unwrap(), peers)
+    }
+
+    fn equal_weights(n: usize) -> HashMap<PeerId, u64> {
+        iter::repeat_with(|| (PeerId::random(), 1))
+            .take(n)
+            .collect()
+    }
+
+    fn ctx_with_echoes(quorum: &Quorum, peers: &[PeerId]) -> ProtocolContext {
         let mut ctx = ProtocolContext {
             local_peer_id: PeerId::random(),
             hash: [0; 32].into(),
             echo: HashSet::default(),
             vote: HashSet::default(),
-            quorum: Quorum::new(10),
+            group_id: 0,
+            quorum: quorum.clone(),
             delivered: false,
+            started_at: Instant::now(),
         };
-        for _ in 0..n {
-            ctx.echo.insert(PeerId::random());
-        }
+        ctx.echo.extend(peers);
         ctx
     }
 
-    fn ctx_with_nr_votes(n: usize, local_peer_id: Option<PeerId>) -> ProtocolContext {
+    fn ctx_with_votes(
+        quorum: &Quorum,
+        peers: &[PeerId],
+        local_peer_id: Option<PeerId>,
+    ) -> ProtocolContext {
         let mut ctx = ProtocolContext {
             local_peer_id: local_peer_id.unwrap_or(PeerId::random()),
             hash: [0; 32].into(),
             echo: HashSet::default(),
             vote: HashSet::default(),
-            quorum: Quorum::new(10),
+            group_id: 0,
+            quorum: quorum.clone(),
             delivered: false,
+            started_at: Instant::now(),
         };
-        for _ in 0..n {
-            ctx.vote.insert(PeerId::random());
-        }
+        ctx.vote.extend(peers);
         if let Some(id) = local_peer_id {
             ctx.vote.insert(id);
         }
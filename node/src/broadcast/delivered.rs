@@ -0,0 +1,255 @@
+//! Blocks members publish after reliable broadcast has delivered them.
+//!
+//! Observers are not part of the broadcast group, so they can't follow reliable broadcast itself.
+//! Instead the creator of a block publishes it together with all the certificates it collected and
+//! the broadcast group of the block. An observer accepts the block only if the certificates prove
+//! that a quorum of the members it knows agreed on it.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    block::types::block::Block,
+    broadcast::bracha::quorum::{Quorum, QuorumError},
+    config::QuorumPolicy,
+    peer::{PeerId, ToPeerId},
+    utilities::{crypto::Certificate, hash::Hash},
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub(crate) enum DeliveredBlockError {
+    #[error("Block hash is invalid: {0}")]
+    InvalidHash(Hash),
+    #[error("Certificate of {0} is invalid")]
+    InvalidCertificate(PeerId),
+    #[error("Broadcast group member {0} is not a known member")]
+    UnknownMember(PeerId),
+    #[error("Block creator {0} is not part of the broadcast group")]
+    CreatorNotInGroup(PeerId),
+    #[error("Broadcast group doesn't satisfy quorum policy: {0}")]
+    Quorum(#[from] QuorumError),
+    #[error("Certificates weight {weight} is below deliver threshold {threshold}")]
+    NotEnoughCertificates { weight: u64, threshold: u64 },
+}
+
+impl DeliveredBlockError {
+    /// Returns true if the block is invalid regardless of which members are known locally.
+    pub(crate) fn is_invalid(&self) -> bool {
+        matches!(
+            self,
+            DeliveredBlockError::InvalidHash(_)
+                | DeliveredBlockError::InvalidCertificate(_)
+                | DeliveredBlockError::CreatorNotInGroup(_)
+                | DeliveredBlockError::NotEnoughCertificates { .. }
+        )
+    }
+}
+
+/// A block delivered by reliable broadcast.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct DeliveredBlock {
+    pub(crate) block: Block,
+    /// Certificates of the members who echoed or voted for the block, creator included.
+    pub(crate) certificates: HashSet<Certificate>,
+    /// Broadcast group the block was delivered in.
+    pub(crate) group: HashSet<PeerId>,
+}
+
+impl DeliveredBlock {
+    pub(crate) fn new(
+        block: Block,
+        certificates: HashSet<Certificate>,
+        group: HashSet<PeerId>,
+    ) -> Self {
+        Self {
+            block,
+            certificates,
+            group,
+        }
+    }
+
+    /// Verifies that a quorum of `members`, the members known locally, signed the block.
+    ///
+    /// The quorum doesn't depend on the group the creator sent, so a creator can't lower the
+    /// threshold by leaving members out of it. The group is only checked to be consistent with
+    /// `members`.
+    ///
+    /// Returns the members together with the weights.
+    pub(crate) fn verify(
+        &self,
+        members: &HashMap<PeerId, u64>,
+        policy: &QuorumPolicy,
+    ) -> Result<HashMap<PeerId, u64>, DeliveredBlockError> {
+        let hash = self.block.header.hash;
+        if !self
+            .block
+            .hash_with_default_hasher()
+            .is_ok_and(|computed| computed == hash)
+        {
+            return Err(DeliveredBlockError::InvalidHash(hash));
+        }
+
+        let creator = self.block.header.creator;
+        if !self.group.contains(&creator) {
+            return Err(DeliveredBlockError::CreatorNotInGroup(creator));
+        }
+        if let Some(unknown) = self
+            .group
+            .iter()
+            .find(|peer_id| !members.contains_key(peer_id))
+        {
+            return Err(DeliveredBlockError::UnknownMember(*unknown));
+        }
+
+        let mut signers = HashSet::new();
+        for certificate in &self.certificates {
+            let signer = certificate.public_key.peer_id();
+            if !self.block.verify(certificate).unwrap_or(false) {
+                return Err(DeliveredBlockError::InvalidCertificate(signer));
+            }
+            signers.insert(signer);
+        }
+
+        let quorum = Quorum::new(policy, members.clone())?;
+        let weight = quorum.weight_of(&signers);
+        if weight < quorum.deliver_threshold() || quorum.total_weight == 0 {
+            return Err(DeliveredBlockError::NotEnoughCertificates {
+                weight,
+                threshold: quorum.deliver_threshold(),
+            });
+        }
+        Ok(quorum.weights)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block::types::block::{RawBlock, RawBlockHeader};
+    use crate::crypto::{EphemeraKeypair, Keypair};
+
+    use super::*;
+
+    fn block(creator: PeerId, height: u64) -> Block {
+        let raw_block = RawBlock::new(RawBlockHeader::new(creator, height), vec![]);
+        let hash = raw_block.hash_with_default_hasher().unwrap();
+        Block::new(raw_block, hash)
+    }
+
+    struct Cluster {
+        keypairs: Vec<Keypair>,
+        block: Block,
+    }
+
+    impl Cluster {
+        fn new(size: usize) -> Self {
+            let keypairs = (0..size)
+                .map(|_| Keypair::generate(None))
+                .collect::<Vec<_>>();
+            let block = block(keypairs[0].peer_id(), 1);
+            Self { keypairs, block }
+        }
+
+        fn members(&self) -> HashMap<PeerId, u64> {
+            self.keypairs
+                .iter()
+                .map(|keypair| (keypair.peer_id(), 1))
+                .collect()
+        }
+
+        /// Block signed by the first `signers` members in the group of the first `group` members.
+        fn delivered(&self, signers: usize, group: usize) -> DeliveredBlock {
+            let certificates = self.keypairs[..signers]
+                .iter()
+                .map(|keypair| self.block.sign(keypair).unwrap())
+                .collect();
+            let group = self.keypairs[..group]
+                .iter()
+                .map(ToPeerId::peer_id)
+                .collect();
+            DeliveredBlock::new(self.block.clone(), certificates, group)
+        }
+    }
+
+    #[test]
+    fn test_block_certified_by_quorum_is_accepted() {
+        let cluster = Cluster::new(4);
+
+        let group = cluster
+            .delivered(3, 4)
+            .verify(&cluster.members(), &QuorumPolicy::Bft)
+            .unwrap();
+
+        assert_eq!(group, cluster.members());
+    }
+
+    #[test]
+    fn test_block_without_quorum_is_rejected() {
+        let cluster = Cluster::new(4);
+
+        let result = cluster
+            .delivered(2, 4)
+            .verify(&cluster.members(), &QuorumPolicy::Bft);
+
+        assert_eq!(
+            result,
+            Err(DeliveredBlockError::NotEnoughCertificates {
+                weight: 2,
+                threshold: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_shrunk_group_does_not_lower_threshold() {
+        let cluster = Cluster::new(4);
+
+        let result = cluster
+            .delivered(2, 2)
+            .verify(&cluster.members(), &QuorumPolicy::Bft);
+
+        assert_eq!(
+            result,
+            Err(DeliveredBlockError::NotEnoughCertificates {
+                weight: 2,
+                threshold: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_member_is_rejected() {
+        let cluster = Cluster::new(4);
+        let mut members = cluster.members();
+        let unknown = cluster.keypairs[3].peer_id();
+        members.remove(&unknown);
+
+        let result = cluster.delivered(4, 4).verify(&members, &QuorumPolicy::Bft);
+
+        assert_eq!(result, Err(DeliveredBlockError::UnknownMember(unknown)));
+    }
+
+    #[test]
+    fn test_invalid_block_and_certificate_are_rejected() {
+        let cluster = Cluster::new(4);
+
+        let mut delivered = cluster.delivered(3, 4);
+        delivered.block.header.height += 1;
+        let result = delivered.verify(&cluster.members(), &QuorumPolicy::Bft);
+        assert!(matches!(result, Err(DeliveredBlockError::InvalidHash(_))));
+
+        //Signature of another block
+        let mut delivered = cluster.delivered(2, 4);
+        let signer = &cluster.keypairs[2];
+        let other_block = block(cluster.keypairs[0].peer_id(), 2);
+        delivered
+            .certificates
+            .insert(other_block.sign(signer).unwrap());
+        let result = delivered.verify(&cluster.members(), &QuorumPolicy::Bft);
+        assert_eq!(
+            result,
+            Err(DeliveredBlockError::InvalidCertificate(signer.peer_id()))
+        );
+    }
+}
@@ -1,51 +1,90 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroUsize;
 
 use log::warn;
 use lru::LruCache;
 
+use crate::network::libp2p::network_sender::GroupChangeEvent;
 use crate::peer::PeerId;
 use crate::utilities::hash::Hash;
 
 pub(crate) struct BroadcastGroup {
     /// The id of current group. Incremented every time a new snapshot is added.
     pub(crate) current_id: u64,
-    /// A cache of the group snapshots.
-    pub(crate) snapshots: LruCache<u64, HashSet<PeerId>>,
+    /// A cache of the group snapshots. Each snapshot keeps the members together with their weights.
+    pub(crate) snapshots: LruCache<u64, HashMap<PeerId, u64>>,
     /// A cache of the groups for each block.
     pub(crate) broadcast_groups: LruCache<Hash, u64>,
+    /// Group changes waiting until the local block at their activation height is committed.
+    pub(crate) pending: BTreeMap<u64, GroupChangeEvent>,
+    /// Snapshot ids by the first height of the default channel they apply to. Only observers keep
+    /// them, they verify delivered blocks against the group of the block's height.
+    pub(crate) heights: BTreeMap<u64, u64>,
 }
 
 impl BroadcastGroup {
-    pub(crate) fn new() -> BroadcastGroup {
+    // Snapshot ids continue from `current_id`, the last id persisted before restart.
+    pub(crate) fn new(current_id: u64) -> BroadcastGroup {
         let mut snapshots = LruCache::new(NonZeroUsize::new(100).unwrap());
-        snapshots.put(0, HashSet::new());
+        snapshots.put(current_id, HashMap::new());
         BroadcastGroup {
-            current_id: 0,
+            current_id,
             snapshots,
             broadcast_groups: LruCache::new(NonZeroUsize::new(100).unwrap()),
+            pending: BTreeMap::new(),
+            heights: BTreeMap::new(),
         }
     }
 
-    pub(crate) fn add_snapshot(&mut self, snapshot: HashSet<PeerId>) {
+    // Members provider reports the same change many times, the latest one is kept.
+    pub(crate) fn schedule(&mut self, activation_height: u64, change: GroupChangeEvent) {
+        self.pending.insert(activation_height, change);
+    }
+
+    // Returns the changes which become effective after the local block at `height` is committed,
+    // oldest first.
+    pub(crate) fn take_activated(&mut self, height: u64) -> Vec<GroupChangeEvent> {
+        let later = self.pending.split_off(&(height + 1));
+        std::mem::replace(&mut self.pending, later)
+            .into_values()
+            .collect()
+    }
+
+    pub(crate) fn add_snapshot(&mut self, snapshot: HashMap<PeerId, u64>) {
         self.current_id += 1;
         self.snapshots.put(self.current_id, snapshot);
     }
 
+    // The current snapshot applies to blocks from `first_height` on. Only as many heights are kept
+    // as there are cached snapshots.
+    pub(crate) fn apply_from(&mut self, first_height: u64) {
+        self.heights.insert(first_height, self.current_id);
+        while self.heights.len() > self.snapshots.cap().get() {
+            self.heights.pop_first();
+        }
+    }
+
+    // Returns the snapshot which applies to the block at `height`, if it's still known.
+    pub(crate) fn group_at_height(&mut self, height: u64) -> Option<&HashMap<PeerId, u64>> {
+        let (_, id) = self.heights.range(..=height).next_back()?;
+        let id = *id;
+        self.snapshots.get(&id)
+    }
+
     pub(crate) fn is_member(&mut self, id: u64, peer_id: &PeerId) -> bool {
         self.snapshots
             .get(&id)
-            .map_or(false, |s| s.contains(peer_id))
+            .map_or(false, |s| s.contains_key(peer_id))
     }
 
     pub(crate) fn is_empty(&mut self) -> bool {
         self.snapshots
             .get(&self.current_id)
-            .map_or(true, HashSet::is_empty)
+            .map_or(true, HashMap::is_empty)
     }
 
     // Returns empty snapshots(inserted in 'new' fn) if we haven't received any yet.
-    pub(crate) fn current(&mut self) -> &HashSet<PeerId> {
+    pub(crate) fn current(&mut self) -> &HashMap<PeerId, u64> {
         self.snapshots
             .get(&self.current_id)
             .expect("Current group should always exist")
@@ -105,7 +144,7 @@ impl BroadcastGroup {
         true
     }
 
-    pub(crate) fn get_group_by_block_hash(&mut self, hash: Hash) -> Option<&HashSet<PeerId>> {
+    pub(crate) fn get_group_by_block_hash(&mut self, hash: Hash) -> Option<&HashMap<PeerId, u64>> {
         let membership_id = *self.broadcast_groups.get(&hash)?;
         self.snapshots.get(&membership_id)
     }
@@ -113,15 +152,16 @@ impl BroadcastGroup {
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashSet;
+    use std::collections::HashMap;
 
     use crate::broadcast::group::BroadcastGroup;
+    use crate::network::libp2p::network_sender::GroupChangeEvent;
     use crate::peer::PeerId;
     use crate::utilities::hash::Hash;
 
     #[test]
     fn test_no_snapshot() {
-        let group = BroadcastGroup::new();
+        let group = BroadcastGroup::new(0);
         assert_eq!(group.current_id, 0);
         //Including initial default snapshot
         assert_eq!(group.snapshots.len(), 1);
@@ -146,7 +186,7 @@ mod test {
 
     #[test]
     fn check_membership_empty_group() {
-        let mut group = BroadcastGroup::new();
+        let mut group = BroadcastGroup::new(0);
         let hash = Hash::new([0; 32]);
         assert!(!group.check_membership(hash, &PeerId::random(), &PeerId::random()));
         assert!(!group.broadcast_groups.contains(&hash));
@@ -162,7 +202,7 @@ mod test {
     #[test]
     fn check_membership_creator_not_member() {
         let (mut group, snapshots) = group_with_snapshots(1);
-        let sender = snapshots[0].clone().into_iter().next().unwrap();
+        let sender = snapshots[0].clone().into_keys().next().unwrap();
 
         let hash = Hash::new([0; 32]);
         assert!(!group.check_membership(hash, &PeerId::random(), &sender));
@@ -172,7 +212,7 @@ mod test {
     #[test]
     fn check_membership_sender_not_member() {
         let (mut group, snapshots) = group_with_snapshots(1);
-        let creator = snapshots[0].clone().into_iter().next().unwrap();
+        let creator = snapshots[0].clone().into_keys().next().unwrap();
         let hash = Hash::new([0; 32]);
         assert!(!group.check_membership(hash, &creator, &PeerId::random()));
         assert!(!group.broadcast_groups.contains(&hash));
@@ -181,7 +221,7 @@ mod test {
     #[test]
     fn check_snapshot_membership_both_are_members() {
         let (mut group, snapshots) = group_with_snapshots(1);
-        let creator = snapshots[0].clone().into_iter().next().unwrap();
+        let creator = snapshots[0].clone().into_keys().next().unwrap();
         let sender = creator;
         let hash = Hash::new([0; 32]);
         assert!(group.check_membership(hash, &creator, &sender));
@@ -192,7 +232,7 @@ mod test {
     fn check_snapshot_membership_of_current_snapshot() {
         let (mut group, snapshots) = group_with_snapshots(2);
         let current_snapshot = snapshots[1].clone();
-        let creator = current_snapshot.into_iter().next().unwrap();
+        let creator = current_snapshot.into_keys().next().unwrap();
         let sender = creator;
 
         let hash = Hash::new([0; 32]);
@@ -208,11 +248,11 @@ mod test {
 
     #[test]
     fn check_snapshot_membership_of_previous_snapshot() {
-        let mut group = BroadcastGroup::new();
+        let mut group = BroadcastGroup::new(0);
         let first_snapshot = create_snapshot();
         group.add_snapshot(first_snapshot.clone());
 
-        let creator = first_snapshot.into_iter().next().unwrap();
+        let creator = first_snapshot.into_keys().next().unwrap();
         let sender = creator;
         let hash = Hash::new([0; 32]);
         assert!(group.check_membership(hash, &creator, &sender));
@@ -229,8 +269,40 @@ mod test {
         assert!(group.check_membership(hash, &creator, &sender));
     }
 
-    fn group_with_snapshots(count: usize) -> (BroadcastGroup, Vec<HashSet<PeerId>>) {
-        let mut group = BroadcastGroup::new();
+    #[test]
+    fn scheduled_changes_are_taken_after_activation_height() {
+        let mut group = BroadcastGroup::new(0);
+        let first = GroupChangeEvent::PeersUpdated(create_snapshot());
+        let second = GroupChangeEvent::PeersUpdated(create_snapshot());
+        let latest = GroupChangeEvent::NotEnoughPeers(create_snapshot());
+        group.schedule(12, second);
+        group.schedule(10, first.clone());
+        group.schedule(12, latest.clone());
+
+        assert!(group.take_activated(9).is_empty());
+        assert_eq!(group.take_activated(10), vec![first]);
+        assert!(group.take_activated(11).is_empty());
+        assert_eq!(group.take_activated(15), vec![latest]);
+        assert!(group.pending.is_empty());
+    }
+
+    #[test]
+    fn test_group_at_height() {
+        let (mut group, snapshots) = group_with_snapshots(1);
+        group.apply_from(5);
+        let next = create_snapshot();
+        group.add_snapshot(next.clone());
+        group.apply_from(11);
+
+        assert_eq!(group.group_at_height(4), None);
+        assert_eq!(group.group_at_height(5), Some(&snapshots[0]));
+        assert_eq!(group.group_at_height(10), Some(&snapshots[0]));
+        assert_eq!(group.group_at_height(11), Some(&next));
+        assert_eq!(group.group_at_height(100), Some(&next));
+    }
+
+    fn group_with_snapshots(count: usize) -> (BroadcastGroup, Vec<HashMap<PeerId, u64>>) {
+        let mut group = BroadcastGroup::new(0);
         let mut snapshots = Vec::new();
         for _ in 0..count {
             let snapshot = create_snapshot();
@@ -240,10 +312,10 @@ mod test {
         (group, snapshots)
     }
 
-    fn create_snapshot() -> HashSet<PeerId> {
-        let mut snapshot = HashSet::new();
+    fn create_snapshot() -> HashMap<PeerId, u64> {
+        let mut snapshot = HashMap::new();
         let peer_id = PeerId::random();
-        snapshot.insert(peer_id);
+        snapshot.insert(peer_id, 1);
         snapshot
     }
 }
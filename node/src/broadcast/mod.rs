@@ -2,6 +2,7 @@
 //!
 use std::collections::HashSet;
 use std::fmt::{Debug, Display};
+use std::time::Instant;
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -18,6 +19,7 @@ use crate::{
 };
 
 pub(crate) mod bracha;
+pub(crate) mod delivered;
 pub(crate) mod group;
 pub(crate) mod signing;
 
@@ -31,21 +33,32 @@ pub(crate) struct ProtocolContext {
     pub(crate) echo: HashSet<PeerId>,
     /// Peers that sent commit message(this peer included)
     pub(crate) vote: HashSet<PeerId>,
-    /// Quorum logic for Bracha protocol
+    /// Id of the broadcast group snapshot this block is broadcast in
+    pub(crate) group_id: u64,
+    /// Quorum logic for Bracha protocol. Computed from the group snapshot `group_id`
     pub(crate) quorum: Quorum,
     /// Flag indicating if the message was delivered to the client
     pub(crate) delivered: bool,
+    /// When we saw the block first time
+    pub(crate) started_at: Instant,
 }
 
 impl ProtocolContext {
-    pub(crate) fn new(hash: Hash, local_peer_id: PeerId, quorum: Quorum) -> ProtocolContext {
+    pub(crate) fn new(
+        hash: Hash,
+        local_peer_id: PeerId,
+        group_id: u64,
+        quorum: Quorum,
+    ) -> ProtocolContext {
         ProtocolContext {
             local_peer_id,
             hash,
             echo: HashSet::new(),
             vote: HashSet::new(),
+            group_id,
             quorum,
             delivered: false,
+            started_at: Instant::now(),
         }
     }
 
@@ -57,11 +70,11 @@ impl ProtocolContext {
         self.vote.insert(peer);
     }
 
-    fn echoed(&self) -> bool {
+    pub(crate) fn echoed(&self) -> bool {
         self.echo.contains(&self.local_peer_id)
     }
 
-    fn voted(&self) -> bool {
+    pub(crate) fn voted(&self) -> bool {
         self.vote.contains(&self.local_peer_id)
     }
 }
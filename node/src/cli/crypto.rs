@@ -1,6 +1,9 @@
+use std::path::PathBuf;
+
 use crate::crypto::{EphemeraKeypair, Keypair};
 use clap::Parser;
 
+use crate::network::libp2p::swarm_key::{generate_swarm_key, write_swarm_key};
 use crate::utilities::crypto::EphemeraPublicKey;
 
 #[derive(Debug, Clone, Parser)]
@@ -13,3 +16,28 @@ impl GenerateKeypairCmd {
         println!("Public key: {:>5}", keypair.public_key().to_base58());
     }
 }
+
+/// Generates a pre-shared key for a private network.
+#[derive(Debug, Clone, Parser)]
+pub struct GenerateSwarmKeyCmd {
+    /// File to write the key to. Prints the key if not given.
+    #[clap(long)]
+    pub path: Option<PathBuf>,
+}
+
+impl GenerateSwarmKeyCmd {
+    /// # Errors
+    /// Returns an error if the key file exists or cannot be written.
+    pub fn execute(self) -> anyhow::Result<()> {
+        let key = generate_swarm_key();
+        match self.path {
+            Some(path) => {
+                write_swarm_key(&path, key)?;
+                println!("Swarm key written to: {}", path.display());
+                println!("Fingerprint: {}", key.fingerprint());
+            }
+            None => print!("{key}"),
+        }
+        Ok(())
+    }
+}
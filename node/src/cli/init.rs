@@ -1,9 +1,10 @@
-use clap::{Args, Parser};
+use clap::{Args, Parser, ValueEnum};
 
 use crate::config::{
-    BlockManagerConfiguration, Configuration, DatabaseConfiguration, HttpConfiguration,
-    Libp2pConfiguration, MembershipKind as ConfigMembershipKind, NodeConfiguration,
-    WebsocketConfiguration,
+    BlockManagerConfiguration, BroadcastConfiguration, Configuration, DatabaseConfiguration,
+    DhtConfiguration, GossipsubConfiguration, HttpConfiguration, Libp2pConfiguration,
+    MembershipKind as ConfigMembershipKind, NodeConfiguration, PingConfiguration, ProposerSchedule,
+    QuorumPolicy, RateLimitConfiguration, TransportProtocol, WebsocketConfiguration,
 };
 use crate::crypto::{EphemeraKeypair, Keypair};
 
@@ -41,6 +42,44 @@ impl From<MembershipKind> for ConfigMembershipKind {
     }
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Transport {
+    /// TCP with Noise and Yamux
+    Tcp,
+    /// QUIC
+    Quic,
+    /// Both TCP and QUIC on the same port
+    Dual,
+}
+
+impl From<Transport> for TransportProtocol {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            Transport::Tcp => TransportProtocol::Tcp,
+            Transport::Quic => TransportProtocol::Quic,
+            Transport::Dual => TransportProtocol::Dual,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Schedule {
+    /// Every producer proposes at every interval
+    All,
+    /// Members take turns by height
+    RoundRobin,
+    /// Members take turns by height in proportion to their weight
+    Weighted,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Role {
+    /// Takes part in reliable broadcast
+    Member,
+    /// Receives and verifies delivered blocks but doesn't take part in broadcast
+    Observer,
+}
+
 #[derive(Parser)]
 pub struct Cmd {
     /// Name of the node
@@ -52,12 +91,21 @@ pub struct Cmd {
     /// The port which Ephemera uses for peer to peer communication
     #[clap(long, default_value = DEFAULT_LISTEN_PORT)]
     pub protocol_port: u16,
+    /// The transport for peer to peer communication
+    #[clap(long, value_enum, default_value_t = Transport::Tcp)]
+    pub transport: Transport,
+    /// Pre-shared key file of a private network, see `generate-swarm-key`
+    #[clap(long)]
+    pub swarm_key_path: Option<String>,
     /// The port which Ephemera listens on for websocket subscriptions
     #[clap(long)]
     pub websocket_port: u16,
     /// The port which Ephemera listens on for http api
     #[clap(long)]
     pub http_api_port: u16,
+    /// Bearer token for the admin http api, which is disabled without it
+    #[clap(long)]
+    pub admin_token: Option<String>,
     /// Either this node produces blocks or not
     #[clap(long, default_value_t = true)]
     pub block_producer: bool,
@@ -67,12 +115,24 @@ pub struct Cmd {
     /// When next block is created before preious one is finished, should we repeat it with the same messages
     #[clap(long, default_value_t = false)]
     pub repeat_last_block_messages: bool,
+    /// Which producers propose a block at each height
+    #[clap(long, value_enum, default_value_t = Schedule::All)]
+    pub proposer_schedule: Schedule,
+    /// Seconds after which the next member proposes if the scheduled one didn't
+    #[clap(long, default_value_t = 90)]
+    pub proposer_timeout_sec: u64,
     /// The interval at which Ephemera requests the list of members
     #[clap(long, default_value_t = 60 * 60)]
     pub members_provider_delay_sec: u64,
     /// A rule how to choose members based on their online status
     #[command(flatten)]
     pub membership_kind: MembershipKind,
+    /// Use crash fault tolerant(simple majority) quorum instead of byzantine fault tolerant one
+    #[clap(long, default_value_t = false)]
+    pub crash_fault_quorum: bool,
+    /// The role of the node in the cluster
+    #[clap(long, value_enum, default_value_t = Role::Member)]
+    pub role: Role,
 }
 
 impl Cmd {
@@ -103,13 +163,23 @@ impl Cmd {
             node: NodeConfiguration {
                 ip: self.ip,
                 private_key,
+                observer: matches!(self.role, Role::Observer),
             },
             libp2p: Libp2pConfiguration {
                 port: self.protocol_port,
+                transport: self.transport.into(),
+                swarm_key_path: self.swarm_key_path,
                 ephemera_msg_topic_name: DEFAULT_MESSAGES_TOPIC_NAME.to_string(),
                 heartbeat_interval_sec: DEFAULT_HEARTBEAT_INTERVAL_SEC,
                 members_provider_delay_sec: self.members_provider_delay_sec,
                 membership_kind: self.membership_kind.into(),
+                gossipsub: GossipsubConfiguration::default(),
+                rate_limit: RateLimitConfiguration::default(),
+                grace_list: vec![],
+                dht: DhtConfiguration::default(),
+                ping: PingConfiguration::default(),
+                membership_authority: None,
+                health_filter: None,
             },
             storage: DatabaseConfiguration {
                 rocksdb_path: rocksdb_path.as_os_str().to_str().unwrap().to_string(),
@@ -121,12 +191,30 @@ impl Cmd {
             },
             http: HttpConfiguration {
                 port: self.http_api_port,
+                admin_token: self.admin_token.clone(),
             },
             block_manager: BlockManagerConfiguration {
                 producer: self.block_producer,
                 creation_interval_sec: self.block_creation_interval_sec,
                 repeat_last_block_messages: self.repeat_last_block_messages,
+                proposer_schedule: match self.proposer_schedule {
+                    Schedule::All => ProposerSchedule::All,
+                    Schedule::RoundRobin => ProposerSchedule::RoundRobin {
+                        timeout_sec: self.proposer_timeout_sec,
+                    },
+                    Schedule::Weighted => ProposerSchedule::Weighted {
+                        timeout_sec: self.proposer_timeout_sec,
+                    },
+                },
+            },
+            broadcast: BroadcastConfiguration {
+                quorum_policy: if self.crash_fault_quorum {
+                    QuorumPolicy::CrashFault
+                } else {
+                    QuorumPolicy::Bft
+                },
             },
+            channels: vec![],
         };
 
         if let Err(err) = configuration.try_write_home_dir(&self.node_name) {
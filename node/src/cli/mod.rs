@@ -22,6 +22,7 @@ pub enum Subcommand {
     InitLocalPeersConfig(peers::CreateLocalPeersConfiguration),
     RunNode(run_node::RunExternalNodeCmd),
     GenerateKeypair(crypto::GenerateKeypairCmd),
+    GenerateSwarmKey(crypto::GenerateSwarmKeyCmd),
     UpdateConfig(config::UpdateConfigCmd),
 }
 
@@ -40,6 +41,7 @@ impl Cli {
             Subcommand::GenerateKeypair(_) => {
                 GenerateKeypairCmd::execute();
             }
+            Subcommand::GenerateSwarmKey(generate_swarm_key) => generate_swarm_key.execute()?,
             Subcommand::UpdateConfig(update_config) => {
                 update_config.execute();
             }
@@ -1,28 +1,69 @@
 //! This is used to create local peers configuration file. Useful for local cluster development.
 
+use std::path::PathBuf;
+
 use crate::cli::PEERS_CONFIG_FILE;
 use clap::Parser;
 
-use crate::config::Configuration;
+use crate::config::{Configuration, TransportProtocol};
 use crate::crypto::{EphemeraKeypair, EphemeraPublicKey, Keypair};
-use crate::membership::PeerSetting;
+use crate::membership::{
+    read_authority_keypair, JsonPeerInfo, PeerSetting, SignedMembership, DEFAULT_PEER_WEIGHT,
+};
 use crate::network::members::ConfigPeers;
+use crate::utilities::time::EphemeraTime;
 
 #[derive(Debug, Clone, Parser)]
-pub struct CreateLocalPeersConfiguration;
+pub struct CreateLocalPeersConfiguration {
+    /// File with an authority keypair to sign the peers list with, see `generate-keypair`.
+    /// Can be given many times. If given, the peers list is written as a signed membership.
+    #[clap(long)]
+    pub sign_with_file: Vec<PathBuf>,
+    /// Epoch of the signed membership.
+    #[clap(long, default_value_t = 1)]
+    pub epoch: u64,
+    /// How long the signed membership is valid.
+    #[clap(long, default_value_t = 7 * 24 * 60 * 60)]
+    pub valid_for_sec: u64,
+    /// Height of the local block after which nodes switch to the signed membership.
+    #[clap(long)]
+    pub activation_height: Option<u64>,
+}
 
 impl CreateLocalPeersConfiguration {
     /// # Panics
     /// Panics if the configuration file cannot be written.
     pub fn execute(self) {
         let peers = Self::from_ephemera_dev_cluster_conf().unwrap();
-        let config_peers = ConfigPeers::new(peers);
 
         let peers_conf_path = Configuration::ephemera_root_dir()
             .unwrap()
             .join(PEERS_CONFIG_FILE);
 
-        config_peers.try_write(peers_conf_path).unwrap();
+        if self.sign_with_file.is_empty() {
+            let config_peers = ConfigPeers::new(peers);
+            config_peers.try_write(peers_conf_path).unwrap();
+        } else {
+            let membership = self.signed_membership(peers).unwrap();
+            membership.try_write(peers_conf_path).unwrap();
+        }
+    }
+
+    fn signed_membership(&self, peers: Vec<PeerSetting>) -> anyhow::Result<SignedMembership> {
+        let peers = peers
+            .into_iter()
+            .map(|peer| {
+                JsonPeerInfo::new(peer.name, peer.address, peer.public_key).with_weight(peer.weight)
+            })
+            .collect();
+        let valid_from = EphemeraTime::now();
+        let valid_until = valid_from + self.valid_for_sec * 1000;
+        let mut membership = SignedMembership::new(self.epoch, valid_from, valid_until, peers);
+        membership.activation_height = self.activation_height;
+        for path in &self.sign_with_file {
+            membership.sign(&read_authority_keypair(path)?)?;
+        }
+        Ok(membership)
     }
 
     //LOCAL DEV CLUSTER ONLY
@@ -54,8 +95,16 @@ impl CreateLocalPeersConfiguration {
 
                 let peer = PeerSetting {
                     name: node_name.to_string(),
-                    address: format!("/ip4/{}/tcp/{}", node_info.ip, conf.libp2p.port),
+                    address: match conf.libp2p.transport {
+                        TransportProtocol::Quic => {
+                            format!("/ip4/{}/udp/{}/quic-v1", node_info.ip, conf.libp2p.port)
+                        }
+                        TransportProtocol::Tcp | TransportProtocol::Dual => {
+                            format!("/ip4/{}/tcp/{}", node_info.ip, conf.libp2p.port)
+                        }
+                    },
                     public_key: keypair.public_key().to_base58(),
+                    weight: DEFAULT_PEER_WEIGHT,
                 };
                 peers.push(peer);
 
@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -21,6 +22,9 @@ use crate::{
     EphemeraStarterInit,
 };
 
+/// Default file of the membership authority epoch, in the directory of `sqlite_path`.
+const MEMBERSHIP_EPOCH_FILE: &str = "membership_epoch";
+
 #[derive(Clone, Debug)]
 pub struct HttpMembersProviderArg {
     pub url: Url,
@@ -54,11 +58,16 @@ impl RunExternalNodeCmd {
             Err(err) => anyhow::bail!("Error loading configuration file: {err:?}"),
         };
 
-        let members_provider = Self::config_members_provider_with_path(self.peers_config.clone())?;
-        let ephemera = EphemeraStarterInit::new(ephemera_conf.clone())
+        let members_provider =
+            Self::config_members_provider_with_path(self.peers_config.clone(), &ephemera_conf)?;
+        let mut ephemera_builder = EphemeraStarterInit::new(ephemera_conf.clone())
             .unwrap()
-            .with_application(Dummy)
-            .with_members_provider(members_provider)?
+            .with_application(Dummy);
+        for channel in &ephemera_conf.channels {
+            ephemera_builder = ephemera_builder.with_channel_application(&channel.name, Dummy);
+        }
+        let ephemera = ephemera_builder
+            .with_members_provider_updates(members_provider)?
             .build();
 
         let mut ephemera_shutdown = ephemera.ephemera_handle.shutdown.clone();
@@ -109,11 +118,24 @@ impl RunExternalNodeCmd {
         Ok(peers_conf)
     }
 
-    #[allow(dead_code)]
+    //Peers file has to be a signed membership if membership authority is configured
     fn config_members_provider_with_path(
         peers_conf_path: String,
+        ephemera_conf: &Configuration,
     ) -> anyhow::Result<ConfigMembersProvider> {
-        let peers_conf = match ConfigMembersProvider::init(peers_conf_path) {
+        let peers_conf = match &ephemera_conf.libp2p.membership_authority {
+            Some(authority) => {
+                let mut authority = authority.clone();
+                if authority.epoch_file.is_none() {
+                    let epoch_file = Path::new(&ephemera_conf.storage.sqlite_path)
+                        .with_file_name(MEMBERSHIP_EPOCH_FILE);
+                    authority.epoch_file = Some(epoch_file.to_string_lossy().to_string());
+                }
+                ConfigMembersProvider::init_signed(peers_conf_path, (&authority).try_into()?)
+            }
+            None => ConfigMembersProvider::init(peers_conf_path),
+        };
+        let peers_conf = match peers_conf {
             Ok(conf) => conf,
             Err(err) => anyhow::bail!("Error loading peers file: {err:?}"),
         };
@@ -3,14 +3,23 @@
 //! Default location for the configuration file is `~/.ephemera/ephemera.toml`.
 //! Or relative to a node specific directory `~/.ephemera/<node_name>/ephemera.toml`.
 
+use std::collections::HashSet;
+use std::fmt::Display;
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use config::ConfigError;
 use log::{error, info};
 use serde_derive::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::crypto::PublicKey;
+use crate::membership::SignedMembershipError;
+use crate::network::members::signed::validate_threshold;
+use crate::peer::PeerId;
+
 //TODO - validate configuration at load time
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Configuration {
@@ -26,6 +35,12 @@ pub struct Configuration {
     pub http: HttpConfiguration,
     /// Configuration related to block creation
     pub block_manager: BlockManagerConfiguration,
+    /// Configuration for reliable broadcast
+    #[serde(default)]
+    pub broadcast: BroadcastConfiguration,
+    /// Message channels in addition to the default one
+    #[serde(default)]
+    pub channels: Vec<ChannelConfiguration>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -38,6 +53,18 @@ pub struct NodeConfiguration {
     /// Private key is mandatory for a node to be able to function in the network.
     /// It is used to signe protocol messages and identify node in the network.
     pub private_key: String,
+    /// Observer node connects to the cluster but is not part of the broadcast group, so it doesn't
+    /// affect the quorum. It doesn't create, echo or vote blocks. It receives the blocks members
+    /// have delivered together with their certificates, verifies the quorum, stores them and
+    /// serves the APIs.
+    ///
+    /// Members provider must not list observers. Members accept connections from observers only if
+    /// observers are in their grace list.
+    ///
+    /// With a proposer schedule members share one chain and the observer stores one block per height.
+    /// Without it every producer creates its own block at each height, and the observer stores all of them.
+    #[serde(default)]
+    pub observer: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -53,10 +80,33 @@ pub enum MembershipKind {
     AllOnline,
 }
 
+/// Transport Ephemera uses for peer to peer connections.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportProtocol {
+    /// TCP with Noise encryption and Yamux multiplexing. Peer addresses are `/ip4/<IP>/tcp/<PORT>`.
+    #[default]
+    Tcp,
+    /// QUIC. Peer addresses are `/ip4/<IP>/udp/<PORT>/quic-v1`.
+    Quic,
+    /// Listens on TCP and QUIC at the same port and dials each peer over the transport
+    /// of the address its members provider advertises.
+    Dual,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Libp2pConfiguration {
     /// Port to listen on for libp2p internal connections
     pub port: u16,
+    /// Transport to listen on and dial peers with.
+    #[serde(default)]
+    pub transport: TransportProtocol,
+    /// Path to the pre-shared key file of a private network, in `swarm.key` format.
+    ///
+    /// When set, connections are encrypted with the key before any other handshake, so only
+    /// nodes having the same key can connect. Private network works only over TCP transport.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swarm_key_path: Option<String>,
     /// Gossipsub topic to gossip Ephemera messages between peers. Ephemera listens messages
     /// only from this topic. Invalid topic configuration means that Ephemera is not able to
     /// reach messages from other peers.
@@ -69,9 +119,339 @@ pub struct Libp2pConfiguration {
     /// Ephemera uses rendezvous endpoint as authority to tell which nodes are authorized to participate.
     /// So it should be configured and implemented in a manner that nodes always have the most up to date and
     /// accurate information.
+    ///
+    /// It doesn't apply to [`crate::membership::ConfigMembersProvider`], which watches its file for changes.
     pub members_provider_delay_sec: u64,
     /// Defines how the actual membership is decided. See `[ephemera:]` for more details.
     pub membership_kind: MembershipKind,
+    /// Gossipsub mesh, message validation and peer scoring settings.
+    #[serde(default)]
+    pub gossipsub: GossipsubConfiguration,
+    /// Per peer inbound rate limits and bans.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfiguration,
+    /// Peers allowed to connect without being members, for example observers.
+    ///
+    /// Connections with all other peers outside the current membership are denied, also before the
+    /// first membership is known. Peers in the
+    /// grace list don't take part in reliable broadcast, gossip or DHT.
+    #[serde(default)]
+    pub grace_list: Vec<PeerId>,
+    /// Kademlia DHT record settings.
+    #[serde(default)]
+    pub dht: DhtConfiguration,
+    /// Ping settings. Peers whose last ping failed don't count as online for `membership_kind`.
+    #[serde(default)]
+    pub ping: PingConfiguration,
+    /// Authorities who sign the membership. When set, members provider accepts only membership
+    /// signed by enough of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub membership_authority: Option<MembershipAuthorityConfiguration>,
+    /// Health filter of the membership. When set, unresponsive members are excluded from the
+    /// broadcast group until they recover.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_filter: Option<HealthFilterConfiguration>,
+}
+
+/// Membership authorities.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MembershipAuthorityConfiguration {
+    /// Public keys of the authorities.
+    pub public_keys: Vec<PublicKey>,
+    /// How many authorities have to sign a membership. From one to the number of authorities.
+    pub threshold: usize,
+    /// File where the epoch of the last accepted membership is kept across restarts.
+    /// `run-node` defaults to `membership_epoch` in the directory of `sqlite_path`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_file: Option<String>,
+}
+
+impl MembershipAuthorityConfiguration {
+    /// Checks that the threshold can be reached.
+    ///
+    /// # Errors
+    /// If the threshold is zero or greater than the number of distinct authorities.
+    pub fn validate(&self) -> std::result::Result<(), SignedMembershipError> {
+        let authorities = self.public_keys.iter().collect::<HashSet<_>>().len();
+        validate_threshold(self.threshold, authorities)
+    }
+}
+
+/// Health filter of the membership.
+///
+/// Every member is checked regularly. A check fails if the member isn't connected, its last ping
+/// failed or it hasn't sent a heartbeat in time. Members which fail `max_failures` consecutive
+/// checks are excluded from the broadcast group until they pass `recovery_checks` consecutive
+/// checks.
+///
+/// Only nodes with the filter enabled send heartbeats, so it should be enabled on all nodes of
+/// the cluster. Nodes with membership protocol older than `/ephemera/membership/2.0.0` don't
+/// receive heartbeats.
+///
+/// Exclusions change the broadcast group without an activation height, so the filter can't be
+/// used together with a [`ProposerSchedule`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HealthFilterConfiguration {
+    /// How often members are checked and heartbeats are sent.
+    pub check_interval_sec: u64,
+    /// Longest time without a heartbeat from a member before its check fails.
+    pub heartbeat_timeout_sec: u64,
+    /// Consecutive failed checks after which a member is excluded.
+    pub max_failures: u32,
+    /// Consecutive successful checks after which an excluded member is included again.
+    pub recovery_checks: u32,
+}
+
+impl Default for HealthFilterConfiguration {
+    fn default() -> Self {
+        Self {
+            check_interval_sec: 10,
+            heartbeat_timeout_sec: 30,
+            max_failures: 3,
+            recovery_checks: 3,
+        }
+    }
+}
+
+/// Ping protocol settings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PingConfiguration {
+    /// How often connected peers are pinged.
+    pub interval_sec: u64,
+    /// How long a ping waits for the answer before it fails.
+    pub timeout_sec: u64,
+    /// Consecutive failed pings after which the connection with the peer is closed.
+    pub max_failures: u32,
+}
+
+impl Default for PingConfiguration {
+    fn default() -> Self {
+        Self {
+            interval_sec: 15,
+            timeout_sec: 20,
+            max_failures: 3,
+        }
+    }
+}
+
+/// Kademlia DHT record settings.
+///
+/// Records are signed by their publisher and expire after their TTL. Records which have a
+/// namespaced key `/ns/<public key>/<key>` can be published only by the owner of the public key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DhtConfiguration {
+    /// TTL of published records if the store request doesn't specify it.
+    pub record_ttl_sec: u64,
+    /// Maximum TTL of records. Records from other peers which expire later are rejected.
+    pub max_record_ttl_sec: u64,
+    /// How long a query or store waits for peers before it fails with timeout.
+    pub query_timeout_sec: u64,
+    /// Number of peers which must return a valid record, if the query request doesn't specify it.
+    pub read_quorum: DhtQuorum,
+    /// Number of peers which must store a record, if the store request doesn't specify it.
+    pub write_quorum: DhtQuorum,
+}
+
+impl Default for DhtConfiguration {
+    fn default() -> Self {
+        Self {
+            record_ttl_sec: 24 * 60 * 60,
+            max_record_ttl_sec: 7 * 24 * 60 * 60,
+            query_timeout_sec: 60,
+            read_quorum: DhtQuorum::One,
+            write_quorum: DhtQuorum::One,
+        }
+    }
+}
+
+/// Number of peers which must answer a DHT query or store a record.
+///
+/// `majority` and `all` are relative to the number of other members, at most the DHT replication
+/// factor of 20 peers. Written as `"one"`, `"majority"`, `"all"` or a number, e.g. `"3"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(try_from = "String", into = "String")]
+pub enum DhtQuorum {
+    #[default]
+    One,
+    Majority,
+    All,
+    N(NonZeroUsize),
+}
+
+impl DhtQuorum {
+    /// Number of peers the quorum requires out of `total`.
+    #[must_use]
+    pub fn required(&self, total: NonZeroUsize) -> NonZeroUsize {
+        match self {
+            DhtQuorum::One => NonZeroUsize::MIN,
+            DhtQuorum::Majority => NonZeroUsize::MIN.saturating_add(total.get() / 2),
+            DhtQuorum::All => total,
+            DhtQuorum::N(n) => (*n).min(total),
+        }
+    }
+}
+
+impl FromStr for DhtQuorum {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "one" => Ok(DhtQuorum::One),
+            "majority" => Ok(DhtQuorum::Majority),
+            "all" => Ok(DhtQuorum::All),
+            n => n
+                .parse::<NonZeroUsize>()
+                .map(DhtQuorum::N)
+                .map_err(|_| format!("Invalid DHT quorum: {s}")),
+        }
+    }
+}
+
+impl Display for DhtQuorum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DhtQuorum::One => write!(f, "one"),
+            DhtQuorum::Majority => write!(f, "majority"),
+            DhtQuorum::All => write!(f, "all"),
+            DhtQuorum::N(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl TryFrom<String> for DhtQuorum {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<DhtQuorum> for String {
+    fn from(quorum: DhtQuorum) -> Self {
+        quorum.to_string()
+    }
+}
+
+/// Per peer inbound rate limits.
+///
+/// Each peer has a token bucket for gossiped messages it authored and another for reliable broadcast
+/// requests it sent. Both are shared by all message channels. A bucket holds up to `*_burst` tokens
+/// and refills at `*_per_sec` tokens per second.
+///
+/// Messages and requests over a limit, or failing validation, are dropped. Peers who are not
+/// members are also disconnected and banned for `ban_duration_sec`. Members are never banned,
+/// as it would take them out of the broadcast group.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RateLimitConfiguration {
+    /// Average number of gossiped messages per second a peer can send.
+    pub gossip_messages_per_sec: f64,
+    /// Number of gossiped messages a peer can send at once.
+    pub gossip_burst: u32,
+    /// Average number of reliable broadcast requests per second a peer can send.
+    pub broadcast_requests_per_sec: f64,
+    /// Number of reliable broadcast requests a peer can send at once.
+    pub broadcast_burst: u32,
+    /// How long a peer stays banned.
+    pub ban_duration_sec: u64,
+}
+
+impl Default for RateLimitConfiguration {
+    fn default() -> Self {
+        Self {
+            gossip_messages_per_sec: 100.0,
+            gossip_burst: 500,
+            broadcast_requests_per_sec: 50.0,
+            broadcast_burst: 200,
+            ban_duration_sec: 600,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GossipsubValidationMode {
+    /// Messages must be signed and have a sequence number. Unsigned messages are rejected.
+    #[default]
+    Strict,
+    /// Signature and sequence number are checked only if they are present.
+    Permissive,
+    /// Messages are published without author, signature and sequence number.
+    /// Messages which have any of them are rejected.
+    Anonymous,
+    /// Nothing is checked.
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GossipsubConfiguration {
+    /// Target number of peers in the mesh (`D`).
+    pub mesh_n: usize,
+    /// Minimum number of peers in the mesh before more are added (`D_lo`).
+    pub mesh_n_low: usize,
+    /// Maximum number of peers in the mesh before some are removed (`D_hi`).
+    pub mesh_n_high: usize,
+    /// Maximum size of a gossipsub message in bytes.
+    pub max_transmit_size: usize,
+    /// How long message ids are remembered to detect duplicates.
+    pub duplicate_cache_time_sec: u64,
+    /// How strictly messages authenticity is checked.
+    pub validation_mode: GossipsubValidationMode,
+    /// Peer scoring settings.
+    pub peer_scoring: PeerScoringConfiguration,
+}
+
+impl Default for GossipsubConfiguration {
+    fn default() -> Self {
+        Self {
+            mesh_n: 6,
+            mesh_n_low: 5,
+            mesh_n_high: 12,
+            max_transmit_size: 65536,
+            duplicate_cache_time_sec: 60,
+            validation_mode: GossipsubValidationMode::default(),
+            peer_scoring: PeerScoringConfiguration::default(),
+        }
+    }
+}
+
+/// Gossipsub peer scoring.
+///
+/// Every message received over gossipsub is checked by `Application::check_tx`.
+/// Peers who gossip rejected messages are penalized. Peers with low enough score are
+/// not gossiped with and, eventually, all their messages are ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PeerScoringConfiguration {
+    /// If to score peers at all.
+    pub enabled: bool,
+    /// Weight of the penalty for a rejected message. Must be negative.
+    pub invalid_message_weight: f64,
+    /// How much the rejected messages counter decays every decay interval. Between 0 and 1.
+    pub invalid_message_decay: f64,
+    /// Below this score peers are not gossiped with. Must be negative.
+    pub gossip_threshold: f64,
+    /// Below this score messages are not published to peers. Must be below `gossip_threshold`.
+    pub publish_threshold: f64,
+    /// Below this score all messages from peers are ignored. Must be below `publish_threshold`.
+    pub graylist_threshold: f64,
+}
+
+impl Default for PeerScoringConfiguration {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            invalid_message_weight: -10.0,
+            invalid_message_decay: 0.5,
+            gossip_threshold: -10.0,
+            publish_threshold: -50.0,
+            graylist_threshold: -80.0,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -84,6 +464,32 @@ pub struct DatabaseConfiguration {
     pub create_if_not_exists: bool,
 }
 
+impl DatabaseConfiguration {
+    /// Database of a channel, next to the database of the default channel.
+    ///
+    /// For example `ephemera.sqlite` becomes `ephemera-<channel>.sqlite`.
+    #[must_use]
+    pub(crate) fn for_channel(&self, channel: &str) -> Self {
+        let path = |path: &str| {
+            let path = PathBuf::from(path);
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let file_name = match path.extension() {
+                Some(extension) => format!("{stem}-{channel}.{}", extension.to_string_lossy()),
+                None => format!("{stem}-{channel}"),
+            };
+            path.with_file_name(file_name).to_string_lossy().to_string()
+        };
+        Self {
+            rocksdb_path: path(&self.rocksdb_path),
+            sqlite_path: path(&self.sqlite_path),
+            create_if_not_exists: self.create_if_not_exists,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct WebsocketConfiguration {
     /// Port to listen on for WebSocket subscriptions.
@@ -94,6 +500,11 @@ pub struct WebsocketConfiguration {
 pub struct HttpConfiguration {
     /// Port to listen on for HTTP API requests
     pub port: u16,
+    /// Bearer token which authorizes requests to the admin routes.
+    ///
+    /// Admin routes are disabled when it's not set.
+    #[serde(default)]
+    pub admin_token: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -113,6 +524,9 @@ pub struct BlockManagerConfiguration {
     /// If true, Ephemera will repeat messages from the previous block. Otherwise it will take all messages
     /// from mempool as normally.
     pub repeat_last_block_messages: bool,
+    /// Which producers propose a block at each height.
+    #[serde(default)]
+    pub proposer_schedule: ProposerSchedule,
 }
 
 impl BlockManagerConfiguration {
@@ -121,10 +535,85 @@ impl BlockManagerConfiguration {
             producer,
             creation_interval_sec,
             repeat_last_block_messages: repeat_last_block,
+            proposer_schedule: ProposerSchedule::default(),
         }
     }
 }
 
+/// A message channel isolates the messages of an application from the other channels.
+///
+/// Every channel has its own gossip topic, mempool, chain of blocks, Application and database.
+/// The default channel is configured by [`Configuration::block_manager`]. All channels are broadcast
+/// in the same broadcast group, and group changes are activated by the blocks of the default channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChannelConfiguration {
+    /// Name of the channel. It may contain ASCII letters, digits, `-` and `_`.
+    pub name: String,
+    /// Block production of the channel.
+    pub block_manager: BlockManagerConfiguration,
+}
+
+/// Defines which block producers propose a block at each height.
+///
+/// With a deterministic schedule only the designated member of the broadcast group proposes a block
+/// at the next height, members don't echo blocks of other proposers, and all members commit every
+/// delivered block. So the members share one chain of blocks instead of each having its own.
+///
+/// Members who are not producers are still part of the schedule, the next member takes over their turns
+/// after the timeout. Each member echoes a single block per height, and none from rounds it already
+/// left, so a late block of a timed out proposer and the failover block can't both be delivered. If the
+/// members split between the two blocks so that neither gets a quorum, the chain stalls at that height.
+/// The timeout should be well above `creation_interval_sec` and the time it takes to broadcast a block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProposerSchedule {
+    /// Every producer proposes its own block at every interval. It is up to an Application to decide
+    /// which block to use.
+    #[default]
+    All,
+    /// Members, sorted by peer id, take turns by height.
+    RoundRobin {
+        /// Seconds without a delivered block after which the next member proposes instead.
+        timeout_sec: u64,
+    },
+    /// Like round-robin, but each member proposes as many heights in a row as its weight.
+    Weighted {
+        /// Seconds without a delivered block after which the next member proposes instead.
+        timeout_sec: u64,
+    },
+}
+
+/// Defines how many broadcast group members need to agree on a block during reliable broadcast.
+///
+/// Thresholds are expressed in member weights. When all members have the default weight,
+/// it is the same as counting members.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QuorumPolicy {
+    /// Byzantine fault tolerance. Tolerates `f` faulty members where `f < n/3`.
+    #[default]
+    Bft,
+    /// Crash fault tolerance. Tolerates `f` crashed members where `f < n/2`, i.e. a simple
+    /// majority of members is enough to make progress.
+    CrashFault,
+    /// Explicit thresholds.
+    Explicit {
+        /// Weight of echoes needed to send a vote.
+        echo: u64,
+        /// Weight of votes needed to send a vote when we haven't voted yet.
+        vote: u64,
+        /// Weight of votes needed to deliver a block.
+        deliver: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct BroadcastConfiguration {
+    /// Quorum policy used by reliable broadcast. It is validated against the broadcast group
+    /// every time the group changes.
+    pub quorum_policy: QuorumPolicy,
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     /// This is returned if configuration file exists and user tries to create new one.
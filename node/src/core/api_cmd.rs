@@ -1,37 +1,58 @@
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 
-use log::{debug, error, trace};
+use log::{debug, error, info, trace};
 use lru::LruCache;
-use tokio::sync::oneshot::Sender;
+use tokio::sync::{oneshot::Sender, Mutex};
 
-use crate::api::types::{ApiBlockBroadcastInfo, ApiBroadcastInfo};
-use crate::api::{DhtKV, DhtKey, DhtValue};
+use crate::api::types::{
+    ApiBlockBroadcastInfo, ApiBlockBroadcastProgress, ApiBlockManagerState, ApiBlockManagerStatus,
+    ApiBroadcastInfo, ApiBroadcastProgress, ApiDeniedConnections, ApiDhtQueryResponse,
+    ApiDhtStoreRequest, ApiGroupSnapshot, ApiMemberHealth, ApiPeerBan, ApiPeerInfo,
+    ApiPendingBlock,
+};
+use crate::api::{DhtKey, AUDIT_LOG_TARGET};
+use crate::config::DhtQuorum;
 use crate::ephemera_api::ApiEphemeraMessage;
-use crate::peer::ToPeerId;
+use crate::peer::{PeerId, ToPeerId};
 use crate::{
     api::{
         self,
         application::Application,
-        types::{ApiBlock, ApiCertificate, ApiError},
+        types::{ApiBlock, ApiBlockCertificates, ApiError},
         ToEphemeraApiCmd,
     },
-    block::{manager::BlockManagerError, types::message},
+    block::{
+        manager::{BlockManager, BlockManagerError},
+        types::message,
+    },
     crypto::EphemeraKeypair,
     ephemera_api::ApiEphemeraConfig,
     network::libp2p::ephemera_sender::EphemeraEvent,
+    storage::EphemeraDatabase,
     Ephemera,
 };
 
-type DhtPendingQueryReply = Sender<Result<Option<(Vec<u8>, Vec<u8>)>, ApiError>>;
+/// Maximum number of most recently active broadcasts returned by broadcast progress query.
+const MAX_BROADCAST_PROGRESS_BLOCKS: usize = 100;
+
+/// Longest block creation interval the admin API accepts, one day.
+const MAX_BLOCK_CREATION_INTERVAL_SEC: u64 = 24 * 60 * 60;
+
+type DhtPendingQueryReply = Sender<Result<Option<ApiDhtQueryResponse>, ApiError>>;
+
+type DhtPendingStoreReply = Sender<Result<(), ApiError>>;
 
 pub(crate) struct ApiCmdProcessor {
     pub(crate) dht_query_cache: LruCache<Vec<u8>, Vec<DhtPendingQueryReply>>,
+    pub(crate) dht_store_cache: LruCache<Vec<u8>, Vec<DhtPendingStoreReply>>,
 }
 
 impl ApiCmdProcessor {
     pub(crate) fn new() -> Self {
         Self {
             dht_query_cache: LruCache::new(NonZeroUsize::new(1000).unwrap()),
+            dht_store_cache: LruCache::new(NonZeroUsize::new(1000).unwrap()),
         }
     }
 
@@ -41,33 +62,33 @@ impl ApiCmdProcessor {
     ) -> api::Result<()> {
         trace!("Processing API request: {:?}", cmd);
         match cmd {
-            ToEphemeraApiCmd::SubmitEphemeraMessage(api_msg, reply) => {
+            ToEphemeraApiCmd::SubmitEphemeraMessage(channel, api_msg, reply) => {
                 // Ask application to decide if we should accept this message.
-                Self::submit_message(ephemera, api_msg, reply).await?;
+                Self::submit_message(ephemera, channel, api_msg, reply).await?;
             }
 
-            ToEphemeraApiCmd::QueryBlockByHash(block_hash, reply) => {
-                Self::query_block_by_hash(ephemera, &block_hash, reply).await;
+            ToEphemeraApiCmd::QueryBlockByHash(channel, block_hash, reply) => {
+                Self::query_block_by_hash(ephemera, &channel, &block_hash, reply).await;
             }
 
-            ToEphemeraApiCmd::QueryBlockByHeight(height, reply) => {
-                Self::query_block_by_height(ephemera, height, reply).await;
+            ToEphemeraApiCmd::QueryBlockByHeight(channel, height, reply) => {
+                Self::query_block_by_height(ephemera, &channel, height, reply).await;
             }
 
-            ToEphemeraApiCmd::QueryLastBlock(reply) => {
-                Self::query_last_block(ephemera, reply).await;
+            ToEphemeraApiCmd::QueryLastBlock(channel, reply) => {
+                Self::query_last_block(ephemera, &channel, reply).await;
             }
 
-            ToEphemeraApiCmd::QueryBlockCertificates(block_id, reply) => {
-                Self::query_block_certificates(ephemera, &block_id, reply).await;
+            ToEphemeraApiCmd::QueryBlockCertificates(channel, block_id, reply) => {
+                Self::query_block_certificates(ephemera, &channel, &block_id, reply).await;
             }
 
-            ToEphemeraApiCmd::QueryDht(key, reply) => {
-                Self::query_dht(ephemera, key, reply).await;
+            ToEphemeraApiCmd::QueryDht(key, quorum, reply) => {
+                Self::query_dht(ephemera, key, quorum, reply).await;
             }
 
-            ToEphemeraApiCmd::StoreInDht(key, value, reply) => {
-                Self::store_in_dht(ephemera, key, value, reply).await;
+            ToEphemeraApiCmd::StoreInDht(request, reply) => {
+                Self::store_in_dht(ephemera, request, reply).await;
             }
 
             ToEphemeraApiCmd::QueryEphemeraConfig(reply) => {
@@ -77,6 +98,15 @@ impl ApiCmdProcessor {
             ToEphemeraApiCmd::QueryBroadcastGroup(reply) => {
                 Self::broadcast_group(ephemera, reply);
             }
+            ToEphemeraApiCmd::QueryBroadcastProgress(reply) => {
+                Self::broadcast_progress(ephemera, reply);
+            }
+            ToEphemeraApiCmd::QueryGroupHistory(reply) => {
+                Self::query_group_history(ephemera, reply).await;
+            }
+            ToEphemeraApiCmd::QueryGroupSnapshot(id, reply) => {
+                Self::query_group_snapshot(ephemera, id, reply).await;
+            }
             ToEphemeraApiCmd::QueryBlockBroadcastInfo(hash, reply) => {
                 Self::query_block_broadcast_info(ephemera, &hash, reply).await;
             }
@@ -84,10 +114,299 @@ impl ApiCmdProcessor {
                 Self::verify_message_in_block(ephemera, block_hash, message_hash, index, reply)
                     .await;
             }
+            ToEphemeraApiCmd::QueryBannedPeers(reply) => {
+                Self::banned_peers(ephemera, reply);
+            }
+            ToEphemeraApiCmd::UnbanPeer(peer_id, reply) => {
+                Self::unban_peer(ephemera, &peer_id, reply);
+            }
+            ToEphemeraApiCmd::ClearBans(reply) => {
+                Self::clear_bans(ephemera, reply);
+            }
+            ToEphemeraApiCmd::QueryDeniedConnections(reply) => {
+                Self::denied_connections(ephemera, reply);
+            }
+            ToEphemeraApiCmd::QueryNetworkPeers(reply) => {
+                Self::network_peers(ephemera, reply);
+            }
+            ToEphemeraApiCmd::QueryMembersHealth(reply) => {
+                Self::members_health(ephemera, reply);
+            }
+            ToEphemeraApiCmd::QueryDhtRecords(reply) => {
+                Self::dht_records(ephemera, reply);
+            }
+            ToEphemeraApiCmd::RemoveDhtRecord(key, reply) => {
+                Self::remove_dht_record(ephemera, &key, reply);
+            }
+            ToEphemeraApiCmd::PauseBlockProduction(channel, reply) => {
+                Self::pause_block_production(ephemera, channel, reply).await;
+            }
+            ToEphemeraApiCmd::ResumeBlockProduction(channel, reply) => {
+                Self::resume_block_production(ephemera, channel, reply);
+            }
+            ToEphemeraApiCmd::ProduceBlock(channel, reply) => {
+                Self::produce_block(ephemera, channel, reply);
+            }
+            ToEphemeraApiCmd::SetBlockCreationInterval(channel, interval_sec, reply) => {
+                Self::set_block_creation_interval(ephemera, channel, interval_sec, reply);
+            }
         }
         Ok(())
     }
 
+    async fn pause_block_production<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        channel_name: String,
+        reply: Sender<api::Result<ApiBlockManagerState>>,
+    ) {
+        let Some(channel) = ephemera.channels.get_mut(&channel_name) else {
+            reply
+                .send(Err(ApiError::UnknownChannel(channel_name)))
+                .expect("Error sending PauseBlockProduction response to api");
+            return;
+        };
+        if channel.block_manager.pause_by_admin() {
+            info!(target: AUDIT_LOG_TARGET, "Block production of channel {channel_name} paused");
+        }
+        //Group changes waiting for local blocks would never activate
+        let response = match ephemera.apply_pending_group_updates().await {
+            Ok(()) => Ok(Self::block_manager_state(
+                &ephemera
+                    .channels
+                    .get(&channel_name)
+                    .expect("Channel exists")
+                    .block_manager,
+            )),
+            Err(err) => Err(ApiError::Internal(err.to_string())),
+        };
+        reply
+            .send(response)
+            .expect("Error sending PauseBlockProduction response to api");
+    }
+
+    fn resume_block_production<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        channel_name: String,
+        reply: Sender<api::Result<ApiBlockManagerState>>,
+    ) {
+        let Some(channel) = ephemera.channels.get_mut(&channel_name) else {
+            reply
+                .send(Err(ApiError::UnknownChannel(channel_name)))
+                .expect("Error sending ResumeBlockProduction response to api");
+            return;
+        };
+        let block_manager = &mut channel.block_manager;
+        if block_manager.resume_by_admin() {
+            info!(target: AUDIT_LOG_TARGET, "Block production of channel {channel_name} resumed");
+        }
+        reply
+            .send(Ok(Self::block_manager_state(block_manager)))
+            .expect("Error sending ResumeBlockProduction response to api");
+    }
+
+    fn produce_block<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        channel_name: String,
+        reply: Sender<api::Result<ApiBlockManagerState>>,
+    ) {
+        let Some(channel) = ephemera.channels.get_mut(&channel_name) else {
+            reply
+                .send(Err(ApiError::UnknownChannel(channel_name)))
+                .expect("Error sending ProduceBlock response to api");
+            return;
+        };
+        let block_manager = &mut channel.block_manager;
+        let response = match block_manager.request_block() {
+            Ok(()) => {
+                info!(target: AUDIT_LOG_TARGET, "Block production of channel {channel_name} requested");
+                Ok(Self::block_manager_state(block_manager))
+            }
+            Err(BlockManagerError::ProductionUnavailable(reason)) => {
+                info!(
+                    target: AUDIT_LOG_TARGET,
+                    "Block production request of channel {channel_name} refused: {reason}"
+                );
+                Err(ApiError::BlockProductionUnavailable(reason))
+            }
+            Err(err) => Err(ApiError::Internal(err.to_string())),
+        };
+        reply
+            .send(response)
+            .expect("Error sending ProduceBlock response to api");
+    }
+
+    fn set_block_creation_interval<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        channel_name: String,
+        interval_sec: u64,
+        reply: Sender<api::Result<ApiBlockManagerState>>,
+    ) {
+        let response = if interval_sec == 0 || interval_sec > MAX_BLOCK_CREATION_INTERVAL_SEC {
+            Err(ApiError::InvalidArgument(format!(
+                "Block creation interval must be between 1 and {MAX_BLOCK_CREATION_INTERVAL_SEC} seconds"
+            )))
+        } else if let Some(channel) = ephemera.channels.get_mut(&channel_name) {
+            let block_manager = &mut channel.block_manager;
+            let previous = block_manager.config.creation_interval_sec;
+            block_manager.set_creation_interval(interval_sec);
+            info!(
+                target: AUDIT_LOG_TARGET,
+                "Block creation interval of channel {channel_name} changed from {previous}s to {interval_sec}s"
+            );
+            Ok(Self::block_manager_state(block_manager))
+        } else {
+            Err(ApiError::UnknownChannel(channel_name))
+        };
+        reply
+            .send(response)
+            .expect("Error sending SetBlockCreationInterval response to api");
+    }
+
+    fn channel_storage<A: Application>(
+        ephemera: &Ephemera<A>,
+        channel: &str,
+    ) -> api::Result<Arc<Mutex<Box<dyn EphemeraDatabase>>>> {
+        ephemera
+            .channels
+            .get(channel)
+            .map(|channel| channel.storage.clone())
+            .ok_or_else(|| ApiError::UnknownChannel(channel.to_string()))
+    }
+
+    fn block_manager_state(block_manager: &BlockManager) -> ApiBlockManagerState {
+        let status = if block_manager.is_admin_paused() {
+            ApiBlockManagerStatus::PausedByAdmin
+        } else if block_manager.is_running() {
+            ApiBlockManagerStatus::Running
+        } else {
+            ApiBlockManagerStatus::Paused
+        };
+        let pending_block = block_manager.pending_block().map(|block| ApiPendingBlock {
+            hash: block.get_hash().to_string(),
+            height: block.get_height(),
+        });
+        ApiBlockManagerState {
+            producer: block_manager.config.producer,
+            status,
+            creation_interval_sec: block_manager.config.creation_interval_sec,
+            pending_block,
+            backoff_attempt: block_manager.backoff_attempt(),
+        }
+    }
+
+    fn banned_peers<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<Vec<ApiPeerBan>>>,
+    ) {
+        let mut bans = ephemera
+            .ban_list
+            .banned()
+            .into_iter()
+            .map(|(peer_id, ban)| ApiPeerBan::new(peer_id, &ban))
+            .collect::<Vec<_>>();
+        bans.sort_by_key(|ban| ban.banned_at);
+        reply
+            .send(Ok(bans))
+            .expect("Error sending BannedPeers response to api");
+    }
+
+    fn unban_peer<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        peer_id: &PeerId,
+        reply: Sender<api::Result<bool>>,
+    ) {
+        let unbanned = ephemera.ban_list.unban(peer_id);
+        if unbanned {
+            debug!("Unbanned peer {peer_id}");
+        }
+        reply
+            .send(Ok(unbanned))
+            .expect("Error sending UnbanPeer response to api");
+    }
+
+    fn clear_bans<A: Application>(ephemera: &mut Ephemera<A>, reply: Sender<api::Result<usize>>) {
+        let cleared = ephemera.ban_list.clear();
+        debug!("Cleared {cleared} bans");
+        reply
+            .send(Ok(cleared))
+            .expect("Error sending ClearBans response to api");
+    }
+
+    fn denied_connections<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<ApiDeniedConnections>>,
+    ) {
+        let denied = ApiDeniedConnections {
+            inbound: ephemera.denied_connections.inbound(),
+            outbound: ephemera.denied_connections.outbound(),
+        };
+        reply
+            .send(Ok(denied))
+            .expect("Error sending DeniedConnections response to api");
+    }
+
+    fn network_peers<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<Vec<ApiPeerInfo>>>,
+    ) {
+        let mut peers = ephemera
+            .network_peers
+            .peers_stats()
+            .into_iter()
+            .map(|(peer_id, stats)| ApiPeerInfo::new(peer_id, stats))
+            .collect::<Vec<_>>();
+        peers.sort_by_key(|peer| peer.connected_at);
+        reply
+            .send(Ok(peers))
+            .expect("Error sending NetworkPeers response to api");
+    }
+
+    fn members_health<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<Vec<ApiMemberHealth>>>,
+    ) {
+        let mut members = ephemera
+            .network_peers
+            .members_health()
+            .into_iter()
+            .map(|(peer_id, health)| ApiMemberHealth::new(peer_id, &health))
+            .collect::<Vec<_>>();
+        members.sort_by_key(|member| member.peer_id.to_string());
+        reply
+            .send(Ok(members))
+            .expect("Error sending MembersHealth response to api");
+    }
+
+    fn dht_records<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<Vec<ApiDhtQueryResponse>>>,
+    ) {
+        let mut records = ephemera
+            .dht_store
+            .signed_records()
+            .into_iter()
+            .map(ApiDhtQueryResponse::from)
+            .collect::<Vec<_>>();
+        records.sort_by_key(ApiDhtQueryResponse::key);
+        reply
+            .send(Ok(records))
+            .expect("Error sending DhtRecords response to api");
+    }
+
+    fn remove_dht_record<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        key: &[u8],
+        reply: Sender<api::Result<bool>>,
+    ) {
+        let removed = ephemera.dht_store.remove_record(key);
+        if removed {
+            debug!("Removed DHT record {key:?}");
+        }
+        reply
+            .send(Ok(removed))
+            .expect("Error sending RemoveDhtRecord response to api");
+    }
+
     fn broadcast_group<A: Application>(
         ephemera: &mut Ephemera<A>,
         reply: Sender<api::Result<ApiBroadcastInfo>>,
@@ -100,6 +419,30 @@ impl ApiCmdProcessor {
             .expect("Error sending BroadcastGroup response to api");
     }
 
+    fn broadcast_progress<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<ApiBroadcastProgress>>,
+    ) {
+        let channel = ephemera.channels.default_channel();
+        let block_manager = Self::block_manager_state(&channel.block_manager);
+
+        let blocks = channel
+            .broadcaster
+            .contexts()
+            .take(MAX_BROADCAST_PROGRESS_BLOCKS)
+            .map(ApiBlockBroadcastProgress::new)
+            .collect();
+
+        let progress = ApiBroadcastProgress {
+            local_peer_id: ephemera.node_info.peer_id,
+            block_manager,
+            blocks,
+        };
+        reply
+            .send(Ok(progress))
+            .expect("Error sending BroadcastProgress response to api");
+    }
+
     fn ephemera_config<A: Application>(
         ephemera: &mut Ephemera<A>,
         reply: Sender<api::Result<ApiEphemeraConfig>>,
@@ -111,10 +454,20 @@ impl ApiCmdProcessor {
             websocket_address: node_info.ws_address_ws(),
             public_key: node_info.keypair.public_key().to_string(),
             block_producer: node_info.initial_config.block_manager.producer,
-            block_creation_interval_sec: node_info
-                .initial_config
+            block_creation_interval_sec: ephemera
+                .channels
+                .default_channel()
                 .block_manager
+                .config
                 .creation_interval_sec,
+            quorum_policy: node_info
+                .initial_config
+                .broadcast
+                .quorum_policy
+                .clone()
+                .into(),
+            observer: node_info.initial_config.node.observer,
+            channels: ephemera.channels.names(),
         };
         reply
             .send(Ok(api_config))
@@ -123,36 +476,48 @@ impl ApiCmdProcessor {
 
     async fn store_in_dht<A: Application>(
         ephemera: &mut Ephemera<A>,
-        key: DhtKey,
-        value: DhtValue,
+        request: ApiDhtStoreRequest,
         reply: Sender<api::Result<()>>,
     ) {
-        let response = match ephemera
-            .to_network
-            .send_ephemera_event(EphemeraEvent::StoreInDht { key, value })
-            .await
-        {
-            Ok(_) => Ok(()),
+        let key = request.key();
+        let event = EphemeraEvent::StoreInDht {
+            key: key.clone(),
+            value: request.value(),
+            ttl: request.ttl(),
+            namespaced: request.is_namespaced(),
+            quorum: request.quorum(),
+        };
+        match ephemera.to_network.send_ephemera_event(event).await {
+            Ok(()) => {
+                //Reply when the network has stored the record or failed to
+                ephemera
+                    .api_cmd_processor
+                    .dht_store_cache
+                    .get_or_insert_mut(key, Vec::new)
+                    .push(reply);
+            }
             Err(err) => {
                 error!("Error sending StoreInDht to network: {:?}", err);
-                Err(ApiError::Internal("Failed to store in DHT".to_string()))
+                reply
+                    .send(Err(ApiError::Internal(
+                        "Failed to store in DHT".to_string(),
+                    )))
+                    .expect("Error sending StoreInDht response to api");
             }
-        };
-        reply
-            .send(response)
-            .expect("Error sending StoreInDht response to api");
+        }
     }
 
     async fn query_dht<A: Application>(
         ephemera: &mut Ephemera<A>,
         key: DhtKey,
-        reply: Sender<api::Result<Option<DhtKV>>>,
+        quorum: Option<DhtQuorum>,
+        reply: Sender<api::Result<Option<ApiDhtQueryResponse>>>,
     ) {
-        match ephemera
-            .to_network
-            .send_ephemera_event(EphemeraEvent::QueryDht { key: key.clone() })
-            .await
-        {
+        let event = EphemeraEvent::QueryDht {
+            key: key.clone(),
+            quorum,
+        };
+        match ephemera.to_network.send_ephemera_event(event).await {
             Ok(_) => {
                 //Save the reply channel in a map and send the reply when we get the response from the network
                 ephemera
@@ -172,23 +537,30 @@ impl ApiCmdProcessor {
 
     async fn query_block_certificates<A: Application>(
         ephemera: &mut Ephemera<A>,
+        channel: &str,
         block_id: &str,
-        reply: Sender<api::Result<Option<Vec<ApiCertificate>>>>,
+        reply: Sender<api::Result<Option<ApiBlockCertificates>>>,
     ) {
-        let response = match ephemera
-            .storage
-            .lock()
-            .await
-            .get_block_certificates(block_id)
-        {
-            Ok(signatures) => {
-                let certificates = signatures.map(|s| {
-                    s.into_iter()
-                        .map(Into::into)
-                        .collect::<Vec<ApiCertificate>>()
-                });
-                Ok(certificates)
+        let storage = match Self::channel_storage(ephemera, channel) {
+            Ok(storage) => storage,
+            Err(err) => {
+                reply
+                    .send(Err(err))
+                    .expect("Error sending QueryBlockSignatures response to api");
+                return;
             }
+        };
+        let storage = storage.lock().await;
+        let certificates = storage
+            .get_block_certificates(block_id)
+            .and_then(|certificates| {
+                let group = storage.get_block_broadcast_group(block_id)?;
+                Ok(certificates.map(|certificates| {
+                    ApiBlockCertificates::new(certificates, &group.unwrap_or_default())
+                }))
+            });
+        let response = match certificates {
+            Ok(certificates) => Ok(certificates),
             Err(err) => {
                 error!("Error querying block certificates: {:?}", err);
                 Err(ApiError::Internal(
@@ -203,9 +575,19 @@ impl ApiCmdProcessor {
 
     async fn query_last_block<A: Application>(
         ephemera: &mut Ephemera<A>,
+        channel: &str,
         reply: Sender<api::Result<ApiBlock>>,
     ) {
-        let response = match ephemera.storage.lock().await.get_last_block() {
+        let storage = match Self::channel_storage(ephemera, channel) {
+            Ok(storage) => storage,
+            Err(err) => {
+                reply
+                    .send(Err(err))
+                    .expect("Error sending QueryLastBlock response to api");
+                return;
+            }
+        };
+        let response = match storage.lock().await.get_last_block() {
             Ok(Some(block)) => Ok(block.into()),
             Ok(None) => Err(ApiError::Internal(
                 "No blocks found, this is a bug!".to_string(),
@@ -222,10 +604,20 @@ impl ApiCmdProcessor {
 
     async fn query_block_by_height<A: Application>(
         ephemera: &mut Ephemera<A>,
+        channel: &str,
         height: u64,
         reply: Sender<api::Result<Option<ApiBlock>>>,
     ) {
-        let response = match ephemera.storage.lock().await.get_block_by_height(height) {
+        let storage = match Self::channel_storage(ephemera, channel) {
+            Ok(storage) => storage,
+            Err(err) => {
+                reply
+                    .send(Err(err))
+                    .expect("Error sending QueryBlockByHeight response to api");
+                return;
+            }
+        };
+        let response = match storage.lock().await.get_block_by_height(height) {
             Ok(Some(block)) => {
                 let api_block: ApiBlock = block.into();
                 Ok(api_block.into())
@@ -245,10 +637,20 @@ impl ApiCmdProcessor {
 
     async fn query_block_by_hash<A: Application>(
         ephemera: &mut Ephemera<A>,
+        channel: &str,
         block_hash: &str,
         reply: Sender<api::Result<Option<ApiBlock>>>,
     ) {
-        let response = match ephemera.storage.lock().await.get_block_by_hash(block_hash) {
+        let storage = match Self::channel_storage(ephemera, channel) {
+            Ok(storage) => storage,
+            Err(err) => {
+                reply
+                    .send(Err(err))
+                    .expect("Error sending QueryBlockByHash response to api");
+                return;
+            }
+        };
+        let response = match storage.lock().await.get_block_by_hash(block_hash) {
             Ok(Some(block)) => {
                 let api_block: ApiBlock = block.into();
                 Ok(api_block.into())
@@ -268,22 +670,30 @@ impl ApiCmdProcessor {
 
     async fn submit_message<A: Application>(
         ephemera: &mut Ephemera<A>,
+        channel_name: String,
         api_msg: Box<ApiEphemeraMessage>,
         reply: Sender<api::Result<()>>,
     ) -> api::Result<()> {
-        let response = match ephemera.application.check_tx(*api_msg.clone()) {
+        let Some(channel) = ephemera.channels.get_mut(&channel_name) else {
+            reply
+                .send(Err(ApiError::UnknownChannel(channel_name)))
+                .expect("Error sending SubmitEphemeraMessage response to api");
+            return Ok(());
+        };
+        let response = match channel.application.check_tx(*api_msg.clone()) {
             Ok(true) => {
                 trace!("Application accepted ephemera message: {:?}", api_msg);
 
                 // Send to BlockManager to verify it and put into memory pool
                 let ephemera_msg: message::EphemeraMessage = (*api_msg).into();
-                match ephemera.block_manager.on_new_message(ephemera_msg.clone()) {
+                match channel.block_manager.on_new_message(ephemera_msg.clone()) {
                     Ok(_) => {
                         //Gossip to network for other nodes to receive
                         match ephemera
                             .to_network
                             .send_ephemera_event(EphemeraEvent::EphemeraMessage(
                                 ephemera_msg.into(),
+                                channel_name,
                             ))
                             .await
                         {
@@ -296,7 +706,8 @@ impl ApiCmdProcessor {
                     }
                     Err(err) => match err {
                         BlockManagerError::DuplicateMessage(_) => Err(ApiError::DuplicateMessage),
-                        BlockManagerError::BlockManager(err) => {
+                        err @ (BlockManagerError::BlockManager(_)
+                        | BlockManagerError::ProductionUnavailable(_)) => {
                             error!("Error submitting message to block manager: {:?}", err);
                             Err(ApiError::Internal("Failed to submit message".to_string()))
                         }
@@ -345,6 +756,43 @@ impl ApiCmdProcessor {
             .send(response)
             .expect("Error sending QueryBlockBroadcastGroup response to api");
     }
+
+    async fn query_group_history<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        reply: Sender<api::Result<Vec<ApiGroupSnapshot>>>,
+    ) {
+        let response = match ephemera.storage.lock().await.get_group_snapshots() {
+            Ok(snapshots) => Ok(snapshots.into_iter().map(Into::into).collect()),
+            Err(err) => {
+                error!("Error querying group history: {:?}", err);
+                Err(ApiError::Internal(
+                    "Failed to query group history".to_string(),
+                ))
+            }
+        };
+        reply
+            .send(response)
+            .expect("Error sending QueryGroupHistory response to api");
+    }
+
+    async fn query_group_snapshot<A: Application>(
+        ephemera: &mut Ephemera<A>,
+        id: u64,
+        reply: Sender<api::Result<Option<ApiGroupSnapshot>>>,
+    ) {
+        let response = match ephemera.storage.lock().await.get_group_snapshot(id) {
+            Ok(snapshot) => Ok(snapshot.map(Into::into)),
+            Err(err) => {
+                error!("Error querying group snapshot: {:?}", err);
+                Err(ApiError::Internal(
+                    "Failed to query group snapshot".to_string(),
+                ))
+            }
+        };
+        reply
+            .send(response)
+            .expect("Error sending QueryGroupSnapshot response to api");
+    }
     async fn verify_message_in_block<A: Application>(
         ephemera: &mut Ephemera<A>,
         block_hash: String,
@@ -1,9 +1,14 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::future::Future;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::anyhow;
 use futures_util::future::BoxFuture;
 use futures_util::FutureExt;
+use libp2p::pnet::PreSharedKey;
 use log::{debug, error, info};
 use tokio::sync::Mutex;
 
@@ -17,16 +22,19 @@ use crate::{
     block::{builder::BlockManagerBuilder, manager::BlockManager},
     broadcast::bracha::broadcast::Broadcaster,
     broadcast::group::BroadcastGroup,
-    config::Configuration,
+    config::{BlockManagerConfiguration, Configuration, DatabaseConfiguration, ProposerSchedule},
     core::{
         api_cmd::ApiCmdProcessor,
+        channel::{self, header_channel, Channel, Channels},
         shutdown::{Handle, ShutdownManager},
     },
     crypto::Keypair,
     membership,
-    membership::PeerInfo,
+    membership::{MembersProvider, MembersUpdate, PollingMembersProvider},
     network::libp2p::{
-        ephemera_sender::EphemeraToNetworkSender, network_sender::NetCommunicationReceiver,
+        ban_list::BanList, behaviours::membership::DeniedConnections, behaviours::TransportKind,
+        dht::store::DhtRecordStore, ephemera_sender::EphemeraToNetworkSender,
+        network_sender::NetCommunicationReceiver, peers::NetworkPeers, swarm_key::read_swarm_key,
         swarm_network::SwarmNetwork,
     },
     peer::{PeerId, ToPeerId},
@@ -45,11 +53,40 @@ pub(crate) struct NodeInfo {
     pub(crate) peer_id: PeerId,
     pub(crate) keypair: Arc<Keypair>,
     pub(crate) initial_config: Configuration,
+    pub(crate) transport: TransportKind,
+    /// Pre-shared key of the private network, if the node is part of one.
+    pub(crate) swarm_key: Option<PreSharedKey>,
+}
+
+fn proposer_schedules(config: &Configuration) -> impl Iterator<Item = &ProposerSchedule> {
+    std::iter::once(&config.block_manager)
+        .chain(config.channels.iter().map(|channel| &channel.block_manager))
+        .map(|block_manager| &block_manager.proposer_schedule)
+}
+
+//Health exclusions change the group right away, without an activation height. Members would
+//compute the proposers from different groups.
+fn validate_health_filter(config: &Configuration) -> anyhow::Result<()> {
+    if config.libp2p.health_filter.is_none() {
+        return Ok(());
+    }
+    if proposer_schedules(config).any(|schedule| *schedule != ProposerSchedule::All) {
+        return Err(anyhow!(
+            "Health filter can't be used together with a proposer schedule"
+        ));
+    }
+    Ok(())
 }
 
 impl NodeInfo {
     pub(crate) fn new(config: Configuration) -> anyhow::Result<Self> {
         let keypair = KeyManager::read_keypair_from_str(&config.node.private_key)?;
+        let swarm_key = config
+            .libp2p
+            .swarm_key_path
+            .as_ref()
+            .map(read_swarm_key)
+            .transpose()?;
         let info = Self {
             ip: config.node.ip.clone(),
             protocol_port: config.libp2p.port,
@@ -57,13 +94,29 @@ impl NodeInfo {
             ws_port: config.websocket.port,
             peer_id: keypair.peer_id(),
             keypair,
+            transport: config.libp2p.transport.into(),
             initial_config: config,
+            swarm_key,
         };
         Ok(info)
     }
 
+    /// The main address of the node. With dual-stack transport it's the TCP address.
     pub(crate) fn protocol_address(&self) -> String {
-        format!("/ip4/{}/tcp/{}", self.ip, self.protocol_port)
+        self.listen_addresses().remove(0)
+    }
+
+    /// All addresses the node listens on.
+    pub(crate) fn listen_addresses(&self) -> Vec<String> {
+        let tcp = format!("/ip4/{}/tcp/{}", self.ip, self.protocol_port);
+        let quic = format!("/ip4/{}/udp/{}/quic-v1", self.ip, self.protocol_port);
+        match self.transport {
+            TransportKind::Tcp => vec![tcp],
+            TransportKind::Quic => vec![quic],
+            TransportKind::Dual => vec![tcp, quic],
+            #[cfg(test)]
+            TransportKind::Memory => vec![format!("/memory/{}", self.protocol_port)],
+        }
     }
 
     pub(crate) fn api_address_http(&self) -> String {
@@ -108,6 +161,9 @@ pub struct EphemeraStarterInit {
     broadcaster: Broadcaster,
     api_listener: ApiListener,
     api: CommandExecutor,
+    ban_list: BanList,
+    denied_connections: DeniedConnections,
+    network_peers: NetworkPeers,
 }
 
 impl EphemeraStarterInit {
@@ -122,9 +178,20 @@ impl EphemeraStarterInit {
     /// # Errors
     /// * If the node configuration is invalid
     pub fn new(config: Configuration) -> anyhow::Result<Self> {
+        channel::validate_channels(&config.channels)?;
+        validate_health_filter(&config)?;
+        if let Some(authority) = &config.libp2p.membership_authority {
+            authority.validate()?;
+        }
         let instance_info = NodeInfo::new(config.clone())?;
-        let broadcaster = Broadcaster::new(instance_info.peer_id);
+        let broadcaster = Broadcaster::new(
+            instance_info.peer_id,
+            config.broadcast.quorum_policy.clone(),
+        );
         let (api, api_listener) = CommandExecutor::new();
+        let ban_list = BanList::new(Duration::from_secs(
+            config.libp2p.rate_limit.ban_duration_sec,
+        ));
 
         let builder = EphemeraStarterInit {
             config,
@@ -132,10 +199,20 @@ impl EphemeraStarterInit {
             broadcaster,
             api_listener,
             api,
+            ban_list,
+            denied_connections: DeniedConnections::default(),
+            network_peers: NetworkPeers::default(),
         };
         Ok(builder)
     }
 
+    /// Makes the node use in-process memory transport instead of TCP.
+    #[cfg(test)]
+    pub(crate) fn with_memory_transport(mut self) -> Self {
+        self.node_info.transport = TransportKind::Memory;
+        self
+    }
+
     pub fn with_application<A: Application>(
         self,
         application: A,
@@ -143,6 +220,7 @@ impl EphemeraStarterInit {
         EphemeraStarterWithApplication {
             init: self,
             application,
+            channel_applications: HashMap::new(),
         }
     }
 }
@@ -157,9 +235,25 @@ struct ServiceInfo {
 pub struct EphemeraStarterWithApplication<A: Application> {
     init: EphemeraStarterInit,
     application: A,
+    channel_applications: HashMap<String, Arc<dyn Application + Send + Sync>>,
 }
 
 impl<A: Application> EphemeraStarterWithApplication<A> {
+    /// Sets the application of a configured channel. Every channel in the configuration needs one.
+    ///
+    /// # Arguments
+    /// * `channel` - name of the channel in [`Configuration::channels`]
+    /// * `application` - [Application] of the channel
+    #[must_use]
+    pub fn with_channel_application<C>(mut self, channel: &str, application: C) -> Self
+    where
+        C: Application + Send + Sync + 'static,
+    {
+        self.channel_applications
+            .insert(channel.to_string(), Arc::new(application));
+        self
+    }
+
     /// Initialize Ephemera with the given application.
     /// It also tries to open the database connection.
     ///
@@ -171,77 +265,160 @@ impl<A: Application> EphemeraStarterWithApplication<A> {
     ///
     /// # Errors
     /// * If the node configuration is invalid or the database connection cannot be opened
-    pub fn with_members_provider<
-        P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
-    >(
+    pub fn with_members_provider<P, T>(
+        self,
+        provider: P,
+    ) -> anyhow::Result<EphemeraStarterWithProvider<A>>
+    where
+        P: Future<Output = membership::Result<T>> + Send + Unpin + 'static,
+        T: Into<MembersUpdate>,
+    {
+        let delay = Duration::from_secs(self.init.config.libp2p.members_provider_delay_sec);
+        self.with_members_provider_updates(PollingMembersProvider::new(provider, delay))
+    }
+
+    /// Initialize Ephemera with a [`MembersProvider`] which yields membership updates as soon as
+    /// membership changes. It also tries to open the database connection.
+    ///
+    /// # Arguments
+    /// * `provider` - [`MembersProvider`] to be used
+    ///
+    /// # Returns
+    /// [`EphemeraStarterWithProvider`]
+    ///
+    /// # Errors
+    /// * If the node configuration is invalid or the database connection cannot be opened
+    pub fn with_members_provider_updates<P: MembersProvider>(
         mut self,
         provider: P,
     ) -> anyhow::Result<EphemeraStarterWithProvider<A>> {
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "sqlite_storage")] {
-                let mut storage = self.connect_sqlite()?;
-                debug!("Connected to sqlite database")
-            } else if #[cfg(feature = "rocksdb_storage")] {
-                let mut storage = self.connect_rocksdb()?;
-                debug!("Connected to rocksdb database")
-            } else {
-                compile_error!("Must enable either sqlite or rocksdb feature");
-            }
-        }
-
-        let block_manager = self.init_block_manager(&mut storage)?;
+        let mut storage = Self::connect(self.init.config.storage.clone())?;
+        let block_manager = self.init_block_manager(
+            self.init.config.block_manager.clone(),
+            None,
+            storage.as_mut(),
+        )?;
+        let channels = self.init_channels()?;
+        let last_group_snapshot_id = storage.get_last_group_snapshot_id()?.unwrap_or_default();
+        let dht_store =
+            DhtRecordStore::open(self.init.node_info.peer_id.into(), storage.dht_records()?)?;
 
         let (mut shutdown_manager, shutdown_handle) = ShutdownManager::init();
 
         let mut service_data = ServiceInfo::default();
-        let services = self.init_services(&mut service_data, &mut shutdown_manager, provider)?;
+        let services = self.init_services(
+            &mut service_data,
+            &mut shutdown_manager,
+            provider,
+            dht_store.clone(),
+        )?;
 
         Ok(EphemeraStarterWithProvider {
             with_application: self,
             block_manager: Some(block_manager),
+            channels,
+            broadcast_group: Some(BroadcastGroup::new(last_group_snapshot_id)),
             service_data,
             services,
-            storage: Some(Box::new(storage)),
+            storage: Some(storage),
+            dht_store,
             shutdown_manager: Some(shutdown_manager),
             shutdown_handle: Some(shutdown_handle),
         })
     }
 
+    fn connect(config: DatabaseConfiguration) -> anyhow::Result<Box<dyn EphemeraDatabase>> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "sqlite_storage")] {
+                let storage = Self::connect_sqlite(config)?;
+                debug!("Connected to sqlite database");
+            } else if #[cfg(feature = "rocksdb_storage")] {
+                let storage = Self::connect_rocksdb(config)?;
+                debug!("Connected to rocksdb database");
+            } else {
+                compile_error!("Must enable either sqlite or rocksdb feature");
+            }
+        }
+        Ok(Box::new(storage))
+    }
+
     //allocate database connection
     #[cfg(feature = "rocksdb_storage")]
-    fn connect_rocksdb(&self) -> anyhow::Result<RocksDbStorage> {
+    fn connect_rocksdb(config: DatabaseConfiguration) -> anyhow::Result<RocksDbStorage> {
         info!("Opening database...");
-        RocksDbStorage::open(self.init.config.storage.clone())
-            .map_err(|e| anyhow::anyhow!("Failed to open database: {}", e))
+        RocksDbStorage::open(config).map_err(|e| anyhow::anyhow!("Failed to open database: {}", e))
     }
 
     #[cfg(feature = "sqlite_storage")]
-    fn connect_sqlite(&mut self) -> anyhow::Result<SqliteStorage> {
+    fn connect_sqlite(config: DatabaseConfiguration) -> anyhow::Result<SqliteStorage> {
         info!("Opening database...");
-        SqliteStorage::open(self.init.config.storage.clone())
-            .map_err(|e| anyhow::anyhow!("Failed to open database: {}", e))
+        SqliteStorage::open(config).map_err(|e| anyhow::anyhow!("Failed to open database: {}", e))
     }
 
     fn init_block_manager<D: EphemeraDatabase + ?Sized>(
         &mut self,
+        block_manager_configuration: BlockManagerConfiguration,
+        channel: Option<String>,
         db: &mut D,
     ) -> anyhow::Result<BlockManager> {
-        let block_manager_configuration = self.init.config.block_manager.clone();
         let keypair = self.init.node_info.keypair.clone();
-        let builder = BlockManagerBuilder::new(block_manager_configuration, keypair);
+        let builder =
+            BlockManagerBuilder::new(block_manager_configuration, keypair).with_channel(channel);
         builder.build(db)
     }
 
-    fn init_services<
-        P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
-    >(
+    //Channels other than the default one, each with its own database and block manager
+    fn init_channels(&mut self) -> anyhow::Result<HashMap<String, Channel>> {
+        let configured = self.init.config.channels.clone();
+        if let Some(name) = self
+            .channel_applications
+            .keys()
+            .find(|name| !configured.iter().any(|channel| &channel.name == *name))
+        {
+            return Err(anyhow!("Application given for unknown channel: {name}"));
+        }
+
+        let mut channels = HashMap::new();
+        for config in configured {
+            let application = self
+                .channel_applications
+                .remove(&config.name)
+                .ok_or(anyhow!("No application for channel: {}", config.name))?;
+            let mut storage = Self::connect(self.init.config.storage.for_channel(&config.name))?;
+            let block_manager = self.init_block_manager(
+                config.block_manager,
+                header_channel(&config.name),
+                storage.as_mut(),
+            )?;
+            let broadcaster = Broadcaster::new(
+                self.init.node_info.peer_id,
+                self.init.config.broadcast.quorum_policy.clone(),
+            );
+            let channel = Channel {
+                block_manager,
+                broadcaster,
+                storage: Arc::new(Mutex::new(storage)),
+                application,
+            };
+            channels.insert(config.name, channel);
+        }
+        Ok(channels)
+    }
+
+    fn init_services<P: MembersProvider>(
         &mut self,
         service_data: &mut ServiceInfo,
         shutdown_manager: &mut ShutdownManager,
         provider: P,
+        dht_store: DhtRecordStore,
     ) -> anyhow::Result<Vec<BoxFuture<'static, anyhow::Result<()>>>> {
         let services = vec![
-            self.init_libp2p(service_data, shutdown_manager.subscribe(), provider)?,
+            self.init_libp2p(
+                service_data,
+                shutdown_manager.subscribe(),
+                provider,
+                dht_store,
+            )?,
             self.init_http(shutdown_manager.subscribe())?,
             self.init_websocket(service_data, shutdown_manager.subscribe()),
         ];
@@ -305,18 +482,23 @@ impl<A: Application> EphemeraStarterWithApplication<A> {
         Ok(fut)
     }
 
-    fn init_libp2p<
-        P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
-    >(
+    fn init_libp2p<P: MembersProvider>(
         &mut self,
         service_data: &mut ServiceInfo,
         mut shutdown: Shutdown,
         provider: P,
+        dht_store: DhtRecordStore,
     ) -> anyhow::Result<BoxFuture<'static, anyhow::Result<()>>> {
         info!("Starting network...",);
 
-        let (mut network, from_network, to_network) =
-            SwarmNetwork::new(self.init.node_info.clone(), provider)?;
+        let (mut network, from_network, to_network) = SwarmNetwork::new(
+            self.init.node_info.clone(),
+            provider,
+            self.init.ban_list.clone(),
+            self.init.denied_connections.clone(),
+            dht_store,
+            self.init.network_peers.clone(),
+        )?;
 
         service_data.from_network = Some(from_network);
         service_data.to_network = Some(to_network);
@@ -349,8 +531,11 @@ where
 {
     with_application: EphemeraStarterWithApplication<A>,
     block_manager: Option<BlockManager>,
+    channels: HashMap<String, Channel>,
+    broadcast_group: Option<BroadcastGroup>,
     service_data: ServiceInfo,
     storage: Option<Box<dyn EphemeraDatabase>>,
+    dht_store: DhtRecordStore,
     services: Vec<BoxFuture<'static, anyhow::Result<()>>>,
     shutdown_manager: Option<ShutdownManager>,
     shutdown_handle: Option<Handle>,
@@ -358,7 +543,7 @@ where
 
 impl<A> EphemeraStarterWithProvider<A>
 where
-    A: Application + 'static,
+    A: Application + Send + Sync + 'static,
 {
     pub fn build(self) -> Ephemera<A> {
         self.ephemera()
@@ -373,6 +558,9 @@ where
         let node_info = self.with_application.init.node_info;
         let application = self.with_application.application;
         let block_manager = self.block_manager.expect("Block manager not initialized");
+        let broadcast_group = self
+            .broadcast_group
+            .expect("Broadcast group not initialized");
         let broadcaster = self.with_application.init.broadcaster;
         let from_network = self
             .service_data
@@ -382,12 +570,23 @@ where
             .service_data
             .to_network
             .expect("To network not initialized");
-        let storage = self.storage.expect("Storage not initialized");
+        let storage = Arc::new(Mutex::new(self.storage.expect("Storage not initialized")));
+        let default_channel = Channel {
+            block_manager,
+            broadcaster,
+            storage: storage.clone(),
+            application: Arc::new(application),
+        };
+        let channels = Channels::new(default_channel, self.channels);
         let ws_message_broadcast = self
             .service_data
             .ws_message_broadcast
             .expect("WS message broadcast not initialized");
         let api_listener = self.with_application.init.api_listener;
+        let ban_list = self.with_application.init.ban_list;
+        let denied_connections = self.with_application.init.denied_connections;
+        let network_peers = self.with_application.init.network_peers;
+        let dht_store = self.dht_store;
         let shutdown_manager = self
             .shutdown_manager
             .expect("Shutdown manager not initialized");
@@ -395,19 +594,22 @@ where
 
         Ephemera {
             node_info,
-            block_manager,
-            broadcaster,
+            channels,
             from_network,
             to_network,
-            broadcast_group: BroadcastGroup::new(),
-            storage: Arc::new(Mutex::new(storage)),
+            broadcast_group,
+            storage,
             ws_message_broadcast,
             api_listener,
             api_cmd_processor: ApiCmdProcessor::new(),
-            application: application.into(),
+            application: PhantomData,
             ephemera_handle,
             shutdown_manager,
             services,
+            ban_list,
+            denied_connections,
+            network_peers,
+            dht_store,
         }
     }
 }
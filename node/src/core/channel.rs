@@ -0,0 +1,194 @@
+//! Message channels.
+//!
+//! Every node has the default channel, configured by the block manager configuration, and the
+//! channels from [`ChannelConfiguration`]. Each channel has its own mempool, chain of blocks,
+//! reliable broadcast instances, Application and database. Network gossips the messages of each
+//! channel in its own topic, and the blocks of a channel have its name in the header.
+//!
+//! All channels share the broadcast group. Group changes with activation height are activated by
+//! the blocks of the default channel.
+
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::anyhow;
+use futures::Stream;
+use futures_util::StreamExt;
+use log::{error, trace};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::application::Application,
+    block::{
+        manager::BlockManager,
+        types::{block::Block, message::EphemeraMessage},
+    },
+    broadcast::bracha::broadcast::Broadcaster,
+    config::ChannelConfiguration,
+    network::libp2p::network_sender::MessageValidation,
+    storage::EphemeraDatabase,
+    utilities::crypto::Certificate,
+};
+
+/// Name of the channel every node has.
+pub(crate) const DEFAULT_CHANNEL: &str = "default";
+
+/// Returns the name of the channel the block belongs to.
+pub(crate) fn block_channel(block: &Block) -> &str {
+    block.header.channel.as_deref().unwrap_or(DEFAULT_CHANNEL)
+}
+
+/// Returns the channel blocks have in the header. It's not set for the default channel.
+pub(crate) fn header_channel(name: &str) -> Option<String> {
+    (name != DEFAULT_CHANNEL).then(|| name.to_string())
+}
+
+/// Checks that channel names are valid and unique.
+pub(crate) fn validate_channels(channels: &[ChannelConfiguration]) -> anyhow::Result<()> {
+    let mut names = HashSet::new();
+    for channel in channels {
+        let name = &channel.name;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("Invalid channel name: '{name}'"));
+        }
+        if name == DEFAULT_CHANNEL {
+            return Err(anyhow!("Channel name '{DEFAULT_CHANNEL}' is reserved"));
+        }
+        if !names.insert(name) {
+            return Err(anyhow!("Channel '{name}' is configured more than once"));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) struct Channel {
+    /// Block manager responsibility includes:
+    /// - Block production and signing
+    /// - Block verification for externally received blocks
+    /// - Message verification sent by clients and gossiped other nodes
+    pub(crate) block_manager: BlockManager,
+
+    /// Broadcaster is making sure that blocks are deterministically agreed by all nodes.
+    pub(crate) broadcaster: Broadcaster,
+
+    /// Database of the blocks of the channel.
+    pub(crate) storage: Arc<Mutex<Box<dyn EphemeraDatabase>>>,
+
+    /// An implementation of Application trait. Provides callbacks to broadcast.
+    pub(crate) application: Arc<dyn Application + Send + Sync>,
+}
+
+impl Channel {
+    /// Asks the Application to check a message from network, and puts it into the mempool if it's accepted.
+    pub(crate) fn on_network_message(&mut self, message: EphemeraMessage) -> MessageValidation {
+        //Only Application checks if messages are valid(possibly message origin).
+        //For messages we don't check if sender belongs to group.
+        match self.application.check_tx(message.clone().into()) {
+            Ok(true) => {
+                trace!("Application accepted message: {:?}", message);
+
+                // Send to BlockManager to store in mempool.
+                if let Err(err) = self.block_manager.on_new_message(message) {
+                    error!("Error sending signed message to block manager: {:?}", err);
+                }
+                MessageValidation::Accept
+            }
+            Ok(false) => {
+                trace!("Application rejected message: {:?}", message);
+                MessageValidation::Reject
+            }
+            Err(err) => {
+                error!("Application check_tx failed: {:?}", err);
+                MessageValidation::Ignore
+            }
+        }
+    }
+}
+
+/// All channels of the node, the default one included.
+pub(crate) struct Channels {
+    channels: HashMap<String, Channel>,
+}
+
+impl Channels {
+    pub(crate) fn new(default: Channel, others: HashMap<String, Channel>) -> Self {
+        let mut channels = others;
+        channels.insert(DEFAULT_CHANNEL.to_string(), default);
+        Self { channels }
+    }
+
+    pub(crate) fn default_channel(&self) -> &Channel {
+        &self.channels[DEFAULT_CHANNEL]
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Channel> {
+        self.channels.get(name)
+    }
+
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut Channel> {
+        self.channels.get_mut(name)
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Channel)> {
+        self.channels.iter_mut()
+    }
+
+    /// Names of the channels, sorted.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names = self.channels.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+/// New blocks of the block managers of all channels, together with the channel name.
+impl Stream for Channels {
+    type Item = (String, Block, Certificate);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        //Every block manager is polled, so that all of them get woken up
+        for (name, channel) in &mut self.channels {
+            if let Poll::Ready(Some((block, certificate))) =
+                channel.block_manager.poll_next_unpin(cx)
+            {
+                return Poll::Ready(Some((name.clone(), block, certificate)));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::BlockManagerConfiguration;
+
+    use super::*;
+
+    fn channel(name: &str) -> ChannelConfiguration {
+        ChannelConfiguration {
+            name: name.to_string(),
+            block_manager: BlockManagerConfiguration::new(true, 10, false),
+        }
+    }
+
+    #[test]
+    fn test_validate_channels() {
+        assert!(validate_channels(&[channel("rewards"), channel("dkg_1")]).is_ok());
+        assert!(validate_channels(&[channel("")]).is_err());
+        assert!(validate_channels(&[channel("metrics/v1")]).is_err());
+        assert!(validate_channels(&[channel(DEFAULT_CHANNEL)]).is_err());
+        assert!(validate_channels(&[channel("rewards"), channel("rewards")]).is_err());
+    }
+
+    #[test]
+    fn test_header_channel() {
+        assert_eq!(header_channel(DEFAULT_CHANNEL), None);
+        assert_eq!(header_channel("rewards"), Some("rewards".to_string()));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
 use anyhow::anyhow;
@@ -7,29 +9,38 @@ use log::{debug, error, info, trace};
 use thiserror::Error;
 use tokio::sync::Mutex;
 
-use crate::broadcast::bracha::quorum::Quorum;
 use crate::storage::DatabaseError;
 use crate::{
-    api::{application::Application, application::CheckBlockResult, ApiListener},
-    block::{manager::BlockManager, types::block::Block},
+    api::{
+        application::Application,
+        application::CheckBlockResult,
+        types::{ApiDhtQueryResponse, ApiError},
+        ApiListener,
+    },
+    block::types::block::Block,
     broadcast::{
-        bracha::broadcast::BroadcastResponse, bracha::broadcast::Broadcaster,
-        group::BroadcastGroup, RbMsg,
+        bracha::broadcast::BroadcastResponse, delivered::DeliveredBlock, group::BroadcastGroup,
+        MessageType, RbMsg,
     },
     core::{
         api_cmd::ApiCmdProcessor,
         builder::{EphemeraHandle, NodeInfo},
+        channel::{block_channel, Channels, DEFAULT_CHANNEL},
         shutdown::ShutdownManager,
     },
     network::{
         libp2p::network_sender::GroupChangeEvent,
         libp2p::{
+            ban_list::{BanList, BanReason},
+            behaviours::membership::DeniedConnections,
+            dht::store::DhtRecordStore,
             ephemera_sender::{EphemeraEvent, EphemeraToNetworkSender},
-            network_sender::{NetCommunicationReceiver, NetworkEvent},
+            network_sender::{MessageValidation, NetCommunicationReceiver, NetworkEvent},
+            peers::NetworkPeers,
         },
     },
-    storage::EphemeraDatabase,
-    utilities::crypto::Certificate,
+    storage::{EphemeraDatabase, StoredGroupSnapshot},
+    utilities::{crypto::Certificate, time::EphemeraTime},
     websocket::ws_manager::WsMessageBroadcaster,
 };
 
@@ -48,14 +59,8 @@ pub struct Ephemera<A: Application> {
     /// Node info
     pub(crate) node_info: NodeInfo,
 
-    /// Block manager responsibility includes:
-    /// - Block production and signing
-    /// - Block verification for externally received blocks
-    /// - Message verification sent by clients and gossiped other nodes
-    pub(crate) block_manager: BlockManager,
-
-    /// Broadcaster is making sure that blocks are deterministically agreed by all nodes.
-    pub(crate) broadcaster: Broadcaster,
+    /// Message channels, each with its own block production, broadcast, storage and Application.
+    pub(crate) channels: Channels,
 
     /// A component which receives messages from network.
     pub(crate) from_network: NetCommunicationReceiver,
@@ -66,7 +71,7 @@ pub struct Ephemera<A: Application> {
     /// A component which keeps track of broadcast group over time.
     pub(crate) broadcast_group: BroadcastGroup,
 
-    /// A component which has mutable access to database.
+    /// A component which has mutable access to database. It's the database of the default channel.
     pub(crate) storage: Arc<Mutex<Box<dyn EphemeraDatabase>>>,
 
     /// A component which broadcasts messages to websocket clients.
@@ -78,8 +83,8 @@ pub struct Ephemera<A: Application> {
     /// A component which processes API requests.
     pub(crate) api_cmd_processor: ApiCmdProcessor,
 
-    /// An implementation of Application trait. Provides callbacks to broadcast.
-    pub(crate) application: Arc<A>,
+    /// Application of the default channel is kept in `channels`.
+    pub(crate) application: PhantomData<A>,
 
     ///Interface to external Rust code
     pub(crate) ephemera_handle: EphemeraHandle,
@@ -89,6 +94,18 @@ pub struct Ephemera<A: Application> {
 
     /// A list of services which are running in background.
     pub(crate) services: Vec<BoxFuture<'static, anyhow::Result<()>>>,
+
+    /// Peers temporarily banned by the network.
+    pub(crate) ban_list: BanList,
+
+    /// Connections denied because the peer is not a member.
+    pub(crate) denied_connections: DeniedConnections,
+
+    /// Connected peers with their latency and versions.
+    pub(crate) network_peers: NetworkPeers,
+
+    /// Kademlia records stored by the node.
+    pub(crate) dht_store: DhtRecordStore,
 }
 
 impl<A: Application> Ephemera<A> {
@@ -119,8 +136,8 @@ impl<A: Application> Ephemera<A> {
         loop {
             tokio::select! {
                 // GENERATING NEW BLOCKS
-                Some((new_block, certificate)) = self.block_manager.next() => {
-                    if let Err(err) = self.process_new_local_block(new_block, certificate).await{
+                Some((channel, new_block, certificate)) = self.channels.next() => {
+                    if let Err(err) = self.process_new_local_block(&channel, new_block, certificate).await{
                         error!("Error processing new block: {:?}", err);
                     }
                 }
@@ -167,42 +184,46 @@ impl<A: Application> Ephemera<A> {
         trace!("New network event: {:?}", net_event);
 
         match net_event {
-            NetworkEvent::EphemeraMessage(em) => {
-                let api_msg = (*em.clone()).into();
-                trace!("New ephemera message from network: {:?}", api_msg);
-
-                //Only Application checks if messages are valid(possibly message origin).
-                //For messages we don't check if sender belongs to group.
+            NetworkEvent::EphemeraMessage(em, source, channel) => {
+                trace!(
+                    "New ephemera message from network in channel {channel}: {:?}",
+                    em
+                );
 
                 // Ask application to decide if we should accept this message.
-                match self.application.check_tx(api_msg) {
-                    Ok(true) => {
-                        trace!("Application accepted message: {:?}", em);
-
-                        // Send to BlockManager to store in mempool.
-                        if let Err(err) = self.block_manager.on_new_message(*em) {
-                            error!("Error sending signed message to block manager: {:?}", err);
-                        }
-                    }
-                    Ok(false) => {
-                        trace!("Application rejected message: {:?}", em);
-                    }
-                    Err(err) => {
-                        error!("Application check_tx failed: {:?}", err);
-                    }
-                }
+                let result = if let Some(channel) = self.channels.get_mut(&channel) {
+                    channel.on_network_message(*em)
+                } else {
+                    trace!("Ignoring message of unknown channel {channel}");
+                    MessageValidation::Ignore
+                };
+
+                //Gossipsub forwards the message only if it's accepted and penalizes the sender if it's rejected.
+                self.to_network
+                    .send_ephemera_event(EphemeraEvent::MessageValidated { source, result })
+                    .await?;
             }
             NetworkEvent::BroadcastMessage(rb_msg) => {
                 self.process_block_from_network(*rb_msg).await?;
             }
-            NetworkEvent::GroupUpdate(event) => {
-                self.process_group_update(event);
+            NetworkEvent::DeliveredBlock(delivered, source) => {
+                let result = self.process_delivered_block(*delivered).await?;
+                self.to_network
+                    .send_ephemera_event(EphemeraEvent::MessageValidated { source, result })
+                    .await?;
             }
-            NetworkEvent::QueryDhtResponse { key, value } => {
+            NetworkEvent::GroupUpdate {
+                event,
+                activation_height,
+            } => {
+                self.process_group_update(event, activation_height).await?;
+            }
+            NetworkEvent::QueryDhtResponse { key, result } => {
                 match self.api_cmd_processor.dht_query_cache.pop(&key) {
                     Some(replies) => {
+                        let response = result.map(|record| record.map(ApiDhtQueryResponse::from));
                         for reply in replies {
-                            let response = Ok(Some((key.clone(), value.clone())));
+                            let response = response.clone().map_err(ApiError::from_dht_error);
                             if let Err(err) = reply.send(response) {
                                 error!("Error sending dht query response: {:?}", err);
                             }
@@ -216,35 +237,160 @@ impl<A: Application> Ephemera<A> {
                     }
                 }
             }
+            NetworkEvent::StoreDhtResponse { key, result } => {
+                match self.api_cmd_processor.dht_store_cache.pop(&key) {
+                    Some(replies) => {
+                        for reply in replies {
+                            let response = result.clone().map_err(ApiError::from_dht_error);
+                            if let Err(err) = reply.send(response) {
+                                error!("Error sending dht store response: {:?}", err);
+                            }
+                        }
+                    }
+                    None => {
+                        trace!(
+                            "No dht store cache found for key: {:?}",
+                            String::from_utf8(key)
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Group changes with activation height become effective after the local block at that height
+    /// is committed, so that all nodes switch groups at the same logical point.
+    async fn process_group_update(
+        &mut self,
+        event: GroupChangeEvent,
+        activation_height: Option<u64>,
+    ) -> Result<()> {
+        if let Some(height) = activation_height {
+            //Node whose committed height doesn't advance would wait forever: it doesn't commit
+            //blocks, its block production is paused or stopped, or it's outside the current group.
+            let local_peer_id = self.node_info.peer_id;
+            let in_group = self.broadcast_group.current().contains_key(&local_peer_id);
+            let block_manager = &self.channels.default_channel().block_manager;
+            let committed_height = block_manager.last_committed_height();
+            if in_group && block_manager.advances_height() && height > committed_height {
+                debug!(
+                    "Group update pending until block {height} is committed, last committed block {committed_height}"
+                );
+                self.broadcast_group.schedule(height, event);
+                return Ok(());
+            }
+            //Older pending changes are superseded
+            self.broadcast_group.take_activated(height);
+        }
+        self.apply_group_update(event).await?;
+
+        //Observer verifies delivered blocks against the group of their height
+        if self.node_info.initial_config.node.observer {
+            let first_height = match activation_height {
+                Some(height) => height + 1,
+                None => self.next_delivered_height().await?,
+            };
+            self.broadcast_group.apply_from(first_height);
+        }
+        Ok(())
+    }
+
+    /// Height of the next block of the default channel the observer delivers.
+    async fn next_delivered_height(&mut self) -> Result<u64> {
+        let storage = self.channels.default_channel().storage.lock().await;
+        let last_block = storage
+            .get_last_block()
+            .map_err(EphemeraCoreError::DatabaseFailure)?;
+        Ok(last_block.map_or(1, |block| block.get_height() + 1))
+    }
+
+    /// Applies the pending group changes when the committed height stops advancing, for example
+    /// when block production is paused.
+    pub(crate) async fn apply_pending_group_updates(&mut self) -> anyhow::Result<()> {
+        let block_manager = &self.channels.default_channel().block_manager;
+        if block_manager.advances_height() || self.broadcast_group.pending.is_empty() {
+            return Ok(());
+        }
+        info!("Block production is paused, applying pending group updates");
+        self.activate_group_updates(u64::MAX).await?;
+        Ok(())
+    }
+
+    async fn activate_group_updates(&mut self, committed_height: u64) -> Result<()> {
+        for event in self.broadcast_group.take_activated(committed_height) {
+            info!("Group update activated after block at height {committed_height}");
+            self.apply_group_update(event).await?;
         }
         Ok(())
     }
 
-    fn process_group_update(&mut self, event: GroupChangeEvent) {
+    async fn apply_group_update(&mut self, event: GroupChangeEvent) -> Result<()> {
         match event {
             GroupChangeEvent::PeersUpdated(peers) => {
                 info!("New group: {:?}", peers);
-                info!("{}", Quorum::cluster_size_info(peers.len()));
-                self.broadcaster.group_updated(peers.len());
-                self.broadcast_group.add_snapshot(peers);
-                self.block_manager.start();
+                self.broadcast_group.add_snapshot(peers.clone());
+                let group_id = self.broadcast_group.current_id;
+                for (name, channel) in self.channels.iter_mut() {
+                    channel.block_manager.group_updated(&peers);
+                    match channel.broadcaster.group_updated(group_id, peers.clone()) {
+                        Ok(quorum) => {
+                            info!("Channel {name}: {}", quorum.cluster_size_info());
+                            channel.block_manager.start();
+                        }
+                        Err(err) => {
+                            error!("Group doesn't satisfy quorum policy: {err}");
+                            channel.block_manager.stop();
+                        }
+                    }
+                }
             }
             GroupChangeEvent::LocalPeerRemoved(peers) | GroupChangeEvent::NotEnoughPeers(peers) => {
                 info!("New group: {:?}", peers);
                 info!("Group update: Local peer removed or not enough peers");
-                self.broadcaster.group_updated(0);
-                self.broadcast_group.add_snapshot(peers);
-                self.block_manager.stop();
+                self.broadcast_group.add_snapshot(peers.clone());
+                let group_id = self.broadcast_group.current_id;
+                for (_, channel) in self.channels.iter_mut() {
+                    channel.block_manager.group_updated(&peers);
+                    //New blocks are not broadcast, in-flight blocks are completed in their own groups
+                    if let Err(err) = channel.broadcaster.group_updated(group_id, HashMap::new()) {
+                        error!("Failed to reset broadcast quorum: {err}");
+                    }
+                    channel.block_manager.stop();
+                }
             }
         }
+        self.store_group_snapshot().await
+    }
+
+    //Keeps the history of the groups after the snapshots are evicted from the cache
+    async fn store_group_snapshot(&mut self) -> Result<()> {
+        let snapshot = StoredGroupSnapshot {
+            id: self.broadcast_group.current_id,
+            timestamp: EphemeraTime::now(),
+            activation_height: self
+                .channels
+                .default_channel()
+                .block_manager
+                .last_committed_height(),
+            members: self.broadcast_group.current().clone(),
+        };
+        if let Err(e) = self.storage.lock().await.store_group_snapshot(&snapshot) {
+            return Err(EphemeraCoreError::DatabaseFailure(e));
+        }
+        Ok(())
     }
 
     async fn process_new_local_block(
         &mut self,
+        name: &str,
         new_block: Block,
         certificate: Certificate,
     ) -> Result<()> {
-        debug!("New block from block manager: {:?}", new_block.get_hash());
+        debug!(
+            "New block from block manager of channel {name}: {:?}",
+            new_block.get_hash()
+        );
 
         let hash = new_block.header.hash;
         let block_creator = &self.node_info.peer_id;
@@ -259,8 +405,13 @@ impl<A: Application> Ephemera<A> {
             return Ok(());
         }
 
+        let channel = self
+            .channels
+            .get_mut(name)
+            .ok_or(anyhow!("Unknown channel: {name}"))?;
+
         //Ephemera ABCI
-        match self.application.check_block(&new_block.clone().into()) {
+        match channel.application.check_block(&new_block.clone().into()) {
             Ok(response) => match response {
                 CheckBlockResult::Accept => {
                     debug!("Application accepted new block: {hash:?}",);
@@ -271,7 +422,8 @@ impl<A: Application> Ephemera<A> {
                 }
                 CheckBlockResult::RejectAndRemoveMessages(messages_to_remove) => {
                     debug!("Application rejected block: {:?}", messages_to_remove);
-                    self.block_manager
+                    channel
+                        .block_manager
                         .on_application_rejected_block(messages_to_remove)
                         .map_err(|err| {
                             anyhow!("Error rejecting block from block manager: {:?}", err)
@@ -285,7 +437,7 @@ impl<A: Application> Ephemera<A> {
 
         //Block manager generated new block that nobody hasn't seen yet.
         //We start reliable broadcaster protocol to broadcaster it to other nodes.
-        match self.broadcaster.new_broadcast(new_block) {
+        match channel.broadcaster.new_broadcast(new_block) {
             Ok(resp) => {
                 if let BroadcastResponse::Broadcast(msg) = resp {
                     trace!("Broadcasting new block: {:?}", msg);
@@ -303,7 +455,166 @@ impl<A: Application> Ephemera<A> {
         Ok(())
     }
 
+    /// Observers deliver blocks that a quorum of the broadcast group at the block's height has certified,
+    /// from the member scheduled to propose it. Groups change at heights of the default channel, so
+    /// blocks of other channels are verified against the group at the next height of the default one.
+    ///
+    /// With a proposer schedule the first valid block at each height of a channel is delivered, later
+    /// ones are ignored. Without it every producer creates its own block at each height, and all of
+    /// them are delivered.
+    async fn process_delivered_block(
+        &mut self,
+        delivered: DeliveredBlock,
+    ) -> Result<MessageValidation> {
+        let hash = delivered.block.header.hash;
+        trace!("New delivered block from network: {hash:?}");
+
+        let name = block_channel(&delivered.block).to_string();
+        if self.channels.get(&name).is_none() {
+            debug!("Delivered block {hash:?} of unknown channel");
+            return Ok(MessageValidation::Ignore);
+        }
+
+        let group_height = if name == DEFAULT_CHANNEL {
+            delivered.block.header.height
+        } else {
+            self.next_delivered_height().await?
+        };
+        let Some(group) = self.broadcast_group.group_at_height(group_height).cloned() else {
+            debug!(
+                "Delivered block {hash:?} not accepted: no group known at height {group_height}"
+            );
+            return Ok(MessageValidation::Ignore);
+        };
+        let channel = self
+            .channels
+            .get(&name)
+            .ok_or(anyhow!("Unknown channel: {name}"))?;
+
+        let policy = &self.node_info.initial_config.broadcast.quorum_policy;
+        let members = match delivered.verify(&group, policy) {
+            Ok(members) => members,
+            Err(err) => {
+                debug!("Delivered block {hash:?} not accepted: {err}");
+                return Ok(if err.is_invalid() {
+                    MessageValidation::Reject
+                } else {
+                    MessageValidation::Ignore
+                });
+            }
+        };
+        if !channel
+            .block_manager
+            .is_scheduled_proposer_among(&delivered.block, &group)
+        {
+            debug!(
+                "Delivered block {hash:?} not accepted: {} is not scheduled to propose it",
+                delivered.block.header.creator
+            );
+            return Ok(MessageValidation::Ignore);
+        }
+
+        let DeliveredBlock {
+            block,
+            certificates,
+            ..
+        } = delivered;
+        {
+            //With a proposer schedule members share one chain, so like them the observer keeps
+            //the first block of each height
+            let shares_chain = channel.block_manager.rotates_proposers();
+            let mut storage = channel.storage.lock().await;
+            let stored = storage
+                .get_block_by_hash(&hash.to_string())
+                .and_then(|stored| match stored {
+                    None if shares_chain => storage.get_block_by_height(block.header.height),
+                    stored => Ok(stored),
+                })
+                .map_err(EphemeraCoreError::DatabaseFailure)?;
+            if stored.is_some() {
+                trace!("Block at height {} already delivered", block.header.height);
+                return Ok(MessageValidation::Ignore);
+            }
+            storage
+                .store_block(&block, certificates, members)
+                .map_err(EphemeraCoreError::DatabaseFailure)?;
+        }
+
+        channel
+            .application
+            .deliver_block(Into::into(block.clone()))
+            .map_err(|e| anyhow!("Error: Deliver block to Application failed: {e:?}",))?;
+        self.ws_message_broadcast.send_block(&block)?;
+        info!(
+            "Delivered block of {} from network: {hash:?}",
+            block.header.creator
+        );
+        Ok(MessageValidation::Accept)
+    }
+
+    /// Stores the committed block and delivers it to the application, websocket subscribers and observers.
+    async fn commit_block(&mut self, name: &str, block: &Block) -> Result<()> {
+        let hash = block.header.hash;
+
+        //Group changes waiting for this block
+        if name == DEFAULT_CHANNEL {
+            self.activate_group_updates(block.header.height).await?;
+        }
+
+        let channel = self
+            .channels
+            .get_mut(name)
+            .ok_or(anyhow!("Unknown channel: {name}"))?;
+
+        //Save to database
+        let certificates = channel
+            .block_manager
+            .get_block_certificates(&block.header.hash)
+            .ok_or(anyhow!(
+                "Error: Block certificates not found for block: {hash:?}"
+            ))?
+            .clone();
+        let members = self
+            .broadcast_group
+            .get_group_by_block_hash(block.get_hash())
+            .ok_or(anyhow!("Error: Group not found for block: {hash:?}"))?
+            .clone();
+
+        if let Err(e) =
+            channel
+                .storage
+                .lock()
+                .await
+                .store_block(block, certificates.clone(), members.clone())
+        {
+            return Err(EphemeraCoreError::DatabaseFailure(e));
+        }
+
+        //Observers, the creator publishes its block
+        if block.header.creator == self.node_info.peer_id {
+            let delivered =
+                DeliveredBlock::new(block.clone(), certificates, members.into_keys().collect());
+            self.to_network
+                .send_ephemera_event(EphemeraEvent::DeliveredBlock(delivered.into()))
+                .await?;
+        }
+
+        // It is open question how much Application `deliver_block` failure should affect
+        // continuing with next block.
+        //Application(ABCI)
+        channel
+            .application
+            .deliver_block(Into::into(block.clone()))
+            .map_err(|e| anyhow!("Error: Deliver block to Application failed: {e:?}",))?;
+
+        //WS
+        self.ws_message_broadcast.send_block(block)?;
+        info!("Block broadcast complete: {hash:?}",);
+        Ok(())
+    }
+
     //TODO: should we accept more blocks(certificates) from peers after its committed?
+    #[allow(clippy::too_many_lines)]
     async fn process_block_from_network(&mut self, msg: RbMsg) -> Result<()> {
         let msg_id = msg.id.clone();
         let block = msg.block();
@@ -314,6 +625,24 @@ impl<A: Application> Ephemera<A> {
 
         trace!("New broadcast message from network: {:?}", msg);
 
+        let name = block_channel(block).to_string();
+        let Some(channel) = self.channels.get_mut(&name) else {
+            debug!("Ignoring block {hash:?} of unknown channel {name}");
+            return Ok(());
+        };
+
+        //Members echo one block per height, votes are counted for any scheduled block
+        let is_echo = matches!(msg.phase, MessageType::Echo(_));
+        let scheduled = if is_echo {
+            channel.block_manager.accepts_echo(block)
+        } else {
+            channel.block_manager.is_scheduled_proposer(block)
+        };
+        if !scheduled {
+            debug!("Ignoring block {hash:?} from {block_creator}, it's not scheduled to propose");
+            return Ok(());
+        }
+
         if !self
             .broadcast_group
             .check_membership(hash, block_creator, sender)
@@ -321,17 +650,25 @@ impl<A: Application> Ephemera<A> {
             return Err(anyhow!("Block doesn't match broacast group").into());
         }
 
-        if let Err(err) = self.block_manager.on_block(sender, block, &certificate) {
+        if let Err(err) = channel.block_manager.on_block(sender, block, &certificate) {
+            let ban = EphemeraEvent::BanPeer {
+                peer_id: *sender,
+                reason: BanReason::InvalidBlock(err.to_string()),
+            };
+            self.to_network.send_ephemera_event(ban).await?;
             return Err(anyhow!("Error sending block to block manager: {:?}", err).into());
         }
+        if is_echo {
+            channel.block_manager.lock(block);
+        }
         let raw_mgs = msg.into();
-        match self.broadcaster.handle(&raw_mgs) {
+        match channel.broadcaster.handle(&raw_mgs) {
             Ok(resp) => {
                 match resp {
                     BroadcastResponse::Broadcast(msg) => {
                         trace!("Broadcasting block to network: {:?}", msg);
 
-                        match self.block_manager.sign_block(&msg.block()) {
+                        match channel.block_manager.sign_block(&msg.block()) {
                             Ok(certificate) => {
                                 let rb_msg = RbMsg::new(msg, certificate);
                                 self.to_network
@@ -347,55 +684,36 @@ impl<A: Application> Ephemera<A> {
                     }
                     BroadcastResponse::Deliver(hash) => {
                         trace!("Block broadcast complete: {hash:?}",);
-                        let block = self.block_manager.get_block_by_hash(&hash);
+                        let block_manager = &mut channel.block_manager;
+                        let block = block_manager.get_block_by_hash(&hash);
                         match block {
                             Some(block) => {
-                                if block.header.creator == self.node_info.peer_id {
+                                if block_manager.rotates_proposers() {
+                                    //Members share one chain. Each member takes part in one block per height,
+                                    //so a block at a committed height is only a stale delivery of the same block.
+                                    if block.get_height() <= block_manager.last_committed_height() {
+                                        info!(
+                                            "Block {hash:?} delivered at already committed height {}",
+                                            block.get_height()
+                                        );
+                                        return Ok(());
+                                    }
+                                    block_manager.on_block_delivered(&block).map_err(|e| {
+                                        anyhow!(
+                                            "Error: BlockManager failed to process block: {e:?}",
+                                        )
+                                    })?;
+                                    self.commit_block(&name, &block).await?;
+                                } else if block.header.creator == self.node_info.peer_id {
                                     info!("Block committed, ready to deliver...: {hash:?}",);
 
                                     //BlockManager
-                                    self.block_manager.on_block_committed(&block).map_err(|e| {
+                                    block_manager.on_block_committed(&block).map_err(|e| {
                                         anyhow!(
                                             "Error: BlockManager failed to process block: {e:?}",
                                         )
                                     })?;
-
-                                    //Save to database
-                                    let certificates = self
-                                        .block_manager
-                                        .get_block_certificates(&block.header.hash)
-                                        .ok_or(anyhow!(
-                                            "Error: Block certificates not found for block: {hash:?}"
-                                        ))?;
-                                    let members = self
-                                        .broadcast_group
-                                        .get_group_by_block_hash(block.get_hash())
-                                        .ok_or(anyhow!(
-                                            "Error: Group not found for block: {hash:?}"
-                                        ))?;
-
-                                    if let Err(e) = self.storage.lock().await.store_block(
-                                        &block,
-                                        certificates.clone(),
-                                        members.clone(),
-                                    ) {
-                                        return Err(EphemeraCoreError::DatabaseFailure(e));
-                                    }
-
-                                    // It is open question how much Application `deliver_block` failure should affect
-                                    // continuing with next block.
-                                    //Application(ABCI)
-                                    self.application
-                                        .deliver_block(Into::into(block.clone()))
-                                        .map_err(|e| {
-                                            anyhow!(
-                                                "Error: Deliver block to Application failed: {e:?}",
-                                            )
-                                        })?;
-
-                                    //WS
-                                    self.ws_message_broadcast.send_block(&block)?;
-                                    info!("Block broadcast complete: {hash:?}",);
+                                    self.commit_block(&name, &block).await?;
                                 }
                             }
                             None => {
@@ -1,4 +1,5 @@
 pub(crate) mod api_cmd;
 pub(crate) mod builder;
+pub(crate) mod channel;
 pub(crate) mod ephemera;
 pub(crate) mod shutdown;
@@ -44,10 +44,13 @@ pub mod ephemera_api {
         },
         http::client::{Client, Error as HttpClientError, Result as HttpClientResult},
         types::{
-            ApiBlock, ApiBlockBroadcastInfo, ApiBroadcastInfo, ApiCertificate, ApiDhtQueryRequest,
-            ApiDhtQueryResponse, ApiDhtStoreRequest, ApiEphemeraConfig, ApiEphemeraMessage,
-            ApiError, ApiHealth, ApiPublicKey, ApiSignature, ApiVerifyMessageInBlock,
-            RawApiEphemeraMessage,
+            ApiBlock, ApiBlockBroadcastInfo, ApiBlockBroadcastProgress, ApiBlockCertificate,
+            ApiBlockCertificates, ApiBlockCreationInterval, ApiBlockManagerState,
+            ApiBlockManagerStatus, ApiBroadcastInfo, ApiBroadcastProgress, ApiCertificate,
+            ApiDeniedConnections, ApiDhtQueryRequest, ApiDhtQueryResponse, ApiDhtStoreRequest,
+            ApiEphemeraConfig, ApiEphemeraMessage, ApiError, ApiExclusionReason, ApiGroupSnapshot,
+            ApiHealth, ApiMemberHealth, ApiPeerBan, ApiPeerInfo, ApiPendingBlock, ApiPublicKey,
+            ApiQuorumPolicy, ApiSignature, ApiVerifyMessageInBlock, RawApiEphemeraMessage,
         },
         CommandExecutor,
     };
@@ -61,9 +64,17 @@ pub mod peer {
 
 /// Ephemera membership. How to find other nodes in the cluster.
 pub mod membership {
+    pub use super::network::members::provider::{
+        MembersProvider, MembersStream, MembersUpdate, PollingMembersProvider,
+        StreamMembersProvider,
+    };
+    pub use super::network::members::signed::{
+        read_authority_keypair, AuthoritySignature, MembershipAuthority, SignedMembership,
+        SignedMembershipError,
+    };
     pub use super::network::members::{
         ConfigMembersProvider, DummyMembersProvider, HttpMembersProvider, JsonPeerInfo, PeerInfo,
-        PeerSetting, Result,
+        PeerSetting, Result, DEFAULT_PEER_WEIGHT,
     };
 }
 
@@ -81,7 +92,12 @@ pub mod codec {
 
 /// Ephemera node configuration
 pub mod configuration {
-    pub use super::config::Configuration;
+    pub use super::config::{
+        ChannelConfiguration, Configuration, DhtConfiguration, DhtQuorum, GossipsubConfiguration,
+        GossipsubValidationMode, HealthFilterConfiguration, MembershipAuthorityConfiguration,
+        PeerScoringConfiguration, PingConfiguration, ProposerSchedule, QuorumPolicy,
+        RateLimitConfiguration, TransportProtocol,
+    };
 }
 
 /// Ephemera CLI. Helpers for creating configuration, running node, etc.
@@ -118,3 +134,7 @@ mod utilities;
 
 /// Ephemera websocket. Websocket server where external clients can subscribe.
 mod websocket;
+
+/// In-process multi-node simulation for tests.
+#[cfg(test)]
+mod simulation;
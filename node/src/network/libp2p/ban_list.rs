@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::peer::PeerId;
+use crate::utilities::time::EphemeraTime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BanReason {
+    /// Peer sent gossip messages faster than allowed.
+    GossipRateExceeded,
+    /// Peer sent reliable broadcast requests faster than allowed.
+    BroadcastRateExceeded,
+    /// Peer gossiped a message which failed validation.
+    InvalidMessage(String),
+    /// Peer sent a reliable broadcast request or block which failed validation.
+    InvalidBlock(String),
+}
+
+impl Display for BanReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BanReason::GossipRateExceeded => write!(f, "Gossip rate limit exceeded"),
+            BanReason::BroadcastRateExceeded => write!(f, "Broadcast rate limit exceeded"),
+            BanReason::InvalidMessage(reason) => write!(f, "Invalid message: {reason}"),
+            BanReason::InvalidBlock(reason) => write!(f, "Invalid block: {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Ban {
+    pub(crate) reason: BanReason,
+    /// Unix timestamp in milliseconds.
+    pub(crate) banned_at: u64,
+    /// Unix timestamp in milliseconds.
+    pub(crate) expires_at: u64,
+    expires: Instant,
+}
+
+/// Temporarily banned peers.
+///
+/// Network refuses connections and drops traffic from banned peers. Bans expire after the configured
+/// duration or when they are cleared through the API. Clones share the same list.
+#[derive(Clone)]
+pub(crate) struct BanList {
+    duration: Duration,
+    bans: Arc<Mutex<HashMap<PeerId, Ban>>>,
+}
+
+impl BanList {
+    pub(crate) fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            bans: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Bans a peer. Returns false if the peer is already banned, the existing ban is kept then.
+    pub(crate) fn ban(&self, peer_id: PeerId, reason: BanReason) -> bool {
+        let mut bans = self.bans();
+        if bans.contains_key(&peer_id) {
+            return false;
+        }
+
+        let banned_at = EphemeraTime::now();
+        let duration_ms = u64::try_from(self.duration.as_millis()).unwrap_or(u64::MAX);
+        let ban = Ban {
+            reason,
+            banned_at,
+            expires_at: banned_at.saturating_add(duration_ms),
+            expires: Instant::now() + self.duration,
+        };
+        bans.insert(peer_id, ban);
+        true
+    }
+
+    pub(crate) fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.bans().contains_key(peer_id)
+    }
+
+    pub(crate) fn banned(&self) -> Vec<(PeerId, Ban)> {
+        self.bans()
+            .iter()
+            .map(|(peer_id, ban)| (*peer_id, ban.clone()))
+            .collect()
+    }
+
+    /// Returns false if the peer wasn't banned.
+    pub(crate) fn unban(&self, peer_id: &PeerId) -> bool {
+        self.bans().remove(peer_id).is_some()
+    }
+
+    /// Returns the number of removed bans.
+    pub(crate) fn clear(&self) -> usize {
+        let mut bans = self.bans();
+        let removed = bans.len();
+        bans.clear();
+        removed
+    }
+
+    //Returns bans without the expired ones
+    fn bans(&self) -> std::sync::MutexGuard<'_, HashMap<PeerId, Ban>> {
+        let mut bans = self.bans.lock().expect("Ban list lock poisoned");
+        let now = Instant::now();
+        bans.retain(|_, ban| ban.expires > now);
+        bans
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ban_and_unban() {
+        let ban_list = BanList::new(Duration::from_secs(60));
+        let peer_id = PeerId::random();
+
+        assert!(ban_list.ban(peer_id, BanReason::GossipRateExceeded));
+        assert!(!ban_list.ban(peer_id, BanReason::BroadcastRateExceeded));
+        assert!(ban_list.is_banned(&peer_id));
+
+        let banned = ban_list.banned();
+        assert_eq!(banned.len(), 1);
+        assert_eq!(banned[0].1.reason, BanReason::GossipRateExceeded);
+        assert_eq!(banned[0].1.expires_at - banned[0].1.banned_at, 60_000);
+
+        assert!(ban_list.unban(&peer_id));
+        assert!(!ban_list.unban(&peer_id));
+        assert!(!ban_list.is_banned(&peer_id));
+    }
+
+    #[test]
+    fn test_clear() {
+        let ban_list = BanList::new(Duration::from_secs(60));
+        ban_list.ban(PeerId::random(), BanReason::GossipRateExceeded);
+        ban_list.ban(
+            PeerId::random(),
+            BanReason::InvalidBlock("bad signature".to_string()),
+        );
+
+        assert_eq!(ban_list.clear(), 2);
+        assert!(ban_list.banned().is_empty());
+    }
+
+    #[test]
+    fn test_bans_expire() {
+        let ban_list = BanList::new(Duration::ZERO);
+        let peer_id = PeerId::random();
+
+        ban_list.ban(peer_id, BanReason::GossipRateExceeded);
+        assert!(!ban_list.is_banned(&peer_id));
+    }
+}
@@ -6,21 +6,31 @@
 //!
 //! This behaviour is responsible for keeping membership up to date.
 //!
-//! `MembersProviderFuture`: `Future<Output = membership::Result<Vec<PeerInfo>>> + Send + 'static`.
-//!
-//! User provides a [`MembersProviderFuture`] implementation to the [Behaviour] which is responsible for fetching the list of peers.
+//! User provides a [`MembersProvider`] implementation to the [Behaviour] which is responsible for fetching the list of peers.
+//! It can push updates as soon as membership changes or poll the list periodically, see [`PollingMembersProvider`].
 //!
 //! [Behaviour] accepts only peers that are actually online.
 //!
 //! When peers become available or unavailable, [Behaviour] adjusts the list of connected peers accordingly and notifies `reliable broadcast`
 //! about the membership change.
 //!
-//! It is configurable what `threshold` of peers(from the total list provided by [`MembersProviderFuture`]) should be available at any given time.
+//! It is configurable what `threshold` of peers(from the total list provided by [`MembersProvider`]) should be available at any given time.
 //! Or if just to use all peers who are online. See [`MembershipKind`] for more details.
 //!
-//! Ideally [`MembersProviderFuture`] can depend on a resource that gives reliable results. Some kind of registry which itself keeps track of actually online nodes.
-//! As Ephemera uses only peers provided by [`MembersProviderFuture`], it depends on its accuracy.
-//! At the same time it tries to be flexible and robust to handle less reliable [`MembersProviderFuture`] implementations.
+//! [Behaviour] denies connections with peers who are not part of the current or pending membership,
+//! unless they are in the grace list. Until [`MembersProvider`] returns the first membership, only peers in
+//! the grace list can connect. Because all swarm behaviours share the connections, other behaviours
+//! never see non-members. Connections with peers who are removed from the membership are closed.
+//!
+//! Optionally [Behaviour] runs a [`HealthFilter`] which excludes unresponsive members from the effective membership
+//! until they recover. Excluded members don't count as connected and are not part of the broadcast group.
+//!
+//! An observer node is never part of the membership. Its [Behaviour] still connects to the members and
+//! always reports them with [`Event::LocalRemoved`], members need to have the observer in their grace list.
+//!
+//! Ideally [`MembersProvider`] can depend on a resource that gives reliable results. Some kind of registry which itself keeps track of actually online nodes.
+//! As Ephemera uses only peers provided by [`MembersProvider`], it depends on its accuracy.
+//! At the same time it tries to be flexible and robust to handle less reliable [`MembersProvider`] implementations.
 
 // When peer gets disconnected, we try to dial it and if that fails, we update group.
 // (it may connect us meanwhile).
@@ -32,18 +42,17 @@
 //a)when peer disconnects, we try to dial it and if that fails, we update group.
 //b)when peer connects, we will update the group.
 
-use std::future::Future;
 use std::time::Duration;
 use std::{
     collections::HashMap,
     collections::HashSet,
+    collections::VecDeque,
     fmt::Debug,
     task::{Context, Poll},
 };
 
-use futures_util::FutureExt;
 use libp2p::core::Endpoint;
-use libp2p::swarm::{ConnectionDenied, NotifyHandler, THandler};
+use libp2p::swarm::{CloseConnection, ConnectionDenied, NotifyHandler, THandler};
 use libp2p::{
     swarm::ToSwarm,
     swarm::{
@@ -55,15 +64,26 @@ use libp2p::{
     Multiaddr,
 };
 use libp2p_identity::PeerId;
-use log::{debug, error, trace, warn};
+use log::{debug, error, info, trace, warn};
+use thiserror::Error;
 use tokio::time;
 use tokio::time::{Instant, Interval};
 
-use crate::network::libp2p::behaviours::membership::handler::ToHandler;
-use crate::network::libp2p::behaviours::membership::{Membership, MEMBERSHIP_SYNC_INTERVAL_SEC};
+#[cfg(doc)]
+use crate::membership::PollingMembersProvider;
+
+use crate::config::HealthFilterConfiguration;
+use crate::network::libp2p::behaviours::membership::handler::{FromHandler, ToHandler};
+use crate::network::libp2p::behaviours::membership::health::{
+    ExclusionReason, HealthFilter, MemberHealth,
+};
+use crate::network::libp2p::behaviours::membership::{
+    DeniedConnections, Membership, MEMBERSHIP_SYNC_INTERVAL_SEC,
+};
+use crate::network::libp2p::peers::NetworkPeers;
 use crate::network::Peer;
 use crate::{
-    membership,
+    membership::{MembersProvider, MembersUpdate},
     network::{
         libp2p::behaviours::{
             membership::connections::ConnectedPeers,
@@ -75,7 +95,7 @@ use crate::{
     },
 };
 
-/// [`MembersProviderFuture`] state when we are trying to connect to new peers.
+/// [`MembersProvider`] state when we are trying to connect to new peers.
 ///
 /// We try to connect few times before giving up. Generally speaking an another peer is either online or offline
 /// at any given time. But it has been helpful for testing when whole cluster comes up around the same time.
@@ -118,27 +138,31 @@ pub(crate) enum Event {
     /// We are going to try to connect to them.
     PeerUpdatePending,
     /// We have finished trying to connect to new peers and going to report it.
-    PeersUpdated(HashSet<PeerId>),
-    /// MembersProviderFuture reported us new peers and this set doesn't contain our local peer.
-    LocalRemoved(HashSet<PeerId>),
-    /// MembersProviderFuture reported us new peers and we failed to connect to enough of them.
-    NotEnoughPeers(HashSet<PeerId>),
+    /// Peers are reported together with their weights.
+    PeersUpdated(HashMap<PeerId, u64>),
+    /// `MembersProvider` reported us new peers and this set doesn't contain our local peer.
+    /// Reported together with the activation height of the membership.
+    LocalRemoved(HashMap<PeerId, u64>, Option<u64>),
+    /// `MembersProvider` reported us new peers and we failed to connect to enough of them.
+    /// Reported together with the activation height of the membership.
+    NotEnoughPeers(HashMap<PeerId, u64>, Option<u64>),
 }
 
+/// Reason of denied connection.
+#[derive(Debug, Error)]
+#[error("Peer {0} is not a member")]
+pub(crate) struct NotMember(PeerId);
+
 pub(crate) struct Behaviour<P>
 where
-    P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + 'static,
+    P: MembersProvider,
 {
     /// All peers that are part of the current group.
     memberships: Memberships,
     /// Local peer id.
     local_peer_id: PeerId,
-    /// Future that provides new peers.
+    /// Provides new peers.
     members_provider: P,
-    /// Interval between requesting new peers from the members provider.
-    members_provider_interval: Option<Interval>,
-    /// Delay between dial attempts.
-    members_provider_delay: Duration,
     /// Current behaviour state.
     state: State,
     /// Current state of all incoming and outgoing connections.
@@ -149,72 +173,142 @@ where
     last_sync_time: Instant,
     /// Minimum time between members provider updates.
     minimum_time_between_sync: Duration,
+    /// Peers who are allowed to connect without being members.
+    grace_list: HashSet<PeerId>,
+    /// Counters of denied connections.
+    denied_connections: DeniedConnections,
+    /// Connected peers who are not members anymore.
+    to_disconnect: VecDeque<PeerId>,
+    /// Health of connected peers, reported by ping.
+    network_peers: NetworkPeers,
+    /// Excludes unresponsive members, if enabled.
+    health_filter: Option<HealthFilter>,
+    /// Members who are sent a heartbeat next.
+    to_heartbeat: VecDeque<PeerId>,
+    /// Local peer only follows the members.
+    observer: bool,
 }
 
 impl<P> Behaviour<P>
 where
-    P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
     pub(crate) fn new(
         members_provider: P,
-        members_provider_delay: Duration,
         local_peer_id: PeerId,
         membership_kind: MembershipKind,
+        grace_list: HashSet<PeerId>,
+        denied_connections: DeniedConnections,
+        network_peers: NetworkPeers,
+        health_filter: Option<HealthFilterConfiguration>,
     ) -> Self {
-        let initial_delay = Instant::now() + Duration::from_secs(5);
-        let delay = tokio::time::interval_at(initial_delay, members_provider_delay);
         Behaviour {
             memberships: Memberships::new(),
             local_peer_id,
             members_provider,
-            members_provider_interval: Some(delay),
-            members_provider_delay,
             state: State::WaitingPeers,
             all_connections: ConnectedPeers::default(),
             membership_kind,
             last_sync_time: Instant::now(),
             minimum_time_between_sync: Duration::from_secs(MEMBERSHIP_SYNC_INTERVAL_SEC),
+            grace_list,
+            denied_connections,
+            to_disconnect: VecDeque::new(),
+            network_peers,
+            health_filter: health_filter.map(HealthFilter::new),
+            to_heartbeat: VecDeque::new(),
+            observer: false,
         }
     }
 
+    /// Connects to members without being one of them.
+    pub(crate) fn set_observer(&mut self) {
+        self.observer = true;
+    }
+
+    /// Returns true if the peer is part of the current or pending membership.
+    pub(crate) fn is_member(&mut self, peer_id: &PeerId) -> bool {
+        self.memberships.is_member(peer_id)
+    }
+
+    /// Members and peers in the grace list can connect. Until we know the first membership,
+    /// only peers in the grace list can.
+    fn allows_connection(&mut self, peer_id: &PeerId) -> bool {
+        *peer_id == self.local_peer_id
+            || self.grace_list.contains(peer_id)
+            || self.memberships.is_member(peer_id)
+    }
+
+    fn deny_inbound(&mut self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.allows_connection(&peer_id) {
+            return Ok(());
+        }
+        self.denied_connections.deny_inbound();
+        debug!("Denied inbound connection from non-member {peer_id}");
+        Err(ConnectionDenied::new(NotMember(peer_id)))
+    }
+
+    fn deny_outbound(&mut self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.allows_connection(&peer_id) {
+            return Ok(());
+        }
+        self.denied_connections.deny_outbound();
+        debug!("Denied outbound connection to non-member {peer_id}");
+        Err(ConnectionDenied::new(NotMember(peer_id)))
+    }
+
+    /// Returns the number of current members, excluding local peer.
+    pub(crate) fn remote_member_count(&mut self) -> usize {
+        let local_peer_id = self.local_peer_id;
+        self.memberships
+            .current()
+            .all_peer_ids()
+            .iter()
+            .filter(|peer_id| **peer_id != local_peer_id)
+            .count()
+    }
+
     /// Returns the list of peers that are part of current group.
     pub(crate) fn active_peer_ids(&mut self) -> &HashSet<PeerId> {
         self.memberships.current().connected_peers()
     }
 
-    pub(crate) fn active_peer_ids_with_local(&mut self) -> HashSet<PeerId> {
-        self.memberships.current().connected_peer_ids_with_local()
+    /// Returns the peers of current group, including local peer, together with their weights.
+    pub(crate) fn active_peer_weights_with_local(&mut self) -> HashMap<PeerId, u64> {
+        self.memberships
+            .current()
+            .connected_peer_weights_with_local()
+    }
+
+    /// Returns the height of the local block after which the current group becomes effective.
+    pub(crate) fn activation_height(&mut self) -> Option<u64> {
+        self.memberships.current().activation_height()
     }
 
     fn waiting_peers(&mut self, cx: &mut Context) -> Poll<ToSwarm<Event, ToHandler>> {
-        if let Some(mut tick) = self.members_provider_interval.take() {
-            if !tick.poll_tick(cx).is_ready() {
-                self.members_provider_interval = Some(tick);
-                return Poll::Pending;
-            }
-        }
-        let peers = match self.members_provider.poll_unpin(cx) {
-            Poll::Ready(peers) => {
-                let wait_time = Instant::now() + self.members_provider_delay;
-                self.members_provider_interval =
-                    time::interval_at(wait_time, self.members_provider_delay).into();
+        let update = match self.members_provider.poll_members(cx) {
+            Poll::Ready(update) => {
                 self.last_sync_time = Instant::now();
-                peers
+                update
             }
             Poll::Pending => {
                 return Poll::Pending;
             }
         };
 
-        match peers {
-            Ok(peers) => {
+        match update {
+            Ok(MembersUpdate {
+                peers,
+                activation_height,
+            }) => {
                 if peers.is_empty() {
                     //Not sure what to do here. Tempted to think that if this happens
                     //we should ignore it and assume that this is probably a bug in the membership service.
 
                     warn!("Received empty peers from provider. To try again before preconfigured interval, please restart the node.");
                     return Poll::Ready(ToSwarm::GenerateEvent(Event::NotEnoughPeers(
-                        HashSet::default(),
+                        HashMap::default(),
+                        None,
                     )));
                 }
 
@@ -232,20 +326,30 @@ where
                     }
                 }
 
+                if self.observer && new_peers.remove(&self.local_peer_id).is_some() {
+                    warn!("Members provider lists local observer peer, ignoring it");
+                }
+
                 //If we are not part of the new membership, notify immediately
-                if !new_peers.contains_key(&self.local_peer_id) {
+                if !new_peers.contains_key(&self.local_peer_id) && !self.observer {
                     debug!(
                         "Local peer {:?} is not part of the new membership. Notifying immediately.",
                         self.local_peer_id
                     );
-                    let pending_membership = Membership::new(new_peers.clone());
+                    let pending_membership = Membership::new(new_peers.clone())
+                        .with_activation_height(activation_height);
                     self.memberships.set_pending(pending_membership);
                     self.state = State::NotifyPeersUpdated;
+                    cx.waker().wake_by_ref();
                     return Poll::Pending;
                 }
 
-                let mut pending_membership =
-                    Membership::new_with_local(new_peers.clone(), self.local_peer_id);
+                let mut pending_membership = if self.observer {
+                    Membership::new(new_peers.clone())
+                } else {
+                    Membership::new_with_local(new_peers.clone(), self.local_peer_id)
+                }
+                .with_activation_height(activation_height);
                 let mut pending_update = PendingPeersUpdate::default();
 
                 for peer_id in new_peers.keys() {
@@ -272,7 +376,8 @@ where
             Err(err) => {
                 error!("Error while getting peers from provider: {:?}", err);
                 Poll::Ready(ToSwarm::GenerateEvent(Event::NotEnoughPeers(
-                    HashSet::default(),
+                    HashMap::default(),
+                    None,
                 )))
             }
         }
@@ -324,11 +429,13 @@ where
                 let connected_peers = pending_membership.connected_peers();
 
                 //Exclude local peer
-                let all_connected = connected_peers.len() == all_peers.len() - 1;
+                let all_connected = connected_peers.len()
+                    == all_peers.len() - usize::from(pending_membership.includes_local());
 
                 if all_connected || *dial_attempts >= MAX_DIAL_ATTEMPT_ROUNDS {
                     interval_between_dial_attempts.take();
                     self.state = State::NotifyPeersUpdated;
+                    cx.waker().wake_by_ref();
                     return Poll::Pending;
                 }
                 //Try again few times before notifying the rest of the system about membership update.
@@ -346,6 +453,8 @@ where
                 }
                 if *dial_attempts > 0 {
                     waiting_to_dial.extend(all_peers.difference(connected_peers).copied());
+                    //Nothing else may wake the behaviour before the next tick
+                    cx.waker().wake_by_ref();
                 }
 
                 Poll::Pending
@@ -355,25 +464,130 @@ where
         }
     }
 
+    /// Checks the health of the members when it's time. If a member was excluded or included again,
+    /// the current membership is reported again with the exclusions applied.
+    fn check_health(&mut self, cx: &mut Context<'_>) {
+        let Some(health_filter) = &mut self.health_filter else {
+            return;
+        };
+        if health_filter.poll_check(cx).is_pending() {
+            return;
+        }
+
+        let local_peer_id = self.local_peer_id;
+        let members = self
+            .memberships
+            .current()
+            .all_peer_ids()
+            .iter()
+            .filter(|peer_id| **peer_id != local_peer_id)
+            .copied()
+            .collect::<HashSet<_>>();
+
+        let all_connections = &self.all_connections;
+        let network_peers = &self.network_peers;
+        self.to_heartbeat.extend(
+            members
+                .iter()
+                .filter(|peer_id| all_connections.is_peer_connected(peer_id)),
+        );
+        let changed = health_filter.check(&members, |peer_id| {
+            if !all_connections.is_peer_connected(peer_id) {
+                Some(ExclusionReason::Disconnected)
+            } else if network_peers.is_unhealthy(&(*peer_id).into()) {
+                Some(ExclusionReason::PingFailed)
+            } else {
+                None
+            }
+        });
+        self.publish_members_health();
+        if !self.to_heartbeat.is_empty() {
+            cx.waker().wake_by_ref();
+        }
+
+        //Pending membership gets the exclusions when it's activated
+        if changed
+            && matches!(self.state, State::WaitingPeers)
+            && self.memberships.pending().is_none()
+        {
+            let connected = self
+                .all_connections
+                .all_connected_peers_ref()
+                .into_iter()
+                .copied()
+                .collect();
+            let membership = self
+                .memberships
+                .current()
+                .clone()
+                .with_connected(&connected);
+            self.memberships.set_pending(membership);
+            self.state = State::NotifyPeersUpdated;
+            cx.waker().wake_by_ref();
+        }
+    }
+
+    /// Reports the health of the current members, all of them are healthy if the filter is disabled.
+    fn publish_members_health(&mut self) {
+        let local_peer_id = self.local_peer_id;
+        let mut members = self
+            .memberships
+            .current()
+            .all_peer_ids()
+            .iter()
+            .filter(|peer_id| **peer_id != local_peer_id)
+            .map(|peer_id| ((*peer_id).into(), MemberHealth::default()))
+            .collect::<HashMap<_, _>>();
+        if let Some(health_filter) = &self.health_filter {
+            for (peer_id, health) in health_filter.members_health() {
+                members.insert((*peer_id).into(), health.clone());
+            }
+        }
+        self.network_peers.members_updated(members);
+    }
+
     fn notify_peers_updated(&mut self) -> Poll<ToSwarm<Event, ToHandler>> {
         if let Some(membership) = self.memberships.remove_pending() {
-            self.memberships.update(membership);
+            let excluded = self
+                .health_filter
+                .as_ref()
+                .map(HealthFilter::excluded)
+                .unwrap_or_default();
+            self.memberships
+                .update(membership.with_exclusions(&excluded));
+            self.publish_members_health();
+        }
+
+        let current = self.memberships.current();
+        for peer_id in self.all_connections.all_connected_peers_ref() {
+            if *peer_id != self.local_peer_id
+                && !self.grace_list.contains(peer_id)
+                && !current.contains(peer_id)
+            {
+                self.to_disconnect.push_back(*peer_id);
+            }
         }
 
         let membership = self.memberships.current();
-        let membership_connected_peers = membership.connected_peer_ids();
+        for (peer_id, reason) in membership.excluded() {
+            info!("Member {peer_id} is excluded from the effective membership: {reason}");
+        }
+        let membership_connected_peers = membership.connected_peer_weights();
+        let activation_height = membership.activation_height();
 
+        let network_peers = &self.network_peers;
+        let is_healthy = |peer_id: &PeerId| !network_peers.is_unhealthy(&(*peer_id).into());
         let event = if membership.includes_local() {
-            if self.membership_kind.accept(membership) {
+            if self.membership_kind.accept(membership, is_healthy) {
                 debug!("Membership accepted by kind: {:?}", self.membership_kind);
                 Event::PeersUpdated(membership_connected_peers)
             } else {
                 debug!("Membership rejected by kind: {:?}", self.membership_kind);
-                Event::NotEnoughPeers(membership_connected_peers)
+                Event::NotEnoughPeers(membership_connected_peers, activation_height)
             }
         } else {
             debug!("Membership does not include local peer");
-            Event::LocalRemoved(membership_connected_peers)
+            Event::LocalRemoved(membership_connected_peers, activation_height)
         };
 
         //TODO: this list should also include "old" peers(peers who aren't part of new membership).
@@ -409,7 +623,7 @@ where
 
 impl<P> NetworkBehaviour for Behaviour<P>
 where
-    P: Future<Output = membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
     type ConnectionHandler = Handler;
     type OutEvent = Event;
@@ -420,7 +634,7 @@ where
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
     ) -> Result<(), ConnectionDenied> {
-        //TODO: we can refuse connections from peers that are not part of the current membership.
+        //Peer id is known only after the handshake
         Ok(())
     }
 
@@ -431,6 +645,7 @@ where
         _local_addr: &Multiaddr,
         _remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.deny_inbound(peer)?;
         trace!("Established inbound connection with peer: {:?}", peer);
         Ok(Handler::new())
     }
@@ -445,7 +660,10 @@ where
         //FIXME: deprecated
         #[allow(deprecated)]
         match maybe_peer {
-            Some(peer_id) => Ok(self.addresses_of_peer(&peer_id)),
+            Some(peer_id) => {
+                self.deny_outbound(peer_id)?;
+                Ok(self.addresses_of_peer(&peer_id))
+            }
             None => Ok(vec![]),
         }
     }
@@ -457,6 +675,8 @@ where
         addr: &Multiaddr,
         _role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        //Dials without peer id are checked only here
+        self.deny_outbound(peer)?;
         trace!(
             "Established outbound connection with peer: {:?} {:?}",
             peer,
@@ -528,10 +748,17 @@ where
             peer_id
         );
 
+        if let FromHandler::Message(ProtocolMessage::Heartbeat) = event {
+            if let Some(health_filter) = &mut self.health_filter {
+                health_filter.heartbeat(peer_id);
+            }
+            return;
+        }
+
         //TODO: we may need to check who sent the update: probably we should accept only updates from members who we already know
         if let State::WaitingPeers = self.state {
             if self.last_sync_time + self.minimum_time_between_sync < Instant::now() {
-                self.members_provider_interval = None;
+                self.members_provider.refresh();
                 debug!("Received sync notification from peer {peer_id:?}, requesting membership update");
             }
         }
@@ -542,6 +769,21 @@ where
         cx: &mut Context<'_>,
         _params: &mut impl PollParameters,
     ) -> Poll<ToSwarm<Self::OutEvent, THandlerInEvent<Self>>> {
+        if let Some(peer_id) = self.to_disconnect.pop_front() {
+            debug!("Closing connections with removed member {peer_id}");
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+        if let Some(peer_id) = self.to_heartbeat.pop_front() {
+            return Poll::Ready(ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::Any,
+                event: ToHandler::Message(ProtocolMessage::Heartbeat),
+            });
+        }
+        self.check_health(cx);
         match &mut self.state {
             State::WaitingPeers => self.waiting_peers(cx),
             State::WaitingDial(_) => self.waiting_dial(cx),
@@ -550,3 +792,116 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::crypto::{EphemeraKeypair, Keypair};
+    use crate::membership::StreamMembersProvider;
+    use crate::network::libp2p::behaviours::membership::connections::Endpoint;
+    use crate::peer::ToPeerId;
+
+    use super::*;
+
+    type TestProvider =
+        StreamMembersProvider<futures::stream::Pending<crate::membership::Result<Vec<PeerInfo>>>>;
+
+    fn behaviour(local_peer_id: PeerId, grace_list: HashSet<PeerId>) -> Behaviour<TestProvider> {
+        Behaviour::new(
+            StreamMembersProvider::new(futures::stream::pending()),
+            local_peer_id,
+            MembershipKind::AnyOnline,
+            grace_list,
+            DeniedConnections::default(),
+            NetworkPeers::default(),
+            None,
+        )
+    }
+
+    fn membership_of(peer_ids: &[PeerId]) -> Membership {
+        let members = peer_ids
+            .iter()
+            .map(|peer_id| {
+                let public_key = Keypair::generate(None).public_key();
+                let peer = Peer {
+                    peer_id: public_key.peer_id(),
+                    public_key,
+                    address: "/ip4/127.0.0.1/tcp/3000".parse().unwrap(),
+                    name: peer_id.to_string(),
+                    weight: 1,
+                };
+                (*peer_id, peer)
+            })
+            .collect();
+        Membership::new(members)
+    }
+
+    #[tokio::test]
+    async fn test_only_grace_list_connects_until_membership_is_known() {
+        let observer = PeerId::random();
+        let mut behaviour = behaviour(PeerId::random(), HashSet::from([observer]));
+
+        assert!(behaviour.deny_inbound(observer).is_ok());
+        assert!(behaviour.deny_inbound(PeerId::random()).is_err());
+        assert!(behaviour.deny_outbound(PeerId::random()).is_err());
+        assert_eq!(behaviour.denied_connections.inbound(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_members_are_denied() {
+        let (local, member, observer) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut behaviour = behaviour(local, HashSet::from([observer]));
+        behaviour
+            .memberships
+            .update(membership_of(&[local, member]));
+
+        assert!(behaviour.deny_inbound(member).is_ok());
+        assert!(behaviour.deny_inbound(observer).is_ok());
+        assert!(behaviour.deny_inbound(PeerId::random()).is_err());
+        assert!(behaviour.deny_outbound(PeerId::random()).is_err());
+        assert!(behaviour.deny_outbound(PeerId::random()).is_err());
+
+        assert_eq!(behaviour.denied_connections.inbound(), 1);
+        assert_eq!(behaviour.denied_connections.outbound(), 2);
+        assert!(behaviour.is_member(&member));
+        assert!(!behaviour.is_member(&observer));
+    }
+
+    #[tokio::test]
+    async fn test_pending_members_are_admitted() {
+        let (local, member) = (PeerId::random(), PeerId::random());
+        let mut behaviour = behaviour(local, HashSet::new());
+        behaviour.memberships.update(membership_of(&[local]));
+        behaviour
+            .memberships
+            .set_pending(membership_of(&[local, member]));
+
+        assert!(behaviour.deny_outbound(member).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_removed_members_are_disconnected() {
+        let (local, member, removed, observer) = (
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+        );
+        let mut behaviour = behaviour(local, HashSet::from([observer]));
+        behaviour
+            .memberships
+            .update(membership_of(&[local, member, removed]));
+        for peer_id in [member, removed, observer] {
+            let address = "/ip4/127.0.0.1/tcp/3000".parse().unwrap();
+            behaviour
+                .all_connections
+                .insert(peer_id, Endpoint::Dialer { address });
+        }
+
+        behaviour
+            .memberships
+            .set_pending(membership_of(&[local, member]));
+        let _ = behaviour.notify_peers_updated();
+
+        assert_eq!(behaviour.to_disconnect, VecDeque::from([removed]));
+    }
+}
@@ -31,6 +31,7 @@ use thiserror::Error;
 use crate::network::libp2p::behaviours::membership::protocol::{
     MembershipCodec, Protocol, ProtocolMessage,
 };
+use crate::network::libp2p::versions::MembershipVersion;
 
 #[derive(Error, Debug)]
 pub(crate) enum Error {
@@ -58,6 +59,8 @@ enum OutboundSubstreamState {
 
 pub(crate) struct Handler {
     outbound_substream: Option<OutboundSubstreamState>,
+    /// Version negotiated for the outbound substream.
+    outbound_version: MembershipVersion,
     inbound_substream: Option<InboundSubstreamState>,
     send_queue: Vec<ProtocolMessage>,
     outbound_substream_establishing: bool,
@@ -69,6 +72,7 @@ impl Handler {
     pub(crate) fn new() -> Self {
         Self {
             outbound_substream: None,
+            outbound_version: MembershipVersion::V1,
             inbound_substream: None,
             send_queue: vec![],
             outbound_substream_establishing: false,
@@ -145,6 +149,13 @@ impl Handler {
         None
     }
 
+    fn can_send(&self, message: &ProtocolMessage) -> bool {
+        match message {
+            ProtocolMessage::Sync => true,
+            ProtocolMessage::Heartbeat => self.outbound_version.supports_heartbeat(),
+        }
+    }
+
     //Process outbound stream messages
     //WAITING_OUTPUT
     //  - if send queue is not empty, go to PENDING_SEND
@@ -162,6 +173,11 @@ impl Handler {
                 // outbound idle state
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
                     if let Some(message) = self.send_queue.pop() {
+                        if !self.can_send(&message) {
+                            self.outbound_substream =
+                                Some(OutboundSubstreamState::WaitingOutput(substream));
+                            continue;
+                        }
                         self.outbound_substream =
                             Some(OutboundSubstreamState::PendingSend(substream, message));
                         continue;
@@ -319,14 +335,15 @@ impl ConnectionHandler for Handler {
                 self.inbound_substream = Some(InboundSubstreamState::WaitingInput(stream));
             }
             ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
-                protocol,
+                protocol: (stream, version),
                 info: _,
             }) => {
                 if self.outbound_substream_attempts > MAX_SUBSTREAM_ATTEMPTS {
                     log::warn!("Too many outbound substream attempts, refusing stream");
                     return;
                 }
-                self.outbound_substream = Some(OutboundSubstreamState::WaitingOutput(protocol));
+                self.outbound_version = version;
+                self.outbound_substream = Some(OutboundSubstreamState::WaitingOutput(stream));
             }
             ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error }) => {
                 error!("DialUpgradeError: info: {:?}, error: {:?}", info, error);
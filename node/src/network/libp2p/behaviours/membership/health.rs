@@ -0,0 +1,215 @@
+//! Optional health filter of the membership.
+//!
+//! Members provider tells who is allowed to take part in reliable broadcast, but some of the members
+//! may be unresponsive. The filter checks every member regularly and excludes the ones which fail
+//! [`HealthFilterConfiguration::max_failures`] consecutive checks from the effective membership.
+//! An excluded member is included again only after [`HealthFilterConfiguration::recovery_checks`]
+//! consecutive successful checks, so that a flapping peer doesn't change the broadcast group on
+//! every check.
+//!
+//! A check fails if the member isn't connected, its last ping failed or it hasn't sent a heartbeat
+//! within [`HealthFilterConfiguration::heartbeat_timeout_sec`]. Heartbeats are sent over the
+//! membership protocol every check interval.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use libp2p_identity::PeerId;
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
+
+use crate::config::HealthFilterConfiguration;
+use crate::utilities::time::EphemeraTime;
+
+/// Why a member failed a health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExclusionReason {
+    /// There is no connection with the member.
+    Disconnected,
+    /// The last ping to the member failed.
+    PingFailed,
+    /// The member hasn't sent a heartbeat in time.
+    HeartbeatMissed,
+}
+
+impl Display for ExclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExclusionReason::Disconnected => write!(f, "disconnected"),
+            ExclusionReason::PingFailed => write!(f, "ping failed"),
+            ExclusionReason::HeartbeatMissed => write!(f, "heartbeat missed"),
+        }
+    }
+}
+
+/// Health of a member as seen by the local node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct MemberHealth {
+    /// Set while the member is excluded from the effective membership.
+    pub(crate) excluded: Option<ExclusionReason>,
+    /// Why the last check failed. None if it passed.
+    pub(crate) last_failure: Option<ExclusionReason>,
+    pub(crate) consecutive_failures: u32,
+    pub(crate) consecutive_successes: u32,
+    /// Unix timestamp in milliseconds of the last heartbeat from the member.
+    pub(crate) last_heartbeat: Option<u64>,
+}
+
+pub(crate) struct HealthFilter {
+    config: HealthFilterConfiguration,
+    members: HashMap<PeerId, MemberHealth>,
+    /// Last heartbeat of each member. New members get the heartbeat timeout from the time they
+    /// were first checked.
+    heartbeats: HashMap<PeerId, Instant>,
+    checks: Interval,
+}
+
+impl HealthFilter {
+    pub(crate) fn new(config: HealthFilterConfiguration) -> Self {
+        let period = Duration::from_secs(config.check_interval_sec.max(1));
+        let mut checks = time::interval_at(Instant::now() + period, period);
+        checks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            config,
+            members: HashMap::new(),
+            heartbeats: HashMap::new(),
+            checks,
+        }
+    }
+
+    /// Ready when it's time for the next check.
+    pub(crate) fn poll_check(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.checks.poll_tick(cx).map(|_| ())
+    }
+
+    pub(crate) fn heartbeat(&mut self, peer_id: PeerId) {
+        self.heartbeats.insert(peer_id, Instant::now());
+        if let Some(health) = self.members.get_mut(&peer_id) {
+            health.last_heartbeat = Some(EphemeraTime::now());
+        }
+    }
+
+    /// Checks all `members`. `failure` tells why the connection with a member is unhealthy, if it is.
+    ///
+    /// Returns true if a member was excluded or included again.
+    pub(crate) fn check<F>(&mut self, members: &HashSet<PeerId>, failure: F) -> bool
+    where
+        F: Fn(&PeerId) -> Option<ExclusionReason>,
+    {
+        self.members.retain(|peer_id, _| members.contains(peer_id));
+        self.heartbeats
+            .retain(|peer_id, _| members.contains(peer_id));
+
+        let now = Instant::now();
+        let heartbeat_timeout = Duration::from_secs(self.config.heartbeat_timeout_sec);
+        let mut changed = false;
+        for peer_id in members {
+            let last_heartbeat = *self.heartbeats.entry(*peer_id).or_insert(now);
+            let failed = failure(peer_id).or_else(|| {
+                (now.duration_since(last_heartbeat) > heartbeat_timeout)
+                    .then_some(ExclusionReason::HeartbeatMissed)
+            });
+
+            let health = self.members.entry(*peer_id).or_default();
+            health.last_failure = failed;
+            if let Some(reason) = failed {
+                health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+                health.consecutive_successes = 0;
+                if health.excluded.is_none()
+                    && health.consecutive_failures >= self.config.max_failures
+                {
+                    health.excluded = Some(reason);
+                    changed = true;
+                }
+            } else {
+                health.consecutive_successes = health.consecutive_successes.saturating_add(1);
+                health.consecutive_failures = 0;
+                if health.excluded.is_some()
+                    && health.consecutive_successes >= self.config.recovery_checks
+                {
+                    health.excluded = None;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Members excluded from the effective membership together with the reason.
+    pub(crate) fn excluded(&self) -> HashMap<PeerId, ExclusionReason> {
+        self.members
+            .iter()
+            .filter_map(|(peer_id, health)| health.excluded.map(|reason| (*peer_id, reason)))
+            .collect()
+    }
+
+    pub(crate) fn members_health(&self) -> &HashMap<PeerId, MemberHealth> {
+        &self.members
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter() -> HealthFilter {
+        HealthFilter::new(HealthFilterConfiguration {
+            check_interval_sec: 1,
+            heartbeat_timeout_sec: 5,
+            max_failures: 2,
+            recovery_checks: 3,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_member_is_excluded_and_included_with_hysteresis() {
+        let mut filter = filter();
+        let (healthy, flaky) = (PeerId::random(), PeerId::random());
+        let members = HashSet::from([healthy, flaky]);
+        let disconnected =
+            |peer_id: &PeerId| (*peer_id == flaky).then_some(ExclusionReason::Disconnected);
+
+        assert!(!filter.check(&members, disconnected));
+        assert!(filter.excluded().is_empty());
+        assert!(filter.check(&members, disconnected));
+        assert_eq!(
+            filter.excluded(),
+            HashMap::from([(flaky, ExclusionReason::Disconnected)])
+        );
+
+        //Stays excluded until enough consecutive checks pass
+        assert!(!filter.check(&members, |_| None));
+        assert!(!filter.check(&members, disconnected));
+        assert!(!filter.check(&members, |_| None));
+        assert!(!filter.check(&members, |_| None));
+        assert_eq!(filter.excluded().len(), 1);
+        assert!(filter.check(&members, |_| None));
+        assert!(filter.excluded().is_empty());
+        assert_eq!(filter.members_health()[&flaky].consecutive_successes, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_member_without_heartbeats_is_excluded() {
+        let mut filter = filter();
+        let (alive, silent) = (PeerId::random(), PeerId::random());
+        let members = HashSet::from([alive, silent]);
+
+        //New members get the heartbeat timeout to send the first heartbeat
+        assert!(!filter.check(&members, |_| None));
+        for _ in 0..3 {
+            time::advance(Duration::from_secs(4)).await;
+            filter.heartbeat(alive);
+            filter.check(&members, |_| None);
+        }
+        assert_eq!(
+            filter.excluded(),
+            HashMap::from([(silent, ExclusionReason::HeartbeatMissed)])
+        );
+        assert!(filter.members_health()[&alive].last_heartbeat.is_some());
+
+        //Non-members are forgotten
+        filter.check(&HashSet::from([alive]), |_| None);
+        assert!(filter.excluded().is_empty());
+    }
+}
@@ -3,15 +3,20 @@
 
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use libp2p_identity::PeerId;
 use lru::LruCache;
 
+use crate::membership::DEFAULT_PEER_WEIGHT;
+use crate::network::libp2p::behaviours::membership::health::ExclusionReason;
 use crate::network::Peer;
 
 pub(crate) mod behaviour;
 mod connections;
 mod handler;
+pub(crate) mod health;
 mod protocol;
 
 const MAX_DIAL_ATTEMPT_ROUNDS: usize = 6;
@@ -48,9 +53,17 @@ impl MembershipKind {
         clippy::cast_sign_loss,
         clippy::cast_possible_truncation
     )]
-    pub(crate) fn accept(&self, membership: &Membership) -> bool {
+    /// Only connected peers which are healthy count as online.
+    pub(crate) fn accept<F>(&self, membership: &Membership, is_healthy: F) -> bool
+    where
+        F: Fn(&PeerId) -> bool,
+    {
         let total_number_of_peers = membership.all_members.len();
-        let connected_peers = membership.connected_peers_ids.len();
+        let connected_peers = membership
+            .connected_peers_ids
+            .iter()
+            .filter(|peer_id| is_healthy(peer_id))
+            .count();
         match self {
             MembershipKind::Threshold(threshold) => {
                 let minimum_available_nodes = (total_number_of_peers as f64 * threshold) as usize;
@@ -106,14 +119,50 @@ impl Memberships {
     pub(crate) fn pending_mut(&mut self) -> Option<&mut Membership> {
         self.pending_membership.as_mut()
     }
+
+    /// Returns true if the peer is part of the current or pending membership.
+    pub(crate) fn is_member(&mut self, peer_id: &PeerId) -> bool {
+        self.pending()
+            .is_some_and(|pending| pending.contains(peer_id))
+            || self.current().contains(peer_id)
+    }
 }
 
-#[derive(Debug)]
+/// Number of connections denied because the peer is not a member. Clones share the counters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DeniedConnections {
+    inbound: Arc<AtomicU64>,
+    outbound: Arc<AtomicU64>,
+}
+
+impl DeniedConnections {
+    pub(crate) fn inbound(&self) -> u64 {
+        self.inbound.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn outbound(&self) -> u64 {
+        self.outbound.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn deny_inbound(&self) {
+        self.inbound.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn deny_outbound(&self) {
+        self.outbound.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct Membership {
     local_peer_id: PeerId,
     all_members: HashMap<PeerId, Peer>,
     all_peers_ids: HashSet<PeerId>,
     connected_peers_ids: HashSet<PeerId>,
+    /// Height of the local block after which the broadcast group switches to this membership.
+    activation_height: Option<u64>,
+    /// Members excluded by the health filter. They don't count as connected.
+    excluded: HashMap<PeerId, ExclusionReason>,
 }
 
 impl Membership {
@@ -127,6 +176,8 @@ impl Membership {
             all_members,
             all_peers_ids,
             connected_peers_ids: HashSet::new(),
+            activation_height: None,
+            excluded: HashMap::new(),
         }
     }
 
@@ -137,13 +188,54 @@ impl Membership {
             all_members,
             all_peers_ids,
             connected_peers_ids: HashSet::new(),
+            activation_height: None,
+            excluded: HashMap::new(),
         }
     }
 
+    pub(crate) fn with_activation_height(mut self, activation_height: Option<u64>) -> Self {
+        self.activation_height = activation_height;
+        self
+    }
+
+    pub(crate) fn activation_height(&self) -> Option<u64> {
+        self.activation_height
+    }
+
+    /// Excludes the members which failed health checks from the connected peers.
+    pub(crate) fn with_exclusions(mut self, excluded: &HashMap<PeerId, ExclusionReason>) -> Self {
+        self.excluded = excluded
+            .iter()
+            .filter(|(peer_id, _)| self.all_members.contains_key(peer_id))
+            .map(|(peer_id, reason)| (*peer_id, *reason))
+            .collect();
+        self.connected_peers_ids
+            .retain(|peer_id| !self.excluded.contains_key(peer_id));
+        self
+    }
+
+    /// Replaces the connected peers with the members among `connected`.
+    pub(crate) fn with_connected(mut self, connected: &HashSet<PeerId>) -> Self {
+        self.connected_peers_ids = connected
+            .iter()
+            .filter(|peer_id| self.all_members.contains_key(peer_id))
+            .copied()
+            .collect();
+        self
+    }
+
+    pub(crate) fn excluded(&self) -> &HashMap<PeerId, ExclusionReason> {
+        &self.excluded
+    }
+
     pub(crate) fn includes_local(&self) -> bool {
         self.all_members.contains_key(&self.local_peer_id)
     }
 
+    pub(crate) fn contains(&self, peer_id: &PeerId) -> bool {
+        self.all_members.contains_key(peer_id)
+    }
+
     pub(crate) fn peer_connected(&mut self, peer_id: PeerId) {
         self.connected_peers_ids.insert(peer_id);
     }
@@ -160,16 +252,31 @@ impl Membership {
         self.connected_peers_ids.clone()
     }
 
-    pub(crate) fn connected_peer_ids_with_local(&self) -> HashSet<PeerId> {
-        let mut active_peers = self.connected_peers_ids.clone();
-        active_peers.insert(self.local_peer_id);
-        active_peers
-    }
-
     pub(crate) fn connected_peers(&self) -> &HashSet<PeerId> {
         &self.connected_peers_ids
     }
 
+    /// Returns connected peers together with their weights.
+    pub(crate) fn connected_peer_weights(&self) -> HashMap<PeerId, u64> {
+        self.connected_peers_ids
+            .iter()
+            .map(|peer_id| (*peer_id, self.peer_weight(peer_id)))
+            .collect()
+    }
+
+    /// Returns connected peers and local peer together with their weights.
+    pub(crate) fn connected_peer_weights_with_local(&self) -> HashMap<PeerId, u64> {
+        let mut weights = self.connected_peer_weights();
+        weights.insert(self.local_peer_id, self.peer_weight(&self.local_peer_id));
+        weights
+    }
+
+    pub(crate) fn peer_weight(&self, peer_id: &PeerId) -> u64 {
+        self.all_members
+            .get(peer_id)
+            .map_or(DEFAULT_PEER_WEIGHT, |peer| peer.weight)
+    }
+
     pub(crate) fn peer_address(&self, peer_id: &PeerId) -> Option<&libp2p::Multiaddr> {
         self.all_members
             .get(peer_id)
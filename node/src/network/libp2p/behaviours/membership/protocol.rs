@@ -1,5 +1,4 @@
 use std::future::Future;
-use std::iter;
 use std::pin::Pin;
 
 use asynchronous_codec::{Decoder, Encoder, Framed};
@@ -11,18 +10,21 @@ use libp2p::{InboundUpgrade, OutboundUpgrade};
 use log::trace;
 use serde::{Deserialize, Serialize};
 
+use crate::network::libp2p::versions::MembershipVersion;
 use crate::utilities::codec::varint_bytes::{read_length_prefixed, write_length_prefixed};
 
-pub const PROTOCOL_NAME: &[u8] = b"/ephemera/membership/1.0.0";
-
 pub(crate) struct Protocol;
 
 impl UpgradeInfo for Protocol {
     type Info = &'static [u8];
-    type InfoIter = iter::Once<Self::Info>;
+    type InfoIter = Vec<Self::Info>;
 
+    //Libp2p picks the first protocol the remote supports
     fn protocol_info(&self) -> Self::InfoIter {
-        iter::once(PROTOCOL_NAME)
+        MembershipVersion::SUPPORTED
+            .into_iter()
+            .map(|version| version.protocol().as_bytes())
+            .collect()
     }
 }
 
@@ -34,10 +36,10 @@ where
     type Error = anyhow::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
 
-    fn upgrade_inbound(self, socket: C, _: Self::Info) -> Self::Future {
+    fn upgrade_inbound(self, socket: C, protocol: Self::Info) -> Self::Future {
         trace!(
             "Inbound upgrade for protocol: {}",
-            String::from_utf8_lossy(PROTOCOL_NAME)
+            String::from_utf8_lossy(protocol)
         );
         Box::pin(future::ok(Framed::new(socket, MembershipCodec {})))
     }
@@ -47,22 +49,29 @@ impl<C> OutboundUpgrade<C> for Protocol
 where
     C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
-    type Output = Framed<C, MembershipCodec>;
+    /// Stream and the version negotiated with the remote, it decides which messages can be sent.
+    type Output = (Framed<C, MembershipCodec>, MembershipVersion);
     type Error = anyhow::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
 
-    fn upgrade_outbound(self, socket: C, _: Self::Info) -> Self::Future {
+    fn upgrade_outbound(self, socket: C, protocol: Self::Info) -> Self::Future {
         trace!(
             "Outbound upgrade for protocol: {}",
-            String::from_utf8_lossy(PROTOCOL_NAME)
+            String::from_utf8_lossy(protocol)
         );
-        Box::pin(future::ok(Framed::new(socket, MembershipCodec {})))
+        let version = MembershipVersion::from_protocol(protocol).unwrap_or(MembershipVersion::V1);
+        Box::pin(future::ok((
+            Framed::new(socket, MembershipCodec {}),
+            version,
+        )))
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum ProtocolMessage {
     Sync,
+    /// Sent regularly to members when the health filter is enabled. Since [`MembershipVersion::V2`].
+    Heartbeat,
 }
 
 pub(crate) struct MembershipCodec {}
@@ -1,19 +1,32 @@
-use std::future::Future;
-use std::{iter, sync::Arc, time::Duration};
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+use std::{sync::Arc, time::Duration};
 
+use futures::future::Either;
+use futures::{AsyncRead, AsyncWrite};
 use libp2p::{
     core::{muxing::StreamMuxerBox, transport::Boxed},
     dns, gossipsub,
     gossipsub::{IdentTopic as Topic, MessageAuthenticity, ValidationMode},
-    kad, noise, request_response as libp2p_request_response,
+    identify, kad, noise, ping,
+    pnet::{PnetConfig, PnetError, PnetOutput, PreSharedKey},
+    request_response as libp2p_request_response,
     swarm::NetworkBehaviour,
     tcp::{tokio::Transport as TokioTransport, Config as TokioConfig},
     yamux, PeerId as Libp2pPeerId, Transport,
 };
+use libp2p_quic as quic;
 use log::info;
 
-use crate::membership::PeerInfo;
-use crate::network::libp2p::behaviours::membership::MembershipKind;
+use crate::config::{
+    DhtConfiguration, GossipsubConfiguration, GossipsubValidationMode, HealthFilterConfiguration,
+    Libp2pConfiguration, PeerScoringConfiguration, PingConfiguration, TransportProtocol,
+};
+use crate::membership::MembersProvider;
+use crate::network::libp2p::behaviours::membership::{DeniedConnections, MembershipKind};
+use crate::network::libp2p::dht::store::DhtRecordStore;
+use crate::network::libp2p::peers::NetworkPeers;
+use crate::network::libp2p::versions::ProtocolVersion;
 use crate::{
     broadcast::RbMsg,
     crypto::Keypair,
@@ -27,16 +40,24 @@ use crate::{
 pub(crate) mod membership;
 pub(crate) mod request_response;
 
+/// Gossipsub default for the minimum number of outbound peers in the mesh.
+const DEFAULT_MESH_OUTBOUND_MIN: usize = 2;
+
+/// Protocol version nodes report to each other with identify protocol.
+const IDENTIFY_PROTOCOL_VERSION: &str = "/ephemera/1.0.0";
+
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "GroupBehaviourEvent")]
 pub(crate) struct GroupNetworkBehaviour<P>
 where
-    P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + 'static,
+    P: MembersProvider,
 {
     pub(crate) members_provider: membership::behaviour::Behaviour<P>,
     pub(crate) gossipsub: gossipsub::Behaviour,
     pub(crate) request_response: libp2p_request_response::Behaviour<RbMsgMessagesCodec>,
-    pub(crate) kademlia: kad::Kademlia<kad::store::MemoryStore>,
+    pub(crate) kademlia: kad::Kademlia<DhtRecordStore>,
+    pub(crate) ping: ping::Behaviour,
+    pub(crate) identify: identify::Behaviour,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -45,6 +66,8 @@ pub(crate) enum GroupBehaviourEvent {
     RequestResponse(libp2p_request_response::Event<RbMsg, RbMsgResponse>),
     Membership(membership::behaviour::Event),
     Kademlia(kad::KademliaEvent),
+    Ping(ping::Event),
+    Identify(identify::Event),
 }
 
 impl From<gossipsub::Event> for GroupBehaviourEvent {
@@ -71,109 +94,309 @@ impl From<kad::KademliaEvent> for GroupBehaviourEvent {
     }
 }
 
+impl From<ping::Event> for GroupBehaviourEvent {
+    fn from(event: ping::Event) -> Self {
+        GroupBehaviourEvent::Ping(event)
+    }
+}
+
+impl From<identify::Event> for GroupBehaviourEvent {
+    fn from(event: identify::Event) -> Self {
+        GroupBehaviourEvent::Identify(event)
+    }
+}
+
 //Create combined behaviour.
 //Gossipsub takes care of message delivery semantics
 //Membership takes care of providing peers who are part of the reliable broadcast group
 //Kademlia takes provides closest neighbours and general DHT functionality
+//Ping and Identify measure latency and versions of connected peers
 pub(crate) fn create_behaviour<P>(
     keypair: &Arc<Keypair>,
-    ephemera_msg_topic: &Topic,
+    topics: &[Topic],
     members_provider: P,
-    members_provider_delay: Duration,
-    membership_kind: MembershipKind,
-) -> GroupNetworkBehaviour<P>
+    config: &Libp2pConfiguration,
+    denied_connections: DeniedConnections,
+    dht_store: DhtRecordStore,
+    network_peers: NetworkPeers,
+) -> anyhow::Result<GroupNetworkBehaviour<P>>
 where
-    P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
     //TODO: review behaviours config(eg. gossipsub minimum peers, kademlia ttl, request-response timeouts etc.)
     let local_peer_id = keypair.peer_id();
-    let gossipsub = create_gossipsub(keypair, ephemera_msg_topic);
+    let gossipsub = create_gossipsub(
+        keypair,
+        topics,
+        Duration::from_secs(config.heartbeat_interval_sec),
+        &config.gossipsub,
+    )?;
     let request_response = create_request_response();
     let rendezvous_behaviour = create_membership(
         members_provider,
-        members_provider_delay,
-        membership_kind,
+        config.membership_kind.clone().into(),
         local_peer_id,
+        config.grace_list.iter().copied().collect(),
+        denied_connections,
+        network_peers,
+        config.health_filter.clone(),
     );
-    let kademlia = create_kademlia(keypair);
+    let kademlia = create_kademlia(keypair, &config.dht, dht_store);
+    let ping = create_ping(&config.ping);
+    let identify = create_identify(keypair);
 
-    GroupNetworkBehaviour {
+    Ok(GroupNetworkBehaviour {
         members_provider: rendezvous_behaviour,
         gossipsub,
         request_response,
         kademlia,
-    }
+        ping,
+        identify,
+    })
 }
 
 // Configure networking messaging stack(Gossipsub)
-pub(crate) fn create_gossipsub(local_key: &Arc<Keypair>, topic: &Topic) -> gossipsub::Behaviour {
+pub(crate) fn create_gossipsub(
+    local_key: &Arc<Keypair>,
+    topics: &[Topic],
+    heartbeat_interval: Duration,
+    config: &GossipsubConfiguration,
+) -> anyhow::Result<gossipsub::Behaviour> {
+    let (validation_mode, authenticity) = match config.validation_mode {
+        GossipsubValidationMode::Strict => (
+            ValidationMode::Strict,
+            MessageAuthenticity::Signed(local_key.inner().clone()),
+        ),
+        GossipsubValidationMode::Permissive => (
+            ValidationMode::Permissive,
+            MessageAuthenticity::Signed(local_key.inner().clone()),
+        ),
+        GossipsubValidationMode::Anonymous => {
+            (ValidationMode::Anonymous, MessageAuthenticity::Anonymous)
+        }
+        GossipsubValidationMode::None => (
+            ValidationMode::None,
+            MessageAuthenticity::Signed(local_key.inner().clone()),
+        ),
+    };
+
     let gossipsub_config = gossipsub::ConfigBuilder::default()
-        //TODO: settings from config
-        .heartbeat_interval(Duration::from_secs(5))
+        .heartbeat_interval(heartbeat_interval)
+        .mesh_n(config.mesh_n)
+        .mesh_n_low(config.mesh_n_low)
+        .mesh_n_high(config.mesh_n_high)
+        //Gossipsub requires it to be at most half of the mesh size
+        .mesh_outbound_min(DEFAULT_MESH_OUTBOUND_MIN.min(config.mesh_n / 2))
+        .max_transmit_size(config.max_transmit_size)
+        .duplicate_cache_time(Duration::from_secs(config.duplicate_cache_time_sec))
         .message_id_fn(|msg: &gossipsub::Message| Hasher::digest(&msg.data).into())
-        .validation_mode(ValidationMode::Strict)
+        .validation_mode(validation_mode)
+        //Messages are forwarded only after Application has accepted them
+        .validate_messages()
         .build()
-        .expect("Valid config");
+        .map_err(|err| anyhow::anyhow!("Invalid gossipsub configuration: {err}"))?;
 
-    let mut behaviour = gossipsub::Behaviour::new(
-        MessageAuthenticity::Signed(local_key.inner().clone()),
-        gossipsub_config,
-    )
-    .expect("Correct configuration");
+    let mut behaviour = gossipsub::Behaviour::new(authenticity, gossipsub_config)
+        .map_err(|err| anyhow::anyhow!("Invalid gossipsub configuration: {err}"))?;
+
+    if config.peer_scoring.enabled {
+        let (params, thresholds) = peer_score_settings(topics, &config.peer_scoring);
+        behaviour
+            .with_peer_score(params, thresholds)
+            .map_err(|err| anyhow::anyhow!("Invalid peer scoring configuration: {err}"))?;
+    }
 
-    info!("Subscribing to topic: {}", topic);
-    behaviour.subscribe(topic).expect("Valid topic");
-    behaviour
+    for topic in topics {
+        info!("Subscribing to topic: {}", topic);
+        behaviour
+            .subscribe(topic)
+            .map_err(|err| anyhow::anyhow!("Failed to subscribe to topic {topic}: {err:?}"))?;
+    }
+    Ok(behaviour)
+}
+
+//Peers are penalized only for gossiping messages Application rejects.
+//Mesh delivery penalties are turned off because Ephemera message rate is low and irregular,
+//and IP colocation penalty because membership is authorized by members provider.
+fn peer_score_settings(
+    topics: &[Topic],
+    config: &PeerScoringConfiguration,
+) -> (gossipsub::PeerScoreParams, gossipsub::PeerScoreThresholds) {
+    let topic_params = gossipsub::TopicScoreParams {
+        topic_weight: 1.0,
+        invalid_message_deliveries_weight: config.invalid_message_weight,
+        invalid_message_deliveries_decay: config.invalid_message_decay,
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        ..Default::default()
+    };
+
+    let mut params = gossipsub::PeerScoreParams {
+        ip_colocation_factor_weight: 0.0,
+        ..Default::default()
+    };
+    for topic in topics {
+        params.topics.insert(topic.hash(), topic_params.clone());
+    }
+
+    let thresholds = gossipsub::PeerScoreThresholds {
+        gossip_threshold: config.gossip_threshold,
+        publish_threshold: config.publish_threshold,
+        graylist_threshold: config.graylist_threshold,
+        ..Default::default()
+    };
+    (params, thresholds)
 }
 
 pub(crate) fn create_request_response() -> libp2p_request_response::Behaviour<RbMsgMessagesCodec> {
     let config = libp2p_request_response::Config::default();
-    libp2p_request_response::Behaviour::new(
-        RbMsgMessagesCodec,
-        iter::once((
-            RbMsgProtocol,
+    //Requests are sent with the first protocol the remote supports
+    let protocols = ProtocolVersion::SUPPORTED.into_iter().map(|version| {
+        (
+            RbMsgProtocol(version),
             libp2p_request_response::ProtocolSupport::Full,
-        )),
-        config,
-    )
+        )
+    });
+    libp2p_request_response::Behaviour::new(RbMsgMessagesCodec, protocols, config)
 }
 
 pub(crate) fn create_membership<P>(
     members_provider: P,
-    members_provider_delay: Duration,
     membership_kind: MembershipKind,
     local_peer_id: PeerId,
+    grace_list: HashSet<PeerId>,
+    denied_connections: DeniedConnections,
+    network_peers: NetworkPeers,
+    health_filter: Option<HealthFilterConfiguration>,
 ) -> membership::behaviour::Behaviour<P>
 where
-    P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
     membership::behaviour::Behaviour::new(
         members_provider,
-        members_provider_delay,
         local_peer_id.into(),
         membership_kind,
+        grace_list.into_iter().map(Into::into).collect(),
+        denied_connections,
+        network_peers,
+        health_filter,
     )
 }
 
-pub(super) fn create_kademlia(local_key: &Arc<Keypair>) -> kad::Kademlia<kad::store::MemoryStore> {
+pub(crate) fn create_ping(config: &PingConfiguration) -> ping::Behaviour {
+    let max_failures = NonZeroU32::new(config.max_failures).unwrap_or(NonZeroU32::MIN);
+    ping::Behaviour::new(
+        ping::Config::new()
+            .with_interval(Duration::from_secs(config.interval_sec))
+            .with_timeout(Duration::from_secs(config.timeout_sec))
+            .with_max_failures(max_failures),
+    )
+}
+
+pub(crate) fn create_identify(local_key: &Arc<Keypair>) -> identify::Behaviour {
+    let config = identify::Config::new(
+        IDENTIFY_PROTOCOL_VERSION.to_string(),
+        local_key.inner().public(),
+    )
+    .with_agent_version(format!("ephemera/{}", env!("CARGO_PKG_VERSION")));
+    identify::Behaviour::new(config)
+}
+
+pub(super) fn create_kademlia(
+    local_key: &Arc<Keypair>,
+    dht_config: &DhtConfiguration,
+    store: DhtRecordStore,
+) -> kad::Kademlia<DhtRecordStore> {
     let peer_id = local_key.peer_id();
     let mut cfg = kad::KademliaConfig::default();
-    cfg.set_query_timeout(Duration::from_secs(5 * 60));
-    let store = kad::store::MemoryStore::new(peer_id.0);
+    cfg.set_query_timeout(Duration::from_secs(dht_config.query_timeout_sec));
+    //Only members are added to the routing table, see `SwarmNetwork`
+    cfg.set_kbucket_inserts(kad::KademliaBucketInserts::Manual);
+    //Inbound records are validated and stored by `SwarmNetwork`
+    cfg.set_record_filtering(kad::KademliaStoreInserts::FilterBoth);
     kad::Kademlia::with_config(*peer_id.inner(), store, cfg)
 }
 
-//Configure networking connection stack(Tcp, Noise, Yamux)
+/// Transport the swarm uses to reach other peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum TransportKind {
+    #[default]
+    Tcp,
+    Quic,
+    /// Both TCP and QUIC.
+    Dual,
+    /// In-process transport. Only nodes running in the same process can reach each other.
+    #[cfg(test)]
+    Memory,
+}
+
+impl From<TransportProtocol> for TransportKind {
+    fn from(protocol: TransportProtocol) -> Self {
+        match protocol {
+            TransportProtocol::Tcp => TransportKind::Tcp,
+            TransportProtocol::Quic => TransportKind::Quic,
+            TransportProtocol::Dual => TransportKind::Dual,
+        }
+    }
+}
+
+//Configure networking connection stack(Tcp, Noise, Yamux), QUIC or both of them.
 //Tcp protocol for networking
+//Pnet protocol for private network, if pre-shared key is given
 //Noise protocol for encryption
 //Yamux protocol for multiplexing
+//QUIC does encryption and multiplexing itself
 pub(crate) fn create_transport(
     local_key: &Arc<Keypair>,
+    kind: TransportKind,
+    swarm_key: Option<PreSharedKey>,
 ) -> anyhow::Result<Boxed<(Libp2pPeerId, StreamMuxerBox)>> {
+    if let Some(swarm_key) = swarm_key {
+        if matches!(kind, TransportKind::Quic | TransportKind::Dual) {
+            anyhow::bail!("Private network works only over TCP transport");
+        }
+        info!(
+            "Private network enabled, swarm key fingerprint: {}",
+            swarm_key.fingerprint()
+        );
+    }
+    match kind {
+        TransportKind::Tcp => create_tcp_transport(local_key, swarm_key),
+        TransportKind::Quic => Ok(create_quic_transport(local_key)),
+        TransportKind::Dual => {
+            //Dialing picks the transport which supports the address
+            let transport = create_quic_transport(local_key)
+                .or_transport(create_tcp_transport(local_key, None)?)
+                .map(|output, _| match output {
+                    Either::Left(output) | Either::Right(output) => output,
+                });
+            Ok(transport.boxed())
+        }
+        #[cfg(test)]
+        TransportKind::Memory => {
+            let transport = libp2p::core::transport::MemoryTransport::default()
+                .and_then(move |socket, _| private_network_handshake(socket, swarm_key));
+            Ok(transport
+                .upgrade(libp2p::core::upgrade::Version::V1)
+                .authenticate(noise::Config::new(local_key.inner())?)
+                .multiplex(yamux::Config::default())
+                .timeout(Duration::from_secs(20))
+                .boxed())
+        }
+    }
+}
+
+fn create_tcp_transport(
+    local_key: &Arc<Keypair>,
+    swarm_key: Option<PreSharedKey>,
+) -> anyhow::Result<Boxed<(Libp2pPeerId, StreamMuxerBox)>> {
+    let noise_config = noise::Config::new(local_key.inner())?;
     let transport = TokioTransport::new(TokioConfig::default().nodelay(true));
     let transport = dns::TokioDnsConfig::system(transport)?;
+    //Peers with a different key fail the Noise handshake which follows
+    let transport =
+        transport.and_then(move |socket, _| private_network_handshake(socket, swarm_key));
 
-    let noise_config = noise::Config::new(local_key.inner())?;
     Ok(transport
         .upgrade(libp2p::core::upgrade::Version::V1)
         .authenticate(noise_config)
@@ -181,3 +404,93 @@ pub(crate) fn create_transport(
         .timeout(Duration::from_secs(20))
         .boxed())
 }
+
+/// Without the swarm key the socket is used as is.
+async fn private_network_handshake<S>(
+    socket: S,
+    swarm_key: Option<PreSharedKey>,
+) -> Result<Either<PnetOutput<S>, S>, PnetError>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    match swarm_key {
+        Some(swarm_key) => PnetConfig::new(swarm_key)
+            .handshake(socket)
+            .await
+            .map(Either::Left),
+        None => Ok(Either::Right(socket)),
+    }
+}
+
+fn create_quic_transport(local_key: &Arc<Keypair>) -> Boxed<(Libp2pPeerId, StreamMuxerBox)> {
+    let config = quic::Config::new(local_key.inner());
+    quic::tokio::Transport::new(config)
+        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+        .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crypto::EphemeraKeypair;
+
+    use super::*;
+
+    fn gossipsub(config: &GossipsubConfiguration) -> anyhow::Result<gossipsub::Behaviour> {
+        let keypair = Arc::new(Keypair::generate(None));
+        let topics = [Topic::new("test")];
+        create_gossipsub(&keypair, &topics, Duration::from_secs(1), config)
+    }
+
+    #[test]
+    fn test_default_configuration_is_valid() {
+        assert!(gossipsub(&GossipsubConfiguration::default()).is_ok());
+    }
+
+    #[test]
+    fn test_all_validation_modes_are_valid() {
+        for validation_mode in [
+            GossipsubValidationMode::Strict,
+            GossipsubValidationMode::Permissive,
+            GossipsubValidationMode::Anonymous,
+            GossipsubValidationMode::None,
+        ] {
+            let config = GossipsubConfiguration {
+                validation_mode,
+                ..Default::default()
+            };
+            assert!(gossipsub(&config).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_small_mesh_is_valid() {
+        let config = GossipsubConfiguration {
+            mesh_n: 2,
+            mesh_n_low: 1,
+            mesh_n_high: 3,
+            ..Default::default()
+        };
+        assert!(gossipsub(&config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_mesh_is_rejected() {
+        let config = GossipsubConfiguration {
+            mesh_n: 4,
+            mesh_n_low: 5,
+            ..Default::default()
+        };
+        assert!(gossipsub(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_peer_scoring_is_rejected() {
+        let mut config = GossipsubConfiguration::default();
+        config.peer_scoring.invalid_message_weight = 1.0;
+        assert!(gossipsub(&config).is_err());
+
+        let mut config = GossipsubConfiguration::default();
+        config.peer_scoring.publish_threshold = config.peer_scoring.gossip_threshold + 1.0;
+        assert!(gossipsub(&config).is_err());
+    }
+}
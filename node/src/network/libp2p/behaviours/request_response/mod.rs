@@ -5,6 +5,7 @@ use log::trace;
 use serde::{Deserialize, Serialize};
 
 use crate::broadcast::RbMsg;
+use crate::network::libp2p::versions::ProtocolVersion;
 use crate::utilities::codec::varint_async::{read_length_prefixed, write_length_prefixed};
 use crate::utilities::id::EphemeraId;
 
@@ -14,11 +15,11 @@ pub(crate) struct RbMsgMessagesCodec;
 impl RbMsgMessagesCodec {}
 
 #[derive(Clone)]
-pub(crate) struct RbMsgProtocol;
+pub(crate) struct RbMsgProtocol(pub(crate) ProtocolVersion);
 
 impl request_response::ProtocolName for RbMsgProtocol {
     fn protocol_name(&self) -> &[u8] {
-        "/ephemera/reliable_broadcast/1.0.0".as_bytes()
+        self.0.broadcast_protocol().as_bytes()
     }
 }
 
@@ -0,0 +1,314 @@
+//! Ephemera stores signed records in Kademlia DHT.
+//!
+//! Kademlia stores raw key-value pairs. Ephemera wraps the value into a [`record::SignedRecord`]
+//! which carries the publisher public key, signature and expiry. Peers validate the records they
+//! are asked to store and the records they receive as query results.
+//!
+//! Records are kept in the node database by [`store::DhtRecordStore`] and survive restarts.
+//!
+//! Every query and store completes: with a record or not-found, with success, quorum failure
+//! or timeout. [`DhtQueries`] keeps track of them until they do.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use libp2p::kad;
+use thiserror::Error;
+
+use crate::config::DhtQuorum;
+use crate::network::libp2p::dht::record::SignedRecord;
+
+pub(crate) mod record;
+pub(crate) mod store;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub(crate) enum DhtError {
+    #[error("DHT request timed out")]
+    Timeout,
+    #[error("DHT quorum not reached, {reached} of {required} peers")]
+    QuorumFailed { reached: usize, required: usize },
+    #[error("Invalid DHT record: {0}")]
+    InvalidRecord(String),
+    #[error("DHT request failed: {0}")]
+    Failed(String),
+}
+
+pub(crate) type DhtQueryResult = Result<Option<SignedRecord>, DhtError>;
+
+pub(crate) type DhtStoreResult = Result<(), DhtError>;
+
+/// Number of peers a record can be replicated to: the other members, at most Kademlia replication factor.
+pub(crate) fn replication_factor(remote_members: usize) -> NonZeroUsize {
+    NonZeroUsize::new(remote_members).map_or(NonZeroUsize::MIN, |members| members.min(kad::K_VALUE))
+}
+
+/// Kademlia quorum with the number of peers `quorum` requires out of `replication`.
+///
+/// Kademlia computes `majority` and `all` from its replication factor, which is more than
+/// the number of members in small clusters.
+pub(crate) fn kad_quorum(quorum: DhtQuorum, replication: NonZeroUsize) -> kad::Quorum {
+    kad::Quorum::N(quorum.required(replication))
+}
+
+enum DhtQuery {
+    Get {
+        key: Vec<u8>,
+        required: usize,
+        found: Vec<SignedRecord>,
+    },
+    Put {
+        /// Key of the store request, before it's namespaced.
+        key: Vec<u8>,
+    },
+}
+
+/// Kademlia queries waiting for their results.
+///
+/// Methods return the key of the request and the result once the query has completed.
+#[derive(Default)]
+pub(crate) struct DhtQueries {
+    queries: HashMap<kad::QueryId, DhtQuery>,
+}
+
+impl DhtQueries {
+    pub(crate) fn get_started(&mut self, id: kad::QueryId, key: Vec<u8>, required: NonZeroUsize) {
+        self.queries.insert(
+            id,
+            DhtQuery::Get {
+                key,
+                required: required.get(),
+                found: vec![],
+            },
+        );
+    }
+
+    pub(crate) fn put_started(&mut self, id: kad::QueryId, key: Vec<u8>) {
+        self.queries.insert(id, DhtQuery::Put { key });
+    }
+
+    /// Query completes when enough peers have returned a valid record.
+    pub(crate) fn record_found(
+        &mut self,
+        id: kad::QueryId,
+        record: SignedRecord,
+    ) -> Option<(Vec<u8>, DhtQueryResult)> {
+        let Some(DhtQuery::Get {
+            found, required, ..
+        }) = self.queries.get_mut(&id)
+        else {
+            return None;
+        };
+        found.push(record);
+        if found.len() < *required {
+            return None;
+        }
+        match self.queries.remove(&id) {
+            Some(DhtQuery::Get { key, found, .. }) => Some((key, Ok(Self::latest(found)))),
+            _ => None,
+        }
+    }
+
+    /// Kademlia finished the query before the quorum was reached.
+    pub(crate) fn get_finished(
+        &mut self,
+        id: kad::QueryId,
+        timed_out: bool,
+    ) -> Option<(Vec<u8>, DhtQueryResult)> {
+        let Some(DhtQuery::Get {
+            key,
+            required,
+            found,
+        }) = self.queries.remove(&id)
+        else {
+            return None;
+        };
+        let result = if found.is_empty() {
+            if timed_out {
+                Err(DhtError::Timeout)
+            } else {
+                Ok(None)
+            }
+        } else {
+            Err(DhtError::QuorumFailed {
+                reached: found.len(),
+                required,
+            })
+        };
+        Some((key, result))
+    }
+
+    pub(crate) fn put_finished(
+        &mut self,
+        id: kad::QueryId,
+        result: &kad::PutRecordResult,
+    ) -> Option<(Vec<u8>, DhtStoreResult)> {
+        let Some(DhtQuery::Put { key }) = self.queries.remove(&id) else {
+            return None;
+        };
+        let result = match result {
+            Ok(_) => Ok(()),
+            Err(kad::PutRecordError::QuorumFailed {
+                success, quorum, ..
+            }) => Err(DhtError::QuorumFailed {
+                reached: success.len(),
+                required: quorum.get(),
+            }),
+            Err(kad::PutRecordError::Timeout { .. }) => Err(DhtError::Timeout),
+        };
+        Some((key, result))
+    }
+
+    fn latest(found: Vec<SignedRecord>) -> Option<SignedRecord> {
+        found.into_iter().max_by_key(|record| record.expires_at)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use crate::crypto::{EphemeraKeypair, Keypair};
+
+    use super::*;
+
+    fn record(expires_at: u64) -> SignedRecord {
+        SignedRecord::new(
+            &Keypair::generate(None),
+            b"key".to_vec(),
+            b"value".to_vec(),
+            expires_at,
+        )
+        .unwrap()
+    }
+
+    fn query_id() -> kad::QueryId {
+        //QueryId can be created only by Kademlia
+        let keypair = libp2p::identity::Keypair::generate_ed25519();
+        let peer_id = keypair.public().to_peer_id();
+        let mut kademlia = kad::Kademlia::new(peer_id, kad::store::MemoryStore::new(peer_id));
+        kademlia.get_record(kad::record::Key::new(&b"key".to_vec()))
+    }
+
+    #[test]
+    fn test_get_completes_when_quorum_is_reached() {
+        let mut queries = DhtQueries::default();
+        let id = query_id();
+        queries.get_started(id, b"key".to_vec(), NonZeroUsize::new(2).unwrap());
+
+        assert!(queries.record_found(id, record(1)).is_none());
+        let (key, result) = queries.record_found(id, record(2)).unwrap();
+        assert_eq!(key, b"key".to_vec());
+        assert_eq!(result.unwrap().unwrap().expires_at, 2);
+
+        //Query is done, late results are ignored
+        assert!(queries.record_found(id, record(3)).is_none());
+        assert!(queries.get_finished(id, false).is_none());
+    }
+
+    #[test]
+    fn test_get_not_found() {
+        let mut queries = DhtQueries::default();
+        let id = query_id();
+        queries.get_started(id, b"key".to_vec(), NonZeroUsize::MIN);
+
+        let (_, result) = queries.get_finished(id, false).unwrap();
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn test_get_timeout() {
+        let mut queries = DhtQueries::default();
+        let id = query_id();
+        queries.get_started(id, b"key".to_vec(), NonZeroUsize::MIN);
+
+        let (_, result) = queries.get_finished(id, true).unwrap();
+        assert_eq!(result, Err(DhtError::Timeout));
+    }
+
+    #[test]
+    fn test_get_quorum_failed() {
+        let mut queries = DhtQueries::default();
+        let id = query_id();
+        let required = DhtQuorum::Majority.required(replication_factor(20));
+        queries.get_started(id, b"key".to_vec(), required);
+
+        assert!(queries.record_found(id, record(1)).is_none());
+        let (_, result) = queries.get_finished(id, true).unwrap();
+        assert_matches!(
+            result,
+            Err(DhtError::QuorumFailed {
+                reached: 1,
+                required: 11
+            })
+        );
+    }
+
+    #[test]
+    fn test_put_results() {
+        let mut queries = DhtQueries::default();
+        let key = kad::record::Key::new(&b"key".to_vec());
+
+        let id = query_id();
+        queries.put_started(id, b"key".to_vec());
+        let ok = Ok(kad::PutRecordOk { key: key.clone() });
+        assert_eq!(
+            queries.put_finished(id, &ok),
+            Some((b"key".to_vec(), Ok(())))
+        );
+        assert!(queries.put_finished(id, &ok).is_none());
+
+        let id = query_id();
+        queries.put_started(id, b"key".to_vec());
+        let timeout = Err(kad::PutRecordError::Timeout {
+            key: key.clone(),
+            success: vec![],
+            quorum: NonZeroUsize::MIN,
+        });
+        assert_eq!(
+            queries.put_finished(id, &timeout).unwrap().1,
+            Err(DhtError::Timeout)
+        );
+
+        let id = query_id();
+        queries.put_started(id, b"key".to_vec());
+        let failed = Err(kad::PutRecordError::QuorumFailed {
+            key,
+            success: vec![],
+            quorum: NonZeroUsize::new(3).unwrap(),
+        });
+        assert_eq!(
+            queries.put_finished(id, &failed).unwrap().1,
+            Err(DhtError::QuorumFailed {
+                reached: 0,
+                required: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_quorum() {
+        let total = NonZeroUsize::new(20).unwrap();
+        assert_eq!(DhtQuorum::One.required(total).get(), 1);
+        assert_eq!(DhtQuorum::Majority.required(total).get(), 11);
+        assert_eq!(DhtQuorum::All.required(total).get(), 20);
+        assert_eq!(
+            DhtQuorum::N(NonZeroUsize::new(30).unwrap())
+                .required(total)
+                .get(),
+            20
+        );
+        assert_eq!("3".parse::<DhtQuorum>().unwrap().to_string(), "3");
+        assert_eq!("majority".parse::<DhtQuorum>(), Ok(DhtQuorum::Majority));
+        assert!("0".parse::<DhtQuorum>().is_err());
+    }
+
+    #[test]
+    fn test_quorum_of_small_cluster() {
+        let replication = replication_factor(3);
+        assert_eq!(replication.get(), 3);
+        assert_eq!(DhtQuorum::Majority.required(replication).get(), 2);
+        assert_eq!(DhtQuorum::All.required(replication).get(), 3);
+        assert_eq!(replication_factor(0).get(), 1);
+        assert_eq!(replication_factor(100), kad::K_VALUE);
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use libp2p::kad;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::crypto::{EphemeraKeypair, EphemeraPublicKey, Keypair, PublicKey};
+use crate::utilities::codec::{Codec, EphemeraCodec};
+use crate::utilities::crypto::Certificate;
+
+/// Prefix of namespaced keys `/ns/<base58 public key>/<key>`.
+const NAMESPACE_PREFIX: &[u8] = b"/ns/";
+
+#[derive(Debug, Error)]
+pub(crate) enum RecordError {
+    #[error("Failed to encode record: {0}")]
+    Encoding(String),
+    #[error("Failed to decode record: {0}")]
+    Decoding(String),
+    #[error("Record key doesn't match the DHT key")]
+    KeyMismatch,
+    #[error("Invalid record signature")]
+    InvalidSignature,
+    #[error("Record expired")]
+    Expired,
+    #[error("Record TTL exceeds the maximum TTL")]
+    TtlTooLong,
+    #[error("Invalid namespace public key")]
+    InvalidNamespace,
+    #[error("Publisher {0} doesn't own the namespace")]
+    NotNamespaceOwner(Box<PublicKey>),
+    #[error("Record is already published by {0}")]
+    PublishedByOther(Box<PublicKey>),
+    #[error("Failed to sign record: {0}")]
+    Signing(String),
+}
+
+pub(crate) type Result<T> = std::result::Result<T, RecordError>;
+
+/// DHT record signed by its publisher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SignedRecord {
+    pub(crate) key: Vec<u8>,
+    pub(crate) value: Vec<u8>,
+    /// Unix timestamp in milliseconds.
+    pub(crate) expires_at: u64,
+    pub(crate) certificate: Certificate,
+}
+
+impl SignedRecord {
+    pub(crate) fn new(
+        keypair: &Keypair,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        expires_at: u64,
+    ) -> Result<Self> {
+        let signature = keypair
+            .sign(&signed_bytes(&key, &value, expires_at)?)
+            .map_err(|err| RecordError::Signing(err.to_string()))?;
+        Ok(Self {
+            key,
+            value,
+            expires_at,
+            certificate: Certificate::new(signature, keypair.public_key()),
+        })
+    }
+
+    pub(crate) fn publisher(&self) -> &PublicKey {
+        &self.certificate.public_key
+    }
+
+    /// Checks the signature, expiry and namespace ownership.
+    ///
+    /// `now` is unix timestamp in milliseconds.
+    pub(crate) fn validate(&self, now: u64, max_ttl: Duration) -> Result<()> {
+        let bytes = signed_bytes(&self.key, &self.value, self.expires_at)?;
+        if !self.publisher().verify(&bytes, &self.certificate.signature) {
+            return Err(RecordError::InvalidSignature);
+        }
+        if self.expires_at <= now {
+            return Err(RecordError::Expired);
+        }
+        if self.expires_at - now > duration_ms(max_ttl) {
+            return Err(RecordError::TtlTooLong);
+        }
+        if let Some(owner) = namespace_owner(&self.key)? {
+            if owner != *self.publisher() {
+                return Err(RecordError::NotNamespaceOwner(Box::new(
+                    self.publisher().clone(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// A record can replace an unexpired record only of the same publisher.
+    pub(crate) fn can_replace(&self, existing: &SignedRecord, now: u64) -> Result<()> {
+        if existing.expires_at > now && existing.publisher() != self.publisher() {
+            return Err(RecordError::PublishedByOther(Box::new(
+                existing.publisher().clone(),
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn from_kad_record(record: &kad::Record) -> Result<Self> {
+        let signed: SignedRecord =
+            Codec::decode(&record.value).map_err(|err| RecordError::Decoding(err.to_string()))?;
+        if signed.key != record.key.to_vec() {
+            return Err(RecordError::KeyMismatch);
+        }
+        Ok(signed)
+    }
+
+    /// `now` is unix timestamp in milliseconds.
+    pub(crate) fn to_kad_record(&self, now: u64) -> Result<kad::Record> {
+        let value = Codec::encode(self).map_err(|err| RecordError::Encoding(err.to_string()))?;
+        let mut record = kad::Record::new(self.key.clone(), value);
+        let ttl = Duration::from_millis(self.expires_at.saturating_sub(now));
+        record.expires = Some(Instant::now() + ttl);
+        Ok(record)
+    }
+}
+
+/// Key in the namespace of the public key. Only the owner of the public key can publish it.
+pub(crate) fn namespaced_key(owner: &PublicKey, key: &[u8]) -> Vec<u8> {
+    let mut namespaced = NAMESPACE_PREFIX.to_vec();
+    namespaced.extend_from_slice(owner.to_base58().as_bytes());
+    namespaced.push(b'/');
+    namespaced.extend_from_slice(key);
+    namespaced
+}
+
+/// Returns the namespace owner if the key is namespaced.
+fn namespace_owner(key: &[u8]) -> Result<Option<PublicKey>> {
+    let Some(rest) = key.strip_prefix(NAMESPACE_PREFIX) else {
+        return Ok(None);
+    };
+    let owner = rest
+        .split(|b| *b == b'/')
+        .next()
+        .and_then(|owner| std::str::from_utf8(owner).ok())
+        .ok_or(RecordError::InvalidNamespace)?;
+    PublicKey::from_base58(owner)
+        .map(Some)
+        .map_err(|_| RecordError::InvalidNamespace)
+}
+
+fn signed_bytes(key: &[u8], value: &[u8], expires_at: u64) -> Result<Vec<u8>> {
+    Codec::encode(&(key, value, expires_at)).map_err(|err| RecordError::Encoding(err.to_string()))
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    const NOW: u64 = 1_000_000;
+    const MAX_TTL: Duration = Duration::from_secs(60);
+
+    fn record(keypair: &Keypair, key: &[u8]) -> SignedRecord {
+        SignedRecord::new(keypair, key.to_vec(), b"value".to_vec(), NOW + 1000).unwrap()
+    }
+
+    #[test]
+    fn test_valid_record() {
+        let keypair = Keypair::generate(None);
+        let record = record(&keypair, b"key");
+
+        assert!(record.validate(NOW, MAX_TTL).is_ok());
+
+        let kad_record = record.to_kad_record(NOW).unwrap();
+        let decoded = SignedRecord::from_kad_record(&kad_record).unwrap();
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn test_tampered_record_is_invalid() {
+        let mut record = record(&Keypair::generate(None), b"key");
+        record.value = b"other".to_vec();
+
+        assert_matches!(
+            record.validate(NOW, MAX_TTL),
+            Err(RecordError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let record = record(&Keypair::generate(None), b"key");
+
+        assert_matches!(
+            record.validate(NOW + 1000, MAX_TTL),
+            Err(RecordError::Expired)
+        );
+        assert_matches!(
+            record.validate(NOW, Duration::from_millis(999)),
+            Err(RecordError::TtlTooLong)
+        );
+    }
+
+    #[test]
+    fn test_namespace_ownership() {
+        let owner = Keypair::generate(None);
+        let key = namespaced_key(&owner.public_key(), b"key");
+
+        assert!(record(&owner, &key).validate(NOW, MAX_TTL).is_ok());
+        assert_matches!(
+            record(&Keypair::generate(None), &key).validate(NOW, MAX_TTL),
+            Err(RecordError::NotNamespaceOwner(_))
+        );
+        assert_matches!(
+            record(&owner, b"/ns/invalid/key").validate(NOW, MAX_TTL),
+            Err(RecordError::InvalidNamespace)
+        );
+    }
+
+    #[test]
+    fn test_only_publisher_replaces_record() {
+        let publisher = Keypair::generate(None);
+        let existing = record(&publisher, b"key");
+
+        assert!(record(&publisher, b"key")
+            .can_replace(&existing, NOW)
+            .is_ok());
+        assert_matches!(
+            record(&Keypair::generate(None), b"key").can_replace(&existing, NOW),
+            Err(RecordError::PublishedByOther(_))
+        );
+        assert!(record(&Keypair::generate(None), b"key")
+            .can_replace(&existing, NOW + 1000)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_mismatching_kad_key() {
+        let record = record(&Keypair::generate(None), b"key");
+        let mut kad_record = record.to_kad_record(NOW).unwrap();
+        kad_record.key = kad::record::Key::new(&b"other".to_vec());
+
+        assert_matches!(
+            SignedRecord::from_kad_record(&kad_record),
+            Err(RecordError::KeyMismatch)
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use libp2p::kad::{
+    self,
+    store::{MemoryStore, RecordStore},
+    ProviderRecord, Record,
+};
+use log::{debug, error, info};
+
+use crate::network::libp2p::dht::record::{RecordError, SignedRecord};
+use crate::storage::{DhtRecordDatabase, StoredDhtRecord};
+use crate::utilities::time::EphemeraTime;
+
+struct Inner {
+    memory: MemoryStore,
+    database: Box<dyn DhtRecordDatabase>,
+}
+
+/// Kademlia record store which keeps the records also in the node database.
+///
+/// Records survive node restarts. They are loaded when the node starts and Kademlia
+/// republishes and replicates them on its usual schedule. Provider records are kept only in memory.
+///
+/// Clones share the same store, the API uses it to list and remove records.
+#[derive(Clone)]
+pub(crate) struct DhtRecordStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl DhtRecordStore {
+    /// Opens the store with the unexpired records of the database. Expired records are removed.
+    pub(crate) fn open(
+        local_peer_id: libp2p::PeerId,
+        mut database: Box<dyn DhtRecordDatabase>,
+    ) -> anyhow::Result<Self> {
+        let mut memory = MemoryStore::new(local_peer_id);
+        let now = EphemeraTime::now();
+        let mut loaded = 0;
+        for stored in database.get_dht_records()? {
+            match Self::to_kad_record(&stored, now) {
+                Ok(record) => {
+                    memory.put(record)?;
+                    loaded += 1;
+                }
+                Err(err) => {
+                    debug!("Removing stored DHT record: {err}");
+                    database.remove_dht_record(&stored.key)?;
+                }
+            }
+        }
+        info!("Loaded {loaded} DHT records from database");
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner { memory, database })),
+        })
+    }
+
+    /// Records stored by the node.
+    pub(crate) fn signed_records(&self) -> Vec<SignedRecord> {
+        self.inner()
+            .memory
+            .records()
+            .filter_map(|record| SignedRecord::from_kad_record(&record).ok())
+            .collect()
+    }
+
+    /// Removes the record from the node. Other peers keep their copies.
+    ///
+    /// Returns false if the node doesn't store the record.
+    pub(crate) fn remove_record(&mut self, key: &[u8]) -> bool {
+        let key = kad::record::Key::new(&key);
+        let stored = self.inner().memory.get(&key).is_some();
+        self.remove(&key);
+        stored
+    }
+
+    fn to_kad_record(stored: &StoredDhtRecord, now: u64) -> Result<Record, RecordError> {
+        let record = Record::new(stored.key.clone(), stored.value.clone());
+        let signed = SignedRecord::from_kad_record(&record)?;
+        if signed.expires_at <= now {
+            return Err(RecordError::Expired);
+        }
+        let mut record = signed.to_kad_record(now)?;
+        record.publisher = stored.publisher.map(Into::into);
+        Ok(record)
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("DHT record store lock poisoned")
+    }
+}
+
+impl RecordStore for DhtRecordStore {
+    type RecordsIter<'a> = std::vec::IntoIter<Cow<'a, Record>>;
+    type ProvidedIter<'a> = std::vec::IntoIter<Cow<'a, ProviderRecord>>;
+
+    fn get(&self, k: &kad::record::Key) -> Option<Cow<'_, Record>> {
+        self.inner()
+            .memory
+            .get(k)
+            .map(|record| Cow::Owned(record.into_owned()))
+    }
+
+    fn put(&mut self, r: Record) -> kad::store::Result<()> {
+        let stored = StoredDhtRecord {
+            key: r.key.to_vec(),
+            value: r.value.clone(),
+            publisher: r.publisher.map(Into::into),
+        };
+        let mut inner = self.inner();
+        let previous = inner.memory.get(&r.key).map(Cow::into_owned);
+        inner.memory.put(r)?;
+        if let Err(err) = inner.database.store_dht_record(&stored) {
+            error!("Failed to store DHT record in database: {err}");
+            //Keep memory and database consistent, the record is not stored at all
+            match previous {
+                Some(previous) => inner.memory.put(previous)?,
+                None => inner.memory.remove(&kad::record::Key::new(&stored.key)),
+            }
+            //Kademlia store errors don't have a variant for database failures
+            return Err(kad::store::Error::MaxRecords);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &kad::record::Key) {
+        let mut inner = self.inner();
+        inner.memory.remove(k);
+        if let Err(err) = inner.database.remove_dht_record(&k.to_vec()) {
+            error!("Failed to remove DHT record from database: {err}");
+        }
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        let records = self
+            .inner()
+            .memory
+            .records()
+            .map(|record| Cow::Owned(record.into_owned()))
+            .collect::<Vec<_>>();
+        records.into_iter()
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> kad::store::Result<()> {
+        self.inner().memory.add_provider(record)
+    }
+
+    fn providers(&self, key: &kad::record::Key) -> Vec<ProviderRecord> {
+        self.inner().memory.providers(key)
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        let provided = self
+            .inner()
+            .memory
+            .provided()
+            .map(|record| Cow::Owned(record.into_owned()))
+            .collect::<Vec<_>>();
+        provided.into_iter()
+    }
+
+    fn remove_provider(&mut self, k: &kad::record::Key, p: &libp2p::PeerId) {
+        self.inner().memory.remove_provider(k, p);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::crypto::{EphemeraKeypair, Keypair};
+    use crate::storage::Result;
+
+    use super::*;
+
+    const NOW: u64 = 1_000_000;
+
+    /// Database shared by the test stores, like the node database across restarts.
+    #[derive(Clone, Default)]
+    struct TestDatabase {
+        records: Arc<Mutex<HashMap<Vec<u8>, StoredDhtRecord>>>,
+    }
+
+    impl DhtRecordDatabase for TestDatabase {
+        fn get_dht_records(&self) -> Result<Vec<StoredDhtRecord>> {
+            Ok(self.records.lock().unwrap().values().cloned().collect())
+        }
+
+        fn store_dht_record(&mut self, record: &StoredDhtRecord) -> Result<()> {
+            self.records
+                .lock()
+                .unwrap()
+                .insert(record.key.clone(), record.clone());
+            Ok(())
+        }
+
+        fn remove_dht_record(&mut self, key: &[u8]) -> Result<()> {
+            self.records.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    struct FailingDatabase;
+
+    impl DhtRecordDatabase for FailingDatabase {
+        fn get_dht_records(&self) -> Result<Vec<StoredDhtRecord>> {
+            Ok(vec![])
+        }
+
+        fn store_dht_record(&mut self, _record: &StoredDhtRecord) -> Result<()> {
+            Err(anyhow::anyhow!("disk full").into())
+        }
+
+        fn remove_dht_record(&mut self, _key: &[u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn kad_record(key: &[u8], expires_at: u64) -> Record {
+        SignedRecord::new(
+            &Keypair::generate(None),
+            key.to_vec(),
+            b"value".to_vec(),
+            expires_at,
+        )
+        .unwrap()
+        .to_kad_record(NOW)
+        .unwrap()
+    }
+
+    fn open(database: &TestDatabase) -> DhtRecordStore {
+        DhtRecordStore::open(libp2p::PeerId::random(), Box::new(database.clone())).unwrap()
+    }
+
+    #[test]
+    fn test_records_survive_restart() {
+        let database = TestDatabase::default();
+        let publisher = libp2p::PeerId::random();
+
+        let mut store = open(&database);
+        let mut record = kad_record(b"key", EphemeraTime::now() + 60_000);
+        record.publisher = Some(publisher);
+        store.put(record.clone()).unwrap();
+        drop(store);
+
+        let store = open(&database);
+        let reloaded = store.get(&record.key).unwrap();
+        assert_eq!(reloaded.value, record.value);
+        assert_eq!(reloaded.publisher, Some(publisher));
+        assert_eq!(store.signed_records().len(), 1);
+    }
+
+    #[test]
+    fn test_expired_records_are_not_loaded() {
+        let database = TestDatabase::default();
+
+        let mut store = open(&database);
+        store.put(kad_record(b"expired", NOW + 1)).unwrap();
+        drop(store);
+
+        let store = open(&database);
+        assert!(store.records().next().is_none());
+        assert!(database.records.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_put_fails_when_database_fails() {
+        let mut store =
+            DhtRecordStore::open(libp2p::PeerId::random(), Box::new(FailingDatabase)).unwrap();
+        let record = kad_record(b"key", EphemeraTime::now() + 60_000);
+
+        assert!(store.put(record.clone()).is_err());
+        assert!(store.get(&record.key).is_none());
+    }
+
+    #[test]
+    fn test_remove_record() {
+        let database = TestDatabase::default();
+
+        let mut store = open(&database);
+        store
+            .put(kad_record(b"key", EphemeraTime::now() + 60_000))
+            .unwrap();
+
+        //Clones share the store
+        let mut api_store = store.clone();
+        assert!(api_store.remove_record(b"key"));
+        assert!(!api_store.remove_record(b"key"));
+        assert!(store.records().next().is_none());
+        assert!(database.records.lock().unwrap().is_empty());
+    }
+}
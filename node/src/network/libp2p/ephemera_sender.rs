@@ -1,15 +1,47 @@
+use std::time::Duration;
+
 use log::trace;
 use tokio::sync::mpsc;
 
 use crate::block::types::message::EphemeraMessage;
+use crate::broadcast::delivered::DeliveredBlock;
 use crate::broadcast::RbMsg;
+use crate::config::DhtQuorum;
+use crate::network::libp2p::ban_list::BanReason;
+use crate::network::libp2p::network_sender::{GossipMessageSource, MessageValidation};
+use crate::peer::PeerId;
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum EphemeraEvent {
-    EphemeraMessage(Box<EphemeraMessage>),
+    /// Message to gossip in the topic of the channel with the given name.
+    EphemeraMessage(Box<EphemeraMessage>, String),
     ProtocolMessage(Box<RbMsg>),
-    StoreInDht { key: Vec<u8>, value: Vec<u8> },
-    QueryDht { key: Vec<u8> },
+    /// Block the local node has delivered, published for observers.
+    DeliveredBlock(Box<DeliveredBlock>),
+    StoreInDht {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        /// Configured default TTL is used if not set.
+        ttl: Option<Duration>,
+        /// Stores the record in the namespace of the local public key.
+        namespaced: bool,
+        /// Configured write quorum is used if not set.
+        quorum: Option<DhtQuorum>,
+    },
+    QueryDht {
+        key: Vec<u8>,
+        /// Configured read quorum is used if not set.
+        quorum: Option<DhtQuorum>,
+    },
+    MessageValidated {
+        source: GossipMessageSource,
+        result: MessageValidation,
+    },
+    /// Bans the peer, unless it's a member.
+    BanPeer {
+        peer_id: PeerId,
+        reason: BanReason,
+    },
 }
 
 pub(crate) struct EphemeraToNetwork;
@@ -1,4 +1,10 @@
-mod behaviours;
+pub(crate) mod ban_list;
+pub(crate) mod behaviours;
+pub(crate) mod dht;
 pub(crate) mod ephemera_sender;
 pub(crate) mod network_sender;
+pub(crate) mod peers;
+pub(crate) mod rate_limit;
+pub(crate) mod swarm_key;
 pub(crate) mod swarm_network;
+pub(crate) mod versions;
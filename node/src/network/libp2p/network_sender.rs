@@ -1,24 +1,62 @@
+use libp2p::gossipsub;
 use log::trace;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use tokio::sync::mpsc;
 
 use crate::block::types::message::EphemeraMessage;
+use crate::broadcast::delivered::DeliveredBlock;
 use crate::broadcast::RbMsg;
+use crate::network::libp2p::dht::{DhtQueryResult, DhtStoreResult};
 use crate::peer::PeerId;
 
+/// Group members are reported together with their weights.
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum GroupChangeEvent {
-    PeersUpdated(HashSet<PeerId>),
-    LocalPeerRemoved(HashSet<PeerId>),
-    NotEnoughPeers(HashSet<PeerId>),
+    PeersUpdated(HashMap<PeerId, u64>),
+    LocalPeerRemoved(HashMap<PeerId, u64>),
+    NotEnoughPeers(HashMap<PeerId, u64>),
+}
+
+/// Identifies a gossiped message which gossipsub holds back until the Application has validated it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GossipMessageSource {
+    pub(crate) message_id: gossipsub::MessageId,
+    pub(crate) propagation_source: libp2p::PeerId,
+}
+
+/// Application verdict on a gossiped message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageValidation {
+    /// Message is valid and is forwarded to other peers.
+    Accept,
+    /// Message is invalid. It is not forwarded and the peer who sent it is penalized.
+    Reject,
+    /// Message is not forwarded, but the peer who sent it is not penalized.
+    Ignore,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum NetworkEvent {
-    EphemeraMessage(Box<EphemeraMessage>),
+    /// Message gossiped in the topic of the channel with the given name.
+    EphemeraMessage(Box<EphemeraMessage>, GossipMessageSource, String),
     BroadcastMessage(Box<RbMsg>),
-    GroupUpdate(GroupChangeEvent),
-    QueryDhtResponse { key: Vec<u8>, value: Vec<u8> },
+    /// Block a member has delivered. Only observers subscribe to these.
+    DeliveredBlock(Box<DeliveredBlock>, GossipMessageSource),
+    /// Without activation height the group change is effective immediately. Otherwise after
+    /// the local block at that height is committed.
+    GroupUpdate {
+        event: GroupChangeEvent,
+        activation_height: Option<u64>,
+    },
+    QueryDhtResponse {
+        key: Vec<u8>,
+        result: DhtQueryResult,
+    },
+    StoreDhtResponse {
+        /// Key of the store request, before it's namespaced.
+        key: Vec<u8>,
+        result: DhtStoreResult,
+    },
 }
 
 pub(crate) struct EphemeraNetworkCommunication;
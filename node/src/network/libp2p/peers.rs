@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::network::libp2p::behaviours::membership::health::MemberHealth;
+use crate::network::libp2p::versions::NegotiatedVersions;
+use crate::peer::PeerId;
+use crate::utilities::time::EphemeraTime;
+
+/// What the node knows about a connected peer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PeerStats {
+    /// Remote address of the first connection with the peer.
+    pub(crate) address: String,
+    /// Unix timestamp in milliseconds.
+    pub(crate) connected_at: u64,
+    /// Round-trip time of the last successful ping.
+    pub(crate) rtt: Option<Duration>,
+    /// Consecutive failed pings since the last successful one.
+    pub(crate) ping_failures: u32,
+    /// Reported by identify protocol.
+    pub(crate) agent_version: Option<String>,
+    /// Reported by identify protocol.
+    pub(crate) protocol_version: Option<String>,
+    /// Protocols the peer supports, reported by identify protocol.
+    pub(crate) protocols: Vec<String>,
+    /// Highest protocol versions both nodes support. Known after identify.
+    pub(crate) versions: Option<NegotiatedVersions>,
+}
+
+impl PeerStats {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            connected_at: EphemeraTime::now(),
+            rtt: None,
+            ping_failures: 0,
+            agent_version: None,
+            protocol_version: None,
+            protocols: vec![],
+            versions: None,
+        }
+    }
+
+    /// A peer is healthy until a ping fails. The next successful ping makes it healthy again.
+    pub(crate) fn is_healthy(&self) -> bool {
+        self.ping_failures == 0
+    }
+}
+
+/// Connected peers with their latency and versions.
+///
+/// Network updates it from connection, ping and identify events. Membership counts only healthy
+/// peers when it decides if a membership is acceptable and reports the health of the members of the
+/// current membership here. Clones share the same peers.
+#[derive(Clone, Default)]
+pub(crate) struct NetworkPeers {
+    peers: Arc<Mutex<HashMap<PeerId, PeerStats>>>,
+    members: Arc<Mutex<HashMap<PeerId, MemberHealth>>>,
+}
+
+impl NetworkPeers {
+    /// Keeps the address of the existing connection if the peer is already connected.
+    pub(crate) fn connected(&self, peer_id: PeerId, address: String) {
+        self.peers()
+            .entry(peer_id)
+            .or_insert_with(|| PeerStats::new(address));
+    }
+
+    /// Called when the last connection with the peer is closed.
+    pub(crate) fn disconnected(&self, peer_id: &PeerId) {
+        self.peers().remove(peer_id);
+    }
+
+    pub(crate) fn ping_succeeded(&self, peer_id: &PeerId, rtt: Duration) {
+        if let Some(stats) = self.peers().get_mut(peer_id) {
+            stats.rtt = Some(rtt);
+            stats.ping_failures = 0;
+        }
+    }
+
+    pub(crate) fn ping_failed(&self, peer_id: &PeerId) {
+        if let Some(stats) = self.peers().get_mut(peer_id) {
+            stats.ping_failures = stats.ping_failures.saturating_add(1);
+        }
+    }
+
+    pub(crate) fn identified(
+        &self,
+        peer_id: &PeerId,
+        agent_version: String,
+        protocol_version: String,
+        protocols: Vec<String>,
+        versions: NegotiatedVersions,
+    ) {
+        if let Some(stats) = self.peers().get_mut(peer_id) {
+            stats.agent_version = Some(agent_version);
+            stats.protocol_version = Some(protocol_version);
+            stats.protocols = protocols;
+            stats.versions = Some(versions);
+        }
+    }
+
+    /// Only a connected peer whose last ping failed is unhealthy. Network may not have seen
+    /// a new connection yet when membership asks.
+    pub(crate) fn is_unhealthy(&self, peer_id: &PeerId) -> bool {
+        self.peers()
+            .get(peer_id)
+            .is_some_and(|stats| !stats.is_healthy())
+    }
+
+    pub(crate) fn peers_stats(&self) -> Vec<(PeerId, PeerStats)> {
+        self.peers()
+            .iter()
+            .map(|(peer_id, stats)| (*peer_id, stats.clone()))
+            .collect()
+    }
+
+    /// Replaces the health of the members of the current membership.
+    pub(crate) fn members_updated(&self, members: HashMap<PeerId, MemberHealth>) {
+        *self.members.lock().expect("Network peers lock poisoned") = members;
+    }
+
+    pub(crate) fn members_health(&self) -> Vec<(PeerId, MemberHealth)> {
+        self.members
+            .lock()
+            .expect("Network peers lock poisoned")
+            .iter()
+            .map(|(peer_id, health)| (*peer_id, health.clone()))
+            .collect()
+    }
+
+    fn peers(&self) -> std::sync::MutexGuard<'_, HashMap<PeerId, PeerStats>> {
+        self.peers.lock().expect("Network peers lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::network::libp2p::versions::ProtocolVersion;
+
+    use super::*;
+
+    #[test]
+    fn test_ping_updates_health() {
+        let peers = NetworkPeers::default();
+        let peer_id = PeerId::random();
+        assert!(!peers.is_unhealthy(&peer_id));
+
+        peers.connected(peer_id, "/memory/1".to_string());
+        assert!(!peers.is_unhealthy(&peer_id));
+
+        peers.ping_failed(&peer_id);
+        peers.ping_failed(&peer_id);
+        assert!(peers.is_unhealthy(&peer_id));
+        assert_eq!(peers.peers_stats()[0].1.ping_failures, 2);
+
+        peers.ping_succeeded(&peer_id, Duration::from_millis(5));
+        assert!(!peers.is_unhealthy(&peer_id));
+        assert_eq!(peers.peers_stats()[0].1.rtt, Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn test_connection_lifecycle() {
+        let peers = NetworkPeers::default();
+        let peer_id = PeerId::random();
+
+        peers.connected(peer_id, "/memory/1".to_string());
+        //Second connection keeps the first address
+        peers.connected(peer_id, "/memory/2".to_string());
+        let protocols = vec!["/ipfs/ping/1.0.0".to_string()];
+        peers.identified(
+            &peer_id,
+            "ephemera/0.1.0".to_string(),
+            "/ephemera/1.0.0".to_string(),
+            protocols.clone(),
+            ProtocolVersion::negotiate(&protocols),
+        );
+
+        let stats = peers.peers_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].1.address, "/memory/1");
+        assert_eq!(stats[0].1.agent_version.as_deref(), Some("ephemera/0.1.0"));
+        assert!(!stats[0].1.versions.unwrap().is_compatible());
+
+        peers.disconnected(&peer_id);
+        assert!(peers.peers_stats().is_empty());
+        //Events of disconnected peers are ignored
+        peers.ping_succeeded(&peer_id, Duration::from_millis(5));
+        assert!(peers.peers_stats().is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use tokio::time::Instant;
+
+use crate::config::RateLimitConfiguration;
+use crate::peer::PeerId;
+
+/// Kind of inbound traffic limited separately for each peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Traffic {
+    /// Gossipsub messages.
+    Gossip,
+    /// Reliable broadcast requests.
+    Broadcast,
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    pub(crate) fn new(refill_per_sec: f64, capacity: u32, now: Instant) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            refill_per_sec,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token if there is one.
+    pub(crate) fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Token bucket rate limits for each peer and [`Traffic`] kind.
+pub(crate) struct RateLimiter {
+    config: RateLimitConfiguration,
+    buckets: HashMap<(PeerId, Traffic), TokenBucket>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimitConfiguration) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Returns false if the peer has exceeded its limit for the traffic kind.
+    pub(crate) fn allow(&mut self, peer_id: PeerId, traffic: Traffic) -> bool {
+        let now = Instant::now();
+        let config = &self.config;
+        self.buckets
+            .entry((peer_id, traffic))
+            .or_insert_with(|| match traffic {
+                Traffic::Gossip => {
+                    TokenBucket::new(config.gossip_messages_per_sec, config.gossip_burst, now)
+                }
+                Traffic::Broadcast => TokenBucket::new(
+                    config.broadcast_requests_per_sec,
+                    config.broadcast_burst,
+                    now,
+                ),
+            })
+            .try_take(now)
+    }
+
+    /// Forgets the buckets of a disconnected peer.
+    pub(crate) fn forget(&mut self, peer_id: &PeerId) {
+        self.buckets.retain(|(peer, _), _| peer != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, 3, now);
+
+        assert!(bucket.try_take(now));
+        assert!(bucket.try_take(now));
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+
+        let now = now + Duration::from_millis(500);
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+    }
+
+    #[test]
+    fn test_bucket_does_not_overfill() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10.0, 2, now);
+
+        let now = now + Duration::from_secs(60);
+        assert!(bucket.try_take(now));
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+    }
+
+    #[test]
+    fn test_limits_are_per_peer_and_traffic() {
+        let config = RateLimitConfiguration {
+            gossip_messages_per_sec: 0.0,
+            gossip_burst: 1,
+            broadcast_requests_per_sec: 0.0,
+            broadcast_burst: 1,
+            ban_duration_sec: 0,
+        };
+        let mut limiter = RateLimiter::new(config);
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+
+        assert!(limiter.allow(peer_a, Traffic::Gossip));
+        assert!(!limiter.allow(peer_a, Traffic::Gossip));
+        assert!(limiter.allow(peer_a, Traffic::Broadcast));
+        assert!(limiter.allow(peer_b, Traffic::Gossip));
+
+        limiter.forget(&peer_a);
+        assert!(limiter.allow(peer_a, Traffic::Gossip));
+    }
+}
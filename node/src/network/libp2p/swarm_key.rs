@@ -0,0 +1,120 @@
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use libp2p::pnet::PreSharedKey;
+use log::warn;
+use rand::RngCore;
+
+/// Generates a random pre-shared key of a private network.
+pub(crate) fn generate_swarm_key() -> PreSharedKey {
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    PreSharedKey::new(key)
+}
+
+/// Reads a pre-shared key from a file in `swarm.key` format:
+///
+/// ```text
+/// /key/swarm/psk/1.0.0/
+/// /base16/
+/// <64 hex characters>
+/// ```
+pub(crate) fn read_swarm_key<P: AsRef<Path>>(path: P) -> anyhow::Result<PreSharedKey> {
+    let path = path.as_ref();
+    warn_if_readable_by_others(path);
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| anyhow!("Failed to read swarm key file {path:?}: {err}"))?;
+    PreSharedKey::from_str(&content)
+        .map_err(|err| anyhow!("Invalid swarm key file {path:?}: {err:?}"))
+}
+
+/// Writes a pre-shared key to a file in `swarm.key` format. Fails if the file exists.
+///
+/// On unix the file is readable only by its owner.
+pub(crate) fn write_swarm_key<P: AsRef<Path>>(path: P, key: PreSharedKey) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::AlreadyExists {
+            anyhow!("Swarm key file {path:?} already exists")
+        } else {
+            anyhow!("Failed to create swarm key file {path:?}: {err}")
+        }
+    })?;
+    file.write_all(key.to_string().as_bytes())
+        .map_err(|err| anyhow!("Failed to write swarm key file {path:?}: {err}"))
+}
+
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.permissions().mode() & 0o077 != 0 {
+            warn!("Swarm key file {path:?} is accessible by other users, it should be readable only by its owner");
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &Path) {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("swarm-{}.key", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_write_and_read_swarm_key() {
+        let path = temp_path();
+        let key = generate_swarm_key();
+
+        write_swarm_key(&path, key).unwrap();
+        let read = read_swarm_key(&path).unwrap();
+        assert_eq!(
+            read.fingerprint().to_string(),
+            key.fingerprint().to_string()
+        );
+
+        assert!(write_swarm_key(&path, generate_swarm_key()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_swarm_key_readable_only_by_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path();
+        write_swarm_key(&path, generate_swarm_key()).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_invalid_swarm_key() {
+        let path = temp_path();
+        std::fs::write(&path, "/key/swarm/psk/1.0.0/\n/base16/\nnot hex\n").unwrap();
+
+        assert!(read_swarm_key(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_generated_keys_differ() {
+        assert_ne!(
+            generate_swarm_key().fingerprint().to_string(),
+            generate_swarm_key().fingerprint().to_string()
+        );
+    }
+}
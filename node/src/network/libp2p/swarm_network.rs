@@ -1,37 +1,52 @@
-use std::collections::HashSet;
-use std::future::Future;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::str::FromStr;
+use std::time::Duration;
 
 use futures::StreamExt;
-use libp2p::kad::{GetClosestPeersResult, GetRecordResult};
-use libp2p::swarm::{NetworkBehaviour, SwarmBuilder};
+use libp2p::kad::{store::RecordStore, GetClosestPeersResult, GetRecordResult};
+use libp2p::swarm::{DialError, ListenError, NetworkBehaviour, SwarmBuilder};
 use libp2p::{
-    gossipsub, gossipsub::IdentTopic as Topic, kad, request_response, swarm::SwarmEvent, Multiaddr,
-    Swarm,
+    gossipsub, gossipsub::IdentTopic as Topic, gossipsub::TopicHash, identify, kad, ping,
+    request_response, swarm::SwarmEvent, Multiaddr, Swarm,
 };
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 
-use crate::membership::PeerInfo;
+use crate::config::DhtQuorum;
+use crate::membership::MembersProvider;
 use crate::{
     block::types::message::EphemeraMessage,
-    broadcast::RbMsg,
+    broadcast::{delivered::DeliveredBlock, RbMsg},
     codec::Encode,
-    core::builder::NodeInfo,
+    core::{builder::NodeInfo, channel::DEFAULT_CHANNEL},
+    crypto::EphemeraKeypair,
     network::libp2p::behaviours,
     network::libp2p::{
+        ban_list::{BanList, BanReason},
         behaviours::{
-            create_behaviour, create_transport, request_response::RbMsgResponse,
-            GroupBehaviourEvent, GroupNetworkBehaviour,
+            create_behaviour, create_transport, membership::DeniedConnections,
+            request_response::RbMsgResponse, GroupBehaviourEvent, GroupNetworkBehaviour,
+        },
+        dht::{
+            self,
+            record::{namespaced_key, SignedRecord},
+            store::DhtRecordStore,
+            DhtError, DhtQueries,
         },
         ephemera_sender::{
             EphemeraEvent, EphemeraToNetwork, EphemeraToNetworkReceiver, EphemeraToNetworkSender,
         },
         network_sender::{
-            EphemeraNetworkCommunication, GroupChangeEvent,
+            EphemeraNetworkCommunication, GossipMessageSource, GroupChangeEvent,
             GroupChangeEvent::{LocalPeerRemoved, NotEnoughPeers},
-            NetCommunicationReceiver, NetCommunicationSender, NetworkEvent,
+            MessageValidation, NetCommunicationReceiver, NetCommunicationSender, NetworkEvent,
         },
+        peers::NetworkPeers,
+        rate_limit::{RateLimiter, Traffic},
+        versions::ProtocolVersion,
     },
+    peer::PeerId,
+    utilities::time::EphemeraTime,
 };
 
 pub(crate) type InitSwarm<P> = (
@@ -42,22 +57,38 @@ pub(crate) type InitSwarm<P> = (
 
 pub struct SwarmNetwork<P>
 where
-    P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
     node_info: NodeInfo,
     swarm: Swarm<GroupNetworkBehaviour<P>>,
     from_ephemera_rcv: EphemeraToNetworkReceiver,
     to_ephemera_tx: NetCommunicationSender,
-    ephemera_msg_topic: Topic,
+    /// Topics of all supported protocol versions, newest first.
+    ephemera_msg_topics: Vec<Topic>,
+    /// Topics of the configured channels other than the default one, by channel name.
+    channel_topics: HashMap<String, Vec<Topic>>,
+    /// Delivered blocks topics of all supported protocol versions, newest first.
+    delivered_blocks_topics: Vec<Topic>,
+    rate_limiter: RateLimiter,
+    ban_list: BanList,
+    dht_queries: DhtQueries,
+    network_peers: NetworkPeers,
 }
 
 impl<P> SwarmNetwork<P>
 where
-    P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + Unpin + 'static,
+    P: MembersProvider,
 {
-    pub(crate) fn new(node_info: NodeInfo, members_provider: P) -> anyhow::Result<InitSwarm<P>>
+    pub(crate) fn new(
+        node_info: NodeInfo,
+        members_provider: P,
+        ban_list: BanList,
+        denied_connections: DeniedConnections,
+        dht_store: DhtRecordStore,
+        network_peers: NetworkPeers,
+    ) -> anyhow::Result<InitSwarm<P>>
     where
-        P: Future<Output = crate::membership::Result<Vec<PeerInfo>>> + Send + 'static,
+        P: MembersProvider,
     {
         let (from_ephemera_tx, from_ephemera_rcv) = EphemeraToNetwork::init();
         let (to_ephemera_tx, to_ephemera_rcv) = EphemeraNetworkCommunication::init();
@@ -66,39 +97,75 @@ where
 
         let local_key = node_info.keypair.clone();
         let peer_id = node_info.peer_id;
-        let ephemera_msg_topic = Topic::new(&libp2p_configuration.ephemera_msg_topic_name);
+        let ephemera_msg_topics =
+            ProtocolVersion::supported_topics(&libp2p_configuration.ephemera_msg_topic_name);
+        let channel_topics = node_info
+            .initial_config
+            .channels
+            .iter()
+            .map(|channel| {
+                let topics = ProtocolVersion::supported_channel_topics(
+                    &libp2p_configuration.ephemera_msg_topic_name,
+                    &channel.name,
+                );
+                (channel.name.clone(), topics)
+            })
+            .collect::<HashMap<_, _>>();
+        let delivered_blocks_topics = ProtocolVersion::supported_delivered_blocks_topics(
+            &libp2p_configuration.ephemera_msg_topic_name,
+        );
 
-        let transport = create_transport(&local_key)?;
+        let transport = create_transport(&local_key, node_info.transport, node_info.swarm_key)?;
 
-        let members_provider_delay =
-            std::time::Duration::from_secs(libp2p_configuration.members_provider_delay_sec);
-        let behaviour = create_behaviour(
+        //Observers don't take messages to mempool, they only follow delivered blocks
+        let observer = node_info.initial_config.node.observer;
+        let topics = if observer {
+            delivered_blocks_topics.clone()
+        } else {
+            let mut topics = ephemera_msg_topics.clone();
+            topics.extend(channel_topics.values().flatten().cloned());
+            topics
+        };
+        let mut behaviour = create_behaviour(
             &local_key,
-            &ephemera_msg_topic,
+            &topics,
             members_provider,
-            members_provider_delay,
-            libp2p_configuration.membership_kind.into(),
-        );
+            &libp2p_configuration,
+            denied_connections,
+            dht_store,
+            network_peers.clone(),
+        )?;
+        if observer {
+            behaviour.members_provider.set_observer();
+        }
 
         let swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id.into()).build();
+        let rate_limiter = RateLimiter::new(libp2p_configuration.rate_limit);
 
         let network = SwarmNetwork {
             node_info,
             swarm,
             from_ephemera_rcv,
             to_ephemera_tx,
-            ephemera_msg_topic,
+            ephemera_msg_topics,
+            channel_topics,
+            delivered_blocks_topics,
+            rate_limiter,
+            ban_list,
+            dht_queries: DhtQueries::default(),
+            network_peers,
         };
 
         Ok((network, to_ephemera_rcv, from_ephemera_tx))
     }
 
     pub(crate) fn listen(&mut self) -> anyhow::Result<()> {
-        let address =
-            Multiaddr::from_str(&self.node_info.protocol_address()).expect("Invalid multi-address");
-        self.swarm.listen_on(address.clone())?;
+        for address in self.node_info.listen_addresses() {
+            let address = Multiaddr::from_str(&address).expect("Invalid multi-address");
+            self.swarm.listen_on(address.clone())?;
 
-        info!("Listening on {address:?}");
+            info!("Listening on {address:?}");
+        }
         Ok(())
     }
 
@@ -118,42 +185,206 @@ where
                     }
                 },
                 Some(event) = self.from_ephemera_rcv.net_event_rcv.recv() => {
-                    self.process_ephemera_events(event);
+                    if let Err(err) = self.process_ephemera_events(event).await {
+                        error!("Error handling ephemera event: {:?}", err);
+                    }
                 }
             }
         }
     }
 
-    fn process_ephemera_events(&mut self, event: EphemeraEvent) {
+    async fn process_ephemera_events(&mut self, event: EphemeraEvent) -> anyhow::Result<()> {
         match event {
-            EphemeraEvent::EphemeraMessage(em) => {
-                self.send_ephemera_message(em.as_ref());
+            EphemeraEvent::EphemeraMessage(em, channel) => {
+                self.send_ephemera_message(em.as_ref(), &channel);
             }
             EphemeraEvent::ProtocolMessage(pm) => {
                 self.send_broadcast_message(pm.as_ref());
             }
-            EphemeraEvent::StoreInDht { key, value } => {
-                let record = kad::Record::new(key, value);
-                let quorum = kad::Quorum::One;
-                match self
-                    .swarm
-                    .behaviour_mut()
-                    .kademlia
-                    .put_record(record, quorum)
-                {
-                    Ok(ok) => {
-                        trace!("StoreDht: {:?}", ok);
-                    }
-                    Err(err) => {
-                        error!("StoreDht: {:?}", err);
-                    }
-                }
+            EphemeraEvent::DeliveredBlock(block) => {
+                self.publish_delivered_block(block.as_ref());
             }
-            EphemeraEvent::QueryDht { key } => {
+            EphemeraEvent::StoreInDht {
+                key,
+                value,
+                ttl,
+                namespaced,
+                quorum,
+            } => {
+                self.store_in_dht(key, value, ttl, namespaced, quorum)
+                    .await?;
+            }
+            EphemeraEvent::QueryDht { key, quorum } => {
                 let kad_key = kad::record::Key::new::<Vec<u8>>(key.as_ref());
                 let query_id = self.swarm.behaviour_mut().kademlia.get_record(kad_key);
                 trace!("QueryDht: {:?}", query_id);
+                let quorum = quorum.unwrap_or(self.node_info.initial_config.libp2p.dht.read_quorum);
+                let required = quorum.required(self.dht_replication_factor());
+                self.dht_queries.get_started(query_id, key, required);
+            }
+            EphemeraEvent::MessageValidated { source, result } => {
+                self.report_message_validation(&source, result);
+            }
+            EphemeraEvent::BanPeer { peer_id, reason } => {
+                self.ban_non_member(peer_id.into(), reason);
+            }
+        }
+        Ok(())
+    }
+
+    async fn store_in_dht(
+        &mut self,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+        namespaced: bool,
+        quorum: Option<DhtQuorum>,
+    ) -> anyhow::Result<()> {
+        let record = match self.sign_dht_record(key.clone(), value, ttl, namespaced) {
+            Ok(record) => record,
+            Err(err) => {
+                let result = Err(DhtError::InvalidRecord(err.to_string()));
+                let event = NetworkEvent::StoreDhtResponse { key, result };
+                return self.to_ephemera_tx.send_network_event(event).await;
+            }
+        };
+
+        let quorum = quorum.unwrap_or(self.node_info.initial_config.libp2p.dht.write_quorum);
+        let quorum = dht::kad_quorum(quorum, self.dht_replication_factor());
+        match self
+            .swarm
+            .behaviour_mut()
+            .kademlia
+            .put_record(record, quorum)
+        {
+            Ok(query_id) => {
+                trace!("StoreDht: {:?}", query_id);
+                self.dht_queries.put_started(query_id, key);
+            }
+            Err(err) => {
+                error!("StoreDht: {:?}", err);
+                let result = Err(DhtError::Failed(format!("{err:?}")));
+                let event = NetworkEvent::StoreDhtResponse { key, result };
+                self.to_ephemera_tx.send_network_event(event).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ban_peer(&mut self, peer_id: libp2p::PeerId, reason: BanReason) {
+        warn!("Banning peer {peer_id}: {reason}");
+        if !self.ban_list.ban(peer_id.into(), reason) {
+            debug!("Peer {peer_id} is already banned");
+        }
+        if self.swarm.disconnect_peer_id(peer_id).is_err() {
+            trace!("Banned peer {peer_id} is not connected");
+        }
+    }
+
+    //Members are never banned, only the offending message or request is dropped. Gossipsub
+    //scoring and the message validation deal with them. Banning them would drop them out of the
+    //broadcast group and could take it below the quorum policy.
+    fn ban_non_member(&mut self, peer_id: libp2p::PeerId, reason: BanReason) {
+        if self.is_member(&peer_id) {
+            debug!("Not banning member {peer_id}: {reason}");
+        } else {
+            self.ban_peer(peer_id, reason);
+        }
+    }
+
+    //Refuses connections from banned peers and forgets rate limits of disconnected ones
+    fn guard_connections<E>(&mut self, swarm_event: &SwarmEvent<GroupBehaviourEvent, E>) {
+        match swarm_event {
+            SwarmEvent::ConnectionEstablished { peer_id, .. }
+                if self.ban_list.is_banned(&(*peer_id).into())
+                    && self.swarm.disconnect_peer_id(*peer_id).is_ok() =>
+            {
+                debug!("Refused connection from banned peer {peer_id}");
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.rate_limiter.forget(&(*peer_id).into());
             }
+            _ => {}
+        }
+    }
+
+    //Keeps the list of connected peers which the API reports
+    fn track_connections<E>(&mut self, swarm_event: &SwarmEvent<GroupBehaviourEvent, E>) {
+        match swarm_event {
+            SwarmEvent::ConnectionEstablished {
+                peer_id, endpoint, ..
+            } => {
+                let address = endpoint.get_remote_address().to_string();
+                self.network_peers.connected((*peer_id).into(), address);
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.network_peers.disconnected(&(*peer_id).into());
+            }
+            _ => {}
+        }
+    }
+
+    //Lets gossipsub forward accepted messages and score the peer who sent the message
+    fn report_message_validation(
+        &mut self,
+        source: &GossipMessageSource,
+        result: MessageValidation,
+    ) {
+        let acceptance = match result {
+            MessageValidation::Accept => gossipsub::MessageAcceptance::Accept,
+            MessageValidation::Reject => gossipsub::MessageAcceptance::Reject,
+            MessageValidation::Ignore => gossipsub::MessageAcceptance::Ignore,
+        };
+        if let Err(err) = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(
+                &source.message_id,
+                &source.propagation_source,
+                acceptance,
+            )
+        {
+            error!("Failed to report message validation result: {err:?}");
+        }
+    }
+
+    //In private network, peers without the same swarm key fail the connection handshake
+    fn report_handshake_errors<E>(&self, swarm_event: &SwarmEvent<GroupBehaviourEvent, E>) {
+        let Some(swarm_key) = self.node_info.swarm_key else {
+            return;
+        };
+        match swarm_event {
+            SwarmEvent::IncomingConnectionError {
+                send_back_addr,
+                error: ListenError::Transport(err),
+                ..
+            } => {
+                warn!(
+                    "Handshake with {send_back_addr} failed: {err}. The peer may not have the swarm key {}",
+                    swarm_key.fingerprint()
+                );
+            }
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Transport(errors),
+                ..
+            } => {
+                for (address, err) in errors {
+                    debug!(
+                        "Connection to {address} failed: {err}. If the peer is online, it may not have the swarm key {}",
+                        swarm_key.fingerprint()
+                    );
+                }
+            }
+            _ => {}
         }
     }
 
@@ -166,6 +397,9 @@ where
                 error!("Error handling behaviour event: {:?}", err);
             }
         } else {
+            self.guard_connections(&swarm_event);
+            self.track_connections(&swarm_event);
+            self.report_handshake_errors(&swarm_event);
             Self::process_other_swarm_events(swarm_event);
         }
         Ok(())
@@ -196,22 +430,128 @@ where
                 if let Err(err) = self.process_kad_event(ev).await {
                     error!("Error processing kademlia event: {:?}", err);
                 }
-            } // GroupBehaviourEvent::Ping(_) => {}
+            }
+            GroupBehaviourEvent::Ping(event) => {
+                self.process_ping_event(event);
+            }
+            GroupBehaviourEvent::Identify(event) => {
+                self.process_identify_event(event);
+            }
         }
         Ok(())
     }
 
+    fn process_ping_event(&mut self, event: ping::Event) {
+        let peer_id: PeerId = event.peer.into();
+        match event.result {
+            Ok(ping::Success::Ping { rtt }) => {
+                trace!("Ping to {peer_id}: {rtt:?}");
+                self.network_peers.ping_succeeded(&peer_id, rtt);
+            }
+            Ok(ping::Success::Pong) => {}
+            Err(err) => {
+                debug!("Ping to {peer_id} failed: {err}");
+                self.network_peers.ping_failed(&peer_id);
+            }
+        }
+    }
+
+    fn process_identify_event(&mut self, event: identify::Event) {
+        match event {
+            identify::Event::Received { peer_id, info } => {
+                trace!(
+                    "Identified {peer_id}: agent:{}, protocol:{}",
+                    info.agent_version,
+                    info.protocol_version
+                );
+                let versions = ProtocolVersion::negotiate(&info.protocols);
+                if !versions.is_compatible() {
+                    warn!(
+                        "Peer {peer_id} ({}) has no common protocol version with this node, \
+                         its protocols: {:?}, supported versions: {:?}",
+                        info.agent_version,
+                        info.protocols,
+                        ProtocolVersion::SUPPORTED
+                    );
+                }
+                self.network_peers.identified(
+                    &peer_id.into(),
+                    info.agent_version,
+                    info.protocol_version,
+                    info.protocols,
+                    versions,
+                );
+            }
+            identify::Event::Error { peer_id, error } => {
+                debug!("Identify with {peer_id} failed: {error}");
+            }
+            identify::Event::Sent { .. } | identify::Event::Pushed { .. } => {}
+        }
+    }
+
     async fn process_gossipsub_event(&mut self, event: gossipsub::Event) -> anyhow::Result<()> {
         match event {
             gossipsub::Event::Message {
-                propagation_source: _,
-                message_id: _,
+                propagation_source,
+                message_id,
                 message,
             } => {
-                let msg: EphemeraMessage = serde_json::from_slice(&message.data[..])?;
-                self.to_ephemera_tx
-                    .send_network_event(NetworkEvent::EphemeraMessage(msg.into()))
-                    .await?;
+                let source = GossipMessageSource {
+                    message_id,
+                    propagation_source,
+                };
+                let peer_id: PeerId = propagation_source.into();
+                if self.ban_list.is_banned(&peer_id) {
+                    trace!("Ignoring message from banned peer {peer_id}");
+                    self.report_message_validation(&source, MessageValidation::Ignore);
+                    return Ok(());
+                }
+                //Peers in the grace list can connect but don't take part in gossip
+                if !self.is_member(&propagation_source) {
+                    trace!("Ignoring message from non-member {peer_id}");
+                    self.report_message_validation(&source, MessageValidation::Ignore);
+                    return Ok(());
+                }
+                //Limits are kept per author, the neighbour only relays the message.
+                //Anonymous messages are left to gossipsub peer scoring.
+                let author = message.source;
+                if let Some(author) = author {
+                    if self.ban_list.is_banned(&author.into()) {
+                        trace!("Ignoring message authored by banned peer {author}");
+                        self.report_message_validation(&source, MessageValidation::Ignore);
+                        return Ok(());
+                    }
+                    if !self.rate_limiter.allow(author.into(), Traffic::Gossip) {
+                        self.report_message_validation(&source, MessageValidation::Ignore);
+                        self.ban_non_member(author, BanReason::GossipRateExceeded);
+                        return Ok(());
+                    }
+                }
+
+                let event = if self
+                    .delivered_blocks_topics
+                    .iter()
+                    .any(|topic| topic.hash() == message.topic)
+                {
+                    serde_json::from_slice::<DeliveredBlock>(&message.data[..])
+                        .map(|block| NetworkEvent::DeliveredBlock(block.into(), source.clone()))
+                } else {
+                    let channel = self.message_channel(&message.topic);
+                    serde_json::from_slice::<EphemeraMessage>(&message.data[..]).map(|msg| {
+                        NetworkEvent::EphemeraMessage(msg.into(), source.clone(), channel)
+                    })
+                };
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => {
+                        self.report_message_validation(&source, MessageValidation::Reject);
+                        if let Some(author) = author {
+                            self.ban_non_member(author, BanReason::InvalidMessage(err.to_string()));
+                        }
+                        return Err(err.into());
+                    }
+                };
+                self.to_ephemera_tx.send_network_event(event).await?;
             }
 
             gossipsub::Event::Subscribed { peer_id, topic } => {
@@ -238,6 +578,29 @@ where
                     request,
                     channel,
                 } => {
+                    let sender: PeerId = peer.into();
+                    if self.ban_list.is_banned(&sender) {
+                        trace!("Ignoring request from banned peer {sender}");
+                        return Ok(());
+                    }
+                    if !self.is_member(&peer) {
+                        trace!("Ignoring request from non-member {sender}");
+                        return Ok(());
+                    }
+                    if !self.rate_limiter.allow(sender, Traffic::Broadcast) {
+                        self.ban_non_member(peer, BanReason::BroadcastRateExceeded);
+                        return Ok(());
+                    }
+                    //Peers send only their own messages directly to each other
+                    if request.original_sender != sender {
+                        let reason = format!(
+                            "Message sender {} is not the connected peer",
+                            request.original_sender
+                        );
+                        self.ban_non_member(peer, BanReason::InvalidBlock(reason));
+                        return Ok(());
+                    }
+
                     let rb_id = request.id.clone();
                     trace!("Received request {:?}", request);
                     self.to_ephemera_tx
@@ -289,7 +652,7 @@ where
                 info!("Peers updated: {:?}", peers_ids);
 
                 let local_peer_id = *self.swarm.local_peer_id();
-                for peer_id in peers_ids {
+                for peer_id in peers_ids.into_keys() {
                     if peer_id == local_peer_id {
                         continue;
                     }
@@ -308,6 +671,8 @@ where
                     }
                 }
 
+                self.remove_non_members_from_routing();
+
                 let query_id = self
                     .swarm
                     .behaviour_mut()
@@ -318,22 +683,75 @@ where
             behaviours::membership::behaviour::Event::PeerUpdatePending => {
                 info!("Peer update pending");
             }
-            behaviours::membership::behaviour::Event::LocalRemoved(peers_ids) => {
+            behaviours::membership::behaviour::Event::LocalRemoved(
+                peers_ids,
+                activation_height,
+            ) => {
                 //TODO: should pause all network block and message activities...?
-                let peers_ids = peers_ids.into_iter().map(Into::into).collect();
-                let update = NetworkEvent::GroupUpdate(LocalPeerRemoved(peers_ids));
+                let peers_ids = peers_ids
+                    .into_iter()
+                    .map(|(peer_id, weight)| (peer_id.into(), weight))
+                    .collect();
+                let update = NetworkEvent::GroupUpdate {
+                    event: LocalPeerRemoved(peers_ids),
+                    activation_height,
+                };
                 self.to_ephemera_tx.send_network_event(update).await?;
             }
-            behaviours::membership::behaviour::Event::NotEnoughPeers(peers_ids) => {
+            behaviours::membership::behaviour::Event::NotEnoughPeers(
+                peers_ids,
+                activation_height,
+            ) => {
                 //TODO: should pause all network block and message activities...?
-                let peers_ids = peers_ids.into_iter().map(Into::into).collect();
-                let update = NetworkEvent::GroupUpdate(NotEnoughPeers(peers_ids));
+                let peers_ids = peers_ids
+                    .into_iter()
+                    .map(|(peer_id, weight)| (peer_id.into(), weight))
+                    .collect();
+                let update = NetworkEvent::GroupUpdate {
+                    event: NotEnoughPeers(peers_ids),
+                    activation_height,
+                };
                 self.to_ephemera_tx.send_network_event(update).await?;
             }
         }
         Ok(())
     }
 
+    fn is_member(&mut self, peer_id: &libp2p::PeerId) -> bool {
+        self.swarm
+            .behaviour_mut()
+            .members_provider
+            .is_member(peer_id)
+    }
+
+    fn dht_replication_factor(&mut self) -> NonZeroUsize {
+        let remote_members = self
+            .swarm
+            .behaviour_mut()
+            .members_provider
+            .remote_member_count();
+        dht::replication_factor(remote_members)
+    }
+
+    fn remove_non_members_from_routing(&mut self) {
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+        let routed = kademlia
+            .kbuckets()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|entry| *entry.node.key.preimage())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for peer_id in routed {
+            if !self.is_member(&peer_id) {
+                debug!("Removing non-member {peer_id} from DHT routing table");
+                self.swarm.behaviour_mut().kademlia.remove_peer(&peer_id);
+            }
+        }
+    }
+
     async fn process_kad_event(&mut self, event: kad::KademliaEvent) -> anyhow::Result<()> {
         match event {
             kad::KademliaEvent::OutboundQueryProgressed {
@@ -354,7 +772,7 @@ where
                         self.process_closest_peers(gcp).await?;
                     }
                     kad::QueryResult::GetRecord(get_res) => {
-                        self.process_get_record(get_res).await?;
+                        self.process_get_record(id, get_res, step.last).await?;
                     }
                     kad::QueryResult::Bootstrap(bt) => {
                         trace!("Bootstrap: {:?}", bt);
@@ -370,6 +788,10 @@ where
                     }
                     kad::QueryResult::PutRecord(pr) => {
                         trace!("PutRecord: {:?}", pr);
+                        if let Some((key, result)) = self.dht_queries.put_finished(id, &pr) {
+                            let event = NetworkEvent::StoreDhtResponse { key, result };
+                            self.to_ephemera_tx.send_network_event(event).await?;
+                        }
                     }
                     kad::QueryResult::RepublishRecord(rr) => {
                         trace!("RepublishRecord: {:?}", rr);
@@ -379,6 +801,15 @@ where
 
             kad::KademliaEvent::InboundRequest { request } => {
                 trace!("Inbound request: {:?}", request);
+                //Records are filtered, we store only records which are valid
+                if let kad::InboundRequest::PutRecord {
+                    source,
+                    record: Some(record),
+                    ..
+                } = request
+                {
+                    self.store_inbound_dht_record(source, record);
+                }
             }
 
             kad::KademliaEvent::RoutingUpdated {
@@ -403,29 +834,115 @@ where
         Ok(())
     }
 
-    async fn process_get_record(&mut self, get_res: GetRecordResult) -> anyhow::Result<()> {
+    async fn process_get_record(
+        &mut self,
+        query_id: kad::QueryId,
+        get_res: GetRecordResult,
+        last: bool,
+    ) -> anyhow::Result<()> {
         trace!("GetRecord: {:?}", get_res);
-        match get_res {
-            Ok(ok) => match ok {
-                kad::GetRecordOk::FoundRecord(fr) => {
-                    let record = fr.record;
-                    let event = NetworkEvent::QueryDhtResponse {
-                        key: record.key.to_vec(),
-                        value: record.value,
-                    };
-                    self.to_ephemera_tx.send_network_event(event).await?;
-                }
-                kad::GetRecordOk::FinishedWithNoAdditionalRecord { .. } => {
-                    trace!("FinishedWithNoAdditionalRecord");
+        let completed = match get_res {
+            Ok(kad::GetRecordOk::FoundRecord(fr)) => {
+                let now = EphemeraTime::now();
+                let max_ttl = self.max_dht_record_ttl();
+                let record = SignedRecord::from_kad_record(&fr.record).and_then(|record| {
+                    record.validate(now, max_ttl)?;
+                    Ok(record)
+                });
+                let completed = match record {
+                    Ok(record) => self.dht_queries.record_found(query_id, record),
+                    Err(err) => {
+                        warn!("Ignoring invalid DHT record from {:?}: {err}", fr.peer);
+                        None
+                    }
+                };
+                if completed.is_some() {
+                    //Quorum is reached, no need to wait for other peers
+                    if let Some(mut query) =
+                        self.swarm.behaviour_mut().kademlia.query_mut(&query_id)
+                    {
+                        query.finish();
+                    }
+                    completed
+                } else if last {
+                    self.dht_queries.get_finished(query_id, false)
+                } else {
+                    None
                 }
-            },
+            }
+            Ok(kad::GetRecordOk::FinishedWithNoAdditionalRecord { .. }) => {
+                trace!("FinishedWithNoAdditionalRecord");
+                self.dht_queries.get_finished(query_id, false)
+            }
+            Err(kad::GetRecordError::Timeout { .. }) => {
+                self.dht_queries.get_finished(query_id, true)
+            }
             Err(err) => {
                 trace!("Not getting record: {:?}", err);
+                self.dht_queries.get_finished(query_id, false)
             }
+        };
+
+        if let Some((key, result)) = completed {
+            let event = NetworkEvent::QueryDhtResponse { key, result };
+            self.to_ephemera_tx.send_network_event(event).await?;
         }
         Ok(())
     }
 
+    fn sign_dht_record(
+        &self,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+        namespaced: bool,
+    ) -> anyhow::Result<kad::Record> {
+        let dht_config = &self.node_info.initial_config.libp2p.dht;
+        let max_ttl = self.max_dht_record_ttl();
+        let ttl = ttl.unwrap_or(Duration::from_secs(dht_config.record_ttl_sec));
+        if ttl > max_ttl {
+            anyhow::bail!("Record TTL {ttl:?} exceeds the maximum TTL {max_ttl:?}");
+        }
+
+        let keypair = &self.node_info.keypair;
+        let key = if namespaced {
+            namespaced_key(&keypair.public_key(), &key)
+        } else {
+            key
+        };
+        let now = EphemeraTime::now();
+        let expires_at = now.saturating_add(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX));
+        let record = SignedRecord::new(keypair, key, value, expires_at)?;
+        Ok(record.to_kad_record(now)?)
+    }
+
+    fn store_inbound_dht_record(&mut self, source: libp2p::PeerId, record: kad::Record) {
+        let now = EphemeraTime::now();
+        let max_ttl = self.max_dht_record_ttl();
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+
+        let validated = SignedRecord::from_kad_record(&record).and_then(|signed| {
+            signed.validate(now, max_ttl)?;
+            if let Some(existing) = kademlia.store_mut().get(&record.key) {
+                if let Ok(existing) = SignedRecord::from_kad_record(&existing) {
+                    signed.can_replace(&existing, now)?;
+                }
+            }
+            Ok(())
+        });
+        if let Err(err) = validated {
+            warn!("Rejected DHT record from {source}: {err}");
+            return;
+        }
+        if let Err(err) = kademlia.store_mut().put(record) {
+            error!("Failed to store DHT record from {source}: {err:?}");
+        }
+    }
+
+    fn max_dht_record_ttl(&self) -> Duration {
+        Duration::from_secs(self.node_info.initial_config.libp2p.dht.max_record_ttl_sec)
+    }
+
     async fn process_closest_peers(&mut self, gcp: GetClosestPeersResult) -> anyhow::Result<()> {
         trace!("GetClosestPeers: {:?}", gcp);
         //TODO: we need also to make sure that we have enough peers
@@ -442,17 +959,17 @@ where
                     gossipsub.add_explicit_peer(&peer_id);
                 }
 
-                let active_peers = self
-                    .swarm
-                    .behaviour_mut()
-                    .members_provider
-                    .active_peer_ids_with_local();
+                let members_provider = &mut self.swarm.behaviour_mut().members_provider;
+                let active_peers = members_provider.active_peer_weights_with_local();
+                let activation_height = members_provider.activation_height();
                 let active_peers = active_peers
                     .into_iter()
-                    .map(Into::into)
-                    .collect::<HashSet<_>>();
-                let group_update =
-                    NetworkEvent::GroupUpdate(GroupChangeEvent::PeersUpdated(active_peers));
+                    .map(|(peer_id, weight)| (peer_id.into(), weight))
+                    .collect::<HashMap<_, _>>();
+                let group_update = NetworkEvent::GroupUpdate {
+                    event: GroupChangeEvent::PeersUpdated(active_peers),
+                    activation_height,
+                };
                 self.to_ephemera_tx.send_network_event(group_update).await?;
             }
             Err(err) => {
@@ -475,11 +992,19 @@ where
         }
     }
 
-    fn send_ephemera_message(&mut self, msg: &EphemeraMessage) {
-        trace!("Sending Ephemera message: {:?}", msg);
+    fn send_ephemera_message(&mut self, msg: &EphemeraMessage, channel: &str) {
+        trace!("Sending Ephemera message to channel {channel}: {:?}", msg);
+        let topics = match self.channel_topics.get(channel) {
+            Some(topics) => topics,
+            None if channel == DEFAULT_CHANNEL => &self.ephemera_msg_topics,
+            None => {
+                error!("Not sending message to unknown channel {channel}");
+                return;
+            }
+        };
         match msg.encode() {
             Ok(vec) => {
-                let topic = self.ephemera_msg_topic.clone();
+                let topic = self.ephemera_msg_topic(topics);
                 if let Err(err) = self.swarm.behaviour_mut().gossipsub.publish(topic, vec) {
                     error!("Error publishing message: {}", err);
                 }
@@ -490,6 +1015,59 @@ where
         }
     }
 
+    //Published even if no observer has subscribed, so that observers joining later get the next blocks
+    fn publish_delivered_block(&mut self, block: &DeliveredBlock) {
+        trace!("Publishing delivered block: {}", block.block.header);
+        let data = match serde_json::to_vec(block) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Error serializing delivered block: {err}");
+                return;
+            }
+        };
+        let topic = self.delivered_blocks_topics[0].clone();
+        match self.swarm.behaviour_mut().gossipsub.publish(topic, data) {
+            Ok(_) => {}
+            Err(gossipsub::PublishError::InsufficientPeers) => {
+                trace!("No observers subscribed to delivered blocks");
+            }
+            Err(err) => {
+                error!("Error publishing delivered block: {err}");
+            }
+        }
+    }
+
+    //Older nodes don't know topics of newer versions, so messages are published to the topic
+    //of the highest version all peers subscribed to Ephemera topics know.
+    fn ephemera_msg_topic(&self, topics: &[Topic]) -> Topic {
+        let hashes = topics.iter().map(Topic::hash).collect::<Vec<_>>();
+        let peers_topics = self
+            .swarm
+            .behaviour()
+            .gossipsub
+            .all_peers()
+            .map(|(_, topics)| topics)
+            .filter(|topics| topics.iter().any(|topic| hashes.contains(topic)))
+            .collect::<Vec<_>>();
+
+        topics
+            .iter()
+            .zip(&hashes)
+            .find(|(_, hash)| peers_topics.iter().all(|topics| topics.contains(hash)))
+            .map_or_else(
+                || topics[topics.len() - 1].clone(),
+                |(topic, _)| topic.clone(),
+            )
+    }
+
+    /// Name of the channel whose messages are gossiped in the topic.
+    fn message_channel(&self, topic: &TopicHash) -> String {
+        self.channel_topics
+            .iter()
+            .find(|(_, topics)| topics.iter().any(|t| t.hash() == *topic))
+            .map_or_else(|| DEFAULT_CHANNEL.to_string(), |(name, _)| name.clone())
+    }
+
     //Just logging
     #[allow(clippy::too_many_lines)]
     fn process_other_swarm_events<E>(swarm_event: SwarmEvent<GroupBehaviourEvent, E>) {
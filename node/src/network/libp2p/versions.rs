@@ -0,0 +1,196 @@
+//! Wire format versions of Ephemera protocols.
+//!
+//! Every version has its own broadcast and membership protocol names and its own gossip topics.
+//! Nodes support all versions in [`ProtocolVersion::SUPPORTED`] and advertise their protocols with
+//! identify protocol. Broadcast and membership streams are negotiated by libp2p, which picks the
+//! first protocol from [`ProtocolVersion::SUPPORTED`] the remote supports. Gossip messages are
+//! published to the topic of the highest version all subscribed peers know.
+//!
+//! To change the wire format, add a new version to the front of [`ProtocolVersion::SUPPORTED`] and
+//! keep the old one until all nodes of the cluster are upgraded.
+//!
+//! Membership protocol is versioned separately, see [`MembershipVersion`].
+
+use libp2p::gossipsub::IdentTopic as Topic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum ProtocolVersion {
+    V1,
+}
+
+impl ProtocolVersion {
+    /// Versions this node supports, newest first.
+    pub(crate) const SUPPORTED: [ProtocolVersion; 1] = [ProtocolVersion::V1];
+
+    pub(crate) fn broadcast_protocol(self) -> &'static str {
+        match self {
+            ProtocolVersion::V1 => "/ephemera/reliable_broadcast/1.0.0",
+        }
+    }
+
+    /// First version keeps the configured topic name so that it's compatible with older nodes.
+    pub(crate) fn topic(self, topic_name: &str) -> Topic {
+        match self {
+            ProtocolVersion::V1 => Topic::new(topic_name),
+        }
+    }
+
+    /// Topic members publish their delivered blocks to. Only observers subscribe to it.
+    pub(crate) fn delivered_blocks_topic(self, topic_name: &str) -> Topic {
+        match self {
+            ProtocolVersion::V1 => Topic::new(format!("{topic_name}-delivered-blocks")),
+        }
+    }
+
+    /// Topics of all supported versions, newest first.
+    pub(crate) fn supported_topics(topic_name: &str) -> Vec<Topic> {
+        Self::SUPPORTED
+            .iter()
+            .map(|version| version.topic(topic_name))
+            .collect()
+    }
+
+    /// Topic of the messages of a channel other than the default one.
+    pub(crate) fn channel_topic(self, topic_name: &str, channel: &str) -> Topic {
+        match self {
+            ProtocolVersion::V1 => Topic::new(format!("{topic_name}-channel-{channel}")),
+        }
+    }
+
+    /// Channel topics of all supported versions, newest first.
+    pub(crate) fn supported_channel_topics(topic_name: &str, channel: &str) -> Vec<Topic> {
+        Self::SUPPORTED
+            .iter()
+            .map(|version| version.channel_topic(topic_name, channel))
+            .collect()
+    }
+
+    /// Delivered blocks topics of all supported versions, newest first.
+    pub(crate) fn supported_delivered_blocks_topics(topic_name: &str) -> Vec<Topic> {
+        Self::SUPPORTED
+            .iter()
+            .map(|version| version.delivered_blocks_topic(topic_name))
+            .collect()
+    }
+
+    /// Negotiates versions with a peer from protocols it advertised with identify protocol.
+    pub(crate) fn negotiate(protocols: &[String]) -> NegotiatedVersions {
+        let advertised = |protocol: &str| protocols.iter().any(|p| p == protocol);
+        NegotiatedVersions {
+            broadcast: Self::SUPPORTED
+                .into_iter()
+                .find(|version| advertised(version.broadcast_protocol())),
+            membership: MembershipVersion::SUPPORTED
+                .into_iter()
+                .find(|version| advertised(version.protocol())),
+        }
+    }
+}
+
+/// Versions of the membership protocol. Membership messages change independently of the broadcast
+/// protocol and gossip topics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum MembershipVersion {
+    V1,
+    /// Adds heartbeats of the health filter.
+    V2,
+}
+
+impl MembershipVersion {
+    /// Versions this node supports, newest first.
+    pub(crate) const SUPPORTED: [MembershipVersion; 2] =
+        [MembershipVersion::V2, MembershipVersion::V1];
+
+    pub(crate) fn protocol(self) -> &'static str {
+        match self {
+            MembershipVersion::V1 => "/ephemera/membership/1.0.0",
+            MembershipVersion::V2 => "/ephemera/membership/2.0.0",
+        }
+    }
+
+    pub(crate) fn from_protocol(protocol: &[u8]) -> Option<MembershipVersion> {
+        Self::SUPPORTED
+            .into_iter()
+            .find(|version| version.protocol().as_bytes() == protocol)
+    }
+
+    /// Peers with older versions can't decode heartbeats.
+    pub(crate) fn supports_heartbeat(self) -> bool {
+        self >= MembershipVersion::V2
+    }
+}
+
+/// Highest versions both this node and a peer support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NegotiatedVersions {
+    pub(crate) broadcast: Option<ProtocolVersion>,
+    pub(crate) membership: Option<MembershipVersion>,
+}
+
+impl NegotiatedVersions {
+    /// A peer without a common broadcast or membership version can't take part in the cluster.
+    pub(crate) fn is_compatible(self) -> bool {
+        self.broadcast.is_some() && self.membership.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_common_versions() {
+        let protocols = vec![
+            "/ipfs/ping/1.0.0".to_string(),
+            "/ephemera/reliable_broadcast/1.0.0".to_string(),
+            "/ephemera/membership/1.0.0".to_string(),
+        ];
+        let versions = ProtocolVersion::negotiate(&protocols);
+        assert_eq!(versions.broadcast, Some(ProtocolVersion::V1));
+        assert_eq!(versions.membership, Some(MembershipVersion::V1));
+        assert!(versions.is_compatible());
+    }
+
+    #[test]
+    fn test_negotiate_highest_membership_version() {
+        let protocols = vec![
+            "/ephemera/reliable_broadcast/1.0.0".to_string(),
+            "/ephemera/membership/1.0.0".to_string(),
+            "/ephemera/membership/2.0.0".to_string(),
+        ];
+        let membership = ProtocolVersion::negotiate(&protocols).membership.unwrap();
+        assert_eq!(membership, MembershipVersion::V2);
+        assert!(membership.supports_heartbeat());
+        assert!(!MembershipVersion::V1.supports_heartbeat());
+        assert_eq!(
+            MembershipVersion::from_protocol(b"/ephemera/membership/1.0.0"),
+            Some(MembershipVersion::V1)
+        );
+    }
+
+    #[test]
+    fn test_channel_topics_are_distinct() {
+        let version = ProtocolVersion::V1;
+        let rewards = version.channel_topic("ephemera", "rewards");
+        assert_ne!(rewards.hash(), version.topic("ephemera").hash());
+        assert_ne!(
+            rewards.hash(),
+            version.channel_topic("ephemera", "metrics").hash()
+        );
+        assert_ne!(
+            rewards.hash(),
+            version.delivered_blocks_topic("ephemera").hash()
+        );
+    }
+
+    #[test]
+    fn test_negotiate_without_common_version() {
+        let protocols = vec![
+            "/ephemera/reliable_broadcast/9.0.0".to_string(),
+            "/ephemera/membership/1.0.0".to_string(),
+        ];
+        let versions = ProtocolVersion::negotiate(&protocols);
+        assert_eq!(versions.broadcast, None);
+        assert!(!versions.is_compatible());
+    }
+}
@@ -3,18 +3,29 @@ use std::future::Future;
 use std::io::Write;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::Poll::Pending;
 use std::task::{Context, Poll};
 
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::StreamExt;
 use futures_util::future::BoxFuture;
-use futures_util::{future, FutureExt};
-use log::{debug, error};
+use futures_util::FutureExt;
+use log::{debug, error, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::crypto::PublicKey;
 use crate::network::{Address, Peer};
 use crate::peer::PeerId;
+use crate::utilities::time::EphemeraTime;
+
+use provider::{MembersProvider, MembersUpdate};
+use signed::{MembershipAuthority, SignedMembership, SignedMembershipError};
+
+pub(crate) mod provider;
+pub(crate) mod signed;
 
 /// Information about an Ephemera peer.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -25,18 +36,22 @@ pub struct PeerInfo {
     /// Expected formats:
     /// 1. `<IP>:<PORT>`
     /// 2. `/ip4/<IP>/tcp/<PORT>` - this is the format used by libp2p multiaddr
+    /// 3. `/ip4/<IP>/udp/<PORT>/quic-v1` - QUIC address in libp2p multiaddr format
     pub address: String,
     /// The public key of the peer. It uniquely identifies the peer.
     /// Public key is used to derive the peer id.
     pub pub_key: PublicKey,
+    /// The voting weight(stake) of the peer. Broadcast quorum thresholds are computed over
+    /// the sum of the weights of the group members. Defaults to [`DEFAULT_PEER_WEIGHT`].
+    pub weight: u64,
 }
 
 impl Display for PeerInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "name {}, address {}, public key {}",
-            self.name, self.address, self.pub_key
+            "name {}, address {}, public key {}, weight {}",
+            self.name, self.address, self.pub_key, self.weight
         )
     }
 }
@@ -52,6 +67,7 @@ impl TryFrom<PeerInfo> for Peer {
             address,
             public_key: public_key.clone(),
             peer_id: PeerId::from_public_key(&public_key),
+            weight: value.weight,
         })
     }
 }
@@ -62,10 +78,28 @@ pub enum ProviderError {
     ResourceUnavailable(String),
     #[error("MembersProvider: {0}")]
     MembersProvider(#[from] anyhow::Error),
+    #[error("InvalidMembership: {0}")]
+    InvalidMembership(#[from] SignedMembershipError),
+}
+
+/// Weight of a peer when membership provider doesn't specify it.
+///
+/// With all peers having the default weight, quorum thresholds are the same as counting peers.
+pub const DEFAULT_PEER_WEIGHT: u64 = 1;
+
+fn default_weight() -> u64 {
+    DEFAULT_PEER_WEIGHT
+}
+
+fn validate_weight(name: &str, weight: u64) -> anyhow::Result<u64> {
+    if weight == 0 {
+        anyhow::bail!("Weight of peer {name} must be greater than zero");
+    }
+    Ok(weight)
 }
 
 /// Future type which allows user to implement their own peers membership source mechanism.
-pub type ProviderFut = BoxFuture<'static, Result<Vec<PeerInfo>>>;
+pub type ProviderFut = BoxFuture<'static, Result<MembersUpdate>>;
 
 pub type Result<T> = std::result::Result<T, ProviderError>;
 
@@ -90,6 +124,10 @@ pub enum ConfigMembersProviderError {
     TomlError(#[from] toml::ser::Error),
     #[error("IoError: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("InvalidMembership: {0}")]
+    InvalidMembership(#[from] SignedMembershipError),
+    #[error("WatchError: {0}")]
+    WatchError(#[from] notify::Error),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -100,6 +138,7 @@ pub struct PeerSetting {
     /// Expected formats:
     /// 1. `<IP>:<PORT>`
     /// 2. `/ip4/<IP>/tcp/<PORT>` - this is the format used by libp2p multiaddr
+    /// 3. `/ip4/<IP>/udp/<PORT>/quic-v1` - QUIC address in libp2p multiaddr format
     pub address: String,
     ///Serialized public key.
     ///
@@ -116,6 +155,10 @@ pub struct PeerSetting {
     /// assert_eq!(public_key, public_key_parsed);
     /// ```
     pub public_key: String,
+    /// The voting weight(stake) of the peer, greater than zero. Optional, defaults to
+    /// [`DEFAULT_PEER_WEIGHT`].
+    #[serde(default = "default_weight")]
+    pub weight: u64,
 }
 
 impl TryFrom<PeerSetting> for PeerInfo {
@@ -123,15 +166,21 @@ impl TryFrom<PeerSetting> for PeerInfo {
 
     fn try_from(setting: PeerSetting) -> std::result::Result<Self, Self::Error> {
         let pub_key = setting.public_key.parse::<PublicKey>()?;
+        let weight = validate_weight(&setting.name, setting.weight)?;
         Ok(PeerInfo {
             name: setting.name,
             address: setting.address,
             pub_key,
+            weight,
         })
     }
 }
 
-///[`ProviderFut`] that reads the peers from a toml config file.
+///[`MembersProvider`] that reads the peers from a toml config file.
+///
+/// The file is watched for changes and read again when it's written. A new members list is
+/// reported only when it differs from the previous one. If the file can't be parsed, for example
+/// while it's being edited, the last valid members list stays in use.
 ///
 /// # Configuration example
 /// ```toml
@@ -144,9 +193,23 @@ impl TryFrom<PeerSetting> for PeerInfo {
 /// name = "node2"
 /// address = "/ip4/127.0.0.1/tcp/3001"
 /// pub_key = "4XTTMFQt2tgNRmwRgEAaGQe2NXygsK6Vr3pkuBfYezhDfoVty"
+/// # Optional, defaults to 1
+/// weight = 2
 /// ```
+///
+/// Created with [`ConfigMembersProvider::init_signed`], it reads a [`SignedMembership`] instead.
 pub struct ConfigMembersProvider {
     config_location: PathBuf,
+    /// Verifies signed membership.
+    authority: Option<MembershipAuthority>,
+    /// Watches the directory of the config file. Editors often replace the file instead of writing it.
+    _watcher: RecommendedWatcher,
+    /// Events of the directory of the config file.
+    file_events: UnboundedReceiver<notify::Result<notify::Event>>,
+    /// Last valid members list which was reported.
+    last_update: Option<MembersUpdate>,
+    /// The file has to be read on next poll.
+    reload: bool,
 }
 
 impl ConfigMembersProvider {
@@ -161,27 +224,71 @@ impl ConfigMembersProvider {
     pub fn init<I: Into<PathBuf>>(
         path: I,
     ) -> std::result::Result<Self, ConfigMembersProviderError> {
-        let path_buf = path.into();
+        Self::init_with_authority(path.into(), None)
+    }
+
+    /// Creates a new [`ConfigMembersProvider`] instance which reads a [`SignedMembership`] file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the signed membership toml file.
+    /// * `authority` - Authorities who must have signed the membership.
+    ///
+    /// # Errors
+    /// Returns [`ConfigMembersProviderError::NotExist`] if the file does not exist.
+    /// Returns [`ConfigMembersProviderError::InvalidMembership`] if the membership can't be verified.
+    pub fn init_signed<I: Into<PathBuf>>(
+        path: I,
+        authority: MembershipAuthority,
+    ) -> std::result::Result<Self, ConfigMembersProviderError> {
+        Self::init_with_authority(path.into(), Some(authority))
+    }
+
+    fn init_with_authority(
+        path_buf: PathBuf,
+        authority: Option<MembershipAuthority>,
+    ) -> std::result::Result<Self, ConfigMembersProviderError> {
         if !path_buf.exists() {
             return Err(ConfigMembersProviderError::NotExist(
                 path_buf.to_string_lossy().to_string(),
             ));
         }
 
-        let provider = Self {
+        let (events_tx, file_events) = mpsc::unbounded();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            //Receiver is dropped together with the watcher
+            let _ = events_tx.unbounded_send(event);
+        })?;
+        let directory = match path_buf.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+        let mut provider = Self {
             config_location: path_buf,
+            authority,
+            _watcher: watcher,
+            file_events,
+            last_update: None,
+            reload: true,
         };
 
-        if provider.read_config().is_err() {
-            return Err(ConfigMembersProviderError::ParsingFailed(
+        match provider.read_config() {
+            Ok(_) => Ok(provider),
+            Err(ProviderError::InvalidMembership(err)) => Err(err.into()),
+            Err(_) => Err(ConfigMembersProviderError::ParsingFailed(
                 config::ConfigError::Message("Failed to parse config".to_string()),
-            ));
+            )),
         }
-
-        Ok(provider)
     }
 
-    pub(crate) fn read_config(&self) -> Result<Vec<PeerInfo>> {
+    pub(crate) fn read_config(&mut self) -> Result<MembersUpdate> {
+        if let Some(authority) = self.authority.as_mut() {
+            let membership = SignedMembership::try_load(self.config_location.clone())
+                .map_err(|err| anyhow::anyhow!(err))?;
+            return Ok(authority.verify(membership, EphemeraTime::now())?);
+        }
+
         let config_peers = ConfigPeers::try_load(self.config_location.clone())
             .map_err(|err| anyhow::anyhow!(err))?;
 
@@ -190,15 +297,95 @@ impl ConfigMembersProvider {
             .iter()
             .map(|peer| PeerInfo::try_from(peer.clone()))
             .collect::<anyhow::Result<Vec<PeerInfo>>>()?;
-        Ok(peers)
+        Ok(peers.into())
+    }
+
+    fn is_config_changed(&self, event: &notify::Event) -> bool {
+        //We read the file ourselves
+        !event.kind.is_access()
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == self.config_location.file_name())
+    }
+
+    fn log_changes(&self, update: &MembersUpdate) {
+        let previous = self
+            .last_update
+            .as_ref()
+            .map(|last| last.peers.as_slice())
+            .unwrap_or_default();
+        let find = |peers: &[PeerInfo], peer: &PeerInfo| {
+            peers
+                .iter()
+                .find(|other| other.pub_key == peer.pub_key)
+                .cloned()
+        };
+        let names = |peers: Vec<&PeerInfo>| {
+            peers
+                .into_iter()
+                .map(|peer| peer.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let added = update
+            .peers
+            .iter()
+            .filter(|peer| find(previous, peer).is_none())
+            .collect();
+        let removed = previous
+            .iter()
+            .filter(|peer| find(&update.peers, peer).is_none())
+            .collect();
+        let changed = update
+            .peers
+            .iter()
+            .filter(|peer| find(previous, peer).is_some_and(|old| old != **peer))
+            .collect();
+        info!(
+            "Members file {} changed, added: {:?}, removed: {:?}, changed: {:?}, activation height: {:?}",
+            self.config_location.display(),
+            names(added),
+            names(removed),
+            names(changed),
+            update.activation_height
+        );
     }
 }
 
-impl Future for ConfigMembersProvider {
-    type Output = Result<Vec<PeerInfo>>;
+impl MembersProvider for ConfigMembersProvider {
+    fn poll_members(&mut self, cx: &mut Context<'_>) -> Poll<Result<MembersUpdate>> {
+        while let Poll::Ready(Some(event)) = self.file_events.poll_next_unpin(cx) {
+            match event {
+                Ok(event) => self.reload |= self.is_config_changed(&event),
+                Err(err) => error!("Error watching members file: {err}"),
+            }
+        }
+        if !std::mem::take(&mut self.reload) {
+            return Pending;
+        }
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        future::ready(self.read_config()).poll_unpin(cx)
+        match self.read_config() {
+            Ok(update) => {
+                if self.last_update.as_ref() == Some(&update) {
+                    debug!("Members file didn't change");
+                    return Pending;
+                }
+                self.log_changes(&update);
+                self.last_update = Some(update.clone());
+                Poll::Ready(Ok(update))
+            }
+            //Before the first valid members list, there's nothing to keep
+            Err(err) if self.last_update.is_none() => Poll::Ready(Err(err)),
+            Err(err) => {
+                warn!("Invalid members file, keeping the last valid members: {err}");
+                Pending
+            }
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.reload = true;
     }
 }
 
@@ -261,6 +448,10 @@ pub struct JsonPeerInfo {
     /// assert_eq!(public_key, public_key_parsed);
     /// ```
     pub public_key: String,
+    /// The voting weight(stake) of the peer, greater than zero. Optional, defaults to
+    /// [`DEFAULT_PEER_WEIGHT`].
+    #[serde(default = "default_weight")]
+    pub weight: u64,
 }
 
 impl JsonPeerInfo {
@@ -270,8 +461,15 @@ impl JsonPeerInfo {
             name,
             address,
             public_key: pub_key,
+            weight: DEFAULT_PEER_WEIGHT,
         }
     }
+
+    #[must_use]
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
 }
 
 impl TryFrom<JsonPeerInfo> for PeerInfo {
@@ -279,17 +477,20 @@ impl TryFrom<JsonPeerInfo> for PeerInfo {
 
     fn try_from(json_peer_info: JsonPeerInfo) -> std::result::Result<Self, Self::Error> {
         let pub_key = json_peer_info.public_key.parse::<PublicKey>()?;
+        let weight = validate_weight(&json_peer_info.name, json_peer_info.weight)?;
         Ok(PeerInfo {
             name: json_peer_info.name,
             address: json_peer_info.address,
             pub_key,
+            weight,
         })
     }
 }
 
 ///[`ProviderFut`] that reads peers from a http endpoint.
 ///
-/// The endpoint must return a json array of [`JsonPeerInfo`].
+/// The endpoint must return a json array of [`JsonPeerInfo`], or a json [`SignedMembership`]
+/// if the provider is created with [`HttpMembersProvider::new_signed`].
 /// # Configuration example
 /// ```json
 /// [
@@ -301,13 +502,16 @@ impl TryFrom<JsonPeerInfo> for PeerInfo {
 ///  {
 ///     "name": "node2",
 ///     "address": "/ip4/",
-///     "public_key": "4XTTMFQt2tgNRmwRgEAaGQe2NXygsK6Vr3pkuBfYezhDfoVty"
+///     "public_key": "4XTTMFQt2tgNRmwRgEAaGQe2NXygsK6Vr3pkuBfYezhDfoVty",
+///     "weight": 2
 ///   }
 /// ]
 /// ```
 pub struct HttpMembersProvider {
     /// The url of the http endpoint.
     members_url: String,
+    /// Verifies signed membership.
+    authority: Option<Arc<Mutex<MembershipAuthority>>>,
     fut: Option<ProviderFut>,
 }
 
@@ -316,15 +520,42 @@ impl HttpMembersProvider {
     pub fn new(members_url: String) -> Self {
         Self {
             members_url,
+            authority: None,
             fut: None,
         }
     }
 
-    async fn request_peers(members_url: String) -> Result<Vec<PeerInfo>> {
+    /// Creates a provider which accepts only membership signed by the authorities.
+    #[must_use]
+    pub fn new_signed(members_url: String, authority: MembershipAuthority) -> Self {
+        Self {
+            members_url,
+            authority: Some(Arc::new(Mutex::new(authority))),
+            fut: None,
+        }
+    }
+
+    async fn request_peers(
+        members_url: String,
+        authority: Option<Arc<Mutex<MembershipAuthority>>>,
+    ) -> Result<MembersUpdate> {
         debug!("Requesting peers from: {:?}", members_url);
-        let json_peers: Vec<JsonPeerInfo> = reqwest::get(members_url)
+        let response = reqwest::get(members_url)
             .await
-            .map_err(|err| anyhow::anyhow!("Failed to get peers: {err}"))?
+            .map_err(|err| anyhow::anyhow!("Failed to get peers: {err}"))?;
+
+        if let Some(authority) = authority {
+            let membership: SignedMembership = response
+                .json()
+                .await
+                .map_err(|err| anyhow::anyhow!("Failed to parse membership: {err}"))?;
+            let mut authority = authority
+                .lock()
+                .expect("Membership authority lock poisoned");
+            return Ok(authority.verify(membership, EphemeraTime::now())?);
+        }
+
+        let json_peers: Vec<JsonPeerInfo> = response
             .json()
             .await
             .map_err(|err| anyhow::anyhow!("Failed to parse peers: {err}"))?;
@@ -334,18 +565,19 @@ impl HttpMembersProvider {
             .map(TryInto::try_into)
             .collect::<anyhow::Result<Vec<PeerInfo>>>()?;
 
-        Ok(peers)
+        Ok(peers.into())
     }
 }
 
 impl Future for HttpMembersProvider {
-    type Output = Result<Vec<PeerInfo>>;
+    type Output = Result<MembersUpdate>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.fut.take() {
             None => {
                 self.fut = Some(Box::pin(HttpMembersProvider::request_peers(
                     self.members_url.clone(),
+                    self.authority.clone(),
                 )));
             }
             Some(mut fut) => {
@@ -367,3 +599,71 @@ impl Future for HttpMembersProvider {
         Pending
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::crypto::{EphemeraKeypair, EphemeraPublicKey, Keypair};
+
+    use super::*;
+
+    fn peer_setting(name: &str) -> PeerSetting {
+        PeerSetting {
+            name: name.to_string(),
+            address: "/ip4/127.0.0.1/tcp/3000".to_string(),
+            public_key: Keypair::generate(None).public_key().to_base58(),
+            weight: DEFAULT_PEER_WEIGHT,
+        }
+    }
+
+    #[test]
+    fn test_zero_weight_is_rejected() {
+        let mut setting = peer_setting("node1");
+        setting.weight = 0;
+        assert!(PeerInfo::try_from(setting).is_err());
+
+        let json = JsonPeerInfo::new(
+            "node1".to_string(),
+            "/ip4/127.0.0.1/tcp/3000".to_string(),
+            Keypair::generate(None).public_key().to_base58(),
+        );
+        assert!(PeerInfo::try_from(json.clone()).is_ok());
+        assert!(PeerInfo::try_from(json.with_weight(0)).is_err());
+    }
+
+    async fn next_update(provider: &mut ConfigMembersProvider) -> Option<MembersUpdate> {
+        let next = futures::future::poll_fn(|cx| provider.poll_members(cx));
+        tokio::time::timeout(Duration::from_millis(500), next)
+            .await
+            .ok()
+            .map(|update| update.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_config_provider_reports_only_valid_changes() {
+        let dir = std::env::temp_dir().join(format!("members-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("peers.toml");
+        let peers = vec![peer_setting("node1")];
+        ConfigPeers::new(peers.clone()).try_write(&path).unwrap();
+
+        let mut provider = ConfigMembersProvider::init(&path).unwrap();
+        assert_eq!(next_update(&mut provider).await.unwrap().peers.len(), 1);
+
+        //Same members
+        ConfigPeers::new(peers.clone()).try_write(&path).unwrap();
+        assert!(next_update(&mut provider).await.is_none());
+
+        //File in the middle of editing
+        std::fs::write(&path, "[[peers]]\nname = ").unwrap();
+        assert!(next_update(&mut provider).await.is_none());
+
+        let mut peers = peers;
+        peers.push(peer_setting("node2"));
+        ConfigPeers::new(peers).try_write(&path).unwrap();
+        assert_eq!(next_update(&mut provider).await.unwrap().peers.len(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+use std::future::Future;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use futures_util::FutureExt;
+use log::warn;
+use tokio::time::{self, Instant, Interval};
+
+use crate::network::members::{PeerInfo, Result};
+
+/// Delay before the first request to a polling members provider.
+const INITIAL_POLL_DELAY: Duration = Duration::from_secs(5);
+
+/// Stream of membership updates. Every item is the full list of members.
+pub type MembersStream = BoxStream<'static, Result<MembersUpdate>>;
+
+/// Full list of members together with the block height it becomes effective at.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MembersUpdate {
+    pub peers: Vec<PeerInfo>,
+    /// Height of the local block after which the broadcast group switches to these members.
+    ///
+    /// Nodes keep broadcasting in the previous group until they have committed their block at this
+    /// height, so that all nodes switch at the same logical point. Without activation height,
+    /// nodes switch as soon as they see the update.
+    ///
+    /// Only with a proposer schedule do members share one chain and so one height. Otherwise every
+    /// producer counts the height of its own blocks, and producers reach the activation height at
+    /// different times. Nodes which don't commit blocks, or are outside the current group, switch
+    /// right away.
+    pub activation_height: Option<u64>,
+}
+
+impl MembersUpdate {
+    /// Creates an update which becomes effective after the block at `activation_height`.
+    #[must_use]
+    pub fn at_height(peers: Vec<PeerInfo>, activation_height: u64) -> Self {
+        Self {
+            peers,
+            activation_height: Some(activation_height),
+        }
+    }
+}
+
+impl From<Vec<PeerInfo>> for MembersUpdate {
+    fn from(peers: Vec<PeerInfo>) -> Self {
+        Self {
+            peers,
+            activation_height: None,
+        }
+    }
+}
+
+/// Source of membership updates.
+///
+/// Push based providers (websocket, file watch, contract events) can yield a new members list as
+/// soon as membership changes, like [`ConfigMembersProvider`] and [`StreamMembersProvider`].
+/// Futures that return the members list when polled, like [`HttpMembersProvider`], are polled
+/// periodically by [`PollingMembersProvider`].
+///
+/// [`ConfigMembersProvider`]: crate::membership::ConfigMembersProvider
+/// [`HttpMembersProvider`]: crate::membership::HttpMembersProvider
+pub trait MembersProvider: Send + Unpin + 'static {
+    /// Returns the next members list when membership changes.
+    fn poll_members(&mut self, cx: &mut Context<'_>) -> Poll<Result<MembersUpdate>>;
+
+    /// Another member reported that membership changed. Providers which poll the members list
+    /// should do it now instead of waiting for the next interval.
+    fn refresh(&mut self) {}
+
+    /// Membership updates as a [`Stream`].
+    fn into_stream(mut self) -> MembersStream
+    where
+        Self: Sized,
+    {
+        futures::stream::poll_fn(move |cx| self.poll_members(cx).map(Some)).boxed()
+    }
+}
+
+/// [`MembersProvider`] that polls a members list future periodically.
+///
+/// The future is polled again after it has returned, so it should start a new request then.
+/// It can return either the list of members or a [`MembersUpdate`].
+pub struct PollingMembersProvider<P> {
+    provider: P,
+    delay: Duration,
+    /// Next time to poll the provider. None when the provider is being polled.
+    interval: Option<Interval>,
+}
+
+impl<P, T> PollingMembersProvider<P>
+where
+    P: Future<Output = Result<T>> + Send + Unpin + 'static,
+    T: Into<MembersUpdate>,
+{
+    /// Creates a new [`PollingMembersProvider`].
+    ///
+    /// # Arguments
+    /// * `provider` - Future which returns the members list.
+    /// * `delay` - How long to wait after the members list is received before polling it again.
+    pub fn new(provider: P, delay: Duration) -> Self {
+        let interval = time::interval_at(Instant::now() + INITIAL_POLL_DELAY, delay);
+        Self {
+            provider,
+            delay,
+            interval: Some(interval),
+        }
+    }
+}
+
+impl<P, T> MembersProvider for PollingMembersProvider<P>
+where
+    P: Future<Output = Result<T>> + Send + Unpin + 'static,
+    T: Into<MembersUpdate>,
+{
+    fn poll_members(&mut self, cx: &mut Context<'_>) -> Poll<Result<MembersUpdate>> {
+        if let Some(mut tick) = self.interval.take() {
+            if !tick.poll_tick(cx).is_ready() {
+                self.interval = Some(tick);
+                return Poll::Pending;
+            }
+        }
+        let peers = futures::ready!(self.provider.poll_unpin(cx));
+        let wait_time = Instant::now() + self.delay;
+        self.interval = Some(time::interval_at(wait_time, self.delay));
+        Poll::Ready(peers.map(Into::into))
+    }
+
+    fn refresh(&mut self) {
+        self.interval = None;
+    }
+}
+
+/// [`MembersProvider`] of a stream which yields membership updates.
+pub struct StreamMembersProvider<S> {
+    stream: Option<S>,
+}
+
+impl<S, T> StreamMembersProvider<S>
+where
+    S: Stream<Item = Result<T>> + Send + Unpin + 'static,
+    T: Into<MembersUpdate>,
+{
+    /// Creates a new [`StreamMembersProvider`]. When the stream ends, membership doesn't change anymore.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: Some(stream),
+        }
+    }
+}
+
+impl<S, T> MembersProvider for StreamMembersProvider<S>
+where
+    S: Stream<Item = Result<T>> + Send + Unpin + 'static,
+    T: Into<MembersUpdate>,
+{
+    fn poll_members(&mut self, cx: &mut Context<'_>) -> Poll<Result<MembersUpdate>> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Poll::Pending;
+        };
+        if let Some(peers) = futures::ready!(stream.poll_next_unpin(cx)) {
+            return Poll::Ready(peers.map(Into::into));
+        }
+        warn!("Members provider stream ended, membership won't be updated anymore");
+        self.stream = None;
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::channel::mpsc;
+
+    use super::*;
+
+    async fn next(provider: &mut impl MembersProvider) -> Result<MembersUpdate> {
+        futures::future::poll_fn(|cx| provider.poll_members(cx)).await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_polling_provider_waits_between_polls() {
+        let started = Instant::now();
+        let mut provider = PollingMembersProvider::new(
+            futures::future::ready(Ok(MembersUpdate::default())).boxed(),
+            Duration::from_secs(60),
+        );
+        assert!(next(&mut provider).await.is_ok());
+        assert_eq!(started.elapsed(), INITIAL_POLL_DELAY);
+
+        //Members list future has to be recreated after it returns
+        provider.provider = futures::future::ready(Ok(MembersUpdate::default())).boxed();
+        assert!(next(&mut provider).await.is_ok());
+        assert_eq!(
+            started.elapsed(),
+            INITIAL_POLL_DELAY + Duration::from_secs(60)
+        );
+
+        provider.provider = futures::future::ready(Ok(MembersUpdate::default())).boxed();
+        provider.refresh();
+        assert!(next(&mut provider).await.is_ok());
+        assert_eq!(
+            started.elapsed(),
+            INITIAL_POLL_DELAY + Duration::from_secs(60)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_provider_yields_pushed_updates() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut updates = StreamMembersProvider::new(rx).into_stream();
+
+        tx.unbounded_send(Ok(MembersUpdate::at_height(vec![], 10)))
+            .unwrap();
+        let update = updates.next().await.unwrap().unwrap();
+        assert!(update.peers.is_empty());
+        assert_eq!(update.activation_height, Some(10));
+
+        //Membership stays the same after the stream ends
+        drop(tx);
+        let ended = futures::future::poll_fn(|cx| Poll::Ready(updates.poll_next_unpin(cx))).await;
+        assert!(ended.is_pending());
+    }
+}
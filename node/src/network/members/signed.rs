@@ -0,0 +1,485 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::MembershipAuthorityConfiguration;
+use crate::crypto::{EphemeraKeypair, EphemeraPublicKey, Keypair, PublicKey};
+use crate::network::members::provider::MembersUpdate;
+use crate::network::members::{ConfigMembersProviderError, JsonPeerInfo, PeerInfo};
+use crate::utilities::crypto::Signature;
+
+/// Prefix of signed bytes, so that membership signatures can't be reused for anything else.
+const SIGNING_DOMAIN: &[u8] = b"ephemera-membership:";
+
+#[derive(Error, Debug)]
+pub enum SignedMembershipError {
+    #[error("Membership epoch {epoch} is older than the current epoch {current}")]
+    StaleEpoch { epoch: u64, current: u64 },
+    #[error("Membership is valid only from {0}")]
+    NotYetValid(u64),
+    #[error("Membership expired at {0}")]
+    Expired(u64),
+    #[error("Membership has {valid} valid authority signatures, {threshold} required")]
+    NotEnoughSignatures { valid: usize, threshold: usize },
+    #[error("Invalid membership peer: {0}")]
+    InvalidPeer(String),
+    #[error("Failed to sign membership: {0}")]
+    Signing(String),
+    #[error("Threshold {threshold} must be from 1 to the number of authorities, {authorities}")]
+    InvalidThreshold {
+        threshold: usize,
+        authorities: usize,
+    },
+    #[error("Membership epoch file {0}")]
+    EpochFile(String),
+}
+
+/// Signature of a membership authority.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuthoritySignature {
+    /// Public key of the authority.
+    pub public_key: PublicKey,
+    /// Signature of the membership, base58 encoded.
+    pub signature: String,
+}
+
+/// Members list signed by membership authorities.
+///
+/// Nodes apply it only if it's signed by enough of the configured authorities, it's valid at the
+/// moment and its epoch is not older than the epoch of the membership they already have.
+///
+/// # Configuration example
+/// ```toml
+/// epoch = 2
+/// valid_from = 1684238400000
+/// valid_until = 1684324800000
+/// # Optional, nodes switch to the membership after their block at this height
+/// activation_height = 1200
+///
+/// [[peers]]
+/// name = "node1"
+/// address = "/ip4/127.0.0.1/tcp/3000"
+/// public_key = "4XTTMEghav9LZThm6opUaHrdGEEYUkrfkakVg4VAetetBZDWJ"
+/// weight = 1
+///
+/// [[signatures]]
+/// public_key = "4XTTMFQt2tgNRmwRgEAaGQe2NXygsK6Vr3pkuBfYezhDfoVty"
+/// signature = "3Bf6xQ..."
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignedMembership {
+    /// Grows with every membership change.
+    pub epoch: u64,
+    /// Unix timestamp in milliseconds.
+    pub valid_from: u64,
+    /// Unix timestamp in milliseconds.
+    pub valid_until: u64,
+    /// See [`MembersUpdate::activation_height`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activation_height: Option<u64>,
+    pub peers: Vec<JsonPeerInfo>,
+    #[serde(default)]
+    pub signatures: Vec<AuthoritySignature>,
+}
+
+#[derive(Serialize)]
+struct SignedPayload<'a> {
+    epoch: u64,
+    valid_from: u64,
+    valid_until: u64,
+    //Memberships without activation height are signed as before it existed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activation_height: Option<u64>,
+    peers: &'a [JsonPeerInfo],
+}
+
+impl SignedMembership {
+    /// Creates a new unsigned membership.
+    #[must_use]
+    pub fn new(epoch: u64, valid_from: u64, valid_until: u64, peers: Vec<JsonPeerInfo>) -> Self {
+        Self {
+            epoch,
+            valid_from,
+            valid_until,
+            activation_height: None,
+            peers,
+            signatures: vec![],
+        }
+    }
+
+    /// Adds the signature of an authority.
+    ///
+    /// # Errors
+    /// If the membership can't be signed.
+    pub fn sign(&mut self, authority: &Keypair) -> Result<(), SignedMembershipError> {
+        let signature = authority
+            .sign(&self.signed_bytes())
+            .map_err(|err| SignedMembershipError::Signing(err.to_string()))?;
+        self.signatures.push(AuthoritySignature {
+            public_key: authority.public_key(),
+            signature: signature.to_base58(),
+        });
+        Ok(())
+    }
+
+    /// Reads a membership from a toml file.
+    ///
+    /// # Errors
+    /// If the file can't be read or parsed.
+    pub fn try_load<I: Into<PathBuf>>(path: I) -> Result<Self, ConfigMembersProviderError> {
+        let config = config::Config::builder()
+            .add_source(config::File::from(path.into()))
+            .build()?;
+        config.try_deserialize().map_err(Into::into)
+    }
+
+    /// Writes the membership to a toml file.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn try_write<I: Into<PathBuf>>(&self, path: I) -> Result<(), ConfigMembersProviderError> {
+        let config = toml::to_string(&self)?;
+        let mut file = std::fs::File::create(path.into())?;
+        file.write_all(config.as_bytes())?;
+        Ok(())
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let payload = SignedPayload {
+            epoch: self.epoch,
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            activation_height: self.activation_height,
+            peers: &self.peers,
+        };
+        let mut bytes = SIGNING_DOMAIN.to_vec();
+        bytes.extend(serde_json::to_vec(&payload).expect("Membership is always serializable"));
+        bytes
+    }
+}
+
+/// Authorities who sign memberships and the epoch of the last applied membership.
+#[derive(Debug, Clone)]
+pub struct MembershipAuthority {
+    public_keys: HashSet<PublicKey>,
+    threshold: usize,
+    epoch: Option<u64>,
+    /// Keeps `epoch` across restarts.
+    epoch_file: Option<PathBuf>,
+}
+
+impl MembershipAuthority {
+    /// Creates a new [`MembershipAuthority`].
+    ///
+    /// # Arguments
+    /// * `public_keys` - Public keys of the authorities.
+    /// * `threshold` - How many authorities have to sign a membership. From one to the number of authorities.
+    ///
+    /// # Errors
+    /// If the threshold is out of range.
+    pub fn new(
+        public_keys: Vec<PublicKey>,
+        threshold: usize,
+    ) -> Result<Self, SignedMembershipError> {
+        let public_keys = public_keys.into_iter().collect::<HashSet<_>>();
+        validate_threshold(threshold, public_keys.len())?;
+        Ok(Self {
+            public_keys,
+            threshold,
+            epoch: None,
+            epoch_file: None,
+        })
+    }
+
+    /// Keeps the epoch of the last verified membership in a file. Without it, a restarted node
+    /// accepts any membership which is still valid, also an older one.
+    ///
+    /// # Arguments
+    /// * `path` - File of the epoch. The epoch is read from it if it exists.
+    ///
+    /// # Errors
+    /// If the file exists but can't be read.
+    pub fn with_epoch_file<P: Into<PathBuf>>(
+        mut self,
+        path: P,
+    ) -> Result<Self, SignedMembershipError> {
+        let path = path.into();
+        if path.exists() {
+            let epoch = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| content.trim().parse().map_err(|err| format!("{err}")))
+                .map_err(|err| {
+                    SignedMembershipError::EpochFile(format!("{}: {err}", path.display()))
+                })?;
+            self.epoch = Some(epoch);
+        }
+        self.epoch_file = Some(path);
+        Ok(self)
+    }
+
+    /// Verifies the membership and returns its peers. The epoch of a verified membership
+    /// becomes the current epoch.
+    ///
+    /// # Arguments
+    /// * `membership` - Membership to verify.
+    /// * `now` - Unix timestamp in milliseconds.
+    ///
+    /// # Errors
+    /// If the membership is not valid at the moment, is older than the current membership
+    /// or doesn't have enough authority signatures.
+    pub fn verify(
+        &mut self,
+        membership: SignedMembership,
+        now: u64,
+    ) -> Result<MembersUpdate, SignedMembershipError> {
+        if let Some(current) = self.epoch {
+            //The same membership can be received many times
+            if membership.epoch < current {
+                return Err(SignedMembershipError::StaleEpoch {
+                    epoch: membership.epoch,
+                    current,
+                });
+            }
+        }
+        if now < membership.valid_from {
+            return Err(SignedMembershipError::NotYetValid(membership.valid_from));
+        }
+        if now >= membership.valid_until {
+            return Err(SignedMembershipError::Expired(membership.valid_until));
+        }
+
+        let signed_bytes = membership.signed_bytes();
+        let signers = membership
+            .signatures
+            .iter()
+            .filter(|signature| self.public_keys.contains(&signature.public_key))
+            .filter(|signature| {
+                bs58::decode(&signature.signature)
+                    .into_vec()
+                    .is_ok_and(|raw| {
+                        signature
+                            .public_key
+                            .verify(&signed_bytes, &Signature::new(raw))
+                    })
+            })
+            .map(|signature| &signature.public_key)
+            .collect::<HashSet<_>>();
+        if signers.len() < self.threshold {
+            return Err(SignedMembershipError::NotEnoughSignatures {
+                valid: signers.len(),
+                threshold: self.threshold,
+            });
+        }
+
+        let peers = membership
+            .peers
+            .into_iter()
+            .map(PeerInfo::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|err| SignedMembershipError::InvalidPeer(err.to_string()))?;
+        if self.epoch != Some(membership.epoch) {
+            self.write_epoch(membership.epoch)?;
+        }
+        self.epoch = Some(membership.epoch);
+        Ok(MembersUpdate {
+            peers,
+            activation_height: membership.activation_height,
+        })
+    }
+
+    //Replaces the file, so that a crash doesn't leave a partially written epoch
+    fn write_epoch(&self, epoch: u64) -> Result<(), SignedMembershipError> {
+        let Some(path) = &self.epoch_file else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, epoch.to_string())
+            .and_then(|()| std::fs::rename(&tmp_path, path))
+            .map_err(|err| SignedMembershipError::EpochFile(format!("{}: {err}", path.display())))
+    }
+}
+
+pub(crate) fn validate_threshold(
+    threshold: usize,
+    authorities: usize,
+) -> Result<(), SignedMembershipError> {
+    if threshold == 0 || threshold > authorities {
+        return Err(SignedMembershipError::InvalidThreshold {
+            threshold,
+            authorities,
+        });
+    }
+    Ok(())
+}
+
+/// Reads an authority keypair from a file, in the format of `ephemera generate-keypair`.
+///
+/// # Errors
+/// If the file can't be read or doesn't contain a keypair.
+pub fn read_authority_keypair<P: AsRef<Path>>(path: P) -> anyhow::Result<Keypair> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Failed to read keypair file {path:?}: {err}"))?;
+    Keypair::from_base58(content.trim())
+        .map_err(|err| anyhow::anyhow!("Invalid keypair in file {path:?}: {err}"))
+}
+
+impl TryFrom<&MembershipAuthorityConfiguration> for MembershipAuthority {
+    type Error = SignedMembershipError;
+
+    fn try_from(config: &MembershipAuthorityConfiguration) -> Result<Self, Self::Error> {
+        let authority = Self::new(config.public_keys.clone(), config.threshold)?;
+        match &config.epoch_file {
+            Some(path) => authority.with_epoch_file(path),
+            None => Ok(authority),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn membership(epoch: u64, authorities: &[&Keypair]) -> SignedMembership {
+        let peer = Keypair::generate(None);
+        let peers = vec![JsonPeerInfo::new(
+            "node1".to_string(),
+            "/ip4/127.0.0.1/tcp/3000".to_string(),
+            peer.public_key().to_base58(),
+        )];
+        let mut membership = SignedMembership::new(epoch, 1000, 2000, peers);
+        for authority in authorities {
+            membership.sign(authority).unwrap();
+        }
+        membership
+    }
+
+    #[test]
+    fn test_verify_signature_threshold() {
+        let authority1 = Keypair::generate(None);
+        let authority2 = Keypair::generate(None);
+        let other = Keypair::generate(None);
+        let mut authority =
+            MembershipAuthority::new(vec![authority1.public_key(), authority2.public_key()], 2)
+                .unwrap();
+
+        //Signatures of unknown keys and duplicate signatures don't count
+        let result = authority.verify(membership(1, &[&authority1, &authority1, &other]), 1500);
+        assert!(matches!(
+            result,
+            Err(SignedMembershipError::NotEnoughSignatures { valid: 1, .. })
+        ));
+
+        let update = authority
+            .verify(membership(1, &[&authority1, &authority2]), 1500)
+            .unwrap();
+        assert_eq!(update.peers.len(), 1);
+    }
+
+    #[test]
+    fn test_signed_membership_file() {
+        let authority1 = Keypair::generate(None);
+        let mut authority = MembershipAuthority::new(vec![authority1.public_key()], 1).unwrap();
+
+        let mut membership = membership(1, &[]);
+        membership.activation_height = Some(100);
+        membership.sign(&authority1).unwrap();
+
+        let path = std::env::temp_dir().join(format!("members-{}.toml", uuid::Uuid::new_v4()));
+        membership.try_write(&path).unwrap();
+        let loaded = SignedMembership::try_load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let update = authority.verify(loaded, 1500).unwrap();
+        assert_eq!(update.activation_height, Some(100));
+    }
+
+    #[test]
+    fn test_verify_tampered_membership() {
+        let authority1 = Keypair::generate(None);
+        let mut authority = MembershipAuthority::new(vec![authority1.public_key()], 1).unwrap();
+
+        let mut tampered = membership(1, &[&authority1]);
+        tampered.peers[0].address = "/ip4/10.0.0.1/tcp/3000".to_string();
+        assert!(authority.verify(tampered, 1500).is_err());
+
+        let mut tampered = membership(1, &[&authority1]);
+        tampered.activation_height = Some(100);
+        assert!(authority.verify(tampered, 1500).is_err());
+    }
+
+    #[test]
+    fn test_verify_epoch_and_validity() {
+        let authority1 = Keypair::generate(None);
+        let mut authority = MembershipAuthority::new(vec![authority1.public_key()], 1).unwrap();
+
+        assert!(matches!(
+            authority.verify(membership(2, &[&authority1]), 500),
+            Err(SignedMembershipError::NotYetValid(1000))
+        ));
+        assert!(matches!(
+            authority.verify(membership(2, &[&authority1]), 2000),
+            Err(SignedMembershipError::Expired(2000))
+        ));
+
+        assert!(authority
+            .verify(membership(2, &[&authority1]), 1500)
+            .is_ok());
+        assert!(authority
+            .verify(membership(2, &[&authority1]), 1500)
+            .is_ok());
+        assert!(matches!(
+            authority.verify(membership(1, &[&authority1]), 1500),
+            Err(SignedMembershipError::StaleEpoch {
+                epoch: 1,
+                current: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_threshold_is_validated() {
+        let keys = vec![Keypair::generate(None).public_key()];
+        assert!(matches!(
+            MembershipAuthority::new(keys.clone(), 0),
+            Err(SignedMembershipError::InvalidThreshold { .. })
+        ));
+        assert!(matches!(
+            MembershipAuthority::new(keys, 2),
+            Err(SignedMembershipError::InvalidThreshold {
+                threshold: 2,
+                authorities: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_epoch_survives_restart() {
+        let authority1 = Keypair::generate(None);
+        let path = std::env::temp_dir().join(format!("epoch-{}", uuid::Uuid::new_v4()));
+        let restart = || {
+            MembershipAuthority::new(vec![authority1.public_key()], 1)
+                .unwrap()
+                .with_epoch_file(&path)
+                .unwrap()
+        };
+
+        let mut authority = restart();
+        assert!(authority
+            .verify(membership(2, &[&authority1]), 1500)
+            .is_ok());
+
+        let mut authority = restart();
+        assert!(matches!(
+            authority.verify(membership(1, &[&authority1]), 1500),
+            Err(SignedMembershipError::StaleEpoch {
+                epoch: 1,
+                current: 2
+            })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
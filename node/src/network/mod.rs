@@ -80,6 +80,16 @@ impl Display for PeerId {
     }
 }
 
+impl FromStr for PeerId {
+    type Err = PeerIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PeerIdType::from_str(s)
+            .map(Self)
+            .map_err(|e| PeerIdError::InvalidPeerId(format!("{s}: {e}")))
+    }
+}
+
 pub trait ToPeerId {
     fn peer_id(&self) -> PeerId;
 }
@@ -109,6 +119,8 @@ pub(crate) struct Peer {
     pub address: Address,
     /// The peer's name. It can be arbitrary and is just for logging/display purposes.
     pub name: String,
+    /// The peer's voting weight in the broadcast group.
+    pub weight: u64,
 }
 
 #[derive(Error, Debug)]
@@ -123,6 +135,10 @@ pub enum AddressError {
 /// 1. `<IP>:<PORT>`
 /// 2. `/ip4/<IP>/tcp/<PORT>` - this is format used by libp2p multiaddr.
 /// 3. `/dns4/<NAME>/tcp/<PORT>` - this is format used by libp2p multiaddr.
+/// 4. `/ip4/<IP>/udp/<PORT>/quic-v1` - QUIC address in libp2p multiaddr format.
+/// 5. `/dns4/<NAME>/udp/<PORT>/quic-v1` - QUIC address in libp2p multiaddr format.
+///
+/// `<IP>:<PORT>` is a TCP address.
 /// See [libp2p/multiaddress](https://github.com/libp2p/specs/blob/master/addressing/README.md) for more details.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Address(pub Multiaddr);
@@ -184,11 +200,19 @@ impl TryFrom<Address> for (IpAddr, u16) {
     type Error = std::io::Error;
 
     fn try_from(addr: Address) -> Result<Self, Self::Error> {
-        let mut multiaddr = addr.0;
-        if let Some(Protocol::Tcp(port)) = multiaddr.pop() {
-            if let Some(Protocol::Ip4(ip)) = multiaddr.pop() {
-                return Ok((IpAddr::V4(ip), port));
-            }
+        let mut protocols = addr.0.iter();
+        let ip = match protocols.next() {
+            Some(Protocol::Ip4(ip)) => Some(IpAddr::V4(ip)),
+            Some(Protocol::Ip6(ip)) => Some(IpAddr::V6(ip)),
+            _ => None,
+        };
+        let port = match (protocols.next(), protocols.next(), protocols.next()) {
+            (Some(Protocol::Tcp(port)), None, None)
+            | (Some(Protocol::Udp(port)), Some(Protocol::QuicV1), None) => Some(port),
+            _ => None,
+        };
+        if let (Some(ip), Some(port)) = (ip, port) {
+            return Ok((ip, port));
         }
         Err(std::io::Error::new(
             std::io::ErrorKind::Other,
@@ -206,6 +230,33 @@ mod test {
         "/ip4/127.0.0.1/tcp/1234".parse::<Address>().unwrap();
     }
 
+    #[test]
+    fn test_parse_quic_multiaddr() {
+        let address = "/ip4/127.0.0.1/udp/1234/quic-v1"
+            .parse::<Address>()
+            .unwrap();
+        let (ip, port): (IpAddr, u16) = address.try_into().unwrap();
+        assert_eq!(ip, IpAddr::from([127, 0, 0, 1]));
+        assert_eq!(port, 1234);
+
+        "/dns4/localhost/udp/1234/quic-v1"
+            .parse::<Address>()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tcp_multiaddr_to_ip_port() {
+        let address = "/ip4/127.0.0.1/tcp/1234".parse::<Address>().unwrap();
+        let (_, port): (IpAddr, u16) = address.try_into().unwrap();
+        assert_eq!(port, 1234);
+    }
+
+    #[test]
+    fn test_fail_parse_quic_multiaddr_without_port() {
+        let result = "/ip4/127.0.0.1/udp//quic-v1".parse::<Address>();
+        assert!(matches!(result, Err(AddressError::ParsingError(_))));
+    }
+
     #[test]
     fn test_parse_ip_port() {
         "127.0.0.1:1234".parse::<Address>().unwrap();
@@ -234,4 +285,13 @@ mod test {
         let result = "1234".parse::<Address>();
         assert!(matches!(result, Err(AddressError::ParsingError(_))));
     }
+
+    #[test]
+    fn test_parse_peer_id() {
+        let peer_id = PeerId::random();
+        assert_eq!(peer_id.to_string().parse::<PeerId>().unwrap(), peer_id);
+
+        let result = "not a peer id".parse::<PeerId>();
+        assert!(matches!(result, Err(PeerIdError::InvalidPeerId(_))));
+    }
 }
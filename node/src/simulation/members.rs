@@ -0,0 +1,67 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use crate::membership::{MembersUpdate, PeerInfo, Result};
+use crate::peer::PeerId;
+
+/// Membership provider driven by the test.
+///
+/// All clones share the same peers list, like nodes asking the same rendezvous endpoint.
+/// Nodes see a change the next time their membership behaviour polls the provider.
+#[derive(Clone)]
+pub(crate) struct ScriptedMembersProvider {
+    update: Arc<Mutex<MembersUpdate>>,
+}
+
+impl ScriptedMembersProvider {
+    pub(crate) fn new(peers: Vec<PeerInfo>) -> Self {
+        Self {
+            update: Arc::new(Mutex::new(peers.into())),
+        }
+    }
+
+    /// Replaces the peers list. Nodes switch to it as soon as they see it.
+    pub(crate) fn set(&self, peers: Vec<PeerInfo>) {
+        *self.update.lock().unwrap() = peers.into();
+    }
+
+    /// Replaces the peers list. Nodes switch to it after their block at `activation_height`.
+    pub(crate) fn set_at_height(&self, peers: Vec<PeerInfo>, activation_height: u64) {
+        *self.update.lock().unwrap() = MembersUpdate::at_height(peers, activation_height);
+    }
+
+    /// Removes a peer from the peers list.
+    pub(crate) fn remove(&self, peer_id: &PeerId) {
+        self.update
+            .lock()
+            .unwrap()
+            .peers
+            .retain(|peer| PeerId::from_public_key(&peer.pub_key) != *peer_id);
+    }
+
+    /// Changes the address of a peer.
+    pub(crate) fn set_address(&self, peer_id: &PeerId, address: String) {
+        let mut update = self.update.lock().unwrap();
+        if let Some(peer) = update
+            .peers
+            .iter_mut()
+            .find(|peer| PeerId::from_public_key(&peer.pub_key) == *peer_id)
+        {
+            peer.address = address;
+        }
+    }
+
+    pub(crate) fn peers(&self) -> Vec<PeerInfo> {
+        self.update.lock().unwrap().peers.clone()
+    }
+}
+
+impl Future for ScriptedMembersProvider {
+    type Output = Result<MembersUpdate>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Poll::Ready(Ok(self.update.lock().unwrap().clone()))
+    }
+}
@@ -0,0 +1,1321 @@
+//! In-process multi-node simulation.
+//!
+//! Runs several Ephemera instances inside one tokio runtime, connected over libp2p memory transport.
+//! The test controls the clock, the membership every node sees and the faults of the network
+//! between the nodes. Nodes can be crashed and restarted with their databases intact.
+//!
+//! Simulations can also run over TCP, QUIC or both of them on localhost. The clock runs in real
+//! time then.
+//!
+//! Each node delivers its own blocks to its application, so the delivered blocks of a node are
+//! the blocks it created and got through reliable broadcast. Observers come after the members and
+//! deliver the blocks of all members. With a proposer schedule all members deliver the same blocks.
+//!
+//! Runs are not deterministic:
+//! - Timers internal to libp2p (connection keep-alive, gossipsub heartbeat, Kademlia queries) run
+//!   on the wall clock, not on the simulation clock.
+//! - Faults and partitions apply only to reliable broadcast messages between the network and
+//!   Ephemera. Gossip, membership and DHT traffic are never dropped, delayed or partitioned, so a
+//!   partitioned node still sees the gossiped messages and the health of the other side.
+//!
+//! The seed makes the fault decisions repeatable for the same sequence of messages, but the
+//! sequence itself depends on task scheduling.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+
+use libp2p::pnet::PreSharedKey;
+
+use crate::api::types::ApiBlock;
+use crate::config::{
+    BlockManagerConfiguration, BroadcastConfiguration, ChannelConfiguration, Configuration,
+    DatabaseConfiguration, DhtConfiguration, GossipsubConfiguration, HealthFilterConfiguration,
+    HttpConfiguration, Libp2pConfiguration, MembershipKind, NodeConfiguration, PingConfiguration,
+    ProposerSchedule, QuorumPolicy, RateLimitConfiguration, TransportProtocol,
+    WebsocketConfiguration,
+};
+use crate::crypto::{EphemeraKeypair, Keypair};
+use crate::membership::{PeerInfo, DEFAULT_PEER_WEIGHT};
+use crate::network::libp2p::behaviours::TransportKind;
+use crate::network::libp2p::swarm_key::write_swarm_key;
+use crate::peer::{PeerId, ToPeerId};
+
+use self::members::ScriptedMembersProvider;
+use self::network::Faults;
+use self::node::SimNode;
+use self::time::SimClock;
+
+pub(crate) mod members;
+pub(crate) mod network;
+pub(crate) mod node;
+pub(crate) mod time;
+
+/// Memory transport addresses are global to the process. Tests running in parallel must not
+/// share them.
+static NEXT_MEMORY_PORT: AtomicU16 = AtomicU16::new(1);
+
+/// Step the clock advances in while the simulation runs.
+const CLOCK_STEP: Duration = Duration::from_millis(100);
+
+pub(crate) struct SimulationBuilder {
+    nodes: usize,
+    seed: u64,
+    block_creation_interval_sec: u64,
+    quorum_policy: QuorumPolicy,
+    transport: TransportKind,
+    swarm_keys: HashMap<usize, PreSharedKey>,
+    health_filter: Option<HealthFilterConfiguration>,
+    observers: usize,
+    proposer_schedule: ProposerSchedule,
+    non_producers: HashSet<usize>,
+    channels: Vec<String>,
+}
+
+impl SimulationBuilder {
+    pub(crate) fn new(nodes: usize) -> Self {
+        Self {
+            nodes,
+            seed: 0,
+            block_creation_interval_sec: 5,
+            quorum_policy: QuorumPolicy::default(),
+            transport: TransportKind::Memory,
+            swarm_keys: HashMap::new(),
+            health_filter: None,
+            observers: 0,
+            proposer_schedule: ProposerSchedule::default(),
+            non_producers: HashSet::new(),
+            channels: vec![],
+        }
+    }
+
+    /// Makes the node part of the private network of the key.
+    pub(crate) fn swarm_key(mut self, node: usize, key: PreSharedKey) -> Self {
+        self.swarm_keys.insert(node, key);
+        self
+    }
+
+    /// Transport between the nodes, memory transport by default.
+    ///
+    /// With dual-stack transport every other node advertises its QUIC address to the members
+    /// provider, the rest advertise TCP.
+    pub(crate) fn transport(mut self, transport: TransportKind) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Seed of all random choices made by fault injection. It doesn't make the run repeatable, see
+    /// the module docs.
+    pub(crate) fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub(crate) fn quorum_policy(mut self, quorum_policy: QuorumPolicy) -> Self {
+        self.quorum_policy = quorum_policy;
+        self
+    }
+
+    /// Enables the membership health filter on all nodes.
+    pub(crate) fn health_filter(mut self, health_filter: HealthFilterConfiguration) -> Self {
+        self.health_filter = Some(health_filter);
+        self
+    }
+
+    pub(crate) fn proposer_schedule(mut self, proposer_schedule: ProposerSchedule) -> Self {
+        self.proposer_schedule = proposer_schedule;
+        self
+    }
+
+    /// The node takes part in reliable broadcast but doesn't produce blocks.
+    pub(crate) fn non_producer(mut self, node: usize) -> Self {
+        self.non_producers.insert(node);
+        self
+    }
+
+    /// Adds a message channel to all nodes, with the same block production as the default one.
+    pub(crate) fn channel(mut self, name: &str) -> Self {
+        self.channels.push(name.to_string());
+        self
+    }
+
+    /// Adds observer nodes after the members. Observers are in the grace list of all nodes but
+    /// not in the membership.
+    pub(crate) fn observers(mut self, observers: usize) -> Self {
+        self.observers = observers;
+        self
+    }
+
+    /// Pauses the clock and starts all nodes. All nodes except observers are members, and block producers
+    /// unless configured otherwise.
+    /// Only memory transport pauses the clock.
+    ///
+    /// Nodes use [`MembershipKind::AnyOnline`], so crashed nodes leave the broadcast group
+    /// until they are back online.
+    ///
+    /// # Panics
+    /// If the nodes can't be started.
+    pub(crate) fn start(self) -> Simulation {
+        let clock = if self.transport == TransportKind::Memory {
+            SimClock::pause(CLOCK_STEP)
+        } else {
+            SimClock::real(CLOCK_STEP)
+        };
+        let dir =
+            std::env::temp_dir().join(format!("ephemera-simulation-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Failed to create simulation directory");
+
+        let keypairs = (0..self.nodes + self.observers)
+            .map(|_| Keypair::generate(None))
+            .collect::<Vec<_>>();
+        let observers = keypairs[self.nodes..]
+            .iter()
+            .map(ToPeerId::peer_id)
+            .collect::<Vec<_>>();
+
+        let mut peers = vec![];
+        let mut nodes = vec![];
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let (port, address) = if self.transport == TransportKind::Memory {
+                next_memory_address()
+            } else {
+                let port = free_port();
+                let quic = match self.transport {
+                    TransportKind::Quic => true,
+                    TransportKind::Dual => i % 2 == 1,
+                    _ => false,
+                };
+                if quic {
+                    (port, format!("/ip4/127.0.0.1/udp/{port}/quic-v1"))
+                } else {
+                    (port, format!("/ip4/127.0.0.1/tcp/{port}"))
+                }
+            };
+
+            if i < self.nodes {
+                peers.push(PeerInfo {
+                    name: format!("node{i}"),
+                    address,
+                    pub_key: keypair.public_key(),
+                    weight: DEFAULT_PEER_WEIGHT,
+                });
+            }
+
+            let mut config = self.node_configuration(&dir, i, port, keypair);
+            config.libp2p.grace_list = observers.clone();
+            nodes.push(SimNode::new(keypair.peer_id(), config, self.transport));
+        }
+
+        let mut simulation = Simulation {
+            clock,
+            members: ScriptedMembersProvider::new(peers),
+            faults: Faults::new(self.seed),
+            shared_chain: self.proposer_schedule != ProposerSchedule::All,
+            nodes,
+            dir,
+        };
+        for i in 0..simulation.nodes.len() {
+            simulation.restart(i);
+        }
+        simulation
+    }
+
+    fn node_configuration(
+        &self,
+        dir: &std::path::Path,
+        i: usize,
+        port: u16,
+        keypair: &Keypair,
+    ) -> Configuration {
+        let path = |extension: &str| {
+            dir.join(format!("node{i}.{extension}"))
+                .to_string_lossy()
+                .to_string()
+        };
+        let swarm_key_path = self.swarm_keys.get(&i).map(|key| {
+            let swarm_key_path = path("swarm.key");
+            write_swarm_key(&swarm_key_path, *key).expect("Failed to write swarm key");
+            swarm_key_path
+        });
+        let block_manager = BlockManagerConfiguration {
+            proposer_schedule: self.proposer_schedule.clone(),
+            ..BlockManagerConfiguration::new(
+                i < self.nodes && !self.non_producers.contains(&i),
+                self.block_creation_interval_sec,
+                false,
+            )
+        };
+        Configuration {
+            node: NodeConfiguration {
+                ip: "127.0.0.1".to_string(),
+                private_key: keypair.to_base58(),
+                observer: i >= self.nodes,
+            },
+            libp2p: Libp2pConfiguration {
+                port,
+                transport: match self.transport {
+                    TransportKind::Quic => TransportProtocol::Quic,
+                    TransportKind::Dual => TransportProtocol::Dual,
+                    //Memory transport is set on the node builder
+                    TransportKind::Tcp | TransportKind::Memory => TransportProtocol::Tcp,
+                },
+                swarm_key_path,
+                ephemera_msg_topic_name: "ephemera-simulation".to_string(),
+                heartbeat_interval_sec: 1,
+                members_provider_delay_sec: 1,
+                membership_kind: MembershipKind::AnyOnline,
+                gossipsub: GossipsubConfiguration::default(),
+                rate_limit: RateLimitConfiguration::default(),
+                grace_list: vec![],
+                dht: DhtConfiguration::default(),
+                ping: PingConfiguration::default(),
+                membership_authority: None,
+                health_filter: self.health_filter.clone(),
+            },
+            storage: DatabaseConfiguration {
+                rocksdb_path: path("rocksdb"),
+                sqlite_path: path("sqlite"),
+                create_if_not_exists: true,
+            },
+            //Http and websocket still run, on ports picked by OS
+            websocket: WebsocketConfiguration { port: 0 },
+            http: HttpConfiguration {
+                port: 0,
+                admin_token: None,
+            },
+            block_manager: block_manager.clone(),
+            broadcast: BroadcastConfiguration {
+                quorum_policy: self.quorum_policy.clone(),
+            },
+            channels: self
+                .channels
+                .iter()
+                .map(|name| ChannelConfiguration {
+                    name: name.clone(),
+                    block_manager: block_manager.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn next_memory_address() -> (u16, String) {
+    let port = NEXT_MEMORY_PORT.fetch_add(1, Ordering::Relaxed);
+    (port, format!("/memory/{port}"))
+}
+
+/// Localhost port which is free for both TCP and UDP.
+fn free_port() -> u16 {
+    loop {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind TCP port");
+        let port = listener
+            .local_addr()
+            .expect("Failed to get TCP port")
+            .port();
+        if UdpSocket::bind(("127.0.0.1", port)).is_ok() {
+            return port;
+        }
+    }
+}
+
+pub(crate) struct Simulation {
+    pub(crate) clock: SimClock,
+    /// Membership provider all nodes share.
+    pub(crate) members: ScriptedMembersProvider,
+    /// Faults between the network and the nodes.
+    pub(crate) faults: Faults,
+    /// Members deliver the blocks of all members.
+    shared_chain: bool,
+    nodes: Vec<SimNode>,
+    dir: PathBuf,
+}
+
+impl Simulation {
+    pub(crate) fn node(&self, i: usize) -> &SimNode {
+        &self.nodes[i]
+    }
+
+    pub(crate) fn peer_id(&self, i: usize) -> PeerId {
+        self.nodes[i].peer_id
+    }
+
+    pub(crate) fn delivered(&self, i: usize) -> Vec<ApiBlock> {
+        self.nodes[i].delivered.blocks()
+    }
+
+    pub(crate) fn delivered_count(&self, i: usize) -> usize {
+        self.nodes[i].delivered.count()
+    }
+
+    /// Blocks of the channel the node delivered.
+    pub(crate) fn channel_delivered(&self, i: usize, channel: &str) -> Vec<ApiBlock> {
+        self.nodes[i].channel_delivered[channel].blocks()
+    }
+
+    pub(crate) async fn crash(&mut self, i: usize) {
+        self.nodes[i].crash().await;
+        //Memory transport releases a port only when the listener is removed, not when it's
+        //dropped. The node comes back on a new address, like after a restart in another host.
+        if self.nodes[i].transport == TransportKind::Memory {
+            let (port, address) = next_memory_address();
+            self.nodes[i].config.libp2p.port = port;
+            self.members.set_address(&self.nodes[i].peer_id, address);
+        }
+    }
+
+    pub(crate) fn restart(&mut self, i: usize) {
+        self.nodes[i].start(self.members.clone(), &self.faults);
+    }
+
+    /// Lets the simulation run for `duration` of virtual time.
+    pub(crate) async fn run_for(&self, duration: Duration) {
+        self.clock.advance(duration).await;
+    }
+
+    /// Runs the simulation until `condition` holds, at most for `timeout` of virtual time.
+    ///
+    /// Returns whether the condition was met.
+    pub(crate) async fn run_until<F>(&self, timeout: Duration, condition: F) -> bool
+    where
+        F: Fn(&Simulation) -> bool,
+    {
+        let deadline = self.clock.elapsed() + timeout;
+        while self.clock.elapsed() < deadline {
+            if condition(self) {
+                return true;
+            }
+            self.clock.advance(CLOCK_STEP).await;
+        }
+        condition(self)
+    }
+
+    /// Checks that every member delivered only its own blocks, each height exactly once and
+    /// without gaps, across restarts.
+    ///
+    /// With a proposer schedule members deliver blocks of all members instead, and all of them
+    /// deliver the same block at the same height.
+    pub(crate) fn assert_safety(&self) {
+        let mut chain = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.config.node.observer {
+                continue;
+            }
+            let blocks = node.delivered.blocks();
+            for block in &blocks {
+                if self.shared_chain {
+                    let hash = chain
+                        .entry(block.header.height)
+                        .or_insert_with(|| block.hash());
+                    assert_eq!(
+                        *hash,
+                        block.hash(),
+                        "node{i} delivered another block at height {}",
+                        block.header.height
+                    );
+                } else {
+                    assert_eq!(
+                        block.header.creator, node.peer_id,
+                        "node{i} delivered a block of another node"
+                    );
+                }
+            }
+            for pair in blocks.windows(2) {
+                assert_eq!(
+                    pair[0].header.height + 1,
+                    pair[1].header.height,
+                    "node{i} delivered heights {} and {} in a row",
+                    pair[0].header.height,
+                    pair[1].header.height
+                );
+            }
+        }
+    }
+
+    pub(crate) async fn shutdown(mut self) {
+        for node in &mut self.nodes {
+            node.crash().await;
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::api::types::{
+        ApiBlockManagerStatus, ApiError, ApiMemberHealth, RawApiEphemeraMessage,
+    };
+    use crate::network::libp2p::swarm_key::generate_swarm_key;
+    use crate::simulation::network::{Fault, Link};
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(180);
+
+    fn all_delivered(simulation: &Simulation, nodes: &[usize], blocks: usize) -> bool {
+        nodes
+            .iter()
+            .all(|i| simulation.delivered_count(*i) >= blocks)
+    }
+
+    fn last_height(simulation: &Simulation, node: usize) -> u64 {
+        simulation
+            .delivered(node)
+            .last()
+            .map_or(0, |block| block.header.height)
+    }
+
+    /// Runs the simulation until the members health reported by the node satisfies `condition`.
+    async fn wait_for_members<F>(simulation: &Simulation, node: usize, condition: F) -> bool
+    where
+        F: Fn(&[ApiMemberHealth]) -> bool,
+    {
+        for _ in 0..TIMEOUT.as_secs() {
+            let api = &simulation.node(node).handle().api;
+            if condition(&api.members_health().await.unwrap()) {
+                return true;
+            }
+            simulation.run_for(Duration::from_secs(1)).await;
+        }
+        false
+    }
+
+    /// Heights of the blocks the node delivered above `height`, together with whether the peer
+    /// was part of the broadcast group of the block.
+    async fn group_membership_above(
+        simulation: &Simulation,
+        node: usize,
+        height: u64,
+        peer_id: PeerId,
+    ) -> Vec<(u64, bool)> {
+        let api = &simulation.node(node).handle().api;
+        let mut membership = vec![];
+        for block in simulation.delivered(node) {
+            if block.header.height <= height {
+                continue;
+            }
+            let info = api
+                .get_block_broadcast_info(block.header.hash.clone())
+                .await
+                .unwrap()
+                .expect("Broadcast group of a delivered block is stored");
+            membership.push((block.header.height, info.weights.contains_key(&peer_id)));
+        }
+        membership
+    }
+
+    #[tokio::test]
+    async fn test_all_nodes_deliver_blocks() {
+        let simulation = SimulationBuilder::new(4).start();
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 3))
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "nodes didn't deliver blocks in time");
+        simulation.shutdown().await;
+    }
+
+    async fn assert_nodes_deliver_blocks_over(transport: TransportKind) {
+        let simulation = SimulationBuilder::new(4).transport(transport).start();
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 2))
+            .await;
+
+        simulation.assert_safety();
+        assert!(
+            live,
+            "nodes didn't deliver blocks over {transport:?} in time"
+        );
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_all_nodes_deliver_blocks_over_tcp() {
+        assert_nodes_deliver_blocks_over(TransportKind::Tcp).await;
+    }
+
+    #[tokio::test]
+    async fn test_all_nodes_deliver_blocks_over_quic() {
+        assert_nodes_deliver_blocks_over(TransportKind::Quic).await;
+    }
+
+    #[tokio::test]
+    async fn test_all_nodes_deliver_blocks_over_dual_stack() {
+        assert_nodes_deliver_blocks_over(TransportKind::Dual).await;
+    }
+
+    #[tokio::test]
+    async fn test_delivers_over_faulty_links() {
+        let simulation = SimulationBuilder::new(4).seed(42).start();
+        let (a, b, c) = (
+            simulation.peer_id(0),
+            simulation.peer_id(1),
+            simulation.peer_id(2),
+        );
+        simulation.faults.add(Link::between(a, b), Fault::Drop(1.0));
+        simulation
+            .faults
+            .add(Link::from_peer(c), Fault::Delay(Duration::from_secs(1)));
+        simulation.faults.add(Link::to_peer(a), Fault::Reorder);
+        simulation.faults.add(Link::any(), Fault::Duplicate);
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 3))
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "nodes didn't deliver blocks in time");
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_private_network_delivers_blocks() {
+        let key = generate_swarm_key();
+        let simulation = (0..4)
+            .fold(SimulationBuilder::new(4), |builder, i| {
+                builder.swarm_key(i, key)
+            })
+            .start();
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 2))
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "nodes didn't deliver blocks in time");
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_node_with_other_swarm_key_cant_connect() {
+        let key = generate_swarm_key();
+        let simulation = SimulationBuilder::new(4)
+            .swarm_key(0, key)
+            .swarm_key(1, key)
+            .swarm_key(2, key)
+            .swarm_key(3, generate_swarm_key())
+            .start();
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 2))
+            .await;
+        assert!(live, "nodes didn't deliver blocks in time");
+
+        let group = simulation
+            .node(0)
+            .handle()
+            .api
+            .get_broadcast_info()
+            .await
+            .unwrap();
+        assert!(!group.current_members.contains(&simulation.peer_id(3)));
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_minority_partition_doesnt_deliver() {
+        let simulation = SimulationBuilder::new(4).start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 1))
+                .await
+        );
+
+        let (isolated, rest) = (
+            simulation.peer_id(0),
+            (1..4).map(|i| simulation.peer_id(i)).collect(),
+        );
+        simulation.faults.partition(vec![vec![isolated], rest]);
+        //Let the blocks in flight finish
+        simulation.run_for(Duration::from_secs(10)).await;
+
+        let isolated_before = simulation.delivered_count(0);
+        let rest_before = [1, 2, 3].map(|i| simulation.delivered_count(i));
+        simulation.run_for(Duration::from_secs(60)).await;
+
+        assert_eq!(simulation.delivered_count(0), isolated_before);
+        for (i, before) in (1..4).zip(rest_before) {
+            assert!(simulation.delivered_count(i) > before, "node{i} stalled");
+        }
+
+        simulation.faults.heal();
+        let live = simulation
+            .run_until(TIMEOUT, |s| s.delivered_count(0) > isolated_before)
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "isolated node didn't recover after healing");
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_crash_and_restart() {
+        let mut simulation = SimulationBuilder::new(4)
+            .quorum_policy(QuorumPolicy::CrashFault)
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 1))
+                .await
+        );
+
+        simulation.crash(3).await;
+        assert!(!simulation.node(3).is_running());
+        let crashed_count = simulation.delivered_count(3);
+
+        let rest: Vec<usize> = (0..3).map(|i| simulation.delivered_count(i) + 2).collect();
+        let live = simulation
+            .run_until(TIMEOUT, |s| (0..3).all(|i| s.delivered_count(i) >= rest[i]))
+            .await;
+        assert!(live, "nodes didn't deliver blocks without crashed node");
+        assert_eq!(simulation.delivered_count(3), crashed_count);
+
+        simulation.restart(3);
+        let live = simulation
+            .run_until(TIMEOUT, |s| s.delivered_count(3) >= crashed_count + 2)
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "restarted node didn't deliver blocks");
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_removed_member_stops_delivering() {
+        let simulation = SimulationBuilder::new(4).start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 1))
+                .await
+        );
+
+        let all_peers = simulation.members.peers();
+        simulation.members.remove(&simulation.peer_id(3));
+        //Let the nodes see the change and the blocks in flight finish
+        simulation.run_for(Duration::from_secs(15)).await;
+
+        let removed_count = simulation.delivered_count(3);
+        let rest: Vec<usize> = (0..3).map(|i| simulation.delivered_count(i) + 2).collect();
+        let live = simulation
+            .run_until(TIMEOUT, |s| (0..3).all(|i| s.delivered_count(i) >= rest[i]))
+            .await;
+        assert!(live, "members didn't deliver blocks after removal");
+        assert_eq!(simulation.delivered_count(3), removed_count);
+
+        simulation.members.set(all_peers);
+        let live = simulation
+            .run_until(TIMEOUT, |s| s.delivered_count(3) > removed_count)
+            .await;
+
+        simulation.assert_safety();
+        assert!(live, "node didn't deliver blocks after joining back");
+        assert!(simulation.delivered(3).len() > removed_count);
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_member_removed_at_activation_height() {
+        let simulation = SimulationBuilder::new(4).start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 2))
+                .await
+        );
+
+        let removed = simulation.peer_id(3);
+        let changed_at = (0..4).map(|i| last_height(&simulation, i)).max().unwrap();
+        let activation_height = changed_at + 3;
+        let mut peers = simulation.members.peers();
+        peers.retain(|peer| PeerId::from_public_key(&peer.pub_key) != removed);
+        simulation.members.set_at_height(peers, activation_height);
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| {
+                (0..3).all(|i| last_height(s, i) >= activation_height + 2)
+            })
+            .await;
+        assert!(
+            live,
+            "members didn't deliver blocks after activation height"
+        );
+
+        //Blocks up to the activation height are still broadcast in the old group
+        for i in 0..3 {
+            for (height, in_group) in
+                group_membership_above(&simulation, i, changed_at, removed).await
+            {
+                assert_eq!(
+                    in_group,
+                    height <= activation_height,
+                    "node{i} block at height {height} was broadcast in the wrong group"
+                );
+            }
+        }
+        assert!(simulation
+            .delivered(3)
+            .iter()
+            .all(|block| block.header.height <= activation_height));
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_member_joins_at_activation_height() {
+        let simulation = SimulationBuilder::new(4).start();
+        let all_peers = simulation.members.peers();
+        let joining = simulation.peer_id(3);
+        simulation.members.remove(&joining);
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 2))
+                .await
+        );
+        assert_eq!(simulation.delivered_count(3), 0);
+
+        let changed_at = (0..3).map(|i| last_height(&simulation, i)).max().unwrap();
+        let activation_height = changed_at + 3;
+        simulation
+            .members
+            .set_at_height(all_peers, activation_height);
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| {
+                (0..3).all(|i| last_height(s, i) >= activation_height + 2)
+                    && s.delivered_count(3) > 0
+            })
+            .await;
+        assert!(live, "nodes didn't deliver blocks after activation height");
+
+        //The new member takes part only in blocks after the activation height
+        for i in 0..3 {
+            for (height, in_group) in
+                group_membership_above(&simulation, i, changed_at, joining).await
+            {
+                assert_eq!(
+                    in_group,
+                    height > activation_height,
+                    "node{i} block at height {height} was broadcast in the wrong group"
+                );
+            }
+        }
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_paused_member_applies_group_update() {
+        let simulation = SimulationBuilder::new(4).start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 2))
+                .await
+        );
+
+        //Node 0 is paused across the activation height, its committed height doesn't advance
+        let api = &simulation.node(0).handle().api;
+        api.pause_block_production().await.unwrap();
+        let removed = simulation.peer_id(3);
+        let activation_height = last_height(&simulation, 0) + 3;
+        let mut peers = simulation.members.peers();
+        peers.retain(|peer| PeerId::from_public_key(&peer.pub_key) != removed);
+        simulation.members.set_at_height(peers, activation_height);
+
+        let mut applied = false;
+        for _ in 0..TIMEOUT.as_secs() {
+            let info = api.get_broadcast_info().await.unwrap();
+            if !info.weights.is_empty() && !info.weights.contains_key(&removed) {
+                applied = true;
+                break;
+            }
+            simulation.run_for(Duration::from_secs(1)).await;
+        }
+        assert!(applied, "paused member didn't apply the group update");
+
+        api.resume_block_production().await.unwrap();
+        let resumed_at = last_height(&simulation, 0);
+        let live = simulation
+            .run_until(TIMEOUT, |s| last_height(s, 0) >= resumed_at + 2)
+            .await;
+        assert!(live, "member didn't deliver blocks after resuming");
+        for (height, in_group) in group_membership_above(&simulation, 0, resumed_at, removed).await
+        {
+            assert!(
+                !in_group,
+                "block at height {height} was broadcast in the old group"
+            );
+        }
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_group_history_survives_restart() {
+        let mut simulation = SimulationBuilder::new(4)
+            .quorum_policy(QuorumPolicy::CrashFault)
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 1))
+                .await
+        );
+
+        let removed = simulation.peer_id(3);
+        simulation.members.remove(&removed);
+        simulation.run_for(Duration::from_secs(15)).await;
+
+        let api = &simulation.node(0).handle().api;
+        let history = api.get_group_history().await.unwrap();
+        assert!(history.windows(2).all(|w| w[0].id < w[1].id));
+        assert!(history.iter().any(|s| s.weights.contains_key(&removed)));
+        assert!(!history.last().unwrap().weights.contains_key(&removed));
+        for snapshot in &history {
+            let stored = api.get_group_snapshot(snapshot.id).await.unwrap();
+            assert_eq!(stored.as_ref(), Some(snapshot));
+        }
+
+        simulation.crash(0).await;
+        simulation.restart(0);
+        let crashed_count = simulation.delivered_count(0);
+        let live = simulation
+            .run_until(TIMEOUT, |s| s.delivered_count(0) > crashed_count)
+            .await;
+        assert!(live, "restarted node didn't deliver blocks");
+
+        //Snapshot ids continue after the restart
+        let api = &simulation.node(0).handle().api;
+        let after_restart = api.get_group_history().await.unwrap();
+        assert!(after_restart.len() > history.len());
+        assert_eq!(after_restart[..history.len()], history[..]);
+        assert!(after_restart.windows(2).all(|w| w[0].id < w[1].id));
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_health_filter_excludes_crashed_member_until_recovered() {
+        let mut simulation = SimulationBuilder::new(4)
+            .quorum_policy(QuorumPolicy::CrashFault)
+            .health_filter(HealthFilterConfiguration {
+                check_interval_sec: 1,
+                heartbeat_timeout_sec: 3,
+                max_failures: 2,
+                recovery_checks: 3,
+            })
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 1))
+                .await
+        );
+
+        let crashed = simulation.peer_id(3);
+        let heartbeats = wait_for_members(&simulation, 0, |members| {
+            members.len() == 3
+                && members
+                    .iter()
+                    .all(|member| member.included && member.last_heartbeat.is_some())
+        })
+        .await;
+        assert!(heartbeats, "members didn't send heartbeats");
+
+        simulation.crash(3).await;
+        let excluded = wait_for_members(&simulation, 0, |members| {
+            members
+                .iter()
+                .any(|member| member.peer_id == crashed && !member.included)
+        })
+        .await;
+        assert!(excluded, "crashed member wasn't excluded");
+        let api = &simulation.node(0).handle().api;
+        assert!(!api
+            .get_broadcast_info()
+            .await
+            .unwrap()
+            .current_members
+            .contains(&crashed));
+
+        simulation.restart(3);
+        let crashed_count = simulation.delivered_count(3);
+        let included = wait_for_members(&simulation, 0, |members| {
+            members
+                .iter()
+                .any(|member| member.peer_id == crashed && member.included)
+        })
+        .await;
+        assert!(included, "recovered member wasn't included again");
+        let api = &simulation.node(0).handle().api;
+        let recovered = api.members_health().await.unwrap();
+        let recovered = recovered
+            .iter()
+            .find(|member| member.peer_id == crashed)
+            .unwrap();
+        assert!(recovered.consecutive_successes >= 3);
+
+        let live = simulation
+            .run_until(TIMEOUT, |s| s.delivered_count(3) > crashed_count)
+            .await;
+        assert!(live, "recovered member didn't deliver blocks");
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_observer_delivers_blocks_of_members() {
+        let simulation = SimulationBuilder::new(3).observers(1).start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 2)
+                    && s.delivered_count(3) >= 6)
+                .await
+        );
+
+        let observer = simulation.peer_id(3);
+        for i in 0..3 {
+            let api = &simulation.node(i).handle().api;
+            let info = api.get_broadcast_info().await.unwrap();
+            assert_eq!(info.current_members.len(), 3);
+            assert!(!info.current_members.contains(&observer));
+        }
+
+        //Without a proposer schedule every producer has its own block at each height
+        let members = (0..3).map(|i| simulation.peer_id(i)).collect::<Vec<_>>();
+        let api = &simulation.node(3).handle().api;
+        let mut creators = HashMap::<u64, HashSet<PeerId>>::new();
+        for block in simulation.delivered(3) {
+            assert!(members.contains(&block.header.creator));
+            creators
+                .entry(block.header.height)
+                .or_default()
+                .insert(block.header.creator);
+            let stored = api.get_block_by_id(block.hash()).await.unwrap().unwrap();
+            assert_eq!(stored.hash(), block.hash());
+            let by_height = api
+                .get_block_by_height(block.header.height)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(by_height.header.height, block.header.height);
+            let certificates = api.get_block_certificates(block.hash()).await.unwrap();
+            let certificates = certificates.unwrap();
+            assert!(certificates.certificates.len() >= 2);
+            assert_eq!(certificates.total_weight, 3);
+            assert_eq!(
+                certificates.signed_weight,
+                certificates.certificates.len() as u64
+            );
+        }
+        assert!(creators.values().any(|creators| creators.len() > 1));
+        assert!(api.get_node_config().await.unwrap().observer);
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_observer_verifies_blocks_in_group_of_their_height() {
+        let simulation = SimulationBuilder::new(4)
+            .observers(1)
+            .proposer_schedule(ProposerSchedule::RoundRobin { timeout_sec: 20 })
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3, 4], 3))
+                .await
+        );
+
+        //Observer switches to the new group right away, blocks up to the activation height are
+        //still certified by the old one
+        let removed = simulation.peer_id(3);
+        let changed_at = (0..4).map(|i| last_height(&simulation, i)).max().unwrap();
+        let activation_height = changed_at + 4;
+        let mut peers = simulation.members.peers();
+        peers.retain(|peer| PeerId::from_public_key(&peer.pub_key) != removed);
+        simulation.members.set_at_height(peers, activation_height);
+
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| {
+                    s.delivered(4)
+                        .iter()
+                        .any(|block| block.header.height >= activation_height + 2)
+                })
+                .await,
+            "observer didn't deliver blocks after activation height"
+        );
+
+        let heights = simulation
+            .delivered(4)
+            .iter()
+            .map(|block| block.header.height)
+            .filter(|height| *height > changed_at)
+            .collect::<BTreeSet<_>>();
+        let expected = (changed_at + 1..=activation_height + 2).collect::<BTreeSet<_>>();
+        assert!(
+            expected.is_subset(&heights),
+            "observer missed blocks: {heights:?}"
+        );
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_channels_deliver_blocks_independently() {
+        let simulation = SimulationBuilder::new(3).channel("rewards").start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 1))
+                .await
+        );
+
+        let keypair = Keypair::generate(None);
+        let message = RawApiEphemeraMessage::new("reward".to_string(), vec![1, 2, 3])
+            .sign(&keypair)
+            .unwrap();
+        let api = &simulation.node(0).handle().api;
+        api.send_channel_message("rewards", message.clone())
+            .await
+            .unwrap();
+        let unknown = api.send_channel_message("metrics", message.clone()).await;
+        assert!(matches!(unknown, Err(ApiError::UnknownChannel(_))));
+
+        let contains =
+            |blocks: &[ApiBlock]| blocks.iter().any(|block| block.messages.contains(&message));
+        let live = simulation
+            .run_until(TIMEOUT, |s| contains(&s.channel_delivered(0, "rewards")))
+            .await;
+        assert!(live, "message wasn't delivered in its channel");
+
+        for i in 0..3 {
+            assert!(!contains(&simulation.delivered(i)));
+            assert!(simulation
+                .delivered(i)
+                .iter()
+                .all(|block| block.header.channel.is_none()));
+            let blocks = simulation.channel_delivered(i, "rewards");
+            assert!(blocks
+                .iter()
+                .all(|block| block.header.channel.as_deref() == Some("rewards")));
+            for pair in blocks.windows(2) {
+                assert_eq!(pair[0].header.height + 1, pair[1].header.height);
+            }
+        }
+
+        let block = simulation
+            .channel_delivered(0, "rewards")
+            .into_iter()
+            .find(|block| block.messages.contains(&message))
+            .unwrap();
+        let stored = api
+            .get_channel_block_by_height("rewards", block.header.height)
+            .await
+            .unwrap();
+        assert_eq!(stored, Some(block.clone()));
+        assert!(api.get_block_by_id(block.hash()).await.unwrap().is_none());
+        let config = api.get_node_config().await.unwrap();
+        assert_eq!(config.channels, vec!["default", "rewards"]);
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_pauses_block_production_per_channel() {
+        let simulation = SimulationBuilder::new(3).channel("rewards").start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 1))
+                .await
+        );
+        let channel_height = |s: &Simulation| {
+            s.channel_delivered(0, "rewards")
+                .last()
+                .map_or(0, |block| block.header.height)
+        };
+
+        for i in 0..3 {
+            let api = &simulation.node(i).handle().api;
+            let state = api.pause_channel_block_production("rewards").await.unwrap();
+            assert_eq!(state.status, ApiBlockManagerStatus::PausedByAdmin);
+        }
+        let unknown = simulation
+            .node(0)
+            .handle()
+            .api
+            .pause_channel_block_production("metrics")
+            .await;
+        assert!(matches!(unknown, Err(ApiError::UnknownChannel(_))));
+
+        //Blocks created before the pause are still delivered
+        simulation.run_for(Duration::from_secs(5)).await;
+        let paused_at = channel_height(&simulation);
+        let default_at = last_height(&simulation, 0);
+        let live = simulation
+            .run_until(TIMEOUT, |s| last_height(s, 0) >= default_at + 3)
+            .await;
+        assert!(live, "default channel stopped with the paused channel");
+        assert_eq!(channel_height(&simulation), paused_at);
+
+        for i in 0..3 {
+            let api = &simulation.node(i).handle().api;
+            api.resume_channel_block_production("rewards")
+                .await
+                .unwrap();
+        }
+        let resumed = simulation
+            .run_until(TIMEOUT, |s| channel_height(s) > paused_at)
+            .await;
+        assert!(resumed, "channel didn't produce blocks after resuming");
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    /// Members sorted the way the proposer schedule sorts them.
+    fn schedule_order(simulation: &Simulation, nodes: usize) -> Vec<PeerId> {
+        let mut members = (0..nodes)
+            .map(|i| simulation.peer_id(i))
+            .collect::<Vec<_>>();
+        members.sort_by_key(ToString::to_string);
+        members
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_proposers_take_turns() {
+        let simulation = SimulationBuilder::new(3)
+            .proposer_schedule(ProposerSchedule::RoundRobin { timeout_sec: 20 })
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 8))
+                .await
+        );
+
+        let order = schedule_order(&simulation, 3);
+        let blocks = simulation.delivered(0);
+        //Early blocks may be proposed before all members have joined the group
+        let stable = blocks
+            .iter()
+            .filter(|block| block.header.height > 3)
+            .collect::<Vec<_>>();
+        assert!(!stable.is_empty());
+        for block in stable {
+            let expected = order[usize::try_from(block.header.height).unwrap() % 3];
+            assert_eq!(block.header.creator, expected);
+        }
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_next_member_proposes_after_timeout() {
+        let simulation = SimulationBuilder::new(3)
+            .proposer_schedule(ProposerSchedule::RoundRobin { timeout_sec: 10 })
+            .non_producer(2)
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2], 8))
+                .await
+        );
+
+        let silent = simulation.peer_id(2);
+        let order = schedule_order(&simulation, 3);
+        let blocks = simulation.delivered(2);
+        assert!(blocks.iter().all(|block| block.header.creator != silent));
+        let taken_over = blocks.iter().any(|block| {
+            block.header.height > 3
+                && order[usize::try_from(block.header.height).unwrap() % 3] == silent
+        });
+        assert!(taken_over, "no member proposed in place of the silent one");
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_late_original_and_failover_block_deliver_one_chain() {
+        let simulation = SimulationBuilder::new(4)
+            .proposer_schedule(ProposerSchedule::RoundRobin { timeout_sec: 10 })
+            .start();
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| all_delivered(s, &[0, 1, 2, 3], 3))
+                .await
+        );
+
+        //Blocks of the slow member arrive after the others moved to the failover round, while
+        //the slow member itself keeps its own block in broadcast
+        let slow = simulation.peer_id(0);
+        simulation
+            .faults
+            .add(Link::from_peer(slow), Fault::Delay(Duration::from_secs(11)));
+        let start = last_height(&simulation, 1);
+        assert!(
+            simulation
+                .run_until(TIMEOUT, |s| (0..4).all(|i| last_height(s, i) >= start + 8))
+                .await
+        );
+
+        let order = schedule_order(&simulation, 4);
+        let next = order[(order.iter().position(|p| *p == slow).unwrap() + 1) % 4];
+        let failover = simulation.delivered(1).into_iter().any(|block| {
+            block.header.height > start
+                && order[usize::try_from(block.header.height).unwrap() % 4] == slow
+                && block.header.creator == next
+                && block.header.round == Some(1)
+        });
+        assert!(
+            failover,
+            "no failover block delivered in place of the late one"
+        );
+
+        simulation.assert_safety();
+        simulation.shutdown().await;
+    }
+
+    #[test]
+    fn test_health_filter_with_proposer_schedule_is_rejected() {
+        let builder = SimulationBuilder::new(1)
+            .health_filter(HealthFilterConfiguration::default())
+            .proposer_schedule(ProposerSchedule::RoundRobin { timeout_sec: 20 });
+        let dir = std::env::temp_dir();
+        let config = builder.node_configuration(&dir, 0, 0, &Keypair::generate(None));
+
+        assert!(crate::EphemeraStarterInit::new(config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_block_creation_interval_is_bounded() {
+        let simulation = SimulationBuilder::new(3).start();
+        let api = &simulation.node(0).handle().api;
+
+        for interval_sec in [0, u64::MAX] {
+            let result = api.set_block_creation_interval(interval_sec).await;
+            assert!(matches!(result, Err(ApiError::InvalidArgument(_))));
+        }
+        let state = api.set_block_creation_interval(60).await.unwrap();
+        assert_eq!(state.creation_interval_sec, 60);
+
+        simulation.shutdown().await;
+    }
+}
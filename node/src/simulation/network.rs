@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use tokio::sync::mpsc;
+
+use crate::network::libp2p::network_sender::{NetCommunicationReceiver, NetworkEvent};
+use crate::peer::PeerId;
+
+/// How often messages held back by [`Fault::Reorder`] are released.
+const REORDER_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A fault applied to reliable broadcast messages on a link.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Fault {
+    /// Drops a message with the given probability.
+    Drop(f64),
+    /// Delivers a message after a delay.
+    Delay(Duration),
+    /// Delivers a message twice.
+    Duplicate,
+    /// Holds messages back and releases them in random order.
+    Reorder,
+}
+
+/// A directed link between two peers. `None` matches any peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Link {
+    pub(crate) from: Option<PeerId>,
+    pub(crate) to: Option<PeerId>,
+}
+
+impl Link {
+    pub(crate) fn any() -> Self {
+        Self {
+            from: None,
+            to: None,
+        }
+    }
+
+    pub(crate) fn between(from: PeerId, to: PeerId) -> Self {
+        Self {
+            from: Some(from),
+            to: Some(to),
+        }
+    }
+
+    pub(crate) fn from_peer(from: PeerId) -> Self {
+        Self {
+            from: Some(from),
+            to: None,
+        }
+    }
+
+    pub(crate) fn to_peer(to: PeerId) -> Self {
+        Self {
+            from: None,
+            to: Some(to),
+        }
+    }
+
+    fn matches(&self, from: &PeerId, to: &PeerId) -> bool {
+        self.from.map_or(true, |peer| peer == *from) && self.to.map_or(true, |peer| peer == *to)
+    }
+}
+
+/// What happens to a single message.
+#[derive(Debug, Default)]
+struct Decision {
+    drop: bool,
+    duplicate: bool,
+    delay: Option<Duration>,
+    reorder: bool,
+}
+
+struct State {
+    rng: StdRng,
+    rules: Vec<(Link, Fault)>,
+    partition: Vec<HashSet<PeerId>>,
+}
+
+/// Faults injected between the network and the nodes.
+///
+/// Faults apply to reliable broadcast messages on the receiving side, keyed by the link from the
+/// message sender to the receiving node. Gossiped messages, group updates and DHT responses are
+/// always passed through, and so is all traffic inside libp2p, membership protocol included.
+/// All random choices come from a single seeded generator.
+#[derive(Clone)]
+pub(crate) struct Faults {
+    state: Arc<Mutex<State>>,
+}
+
+impl Faults {
+    pub(crate) fn new(seed: u64) -> Self {
+        let state = State {
+            rng: StdRng::seed_from_u64(seed),
+            rules: vec![],
+            partition: vec![],
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Applies `fault` to all messages on `link` until [`Faults::clear`] is called.
+    pub(crate) fn add(&self, link: Link, fault: Fault) {
+        self.state.lock().unwrap().rules.push((link, fault));
+    }
+
+    /// Removes all faults and heals the partition.
+    pub(crate) fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rules.clear();
+        state.partition.clear();
+    }
+
+    /// Splits peers into sides. Messages between peers on different sides are dropped.
+    /// Peers not listed on any side can reach everyone.
+    pub(crate) fn partition(&self, sides: Vec<Vec<PeerId>>) {
+        self.state.lock().unwrap().partition = sides
+            .into_iter()
+            .map(|side| side.into_iter().collect())
+            .collect();
+    }
+
+    pub(crate) fn heal(&self) {
+        self.state.lock().unwrap().partition.clear();
+    }
+
+    fn decide(&self, from: &PeerId, to: &PeerId) -> Decision {
+        let mut state = self.state.lock().unwrap();
+        let mut decision = Decision::default();
+
+        let side_of = |peer: &PeerId| state.partition.iter().position(|side| side.contains(peer));
+        if let (Some(from_side), Some(to_side)) = (side_of(from), side_of(to)) {
+            if from_side != to_side {
+                decision.drop = true;
+                return decision;
+            }
+        }
+
+        let State { rng, rules, .. } = &mut *state;
+        for (_, fault) in rules.iter().filter(|(link, _)| link.matches(from, to)) {
+            match fault {
+                Fault::Drop(probability) => {
+                    decision.drop |= rng.gen_bool(*probability);
+                }
+                Fault::Delay(delay) => {
+                    decision.delay = decision.delay.max(Some(*delay));
+                }
+                Fault::Duplicate => decision.duplicate = true,
+                Fault::Reorder => decision.reorder = true,
+            }
+        }
+        decision
+    }
+
+    fn shuffle(&self, events: &mut [NetworkEvent]) {
+        events.shuffle(&mut self.state.lock().unwrap().rng);
+    }
+
+    /// Puts the fault injector between the network and `receiver` of the `local` node.
+    ///
+    /// The injector stops when the network side of the channel is closed.
+    pub(crate) fn intercept(&self, local: PeerId, receiver: &mut NetCommunicationReceiver) {
+        let (tx, rcv) = mpsc::channel(1000);
+        let mut upstream = std::mem::replace(&mut receiver.net_event_rcv, rcv);
+        let faults = self.clone();
+
+        tokio::spawn(async move {
+            let mut held = vec![];
+            let mut flush = tokio::time::interval(REORDER_FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = upstream.recv() => {
+                        let Some(event) = event else {
+                            break;
+                        };
+                        let decision = match &event {
+                            NetworkEvent::BroadcastMessage(msg) if msg.original_sender != local => {
+                                faults.decide(&msg.original_sender, &local)
+                            }
+                            _ => Decision::default(),
+                        };
+                        if decision.drop {
+                            continue;
+                        }
+
+                        let mut events = vec![event.clone()];
+                        if decision.duplicate {
+                            events.push(event);
+                        }
+
+                        if let Some(delay) = decision.delay {
+                            let tx = tx.clone();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(delay).await;
+                                for event in events {
+                                    //Node is gone, nothing to deliver to
+                                    if tx.send(event).await.is_err() {
+                                        break;
+                                    }
+                                }
+                            });
+                        } else if decision.reorder {
+                            held.extend(events);
+                        } else {
+                            for event in events {
+                                if tx.send(event).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    _ = flush.tick() => {
+                        faults.shuffle(&mut held);
+                        for event in held.drain(..) {
+                            if tx.send(event).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::crypto::{EphemeraKeypair, Keypair};
+    use crate::peer::ToPeerId;
+
+    use super::*;
+
+    fn peer() -> PeerId {
+        Keypair::generate(None).peer_id()
+    }
+
+    #[test]
+    fn test_partition_drops_messages_between_sides() {
+        let (a, b, c) = (peer(), peer(), peer());
+        let faults = Faults::new(0);
+        faults.partition(vec![vec![a], vec![b]]);
+
+        assert!(faults.decide(&a, &b).drop);
+        assert!(faults.decide(&b, &a).drop);
+        assert!(!faults.decide(&a, &c).drop);
+
+        faults.heal();
+        assert!(!faults.decide(&a, &b).drop);
+    }
+
+    #[test]
+    fn test_rules_match_links() {
+        let (a, b, c) = (peer(), peer(), peer());
+        let faults = Faults::new(0);
+        faults.add(Link::between(a, b), Fault::Drop(1.0));
+        faults.add(Link::to_peer(c), Fault::Duplicate);
+        faults.add(Link::from_peer(b), Fault::Reorder);
+        faults.add(Link::any(), Fault::Delay(Duration::from_secs(1)));
+
+        let decision = faults.decide(&a, &b);
+        assert!(decision.drop);
+        assert!(!decision.duplicate);
+
+        let decision = faults.decide(&a, &c);
+        assert!(!decision.drop);
+        assert!(decision.duplicate);
+        assert!(!decision.reorder);
+        assert_eq!(decision.delay, Some(Duration::from_secs(1)));
+
+        assert!(faults.decide(&b, &a).reorder);
+
+        faults.clear();
+        let decision = faults.decide(&a, &c);
+        assert!(!decision.duplicate);
+        assert_eq!(decision.delay, None);
+    }
+
+    #[test]
+    fn test_same_seed_same_decisions() {
+        let (a, b) = (peer(), peer());
+        let decisions = |seed| {
+            let faults = Faults::new(seed);
+            faults.add(Link::any(), Fault::Drop(0.5));
+            (0..100)
+                .map(|_| faults.decide(&a, &b).drop)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(decisions(7), decisions(7));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use log::trace;
+use tokio::task::JoinHandle;
+
+use crate::api::application::{Application, CheckBlockResult, Result};
+use crate::api::types::{ApiBlock, ApiEphemeraMessage};
+use crate::config::Configuration;
+use crate::core::builder::{EphemeraHandle, EphemeraStarterInit};
+use crate::network::libp2p::behaviours::TransportKind;
+use crate::peer::PeerId;
+use crate::simulation::members::ScriptedMembersProvider;
+use crate::simulation::network::Faults;
+
+/// Blocks delivered to the application of a node, in delivery order.
+///
+/// It outlives node restarts, like application state would.
+#[derive(Clone, Default)]
+pub(crate) struct DeliveredBlocks {
+    blocks: Arc<Mutex<Vec<ApiBlock>>>,
+}
+
+impl DeliveredBlocks {
+    pub(crate) fn blocks(&self) -> Vec<ApiBlock> {
+        self.blocks.lock().unwrap().clone()
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.blocks.lock().unwrap().len()
+    }
+}
+
+/// Application which accepts everything and records delivered blocks.
+struct RecordingApplication {
+    delivered: DeliveredBlocks,
+}
+
+impl Application for RecordingApplication {
+    fn check_tx(&self, tx: ApiEphemeraMessage) -> Result<bool> {
+        trace!("check_tx: {tx:?}");
+        Ok(true)
+    }
+
+    fn check_block(&self, block: &ApiBlock) -> Result<CheckBlockResult> {
+        trace!("check_block: {block:?}");
+        Ok(CheckBlockResult::Accept)
+    }
+
+    fn deliver_block(&self, block: ApiBlock) -> Result<()> {
+        trace!("deliver_block: {block:?}");
+        self.delivered.blocks.lock().unwrap().push(block);
+        Ok(())
+    }
+}
+
+struct RunningNode {
+    handle: EphemeraHandle,
+    task: JoinHandle<()>,
+}
+
+/// A single Ephemera instance of the simulation.
+pub(crate) struct SimNode {
+    pub(crate) peer_id: PeerId,
+    pub(crate) delivered: DeliveredBlocks,
+    /// Blocks delivered to the applications of the configured channels, by channel name.
+    pub(crate) channel_delivered: HashMap<String, DeliveredBlocks>,
+    pub(crate) config: Configuration,
+    pub(crate) transport: TransportKind,
+    running: Option<RunningNode>,
+}
+
+impl SimNode {
+    pub(crate) fn new(peer_id: PeerId, config: Configuration, transport: TransportKind) -> Self {
+        let channel_delivered = config
+            .channels
+            .iter()
+            .map(|channel| (channel.name.clone(), DeliveredBlocks::default()))
+            .collect();
+        Self {
+            peer_id,
+            delivered: DeliveredBlocks::default(),
+            channel_delivered,
+            config,
+            transport,
+            running: None,
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Api of the running node.
+    ///
+    /// # Panics
+    /// If the node is not running.
+    pub(crate) fn handle(&self) -> &EphemeraHandle {
+        &self.running.as_ref().expect("Node is not running").handle
+    }
+
+    /// Builds the node from its configuration and database and runs it.
+    pub(crate) fn start(&mut self, members: ScriptedMembersProvider, faults: &Faults) {
+        assert!(!self.is_running(), "Node is already running");
+
+        let application = RecordingApplication {
+            delivered: self.delivered.clone(),
+        };
+        let mut init =
+            EphemeraStarterInit::new(self.config.clone()).expect("Invalid node configuration");
+        //Other transports come from the configuration
+        if self.transport == TransportKind::Memory {
+            init = init.with_memory_transport();
+        }
+        let mut init = init.with_application(application);
+        for (channel, delivered) in &self.channel_delivered {
+            let application = RecordingApplication {
+                delivered: delivered.clone(),
+            };
+            init = init.with_channel_application(channel, application);
+        }
+        let mut ephemera = init
+            .with_members_provider(members)
+            .expect("Failed to initialize node")
+            .build();
+
+        faults.intercept(self.peer_id, &mut ephemera.from_network);
+
+        let handle = ephemera.handle();
+        let task = tokio::spawn(ephemera.run());
+        self.running = Some(RunningNode { handle, task });
+    }
+
+    /// Stops all node tasks. Only the database survives until the node is started again.
+    pub(crate) async fn crash(&mut self) {
+        if let Some(mut running) = self.running.take() {
+            running
+                .handle
+                .shutdown
+                .shutdown()
+                .expect("Failed to send shutdown signal");
+            running.task.await.expect("Node task panicked");
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use tokio::time::{self, Instant};
+
+/// How many times the clock yields to other tasks after each step.
+const SETTLE_YIELDS: usize = 100;
+
+/// Virtual clock of the simulation.
+///
+/// The simulation runs on a paused tokio clock. All tokio timers Ephemera uses(block creation,
+/// membership polling, dial attempts) fire only when the test advances the clock or when the
+/// runtime has nothing else to do. Timers internal to libp2p run on wall clock time.
+///
+/// The clock must be created inside a `current_thread` tokio runtime, which is the default for
+/// `#[tokio::test]`.
+///
+/// Simulations over real sockets use a running clock instead, see [`SimClock::real`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SimClock {
+    started: Instant,
+    step: Duration,
+    paused: bool,
+}
+
+impl SimClock {
+    /// Pauses the tokio clock. Time then advances in `step` increments.
+    pub(crate) fn pause(step: Duration) -> Self {
+        time::pause();
+        Self {
+            started: Instant::now(),
+            step,
+            paused: true,
+        }
+    }
+
+    /// Leaves the tokio clock running. Advancing the clock sleeps `step` at a time.
+    ///
+    /// Nodes talking over real sockets need it, a paused clock would jump over the time
+    /// the packets spend in the kernel.
+    pub(crate) fn real(step: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            step,
+            paused: false,
+        }
+    }
+
+    /// Virtual time elapsed since the clock was paused.
+    pub(crate) fn elapsed(&self) -> Duration {
+        Instant::now() - self.started
+    }
+
+    /// Advances the clock by `duration`, one step at a time, letting all tasks make progress
+    /// between the steps.
+    pub(crate) async fn advance(&self, duration: Duration) {
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            let step = remaining.min(self.step);
+            if self.paused {
+                time::advance(step).await;
+            } else {
+                time::sleep(step).await;
+            }
+            Self::settle().await;
+            remaining -= step;
+        }
+    }
+
+    /// Gives all ready tasks a chance to run without moving the clock.
+    pub(crate) async fn settle() {
+        for _ in 0..SETTLE_YIELDS {
+            tokio::task::yield_now().await;
+        }
+    }
+}
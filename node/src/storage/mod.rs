@@ -10,8 +10,9 @@
 //!
 //! To use `SqlLite`, you need to compile with the `sqlite_storage` feature and with `--no-default-features` flag.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::block::types::block::Block;
@@ -41,7 +42,10 @@ pub(crate) trait EphemeraDatabase: Send {
     /// Returns last committed/finalised block.
     fn get_last_block(&self) -> Result<Option<Block>>;
 
-    /// Returns block by its height
+    /// Returns block by its height.
+    ///
+    /// Observers of a cluster without a proposer schedule store a block of every producer at each
+    /// height, then the first stored one is returned.
     fn get_block_by_height(&self, height: u64) -> Result<Option<Block>>;
 
     /// Returns block certificates.
@@ -49,17 +53,66 @@ pub(crate) trait EphemeraDatabase: Send {
     /// Certificates were created as part of broadcast protocol and signed by peers who participated.
     fn get_block_certificates(&self, block_hash: &str) -> Result<Option<Vec<Certificate>>>;
 
-    /// Returns peers who participated in block broadcast.
-    fn get_block_broadcast_group(&self, block_hash: &str) -> Result<Option<Vec<PeerId>>>;
+    /// Returns peers who participated in block broadcast together with their weights.
+    fn get_block_broadcast_group(&self, block_hash: &str) -> Result<Option<HashMap<PeerId, u64>>>;
 
-    /// Stores block and its signatures
+    /// Stores block, its signatures and the broadcast group members with their weights
     fn store_block(
         &mut self,
         block: &Block,
         certificates: HashSet<Certificate>,
-        members: HashSet<PeerId>,
+        members: HashMap<PeerId, u64>,
     ) -> Result<()>;
 
     /// Returns block merkle tree
     fn get_block_merkle_tree(&self, block_hash: &str) -> Result<Option<MerkleTree>>;
+
+    /// Stores broadcast group snapshot
+    fn store_group_snapshot(&mut self, snapshot: &StoredGroupSnapshot) -> Result<()>;
+
+    /// Returns broadcast group snapshot by its id
+    fn get_group_snapshot(&self, id: u64) -> Result<Option<StoredGroupSnapshot>>;
+
+    /// Returns all broadcast group snapshots, oldest first
+    fn get_group_snapshots(&self) -> Result<Vec<StoredGroupSnapshot>>;
+
+    /// Returns the id of the last stored broadcast group snapshot
+    fn get_last_group_snapshot_id(&self) -> Result<Option<u64>>;
+
+    /// Opens a separate handle to the DHT records, owned by Kademlia record store.
+    fn dht_records(&self) -> Result<Box<dyn DhtRecordDatabase>>;
+}
+
+/// Broadcast group snapshot stored by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StoredGroupSnapshot {
+    pub(crate) id: u64,
+    /// Time in milliseconds when the snapshot became the current group.
+    pub(crate) timestamp: u64,
+    /// Height of the last local block committed before the snapshot became the current group.
+    /// Later local blocks were broadcast in this group until the next snapshot.
+    pub(crate) activation_height: u64,
+    /// Members together with their weights.
+    pub(crate) members: HashMap<PeerId, u64>,
+}
+
+/// Kademlia record stored by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StoredDhtRecord {
+    pub(crate) key: Vec<u8>,
+    /// Encoded signed record.
+    pub(crate) value: Vec<u8>,
+    /// Peer who published the record, if known.
+    pub(crate) publisher: Option<PeerId>,
+}
+
+pub(crate) trait DhtRecordDatabase: Send {
+    /// Returns all stored DHT records
+    fn get_dht_records(&self) -> Result<Vec<StoredDhtRecord>>;
+
+    /// Stores DHT record, replacing the record with the same key
+    fn store_dht_record(&mut self, record: &StoredDhtRecord) -> Result<()>;
+
+    /// Removes DHT record
+    fn remove_dht_record(&mut self, key: &[u8]) -> Result<()>;
 }
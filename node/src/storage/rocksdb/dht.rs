@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use log::trace;
+use rocksdb::TransactionDB;
+
+use crate::storage::rocksdb::dht_record_key;
+use crate::storage::{DhtRecordDatabase, Result, StoredDhtRecord};
+
+pub(crate) struct RocksDbDhtRecords {
+    database: Arc<TransactionDB>,
+}
+
+impl RocksDbDhtRecords {
+    pub(crate) fn new(db: Arc<TransactionDB>) -> Self {
+        Self { database: db }
+    }
+
+    fn get_records(&self) -> anyhow::Result<Vec<StoredDhtRecord>> {
+        let prefix = dht_record_key(&[]);
+        let mut records = vec![];
+        for item in self.database.prefix_iterator(&prefix) {
+            let (key, value) = item?;
+            //Iterator continues past the prefix
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            records.push(serde_json::from_slice::<StoredDhtRecord>(&value)?);
+        }
+        trace!("Found {} dht records", records.len());
+        Ok(records)
+    }
+
+    fn store_record(&self, record: &StoredDhtRecord) -> anyhow::Result<()> {
+        let record_bytes = serde_json::to_vec(record)?;
+        self.database
+            .put(dht_record_key(&record.key), record_bytes)?;
+        Ok(())
+    }
+
+    fn remove_record(&self, key: &[u8]) -> anyhow::Result<()> {
+        self.database.delete(dht_record_key(key))?;
+        Ok(())
+    }
+}
+
+impl DhtRecordDatabase for RocksDbDhtRecords {
+    fn get_dht_records(&self) -> Result<Vec<StoredDhtRecord>> {
+        self.get_records().map_err(Into::into)
+    }
+
+    fn store_dht_record(&mut self, record: &StoredDhtRecord) -> Result<()> {
+        self.store_record(record).map_err(Into::into)
+    }
+
+    fn remove_dht_record(&mut self, key: &[u8]) -> Result<()> {
+        self.remove_record(key).map_err(Into::into)
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use log::info;
@@ -7,19 +7,22 @@ use rocksdb::{TransactionDB, TransactionDBOptions};
 use crate::block::types::block::Block;
 use crate::config::DatabaseConfiguration;
 use crate::peer::PeerId;
+use crate::storage::rocksdb::dht::RocksDbDhtRecords;
 use crate::storage::rocksdb::query::Database;
 use crate::storage::rocksdb::store::DbStore;
-use crate::storage::EphemeraDatabase;
 use crate::storage::Result;
+use crate::storage::{DhtRecordDatabase, EphemeraDatabase, StoredGroupSnapshot};
 use crate::utilities::crypto::Certificate;
 use crate::utilities::merkle::MerkleTree;
 
+pub(crate) mod dht;
 pub(crate) mod query;
 pub(crate) mod store;
 
 pub(crate) struct RocksDbStorage {
     pub(crate) db_store: DbStore,
     pub(crate) db_query: Database,
+    db: Arc<TransactionDB>,
 }
 
 const PREFIX_LAST_BLOCK_KEY: &str = "last_block";
@@ -27,7 +30,11 @@ const PREFIX_BLOCK_HASH: &str = "block_hash";
 const PREFIX_BLOCK_HEIGHT: &str = "block_height";
 const PREFIX_CERTIFICATES: &str = "block_certificates";
 const PREFIX_MEMBERS: &str = "block_members";
+const PREFIX_MEMBER_WEIGHTS: &str = "block_member_weights";
 const MERKLE_TREE: &str = "merkle_tree";
+const PREFIX_DHT_RECORD: &str = "dht_record";
+const PREFIX_LAST_GROUP_SNAPSHOT_KEY: &str = "last_group_snapshot";
+const PREFIX_GROUP_SNAPSHOT: &str = "group_snapshot";
 
 impl RocksDbStorage {
     pub fn open(db_conf: &DatabaseConfiguration) -> Result<Self> {
@@ -45,8 +52,12 @@ impl RocksDbStorage {
 
         let db = Arc::new(db);
         let db_store = DbStore::new(db.clone());
-        let db_query = Database::new(db);
-        let storage = Self { db_store, db_query };
+        let db_query = Database::new(db.clone());
+        let storage = Self {
+            db_store,
+            db_query,
+            db,
+        };
 
         info!("Opened RocksDB database at {}", db_conf.rocksdb_path);
         Ok(storage)
@@ -76,7 +87,7 @@ impl EphemeraDatabase for RocksDbStorage {
             .map_err(Into::into)
     }
 
-    fn get_block_broadcast_group(&self, block_id: &str) -> Result<Option<Vec<PeerId>>> {
+    fn get_block_broadcast_group(&self, block_id: &str) -> Result<Option<HashMap<PeerId, u64>>> {
         self.db_query
             .get_block_broadcast_group(block_id)
             .map_err(Into::into)
@@ -86,7 +97,7 @@ impl EphemeraDatabase for RocksDbStorage {
         &mut self,
         block: &Block,
         certificates: HashSet<Certificate>,
-        members: HashSet<PeerId>,
+        members: HashMap<PeerId, u64>,
     ) -> Result<()> {
         self.db_store
             .store_block(block, certificates, members)
@@ -98,6 +109,30 @@ impl EphemeraDatabase for RocksDbStorage {
             .get_block_merkle_tree(block_hash)
             .map_err(Into::into)
     }
+
+    fn store_group_snapshot(&mut self, snapshot: &StoredGroupSnapshot) -> Result<()> {
+        self.db_store
+            .store_group_snapshot(snapshot)
+            .map_err(Into::into)
+    }
+
+    fn get_group_snapshot(&self, id: u64) -> Result<Option<StoredGroupSnapshot>> {
+        self.db_query.get_group_snapshot(id).map_err(Into::into)
+    }
+
+    fn get_group_snapshots(&self) -> Result<Vec<StoredGroupSnapshot>> {
+        self.db_query.get_group_snapshots().map_err(Into::into)
+    }
+
+    fn get_last_group_snapshot_id(&self) -> Result<Option<u64>> {
+        self.db_query
+            .get_last_group_snapshot_id()
+            .map_err(Into::into)
+    }
+
+    fn dht_records(&self) -> Result<Box<dyn DhtRecordDatabase>> {
+        Ok(Box::new(RocksDbDhtRecords::new(self.db.clone())))
+    }
 }
 
 fn block_hash_key(block_hash: &str) -> String {
@@ -120,6 +155,29 @@ fn members_key(block_hash: &str) -> String {
     format!("{PREFIX_MEMBERS}:{block_hash}",)
 }
 
+fn member_weights_key(block_hash: &str) -> String {
+    format!("{PREFIX_MEMBER_WEIGHTS}:{block_hash}",)
+}
+
 fn merkle_tree_key(block_hash: &str) -> String {
     format!("{MERKLE_TREE}:{block_hash}",)
 }
+
+fn dht_record_key(key: &[u8]) -> Vec<u8> {
+    let mut record_key = format!("{PREFIX_DHT_RECORD}:").into_bytes();
+    record_key.extend_from_slice(key);
+    record_key
+}
+
+fn last_group_snapshot_key() -> String {
+    PREFIX_LAST_GROUP_SNAPSHOT_KEY.to_string()
+}
+
+//Zero padded to iterate snapshots in id order
+fn group_snapshot_key(id: u64) -> String {
+    format!("{PREFIX_GROUP_SNAPSHOT}:{id:020}")
+}
+
+fn group_snapshot_prefix() -> String {
+    format!("{PREFIX_GROUP_SNAPSHOT}:")
+}
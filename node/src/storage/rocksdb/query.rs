@@ -1,14 +1,17 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use log::trace;
 use rocksdb::TransactionDB;
 
 use crate::block::types::block::Block;
+use crate::membership::DEFAULT_PEER_WEIGHT;
 use crate::network::PeerId;
 use crate::storage::rocksdb::{
-    block_hash_key, block_height_key, certificates_key, last_block_key, members_key,
-    merkle_tree_key,
+    block_hash_key, block_height_key, certificates_key, group_snapshot_key, group_snapshot_prefix,
+    last_block_key, last_group_snapshot_key, member_weights_key, members_key, merkle_tree_key,
 };
+use crate::storage::StoredGroupSnapshot;
 use crate::utilities::crypto::Certificate;
 use crate::utilities::merkle::MerkleTree;
 
@@ -83,7 +86,7 @@ impl Database {
     pub(crate) fn get_block_broadcast_group(
         &self,
         block_hash: &str,
-    ) -> anyhow::Result<Option<Vec<PeerId>>> {
+    ) -> anyhow::Result<Option<HashMap<PeerId, u64>>> {
         trace!("Getting block broadcast group: {}", block_hash);
 
         let members_key = members_key(block_hash);
@@ -91,7 +94,13 @@ impl Database {
         if let Some(members) = self.database.get(members_key)? {
             let members: Vec<PeerId> = serde_json::from_slice(&members)?;
             trace!("Found members: {:?}", members);
-            Ok(Some(members))
+
+            //Blocks stored before weights were introduced have equal weights
+            let weights: Vec<u64> = match self.database.get(member_weights_key(block_hash))? {
+                Some(weights) => serde_json::from_slice(&weights)?,
+                None => vec![DEFAULT_PEER_WEIGHT; members.len()],
+            };
+            Ok(Some(members.into_iter().zip(weights).collect()))
         } else {
             trace!("Didn't find members");
             Ok(None)
@@ -115,4 +124,43 @@ impl Database {
             Ok(None)
         }
     }
+
+    pub(crate) fn get_group_snapshot(
+        &self,
+        id: u64,
+    ) -> anyhow::Result<Option<StoredGroupSnapshot>> {
+        trace!("Getting broadcast group snapshot: {}", id);
+
+        let snapshot = if let Some(snapshot) = self.database.get(group_snapshot_key(id))? {
+            Some(serde_json::from_slice::<StoredGroupSnapshot>(&snapshot)?)
+        } else {
+            trace!("Didn't find broadcast group snapshot");
+            None
+        };
+        Ok(snapshot)
+    }
+
+    pub(crate) fn get_group_snapshots(&self) -> anyhow::Result<Vec<StoredGroupSnapshot>> {
+        let prefix = group_snapshot_prefix();
+        let mut snapshots = vec![];
+        for item in self.database.prefix_iterator(&prefix) {
+            let (key, value) = item?;
+            //Iterator continues past the prefix
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            snapshots.push(serde_json::from_slice::<StoredGroupSnapshot>(&value)?);
+        }
+        trace!("Found {} broadcast group snapshots", snapshots.len());
+        Ok(snapshots)
+    }
+
+    pub(crate) fn get_last_group_snapshot_id(&self) -> anyhow::Result<Option<u64>> {
+        if let Some(id) = self.database.get(last_group_snapshot_key())? {
+            Ok(Some(String::from_utf8(id)?.parse()?))
+        } else {
+            trace!("Unable to get last broadcast group snapshot");
+            Ok(None)
+        }
+    }
 }
@@ -1,12 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use crate::block::types::block::Block;
 use crate::network::PeerId;
 use crate::storage::rocksdb::{
-    block_hash_key, block_height_key, certificates_key, last_block_key, members_key,
-    merkle_tree_key,
+    block_hash_key, block_height_key, certificates_key, group_snapshot_key, last_block_key,
+    last_group_snapshot_key, member_weights_key, members_key, merkle_tree_key,
 };
+use crate::storage::StoredGroupSnapshot;
 use log::{debug, trace};
 use rocksdb::{TransactionDB, WriteBatchWithTransaction};
 
@@ -25,7 +26,7 @@ impl DbStore {
         &self,
         block: &Block,
         certificates: HashSet<Certificate>,
-        members: HashSet<PeerId>,
+        members: HashMap<PeerId, u64>,
     ) -> anyhow::Result<()> {
         debug!("Storing block: {}", block.header);
         trace!("Storing block certificates: {}", certificates.len());
@@ -36,6 +37,7 @@ impl DbStore {
         let certificates_key = certificates_key(&hash_str);
         let height_key = block_height_key(&block.header.height);
         let members_key = members_key(&hash_str);
+        let member_weights_key = member_weights_key(&hash_str);
         let merkle_tree_key = merkle_tree_key(&hash_str);
 
         // Check UNIQUE constraints
@@ -50,8 +52,10 @@ impl DbStore {
         //May want to check that height is incremented by 1
         batch.put(last_block_key(), hash_str.clone());
 
-        // Store block height
-        batch.put(height_key.as_bytes(), hash_str);
+        // Store block height, the first block of each height is kept
+        if self.connection.get(&height_key)?.is_none() {
+            batch.put(height_key.as_bytes(), hash_str);
+        }
 
         // Store block(without signature)
         let block_bytes = serde_json::to_vec::<Block>(block)?;
@@ -63,10 +67,12 @@ impl DbStore {
                 .map_err(|e| anyhow::anyhow!(e))?;
         batch.put(certificates_key.as_bytes(), certificates_bytes);
 
-        // Store block members
-        let members_bytes = serde_json::to_vec(&members.into_iter().collect::<Vec<PeerId>>())
-            .map_err(|e| anyhow::anyhow!(e))?;
+        // Store block members and their weights
+        let (members, weights): (Vec<PeerId>, Vec<u64>) = members.into_iter().unzip();
+        let members_bytes = serde_json::to_vec(&members).map_err(|e| anyhow::anyhow!(e))?;
         batch.put(members_key.as_bytes(), members_bytes);
+        let weights_bytes = serde_json::to_vec(&weights).map_err(|e| anyhow::anyhow!(e))?;
+        batch.put(member_weights_key.as_bytes(), weights_bytes);
 
         //Store Merkle Tree
         let merkle_tree = block.merkle_tree()?;
@@ -76,4 +82,24 @@ impl DbStore {
         self.connection.write(batch)?;
         Ok(())
     }
+
+    pub(crate) fn store_group_snapshot(
+        &self,
+        snapshot: &StoredGroupSnapshot,
+    ) -> anyhow::Result<()> {
+        debug!("Storing broadcast group snapshot: {}", snapshot.id);
+
+        let snapshot_key = group_snapshot_key(snapshot.id);
+        if self.connection.get(&snapshot_key)?.is_some() {
+            return Err(anyhow::anyhow!("Broadcast group snapshot already exists"));
+        }
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        batch.put(last_group_snapshot_key(), snapshot.id.to_string());
+        let snapshot_bytes = serde_json::to_vec(snapshot)?;
+        batch.put(snapshot_key.as_bytes(), snapshot_bytes);
+
+        self.connection.write(batch)?;
+        Ok(())
+    }
 }
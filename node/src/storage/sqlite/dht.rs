@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use log::{error, trace};
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::config::DatabaseConfiguration;
+use crate::peer::PeerId;
+use crate::storage::{DhtRecordDatabase, Result, StoredDhtRecord};
+
+pub(crate) struct SqliteDhtRecords {
+    connection: Connection,
+}
+
+impl SqliteDhtRecords {
+    pub(crate) fn open(db_conf: DatabaseConfiguration, flags: OpenFlags) -> anyhow::Result<Self> {
+        let connection = Connection::open_with_flags(db_conf.sqlite_path, flags)?;
+        Ok(Self { connection })
+    }
+
+    fn get_records(&self) -> anyhow::Result<Vec<StoredDhtRecord>> {
+        let mut stmt = self
+            .connection
+            .prepare_cached("SELECT record_key, record, publisher FROM dht_records")?;
+
+        let records = stmt
+            .query_map(params![], |row| {
+                let key: Vec<u8> = row.get(0)?;
+                let value: Vec<u8> = row.get(1)?;
+                let publisher: Option<String> = row.get(2)?;
+                let publisher = publisher
+                    .map(|publisher| PeerId::from_str(&publisher))
+                    .transpose()
+                    .map_err(|e| {
+                        error!("Error deserializing dht record publisher: {}", e);
+                        rusqlite::Error::InvalidQuery {}
+                    })?;
+                Ok(StoredDhtRecord {
+                    key,
+                    value,
+                    publisher,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        trace!("Found {} dht records", records.len());
+        Ok(records)
+    }
+
+    fn store_record(&mut self, record: &StoredDhtRecord) -> anyhow::Result<()> {
+        let publisher = record.publisher.map(|publisher| publisher.to_string());
+        let mut statement = self.connection.prepare_cached(
+            "INSERT OR REPLACE INTO dht_records (record_key, record, publisher) VALUES (?1, ?2, ?3)",
+        )?;
+        statement.execute(params![&record.key, &record.value, &publisher])?;
+        Ok(())
+    }
+
+    fn remove_record(&mut self, key: &[u8]) -> anyhow::Result<()> {
+        let mut statement = self
+            .connection
+            .prepare_cached("DELETE FROM dht_records WHERE record_key = ?1")?;
+        statement.execute(params![key])?;
+        Ok(())
+    }
+}
+
+impl DhtRecordDatabase for SqliteDhtRecords {
+    fn get_dht_records(&self) -> Result<Vec<StoredDhtRecord>> {
+        self.get_records().map_err(Into::into)
+    }
+
+    fn store_dht_record(&mut self, record: &StoredDhtRecord) -> Result<()> {
+        self.store_record(record).map_err(Into::into)
+    }
+
+    fn remove_dht_record(&mut self, key: &[u8]) -> Result<()> {
+        self.remove_record(key).map_err(Into::into)
+    }
+}
@@ -1,17 +1,19 @@
 use log::{error, info};
-use rusqlite::Connection;
-use std::collections::HashSet;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
 
 use crate::block::types::block::Block;
 use crate::config::DatabaseConfiguration;
 use crate::peer::PeerId;
+use crate::storage::sqlite::dht::SqliteDhtRecords;
 use crate::storage::sqlite::query::DbQuery;
 use crate::storage::sqlite::store::Database;
-use crate::storage::EphemeraDatabase;
 use crate::storage::Result;
+use crate::storage::{DhtRecordDatabase, EphemeraDatabase, StoredGroupSnapshot};
 use crate::utilities::crypto::Certificate;
 use crate::utilities::merkle::MerkleTree;
 
+pub(crate) mod dht;
 pub(crate) mod query;
 pub(crate) mod store;
 
@@ -24,6 +26,8 @@ mod migrations {
 pub(crate) struct SqliteStorage {
     pub(crate) db_store: Database,
     pub(crate) db_query: DbQuery,
+    db_conf: DatabaseConfiguration,
+    flags: OpenFlags,
 }
 
 impl SqliteStorage {
@@ -39,8 +43,13 @@ impl SqliteStorage {
 
         info!("Starting db backend with path: {}", db_conf.sqlite_path);
         let db_store = Database::open(db_conf.clone(), flags)?;
-        let db_query = DbQuery::open(db_conf, flags)?;
-        let storage = Self { db_store, db_query };
+        let db_query = DbQuery::open(db_conf.clone(), flags)?;
+        let storage = Self {
+            db_store,
+            db_query,
+            db_conf,
+            flags,
+        };
         Ok(storage)
     }
 
@@ -82,7 +91,7 @@ impl EphemeraDatabase for SqliteStorage {
             .map_err(Into::into)
     }
 
-    fn get_block_broadcast_group(&self, block_id: &str) -> Result<Option<Vec<PeerId>>> {
+    fn get_block_broadcast_group(&self, block_id: &str) -> Result<Option<HashMap<PeerId, u64>>> {
         self.db_query
             .get_block_broadcast_group(block_id)
             .map_err(Into::into)
@@ -92,7 +101,7 @@ impl EphemeraDatabase for SqliteStorage {
         &mut self,
         block: &Block,
         certificates: HashSet<Certificate>,
-        members: HashSet<PeerId>,
+        members: HashMap<PeerId, u64>,
     ) -> Result<()> {
         self.db_store
             .store_block(block, certificates, members)
@@ -104,4 +113,29 @@ impl EphemeraDatabase for SqliteStorage {
             .get_block_merkle_tree(block_hash)
             .map_err(Into::into)
     }
+
+    fn store_group_snapshot(&mut self, snapshot: &StoredGroupSnapshot) -> Result<()> {
+        self.db_store
+            .store_group_snapshot(snapshot)
+            .map_err(Into::into)
+    }
+
+    fn get_group_snapshot(&self, id: u64) -> Result<Option<StoredGroupSnapshot>> {
+        self.db_query.get_group_snapshot(id).map_err(Into::into)
+    }
+
+    fn get_group_snapshots(&self) -> Result<Vec<StoredGroupSnapshot>> {
+        self.db_query.get_group_snapshots().map_err(Into::into)
+    }
+
+    fn get_last_group_snapshot_id(&self) -> Result<Option<u64>> {
+        self.db_query
+            .get_last_group_snapshot_id()
+            .map_err(Into::into)
+    }
+
+    fn dht_records(&self) -> Result<Box<dyn DhtRecordDatabase>> {
+        let records = SqliteDhtRecords::open(self.db_conf.clone(), self.flags)?;
+        Ok(Box::new(records))
+    }
 }
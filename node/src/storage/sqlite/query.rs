@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use log::{error, trace};
 use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
 
 use crate::block::types::block::Block;
 use crate::config::DatabaseConfiguration;
+use crate::membership::DEFAULT_PEER_WEIGHT;
 use crate::peer::PeerId;
+use crate::storage::StoredGroupSnapshot;
 use crate::utilities::crypto::Certificate;
 use crate::utilities::merkle::MerkleTree;
 
@@ -54,7 +58,7 @@ impl DbQuery {
     pub(crate) fn get_block_by_height(&self, height: u64) -> anyhow::Result<Option<Block>> {
         let mut stmt = self
             .connection
-            .prepare_cached("SELECT block FROM blocks WHERE height = ?1")?;
+            .prepare_cached("SELECT block FROM blocks WHERE height = ?1 ORDER BY id LIMIT 1")?;
         let block = stmt
             .query_row(params![height], Self::map_block())
             .optional()?;
@@ -100,19 +104,28 @@ impl DbQuery {
     pub(crate) fn get_block_broadcast_group(
         &self,
         block_hash: &str,
-    ) -> anyhow::Result<Option<Vec<PeerId>>> {
-        let mut stmt = self
-            .connection
-            .prepare_cached("SELECT members FROM block_broadcast_group where block_hash = ?1")?;
+    ) -> anyhow::Result<Option<HashMap<PeerId, u64>>> {
+        let mut stmt = self.connection.prepare_cached(
+            "SELECT members, weights FROM block_broadcast_group where block_hash = ?1",
+        )?;
 
         let members = stmt
             .query_row(params![block_hash], |row| {
                 let members: Vec<u8> = row.get(0)?;
+                let weights: Option<Vec<u8>> = row.get(1)?;
                 let members = serde_json::from_slice::<Vec<PeerId>>(&members).map_err(|e| {
                     error!("Error deserializing members: {}", e);
                     rusqlite::Error::InvalidQuery {}
                 })?;
-                Ok(members)
+                //Blocks stored before weights were introduced have equal weights
+                let weights = match weights {
+                    Some(weights) => serde_json::from_slice::<Vec<u64>>(&weights).map_err(|e| {
+                        error!("Error deserializing member weights: {}", e);
+                        rusqlite::Error::InvalidQuery {}
+                    })?,
+                    None => vec![DEFAULT_PEER_WEIGHT; members.len()],
+                };
+                Ok(members.into_iter().zip(weights).collect())
             })
             .optional()?;
 
@@ -154,6 +167,67 @@ impl DbQuery {
         Ok(merkle_tree)
     }
 
+    pub(crate) fn get_group_snapshot(
+        &self,
+        id: u64,
+    ) -> anyhow::Result<Option<StoredGroupSnapshot>> {
+        let mut stmt = self.connection.prepare_cached(
+            "SELECT id, timestamp, activation_height, members, weights FROM broadcast_group_snapshots WHERE id = ?1",
+        )?;
+
+        let snapshot = stmt
+            .query_row(params![id], Self::map_group_snapshot)
+            .optional()?;
+
+        if snapshot.is_some() {
+            trace!("Found broadcast group snapshot {}", id);
+        } else {
+            trace!("Broadcast group snapshot not found: {}", id);
+        }
+
+        Ok(snapshot)
+    }
+
+    pub(crate) fn get_group_snapshots(&self) -> anyhow::Result<Vec<StoredGroupSnapshot>> {
+        let mut stmt = self.connection.prepare_cached(
+            "SELECT id, timestamp, activation_height, members, weights FROM broadcast_group_snapshots ORDER BY id",
+        )?;
+
+        let snapshots = stmt
+            .query_map(params![], Self::map_group_snapshot)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        trace!("Found {} broadcast group snapshots", snapshots.len());
+        Ok(snapshots)
+    }
+
+    pub(crate) fn get_last_group_snapshot_id(&self) -> anyhow::Result<Option<u64>> {
+        let mut stmt = self
+            .connection
+            .prepare_cached("SELECT max(id) FROM broadcast_group_snapshots")?;
+        let id = stmt.query_row(params![], |row| row.get(0))?;
+        Ok(id)
+    }
+
+    fn map_group_snapshot(row: &Row) -> Result<StoredGroupSnapshot, rusqlite::Error> {
+        let members: Vec<u8> = row.get(3)?;
+        let weights: Vec<u8> = row.get(4)?;
+        let members = serde_json::from_slice::<Vec<PeerId>>(&members).map_err(|e| {
+            error!("Error deserializing snapshot members: {}", e);
+            rusqlite::Error::InvalidQuery {}
+        })?;
+        let weights = serde_json::from_slice::<Vec<u64>>(&weights).map_err(|e| {
+            error!("Error deserializing snapshot member weights: {}", e);
+            rusqlite::Error::InvalidQuery {}
+        })?;
+        Ok(StoredGroupSnapshot {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            activation_height: row.get(2)?,
+            members: members.into_iter().zip(weights).collect(),
+        })
+    }
+
     fn map_block() -> impl FnOnce(&Row) -> Result<Block, rusqlite::Error> {
         |row| {
             let body: Vec<u8> = row.get(0)?;
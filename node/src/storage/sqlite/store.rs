@@ -2,10 +2,11 @@ use crate::block::types::block::Block;
 use anyhow::Result;
 use log::debug;
 use rusqlite::{params, Connection, OpenFlags};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::config::DatabaseConfiguration;
 use crate::network::PeerId;
+use crate::storage::StoredGroupSnapshot;
 use crate::utilities::crypto::Certificate;
 
 pub struct Database {
@@ -22,7 +23,7 @@ impl Database {
         &mut self,
         block: &Block,
         certificates: HashSet<Certificate>,
-        members: HashSet<PeerId>,
+        members: HashMap<PeerId, u64>,
     ) -> Result<()> {
         debug!("Storing block: {}", block.header);
 
@@ -32,8 +33,9 @@ impl Database {
         let certificates_bytes =
             serde_json::to_vec(&certificates.into_iter().collect::<Vec<Certificate>>())
                 .map_err(|e| anyhow::anyhow!(e))?;
-        let members_bytes = serde_json::to_vec(&members.into_iter().collect::<Vec<PeerId>>())
-            .map_err(|e| anyhow::anyhow!(e))?;
+        let (members, weights): (Vec<PeerId>, Vec<u64>) = members.into_iter().unzip();
+        let members_bytes = serde_json::to_vec(&members).map_err(|e| anyhow::anyhow!(e))?;
+        let weights_bytes = serde_json::to_vec(&weights).map_err(|e| anyhow::anyhow!(e))?;
         let merkle_tree = block.merkle_tree()?;
         let merkle_tree_bytes = serde_json::to_vec(&merkle_tree).map_err(|e| anyhow::anyhow!(e))?;
 
@@ -51,10 +53,10 @@ impl Database {
             statement.execute(params![&hash, &certificates_bytes,])?;
 
             let mut statement = tx.prepare_cached(
-                "INSERT INTO block_broadcast_group (block_hash, members) VALUES (?1, ?2)",
+                "INSERT INTO block_broadcast_group (block_hash, members, weights) VALUES (?1, ?2, ?3)",
             )?;
 
-            statement.execute(params![&hash, &members_bytes])?;
+            statement.execute(params![&hash, &members_bytes, &weights_bytes])?;
 
             //store Merkle Tree
             let mut statement = tx.prepare_cached(
@@ -68,4 +70,25 @@ impl Database {
 
         Ok(())
     }
+
+    pub(crate) fn store_group_snapshot(&mut self, snapshot: &StoredGroupSnapshot) -> Result<()> {
+        debug!("Storing broadcast group snapshot: {}", snapshot.id);
+
+        let (members, weights): (Vec<PeerId>, Vec<u64>) =
+            snapshot.members.iter().map(|(k, v)| (*k, *v)).unzip();
+        let members_bytes = serde_json::to_vec(&members).map_err(|e| anyhow::anyhow!(e))?;
+        let weights_bytes = serde_json::to_vec(&weights).map_err(|e| anyhow::anyhow!(e))?;
+
+        let mut statement = self.connection.prepare_cached(
+            "INSERT INTO broadcast_group_snapshots (id, timestamp, activation_height, members, weights) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        statement.execute(params![
+            &snapshot.id,
+            &snapshot.timestamp,
+            &snapshot.activation_height,
+            &members_bytes,
+            &weights_bytes
+        ])?;
+        Ok(())
+    }
 }
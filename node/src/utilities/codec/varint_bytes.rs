@@ -5,8 +5,6 @@ use unsigned_varint::{decode, encode};
 
 #[derive(Debug, Error)]
 pub(crate) enum VarintError {
-    #[error("InvalidInput: {0}")]
-    InvalidInput(String),
     #[error("varint error: {0}")]
     Varint(#[from] decode::Error),
     #[error("TooLarge")]
@@ -26,42 +24,27 @@ fn write_varint(dst: &mut BytesMut, len: u32) {
     dst.extend_from_slice(&len_data[..encoded_len]);
 }
 
+/// Reads one length prefixed frame. Leaves `bytes` untouched and returns `None` if the frame
+/// isn't complete yet.
 pub(crate) fn read_length_prefixed(
     bytes: &mut BytesMut,
     max_size: u32,
 ) -> Result<Option<Vec<u8>>, VarintError> {
-    let len = read_varint(bytes)?;
+    let (len, prefix_len) = match decode::u32(bytes) {
+        Ok((len, remaining)) => (len, bytes.len() - remaining.len()),
+        Err(decode::Error::Insufficient) => return Ok(None),
+        Err(err) => Err(err)?,
+    };
     if len > max_size {
         return Err(VarintError::TooLarge);
     }
 
-    if bytes.remaining() < len as usize {
+    if bytes.remaining() < prefix_len + len as usize {
         return Ok(None);
     }
 
-    let vec = bytes.to_vec();
-    bytes.advance(len as usize);
-    Ok(Some(vec))
-}
-
-fn read_varint(bytes: &mut BytesMut) -> Result<u32, VarintError> {
-    let mut buffer = encode::u32_buffer();
-
-    for (i, byte) in bytes.iter().enumerate() {
-        buffer[i] = *byte;
-        match decode::u32(&buffer[..i]) {
-            Ok((len, _)) => {
-                bytes.advance(i);
-                return Ok(len);
-            }
-            Err(decode::Error::Insufficient) => continue,
-            Err(err) => Err(err)?,
-        }
-    }
-
-    Err(VarintError::InvalidInput(
-        "Unable to read varint".to_string(),
-    ))
+    bytes.advance(prefix_len);
+    Ok(Some(bytes.split_to(len as usize).to_vec()))
 }
 
 #[cfg(test)]
@@ -82,4 +65,23 @@ mod test {
         assert_eq!(result, data);
         assert_eq!(encoded.remaining(), 0);
     }
+
+    #[test]
+    fn test_read_partial_and_consecutive_frames() {
+        let mut encoded = BytesMut::with_capacity(0);
+        write_length_prefixed(&mut encoded, "first");
+        write_length_prefixed(&mut encoded, "second");
+
+        let mut partial = encoded.split_to(3);
+        assert!(read_length_prefixed(&mut partial, 100).unwrap().is_none());
+        assert_eq!(partial.remaining(), 3);
+        partial.unsplit(encoded);
+
+        let first = read_length_prefixed(&mut partial, 100).unwrap().unwrap();
+        let second = read_length_prefixed(&mut partial, 100).unwrap().unwrap();
+        assert_eq!(first, b"first");
+        assert_eq!(second, b"second");
+        assert_eq!(partial.remaining(), 0);
+        assert!(read_length_prefixed(&mut partial, 100).unwrap().is_none());
+    }
 }